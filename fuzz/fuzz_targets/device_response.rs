@@ -0,0 +1,17 @@
+#![no_main]
+
+use isomdl::definitions::x509::TrustAnchorRegistry;
+use isomdl::definitions::OID4VPSessionTranscript;
+use isomdl::presentation::reader::validate_device_response;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let session_transcript = OID4VPSessionTranscript::new_oid4vp(
+        "example.com",
+        "https://example.com/response",
+        "nonce",
+        "mdoc generated nonce",
+    )
+    .unwrap();
+    let _ = validate_device_response(data, &session_transcript, &TrustAnchorRegistry::new());
+});
@@ -0,0 +1,18 @@
+#![no_main]
+
+use isomdl::definitions::x509::x5chain::{X5Chain, X509};
+use isomdl::definitions::x509::TrustAnchorRegistry;
+use libfuzzer_sys::fuzz_target;
+use serde_cbor::Value as CborValue;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(cbor) = serde_cbor::from_slice::<CborValue>(data) else {
+        return;
+    };
+    let Ok(x5chain) = X5Chain::from_cbor(&cbor) else {
+        return;
+    };
+    let mut registry = TrustAnchorRegistry::new();
+    registry.add(X509::from_pem(include_bytes!("../../test/presentation/iaca-cert.pem")).unwrap());
+    let _ = x5chain.validate(&registry);
+});
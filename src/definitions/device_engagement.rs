@@ -1,42 +1,53 @@
-use crate::definitions::helpers::Tag24;
-use crate::definitions::helpers::{ByteStr, NonEmptyVec};
-use crate::definitions::CoseKey;
+#[cfg(feature = "transports")]
+use crate::definitions::helpers::ByteStr;
+use crate::definitions::helpers::{NonEmptyVec, Tag24};
+use crate::definitions::{CoseKey, EC2Curve};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use serde_cbor::Value as CborValue;
 use std::{collections::BTreeMap, vec};
+#[cfg(feature = "transports")]
 use uuid::Uuid;
 
 pub mod error;
-pub use error::Error;
+pub use error::{EngagementError, Error};
 
+#[cfg(feature = "transports")]
 pub mod nfc_options;
+#[cfg(feature = "transports")]
 pub use nfc_options::NfcOptions;
 
 pub type EDeviceKeyBytes = Tag24<CoseKey>;
 pub type EReaderKeyBytes = Tag24<CoseKey>;
 
+#[cfg(feature = "transports")]
 pub type DeviceRetrievalMethods = NonEmptyVec<DeviceRetrievalMethod>;
 pub type ProtocolInfo = CborValue;
-pub type Oidc = (u64, String, String);
-pub type WebApi = (u64, String, String);
+pub type Oidc = ServerRetrievalInformation;
+pub type WebApi = ServerRetrievalInformation;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(try_from = "CborValue", into = "CborValue", rename_all = "camelCase")]
 pub struct DeviceEngagement {
     pub version: String,
     pub security: Security,
+    #[cfg(feature = "transports")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub device_retrieval_methods: Option<DeviceRetrievalMethods>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub server_retrieval_methods: Option<ServerRetrievalMethods>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub protocol_info: Option<ProtocolInfo>,
+    /// The origins the presentation is bound to, in browser based flows.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin_infos: Option<NonEmptyVec<OriginInfo>>,
 }
 
+#[cfg(feature = "transports")]
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(try_from = "CborValue", into = "CborValue")]
 pub enum DeviceRetrievalMethod {
+    /// Wi-Fi Aware, transport type 3.
     WIFI(WifiOptions),
     BLE(BleOptions),
     NFC(NfcOptions),
@@ -45,15 +56,58 @@ pub enum DeviceRetrievalMethod {
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Security(pub u64, pub EDeviceKeyBytes);
 
+/// The servers from which the reader may retrieve the mdoc instead of over a device retrieval
+/// method.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct ServerRetrievalMethods {
     #[serde(skip_serializing_if = "Option::is_none")]
-    web_api: Option<WebApi>,
+    pub web_api: Option<WebApi>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    oidc: Option<Oidc>,
+    pub oidc: Option<Oidc>,
+}
+
+/// How to reach the issuing authority's server, encoded as `[version, url, token]`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(from = "(u64, String, String)", into = "(u64, String, String)")]
+pub struct ServerRetrievalInformation {
+    pub version: u64,
+    /// The endpoint of the server.
+    pub url: String,
+    /// The token the reader presents to the server to retrieve the mdoc.
+    pub token: String,
+}
+
+impl From<(u64, String, String)> for ServerRetrievalInformation {
+    fn from((version, url, token): (u64, String, String)) -> Self {
+        Self {
+            version,
+            url,
+            token,
+        }
+    }
+}
+
+impl From<ServerRetrievalInformation> for (u64, String, String) {
+    fn from(info: ServerRetrievalInformation) -> Self {
+        (info.version, info.url, info.token)
+    }
 }
 
+/// An origin that the engagement, and so the session transcript, is bound to.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "CborValue", into = "CborValue")]
+pub struct OriginInfo {
+    /// Whether the origin delivered ([OriginInfo::CAT_DELIVERY]) or received
+    /// ([OriginInfo::CAT_RECEIVE]) the engagement.
+    pub cat: u64,
+    /// The kind of origin, such as [OriginInfo::TYPE_WEBSITE].
+    pub origin_type: u64,
+    /// The details of the origin, as defined for its type.
+    pub details: CborValue,
+}
+
+#[cfg(feature = "transports")]
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(try_from = "CborValue", into = "CborValue")]
 pub struct BleOptions {
@@ -63,28 +117,31 @@ pub struct BleOptions {
     pub central_client_mode: Option<CentralClientMode>,
 }
 
+#[cfg(feature = "transports")]
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct PeripheralServerMode {
     pub uuid: Uuid,
     pub ble_device_address: Option<ByteStr>,
 }
 
+#[cfg(feature = "transports")]
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct CentralClientMode {
     pub uuid: Uuid,
 }
 
+#[cfg(feature = "transports")]
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
 #[serde(try_from = "CborValue", into = "CborValue")]
 pub struct WifiOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
-    pass_phrase: Option<String>,
+    pub pass_phrase: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    channel_info_operating_class: Option<u64>,
+    pub channel_info_operating_class: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    channel_info_channel_number: Option<u64>,
+    pub channel_info_channel_number: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    band_info: Option<ByteStr>,
+    pub band_info: Option<ByteStr>,
 }
 
 impl From<DeviceEngagement> for CborValue {
@@ -101,6 +158,7 @@ impl From<DeviceEngagement> for CborValue {
                 device_engagement.security.1.into(),
             ]),
         );
+        #[cfg(feature = "transports")]
         if let Some(methods) = device_engagement.device_retrieval_methods {
             let methods = Vec::from(methods).into_iter().map(Into::into).collect();
             map.insert(CborValue::Integer(2), CborValue::Array(methods));
@@ -111,6 +169,13 @@ impl From<DeviceEngagement> for CborValue {
         if let Some(_info) = device_engagement.protocol_info {
             // Usage of protocolinfo is RFU and should for now be none
         }
+        if let Some(origin_infos) = device_engagement.origin_infos {
+            let origin_infos = Vec::from(origin_infos)
+                .into_iter()
+                .map(Into::into)
+                .collect();
+            map.insert(CborValue::Integer(5), CborValue::Array(origin_infos));
+        }
 
         CborValue::Map(map)
     }
@@ -134,6 +199,7 @@ impl TryFrom<CborValue> for DeviceEngagement {
             let security: Security = serde_cbor::value::from_value(device_engagement_security)
                 .map_err(|_| Error::Malformed)?;
 
+            #[cfg(feature = "transports")]
             let device_retrieval_methods = map
                 .remove(&CborValue::Integer(2))
                 .map(serde_cbor::value::from_value)
@@ -145,20 +211,25 @@ impl TryFrom<CborValue> for DeviceEngagement {
                 .map(serde_cbor::value::from_value)
                 .transpose()
                 .map_err(|_| Error::Malformed)?;
-            if server_retrieval_methods.is_some() {
-                //tracing::warn!("server_retrieval is unimplemented.")
-            }
             let protocol_info = map.remove(&CborValue::Integer(4));
+            #[cfg(feature = "tracing")]
             if protocol_info.is_some() {
-                //tracing::warn!("protocol_info is RFU and has been ignored in deserialization.")
+                tracing::warn!("protocol_info is RFU and has been ignored in deserialization.")
             }
+            let origin_infos = map
+                .remove(&CborValue::Integer(5))
+                .map(serde_cbor::value::from_value)
+                .transpose()
+                .map_err(|_| Error::Malformed)?;
 
             let device_engagement = DeviceEngagement {
                 version: "1.0".into(),
                 security,
+                #[cfg(feature = "transports")]
                 device_retrieval_methods,
                 server_retrieval_methods,
                 protocol_info,
+                origin_infos,
             };
 
             Ok(device_engagement)
@@ -168,6 +239,193 @@ impl TryFrom<CborValue> for DeviceEngagement {
     }
 }
 
+impl Security {
+    /// Cipher suite 1 of ISO/IEC 18013-5 section 9.1.5.2, the only one defined, for ECDH with
+    /// ephemeral keys on any of the curves of table 22.
+    pub const CIPHER_SUITE_1: u64 = 1;
+
+    /// The security of an engagement with the ephemeral device key `e_device_key`, declaring the
+    /// cipher suite for the curve of the key.
+    pub fn new(e_device_key: EDeviceKeyBytes) -> Result<Self, Error> {
+        let cipher_suite =
+            Self::cipher_suite_for(e_device_key.as_ref()).ok_or(Error::UnsupportedCurve)?;
+        Ok(Self(cipher_suite, e_device_key))
+    }
+
+    /// The cipher suite for ECDH with `key`, if there is one.
+    ///
+    /// Only P-256 keys are supported, as session keys are derived only on that curve.
+    pub fn cipher_suite_for(key: &CoseKey) -> Option<u64> {
+        match key {
+            CoseKey::EC2 {
+                crv: EC2Curve::P256,
+                ..
+            } => Some(Self::CIPHER_SUITE_1),
+            _ => None,
+        }
+    }
+
+    pub fn cipher_suite(&self) -> u64 {
+        self.0
+    }
+
+    /// Check that the offered cipher suite is supported, and that the ephemeral device key is on
+    /// one of its curves.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.0 != Self::CIPHER_SUITE_1 {
+            return Err(Error::UnsupportedCipherSuite(self.0));
+        }
+        match Self::cipher_suite_for(self.1.as_ref()) {
+            Some(cipher_suite) if cipher_suite == self.0 => Ok(()),
+            _ => Err(Error::UnsupportedCurve),
+        }
+    }
+}
+
+/// Builds a [DeviceEngagement] from an ephemeral device key that the caller already holds, for
+/// transports that manage their own keys, or for fixtures.
+#[derive(Debug, Clone, Default)]
+pub struct Builder {
+    security: Option<(u64, CoseKey)>,
+    #[cfg(feature = "transports")]
+    device_retrieval_methods: Option<DeviceRetrievalMethods>,
+    server_retrieval_methods: Option<ServerRetrievalMethods>,
+    origin_infos: Option<NonEmptyVec<OriginInfo>>,
+}
+
+impl Builder {
+    /// Set the cipher suite and the ephemeral device public key.
+    pub fn security(mut self, cipher_suite: u64, public_key: CoseKey) -> Self {
+        self.security = Some((cipher_suite, public_key));
+        self
+    }
+
+    #[cfg(feature = "transports")]
+    pub fn retrieval_methods(mut self, methods: DeviceRetrievalMethods) -> Self {
+        self.device_retrieval_methods = Some(methods);
+        self
+    }
+
+    pub fn server_retrieval_methods(mut self, methods: ServerRetrievalMethods) -> Self {
+        self.server_retrieval_methods = Some(methods);
+        self
+    }
+
+    pub fn origin_infos(mut self, origin_infos: NonEmptyVec<OriginInfo>) -> Self {
+        self.origin_infos = Some(origin_infos);
+        self
+    }
+
+    pub fn build(self) -> Result<DeviceEngagement, Error> {
+        let (cipher_suite, public_key) =
+            self.security.ok_or(Error::MissingParameter("security"))?;
+        let security = Security(cipher_suite, Tag24::new(public_key)?);
+        security.validate()?;
+        Ok(DeviceEngagement {
+            version: "1.0".into(),
+            security,
+            #[cfg(feature = "transports")]
+            device_retrieval_methods: self.device_retrieval_methods,
+            server_retrieval_methods: self.server_retrieval_methods,
+            protocol_info: None,
+            origin_infos: self.origin_infos,
+        })
+    }
+}
+
+impl DeviceEngagement {
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
+    /// Check that the engagement is bound to the website `expected_origin`, so that a
+    /// presentation made to one origin cannot be replayed to another.
+    ///
+    /// Every website origin in the engagement must match, and there must be at least one.
+    pub fn verify_origin(&self, expected_origin: &str) -> Result<(), Error> {
+        let mut base_urls = self
+            .origin_infos
+            .iter()
+            .flat_map(|origin_infos| origin_infos.iter())
+            .filter(|origin_info| origin_info.origin_type == OriginInfo::TYPE_WEBSITE)
+            .map(|origin_info| origin_info.base_url().ok_or(Error::Malformed))
+            .peekable();
+        if base_urls.peek().is_none() {
+            return Err(Error::MissingOriginInfo);
+        }
+        base_urls.try_for_each(|base_url| match base_url? {
+            base_url if base_url == expected_origin => Ok(()),
+            base_url => Err(Error::OriginMismatch(base_url.to_string())),
+        })
+    }
+}
+
+impl OriginInfo {
+    /// The origin delivered the engagement.
+    pub const CAT_DELIVERY: u64 = 0;
+    /// The origin received the engagement.
+    pub const CAT_RECEIVE: u64 = 1;
+    /// The origin is a website, identified by its base URL.
+    pub const TYPE_WEBSITE: u64 = 1;
+
+    /// A website origin, identified by `base_url`.
+    pub fn website(cat: u64, base_url: String) -> Self {
+        Self {
+            cat,
+            origin_type: Self::TYPE_WEBSITE,
+            details: CborValue::Map(BTreeMap::from([(
+                CborValue::Text("baseUrl".into()),
+                CborValue::Text(base_url),
+            )])),
+        }
+    }
+
+    /// The base URL of a website origin.
+    pub fn base_url(&self) -> Option<&str> {
+        match &self.details {
+            CborValue::Map(details) => match details.get(&CborValue::Text("baseUrl".into())) {
+                Some(CborValue::Text(base_url)) => Some(base_url),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl From<OriginInfo> for CborValue {
+    fn from(o: OriginInfo) -> CborValue {
+        CborValue::Map(BTreeMap::from([
+            (CborValue::Text("cat".into()), o.cat.into()),
+            (CborValue::Text("type".into()), o.origin_type.into()),
+            (CborValue::Text("details".into()), o.details),
+        ]))
+    }
+}
+
+impl TryFrom<CborValue> for OriginInfo {
+    type Error = Error;
+
+    fn try_from(v: CborValue) -> Result<Self, Error> {
+        let CborValue::Map(mut map) = v else {
+            return Err(Error::Malformed);
+        };
+        let mut uint = |key: &str| match map.remove(&CborValue::Text(key.into())) {
+            Some(CborValue::Integer(i)) => u64::try_from(i).map_err(|_| Error::Malformed),
+            _ => Err(Error::Malformed),
+        };
+        let cat = uint("cat")?;
+        let origin_type = uint("type")?;
+        let details = map
+            .remove(&CborValue::Text("details".into()))
+            .ok_or(Error::Malformed)?;
+        Ok(OriginInfo {
+            cat,
+            origin_type,
+            details,
+        })
+    }
+}
+
 impl Tag24<DeviceEngagement> {
     const BASE64_CONFIG: base64::Config = base64::Config::new(base64::CharacterSet::UrlSafe, false);
 
@@ -178,14 +436,30 @@ impl Tag24<DeviceEngagement> {
     }
 
     pub fn from_qr_code_uri(qr_code_uri: &str) -> anyhow::Result<Self> {
+        Self::parse_qr_code_uri(qr_code_uri).map_err(Into::into)
+    }
+
+    fn parse_qr_code_uri(qr_code_uri: &str) -> Result<Self, EngagementError> {
         let encoded_de = qr_code_uri
             .strip_prefix("mdoc:")
-            .ok_or_else(|| anyhow::anyhow!("qr code has invalid prefix"))?;
+            .ok_or(EngagementError::WrongScheme)?;
         let decoded_de = base64::decode_config(encoded_de, Self::BASE64_CONFIG)?;
-        Tag24::<DeviceEngagement>::from_bytes(decoded_de).map_err(Into::into)
+        // Decode in two steps so that structural errors in the engagement are reported as such,
+        // rather than as CBOR errors.
+        let cbor: CborValue = serde_cbor::from_slice(&decoded_de)?;
+        DeviceEngagement::try_from(cbor)?;
+        Ok(Tag24::from_bytes(decoded_de).map_err(Error::from)?)
+    }
+}
+
+impl DeviceEngagement {
+    /// Parse a DeviceEngagement from the `mdoc:` URI encoded in a QR code.
+    pub fn from_qr_uri(qr_code_uri: &str) -> Result<Self, EngagementError> {
+        Tag24::<DeviceEngagement>::parse_qr_code_uri(qr_code_uri).map(Tag24::into_inner)
     }
 }
 
+#[cfg(feature = "transports")]
 impl DeviceRetrievalMethod {
     pub fn version(&self) -> u64 {
         1
@@ -200,6 +474,7 @@ impl DeviceRetrievalMethod {
     }
 }
 
+#[cfg(feature = "transports")]
 impl TryFrom<CborValue> for DeviceRetrievalMethod {
     type Error = Error;
     fn try_from(value: CborValue) -> Result<Self, Self::Error> {
@@ -227,6 +502,7 @@ impl TryFrom<CborValue> for DeviceRetrievalMethod {
     }
 }
 
+#[cfg(feature = "transports")]
 impl From<DeviceRetrievalMethod> for CborValue {
     fn from(drm: DeviceRetrievalMethod) -> Self {
         let transport_type = drm.transport_type().into();
@@ -240,6 +516,7 @@ impl From<DeviceRetrievalMethod> for CborValue {
     }
 }
 
+#[cfg(feature = "transports")]
 impl TryFrom<CborValue> for BleOptions {
     type Error = Error;
 
@@ -288,6 +565,7 @@ impl TryFrom<CborValue> for BleOptions {
     }
 }
 
+#[cfg(feature = "transports")]
 impl From<BleOptions> for CborValue {
     fn from(o: BleOptions) -> CborValue {
         let mut map = BTreeMap::new();
@@ -328,6 +606,7 @@ impl From<BleOptions> for CborValue {
     }
 }
 
+#[cfg(feature = "transports")]
 impl TryFrom<CborValue> for WifiOptions {
     type Error = Error;
 
@@ -409,6 +688,7 @@ impl TryFrom<CborValue> for WifiOptions {
     }
 }
 
+#[cfg(feature = "transports")]
 impl From<WifiOptions> for CborValue {
     fn from(o: WifiOptions) -> CborValue {
         let mut map = BTreeMap::<CborValue, CborValue>::new();
@@ -429,22 +709,26 @@ impl From<WifiOptions> for CborValue {
     }
 }
 
+impl From<ServerRetrievalInformation> for CborValue {
+    fn from(info: ServerRetrievalInformation) -> CborValue {
+        CborValue::Array(vec![
+            info.version.into(),
+            info.url.into(),
+            info.token.into(),
+        ])
+    }
+}
+
 impl From<ServerRetrievalMethods> for CborValue {
     fn from(m: ServerRetrievalMethods) -> CborValue {
         let mut map = BTreeMap::<CborValue, CborValue>::new();
 
-        if let Some((x, y, z)) = m.web_api {
-            map.insert(
-                "webApi".to_string().into(),
-                CborValue::Array(vec![x.into(), y.into(), z.into()]),
-            );
+        if let Some(web_api) = m.web_api {
+            map.insert("webApi".to_string().into(), web_api.into());
         }
 
-        if let Some((x, y, z)) = m.oidc {
-            map.insert(
-                "oidc".to_string().into(),
-                CborValue::Array(vec![x.into(), y.into(), z.into()]),
-            );
+        if let Some(oidc) = m.oidc {
+            map.insert("oidc".to_string().into(), oidc.into());
         }
 
         CborValue::Map(map)
@@ -454,10 +738,14 @@ impl From<ServerRetrievalMethods> for CborValue {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::definitions::device_key::cose_key::OKPCurve;
+    #[cfg(feature = "transports")]
     use crate::definitions::session::create_p256_ephemeral_keys;
-    use uuid::Uuid;
+    #[cfg(feature = "transports")]
+    use nfc_options::{CommandDataLength, ResponseDataLength};
 
     #[test]
+    #[cfg(feature = "transports")]
     fn device_engagement_cbor_roundtrip() {
         let key_pair = create_p256_ephemeral_keys().unwrap();
         let public_key = Tag24::new(key_pair.1).unwrap();
@@ -478,6 +766,264 @@ mod test {
             device_retrieval_methods,
             server_retrieval_methods: None,
             protocol_info: None,
+            origin_infos: None,
+        };
+
+        let bytes = serde_cbor::to_vec(&device_engagement).unwrap();
+        let roundtripped = serde_cbor::from_slice(&bytes).unwrap();
+
+        assert_eq!(device_engagement, roundtripped)
+    }
+
+    #[test]
+    fn origin_info_cbor() {
+        // {"cat": 1, "type": 1, "details": {"baseUrl": "https://verifier.example.com"}}
+        let cbor = hex::decode(
+            "a363636174016474797065016764657461696c73a16762617365\
+             55726c781c68747470733a2f2f76657269666965722e6578616d706c652e636f6d",
+        )
+        .unwrap();
+        let origin_info: OriginInfo = serde_cbor::from_slice(&cbor).unwrap();
+        assert_eq!(
+            origin_info,
+            OriginInfo::website(
+                OriginInfo::CAT_RECEIVE,
+                "https://verifier.example.com".into()
+            )
+        );
+        assert_eq!(serde_cbor::to_vec(&origin_info).unwrap(), cbor);
+    }
+
+    #[test]
+    fn device_engagement_origin_infos() {
+        let key_pair = crate::definitions::session::create_p256_ephemeral_keys().unwrap();
+        let device_engagement = DeviceEngagement {
+            version: "1.0".into(),
+            security: Security(1, Tag24::new(key_pair.1).unwrap()),
+            #[cfg(feature = "transports")]
+            device_retrieval_methods: None,
+            server_retrieval_methods: None,
+            protocol_info: None,
+            origin_infos: Some(NonEmptyVec::new(OriginInfo::website(
+                OriginInfo::CAT_RECEIVE,
+                "https://verifier.example.com".into(),
+            ))),
+        };
+
+        let bytes = serde_cbor::to_vec(&device_engagement).unwrap();
+        let roundtripped: DeviceEngagement = serde_cbor::from_slice(&bytes).unwrap();
+        assert_eq!(device_engagement, roundtripped);
+
+        assert_eq!(
+            roundtripped.verify_origin("https://verifier.example.com"),
+            Ok(())
+        );
+        assert_eq!(
+            roundtripped.verify_origin("https://attacker.example.com"),
+            Err(Error::OriginMismatch("https://verifier.example.com".into()))
+        );
+        assert_eq!(
+            DeviceEngagement {
+                origin_infos: None,
+                ..roundtripped
+            }
+            .verify_origin("https://verifier.example.com"),
+            Err(Error::MissingOriginInfo)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "transports")]
+    fn builder() {
+        use crate::definitions::device_key::cose_key::{EC2Curve, EC2Y};
+
+        // The ephemeral device key and BLE options of the engagement in ISO/IEC 18013-5 Annex D.
+        let public_key = CoseKey::EC2 {
+            crv: EC2Curve::P256,
+            x: hex::decode("5a88d182bce5f42efa59943f33359d2e8a968ff289d93e5fa444b624343167fe")
+                .unwrap(),
+            y: EC2Y::Value(
+                hex::decode("b16e8cf858ddc7690407ba61d4c338237a8cfcf3de6aa672fc60a557aa32fc67")
+                    .unwrap(),
+            ),
+        };
+        let ble_option = BleOptions {
+            peripheral_server_mode: None,
+            central_client_mode: Some(CentralClientMode {
+                uuid: Uuid::parse_str("45efef74-2b2c-4837-a9a3-b0e1d05a6917").unwrap(),
+            }),
+        };
+
+        let device_engagement = DeviceEngagement::builder()
+            .security(1, public_key.clone())
+            .retrieval_methods(NonEmptyVec::new(DeviceRetrievalMethod::BLE(ble_option)))
+            .build()
+            .unwrap();
+
+        let bytes = serde_cbor::to_vec(&device_engagement).unwrap();
+        assert_eq!(
+            hex::encode(&bytes),
+            "a30063312e30018201d818584ba4010220012158205a88d182bce5f42efa59943f33359d2e8a968ff2\
+             89d93e5fa444b624343167fe225820b16e8cf858ddc7690407ba61d4c338237a8cfcf3de6aa672fc60a5\
+             57aa32fc670281830201a300f401f50b5045efef742b2c4837a9a3b0e1d05a6917"
+        );
+        let roundtripped: DeviceEngagement = serde_cbor::from_slice(&bytes).unwrap();
+        assert_eq!(roundtripped.security.1.as_ref(), &public_key);
+
+        assert_eq!(
+            DeviceEngagement::builder().build(),
+            Err(Error::MissingParameter("security"))
+        );
+    }
+
+    #[test]
+    fn security_cipher_suite() {
+        let (_, e_device_key_pub) =
+            crate::definitions::session::create_p256_ephemeral_keys().unwrap();
+        let security = Security::new(Tag24::new(e_device_key_pub.clone()).unwrap()).unwrap();
+        assert_eq!(security.cipher_suite(), Security::CIPHER_SUITE_1);
+        assert_eq!(security.validate(), Ok(()));
+        assert_eq!(
+            Security(2, security.1.clone()).validate(),
+            Err(Error::UnsupportedCipherSuite(2))
+        );
+
+        let ed25519 = CoseKey::OKP {
+            crv: OKPCurve::Ed25519,
+            x: vec![0; 32],
+        };
+        assert_eq!(
+            Security::new(Tag24::new(ed25519.clone()).unwrap()),
+            Err(Error::UnsupportedCurve)
+        );
+        assert_eq!(
+            Security(1, Tag24::new(ed25519).unwrap()).validate(),
+            Err(Error::UnsupportedCurve)
+        );
+
+        let x25519 = CoseKey::OKP {
+            crv: OKPCurve::X25519,
+            x: vec![0; 32],
+        };
+        assert_eq!(
+            Security::new(Tag24::new(x25519.clone()).unwrap()),
+            Err(Error::UnsupportedCurve)
+        );
+        assert_eq!(
+            DeviceEngagement::builder().security(1, x25519).build(),
+            Err(Error::UnsupportedCurve)
+        );
+        assert_eq!(
+            DeviceEngagement::builder()
+                .security(2, e_device_key_pub)
+                .build(),
+            Err(Error::UnsupportedCipherSuite(2))
+        );
+    }
+
+    #[test]
+    fn server_retrieval_methods_cbor_roundtrip() {
+        // The device engagement from ISO/IEC 18013-5 Annex D, with server retrieval methods added.
+        let spec_device_engagement = hex::decode(
+            "a30063312e30018201d818584ba4010220012158205a88d182bce5f42efa59943f33359d2e8a968ff2\
+             89d93e5fa444b624343167fe225820b16e8cf858ddc7690407ba61d4c338237a8cfcf3de6aa672fc60a5\
+             57aa32fc670281830201a300f401f50b5045efef742b2c4837a9a3b0e1d05a6917",
+        )
+        .unwrap();
+        let CborValue::Map(mut map) = serde_cbor::from_slice(&spec_device_engagement).unwrap()
+        else {
+            panic!("the device engagement is not a map");
+        };
+        let server_retrieval_methods = CborValue::Map(BTreeMap::from([
+            (
+                CborValue::Text("webApi".into()),
+                CborValue::Array(vec![
+                    1.into(),
+                    CborValue::Text("https://issuer.example.com/mdl".into()),
+                    CborValue::Text("web-api-token".into()),
+                ]),
+            ),
+            (
+                CborValue::Text("oidc".into()),
+                CborValue::Array(vec![
+                    1.into(),
+                    CborValue::Text("https://issuer.example.com/oidc".into()),
+                    CborValue::Text("oidc-token".into()),
+                ]),
+            ),
+        ]));
+        map.insert(CborValue::Integer(3), server_retrieval_methods.clone());
+        let bytes = serde_cbor::to_vec(&CborValue::Map(map)).unwrap();
+
+        let device_engagement: DeviceEngagement = serde_cbor::from_slice(&bytes).unwrap();
+        let methods = device_engagement.server_retrieval_methods.as_ref().unwrap();
+        assert_eq!(
+            methods.web_api,
+            Some(ServerRetrievalInformation {
+                version: 1,
+                url: "https://issuer.example.com/mdl".into(),
+                token: "web-api-token".into(),
+            })
+        );
+        assert_eq!(
+            methods.oidc.as_ref().map(|oidc| oidc.url.as_str()),
+            Some("https://issuer.example.com/oidc")
+        );
+
+        let CborValue::Map(mut reencoded) = CborValue::from(device_engagement.clone()) else {
+            panic!("the device engagement is not a map");
+        };
+        assert_eq!(
+            reencoded.remove(&CborValue::Integer(3)),
+            Some(server_retrieval_methods)
+        );
+        let roundtripped: DeviceEngagement =
+            serde_cbor::from_slice(&serde_cbor::to_vec(&device_engagement).unwrap()).unwrap();
+        assert_eq!(device_engagement, roundtripped);
+    }
+
+    #[test]
+    #[cfg(feature = "transports")]
+    fn nfc_retrieval_method_cbor() {
+        // [1, 1, {0: 255, 1: 256}]
+        let cbor = hex::decode("830101A20018FF01190100").unwrap();
+        let method: DeviceRetrievalMethod = serde_cbor::from_slice(&cbor).unwrap();
+        assert_eq!(
+            method,
+            DeviceRetrievalMethod::NFC(NfcOptions::new(
+                CommandDataLength::MIN,
+                ResponseDataLength::MIN
+            ))
+        );
+        assert_eq!(serde_cbor::to_vec(&method).unwrap(), cbor);
+    }
+
+    #[test]
+    #[cfg(feature = "transports")]
+    fn device_engagement_nfc_cbor_roundtrip() {
+        let key_pair = create_p256_ephemeral_keys().unwrap();
+        let public_key = Tag24::new(key_pair.1).unwrap();
+
+        let nfc_options = NfcOptions::new(
+            CommandDataLength::new(4096).unwrap(),
+            ResponseDataLength::MAX,
+        );
+        let mut device_retrieval_methods =
+            NonEmptyVec::new(DeviceRetrievalMethod::NFC(nfc_options));
+        device_retrieval_methods.push(DeviceRetrievalMethod::BLE(BleOptions {
+            peripheral_server_mode: None,
+            central_client_mode: Some(CentralClientMode {
+                uuid: Uuid::now_v1(&[0, 1, 2, 3, 4, 5]),
+            }),
+        }));
+
+        let device_engagement = DeviceEngagement {
+            version: "1.0".into(),
+            security: Security(1, public_key),
+            device_retrieval_methods: Some(device_retrieval_methods),
+            server_retrieval_methods: None,
+            protocol_info: None,
+            origin_infos: None,
         };
 
         let bytes = serde_cbor::to_vec(&device_engagement).unwrap();
@@ -486,14 +1032,92 @@ mod test {
         assert_eq!(device_engagement, roundtripped)
     }
 
+    #[test]
+    #[cfg(feature = "transports")]
+    fn device_engagement_ble_and_wifi_aware_qr_code_roundtrip() {
+        let key_pair = create_p256_ephemeral_keys().unwrap();
+        let public_key = Tag24::new(key_pair.1).unwrap();
+
+        let mut device_retrieval_methods =
+            NonEmptyVec::new(DeviceRetrievalMethod::BLE(BleOptions {
+                peripheral_server_mode: None,
+                central_client_mode: Some(CentralClientMode {
+                    uuid: Uuid::now_v1(&[0, 1, 2, 3, 4, 5]),
+                }),
+            }));
+        device_retrieval_methods.push(DeviceRetrievalMethod::WIFI(WifiOptions {
+            pass_phrase: Some("pass phrase".into()),
+            channel_info_operating_class: Some(81),
+            channel_info_channel_number: Some(6),
+            band_info: Some(ByteStr::from(vec![0x04])),
+        }));
+
+        let device_engagement = Tag24::new(DeviceEngagement {
+            version: "1.0".into(),
+            security: Security(1, public_key),
+            device_retrieval_methods: Some(device_retrieval_methods),
+            server_retrieval_methods: None,
+            protocol_info: None,
+            origin_infos: None,
+        })
+        .unwrap();
+
+        let qr_code_uri = device_engagement.to_qr_code_uri().unwrap();
+        let roundtripped = Tag24::<DeviceEngagement>::from_qr_code_uri(&qr_code_uri).unwrap();
+        assert_eq!(device_engagement.as_ref(), roundtripped.as_ref());
+        assert!(matches!(
+            roundtripped
+                .as_ref()
+                .device_retrieval_methods
+                .as_ref()
+                .map(|methods| methods.as_ref()),
+            Some([
+                DeviceRetrievalMethod::BLE(_),
+                DeviceRetrievalMethod::WIFI(_)
+            ])
+        ));
+    }
+
+    #[test]
+    fn device_engagement_qr_code_errors() {
+        assert!(matches!(
+            DeviceEngagement::from_qr_uri("https://example.com"),
+            Err(EngagementError::WrongScheme)
+        ));
+        assert!(matches!(
+            DeviceEngagement::from_qr_uri("mdoc:not base64!"),
+            Err(EngagementError::BadBase64(_))
+        ));
+        // 0xFF is a stray break code.
+        assert!(matches!(
+            DeviceEngagement::from_qr_uri("mdoc:_w"),
+            Err(EngagementError::CborDecode(_))
+        ));
+        // {0: "2.0"}
+        assert!(matches!(
+            DeviceEngagement::from_qr_uri("mdoc:oQBjMi4w"),
+            Err(EngagementError::UnsupportedVersion)
+        ));
+        // {0: "1.0"}, missing security.
+        assert!(matches!(
+            DeviceEngagement::from_qr_uri("mdoc:oQBjMS4w"),
+            Err(EngagementError::Invalid(Error::Malformed))
+        ));
+    }
+
     #[test]
     fn device_engagement_qr_code_roundtrip() {
         const EXAMPLE_QR_CODE: &str = "mdoc:owBjMS4wAYIB2BhYS6QBAiABIVgglyWXuAyJ6iRNc8OlYXenvkJt23rJPdtIhlawXqr-yf0iWCC1GQSH8tIwTYVwha_ZoPL20_saYXrGIbrCm133H0ki-QKBgwIBowD1AfQKUH2RiuAEbUVzrsrOiUnSPDw";
         let de = Tag24::<DeviceEngagement>::from_qr_code_uri(EXAMPLE_QR_CODE).unwrap();
         let roundtripped = de.to_qr_code_uri().unwrap();
         assert_eq!(EXAMPLE_QR_CODE, roundtripped);
+        assert_eq!(
+            &DeviceEngagement::from_qr_uri(EXAMPLE_QR_CODE).unwrap(),
+            de.as_ref()
+        );
     }
 
+    #[cfg(feature = "transports")]
     fn wifi_options_cbor_roundtrip_test(wifi_options: WifiOptions) {
         let bytes: Vec<u8> = serde_cbor::to_vec(&wifi_options).unwrap();
         let deserialized: WifiOptions = serde_cbor::from_slice(&bytes).unwrap();
@@ -501,6 +1125,7 @@ mod test {
     }
 
     #[test]
+    #[cfg(feature = "transports")]
     fn wifi_options_cbor_roundtrip_all_some() {
         let wifi_options: WifiOptions = WifiOptions {
             pass_phrase: Some(String::from("secret")),
@@ -513,6 +1138,7 @@ mod test {
     }
 
     #[test]
+    #[cfg(feature = "transports")]
     fn wifi_options_cbor_roundtrip_all_none() {
         let wifi_options: WifiOptions = WifiOptions {
             pass_phrase: None,
@@ -525,6 +1151,7 @@ mod test {
     }
 
     #[test]
+    #[cfg(feature = "transports")]
     fn wifi_options_cbor_roundtrip_even_some() {
         let wifi_options: WifiOptions = WifiOptions {
             pass_phrase: Some(String::from("secret number 2 with spaces and $#$@$!")),
@@ -537,6 +1164,7 @@ mod test {
     }
 
     #[test]
+    #[cfg(feature = "transports")]
     fn wifi_options_cbor_roundtrip_odd_some() {
         let wifi_options: WifiOptions = WifiOptions {
             pass_phrase: None,
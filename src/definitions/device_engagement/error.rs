@@ -29,6 +29,40 @@ pub enum Error {
     InvalidNfcCommandDataLengthError,
     #[error("NFC Response Data Length must be between 256 and 65536")]
     InvalidNfcResponseDataLengthError,
+    #[error("the engagement is not bound to a website origin")]
+    MissingOriginInfo,
+    #[error("the engagement is bound to a different origin: {0}")]
+    OriginMismatch(String),
+    #[error("missing parameter: '{0}'")]
+    MissingParameter(&'static str),
+    #[error("unsupported cipher suite: {0}")]
+    UnsupportedCipherSuite(u64),
+    #[error("the ephemeral device key is not on a curve of the cipher suite")]
+    UnsupportedCurve,
+}
+
+/// Errors that can occur when reading a DeviceEngagement from a scanned QR code.
+#[derive(Debug, thiserror::Error)]
+pub enum EngagementError {
+    #[error("QR code does not use the mdoc: scheme")]
+    WrongScheme,
+    #[error("QR code payload is not base64url encoded: {0}")]
+    BadBase64(#[from] base64::DecodeError),
+    #[error("QR code payload is not valid CBOR: {0}")]
+    CborDecode(#[from] SerdeCborError),
+    #[error("Expected isomdl version 1.0")]
+    UnsupportedVersion,
+    #[error(transparent)]
+    Invalid(Error),
+}
+
+impl From<Error> for EngagementError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::UnsupportedVersion => EngagementError::UnsupportedVersion,
+            e => EngagementError::Invalid(e),
+        }
+    }
 }
 
 impl From<CoseKeyError> for Error {
@@ -22,6 +22,26 @@ pub struct NfcOptions {
     max_len_response_data_field: ResponseDataLength,
 }
 
+impl NfcOptions {
+    pub fn new(
+        max_len_command_data_field: CommandDataLength,
+        max_len_response_data_field: ResponseDataLength,
+    ) -> Self {
+        Self {
+            max_len_command_data_field,
+            max_len_response_data_field,
+        }
+    }
+
+    pub fn max_len_command_data_field(&self) -> &CommandDataLength {
+        &self.max_len_command_data_field
+    }
+
+    pub fn max_len_response_data_field(&self) -> &ResponseDataLength {
+        &self.max_len_response_data_field
+    }
+}
+
 impl TryFrom<CborValue> for NfcOptions {
     type Error = Error;
 
@@ -1,4 +1,4 @@
-use aes::cipher::generic_array::{typenum::U8, GenericArray};
+use aes::cipher::generic_array::GenericArray;
 use cose_rs::algorithm::Algorithm;
 use p256::EncodedPoint;
 use serde::{Deserialize, Serialize};
@@ -89,6 +89,23 @@ impl CoseKey {
             _ => None,
         }
     }
+
+    /// The SEC1 encoding of an EC2 key, on whichever curve it is.
+    pub fn sec1_bytes(&self) -> Result<Vec<u8>, Error> {
+        match self {
+            CoseKey::EC2 {
+                x,
+                y: EC2Y::Value(y),
+                ..
+            } => Ok([&[0x04], x.as_slice(), y.as_slice()].concat()),
+            CoseKey::EC2 {
+                x,
+                y: EC2Y::SignBit(sign),
+                ..
+            } => Ok([&[if *sign { 0x03 } else { 0x02 }], x.as_slice()].concat()),
+            CoseKey::OKP { .. } => Err(Error::InvalidCoseKey),
+        }
+    }
 }
 
 impl From<CoseKey> for CborValue {
@@ -165,9 +182,15 @@ impl TryFrom<CoseKey> for EncodedPoint {
                 x,
                 y,
             } => {
+                if x.len() != 32 {
+                    return Err(Error::InvalidCoseKey);
+                }
                 let x_generic_array = GenericArray::from_slice(x.as_ref());
                 match y {
                     EC2Y::Value(y) => {
+                        if y.len() != 32 {
+                            return Err(Error::InvalidCoseKey);
+                        }
                         let y_generic_array = GenericArray::from_slice(y.as_ref());
 
                         Ok(EncodedPoint::from_affine_coordinates(
@@ -190,13 +213,7 @@ impl TryFrom<CoseKey> for EncodedPoint {
                     }
                 }
             }
-            CoseKey::OKP { crv: _, x } => {
-                let x_generic_array: GenericArray<_, U8> =
-                    GenericArray::clone_from_slice(&x[0..42]);
-                let encoded = EncodedPoint::from_bytes(x_generic_array)
-                    .map_err(|_e| Error::InvalidCoseKey)?;
-                Ok(encoded)
-            }
+            // An octet key pair is never a point on P-256.
             _ => Err(Error::InvalidCoseKey),
         }
     }
@@ -414,4 +431,56 @@ mod test {
             "cbor encoding roundtrip failed"
         );
     }
+
+    #[test]
+    fn malformed_encoded_point() {
+        for key in [
+            CoseKey::EC2 {
+                crv: EC2Curve::P256,
+                x: vec![1; 31],
+                y: EC2Y::Value(vec![1; 32]),
+            },
+            CoseKey::EC2 {
+                crv: EC2Curve::P256,
+                x: vec![1; 32],
+                y: EC2Y::Value(vec![]),
+            },
+            CoseKey::EC2 {
+                crv: EC2Curve::P256,
+                x: vec![],
+                y: EC2Y::SignBit(true),
+            },
+            CoseKey::OKP {
+                crv: OKPCurve::X25519,
+                x: vec![1; 32],
+            },
+        ] {
+            assert!(EncodedPoint::try_from(key).is_err());
+        }
+    }
+
+    #[test]
+    fn sec1_bytes() {
+        let key_bytes = <Vec<u8>>::from_hex(EC_P256).expect("unable to convert cbor hex to bytes");
+        let key: CoseKey = serde_cbor::from_slice(&key_bytes).unwrap();
+        assert_eq!(
+            key.sec1_bytes().unwrap(),
+            EncodedPoint::try_from(key).unwrap().as_bytes()
+        );
+
+        let compressed = CoseKey::EC2 {
+            crv: EC2Curve::P384,
+            x: vec![1; 48],
+            y: EC2Y::SignBit(true),
+        };
+        assert_eq!(
+            compressed.sec1_bytes().unwrap(),
+            [&[3], &[1; 48][..]].concat()
+        );
+        let okp = CoseKey::OKP {
+            crv: OKPCurve::Ed25519,
+            x: vec![1; 32],
+        };
+        assert!(okp.sec1_bytes().is_err());
+    }
 }
@@ -1,17 +1,30 @@
-use crate::definitions::helpers::{NonEmptyMap, NonEmptyVec, Tag24};
+use crate::definitions::helpers::{tag24, NonEmptyMap, NonEmptyVec, Tag24};
+use crate::definitions::session::SessionTranscript;
 use cose_rs::CoseSign1;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::ops::Deref;
 
 pub type ItemsRequestBytes = Tag24<ItemsRequest>;
 pub type DocType = String;
 pub type NameSpace = String;
 pub type IntentToRetain = bool;
 pub type DataElementIdentifier = String;
-pub type DataElements = NonEmptyMap<DataElementIdentifier, IntentToRetain>;
 pub type Namespaces = NonEmptyMap<NameSpace, DataElements>;
 pub type ReaderAuth = CoseSign1;
 
+/// The element identifier that requests every element of a namespace.
+pub const WILDCARD: &str = "*";
+
+/// The elements requested from a namespace.
+///
+/// The [WILDCARD] element identifier requests every element that the device holds in the
+/// namespace, with its intent to retain. The device still asks the holder for consent to each
+/// element.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DataElements(BTreeMap<DataElementIdentifier, IntentToRetain>);
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DeviceRequest {
@@ -37,8 +50,243 @@ pub struct ItemsRequest {
     pub request_info: Option<BTreeMap<String, serde_cbor::Value>>,
 }
 
+pub type ReaderAuthenticationBytes<S> = Tag24<ReaderAuthentication<S>>;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReaderAuthentication<S: SessionTranscript>(
+    &'static str,
+    // See https://github.com/serde-rs/serde/issues/1296.
+    #[serde(bound = "")] S,
+    ItemsRequestBytes,
+);
+
+impl From<BTreeMap<DataElementIdentifier, IntentToRetain>> for DataElements {
+    fn from(elements: BTreeMap<DataElementIdentifier, IntentToRetain>) -> Self {
+        Self(elements)
+    }
+}
+
+impl Deref for DataElements {
+    type Target = BTreeMap<DataElementIdentifier, IntentToRetain>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// The largest NN of an `age_over_NN` element.
+const MAX_AGE_OVER: u8 = 99;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("age_over_NN is only defined for NN from 0 to {MAX_AGE_OVER}, not {0}")]
+    AgeOutOfRange(u8),
+    #[error("a device request must contain at least one document request")]
+    NoDocRequests,
+    #[error("{0} was added before any {1}")]
+    OutOfOrder(&'static str, &'static str),
+    #[error("the request for {0} contains no elements")]
+    Empty(String),
+    #[error("unable to encode the items request: {0}")]
+    Tag24(#[from] tag24::Error),
+}
+
+/// Builder for a [DeviceRequest], adding elements to the most recently added namespace of the
+/// most recently added document request.
+#[derive(Debug, Clone, Default)]
+pub struct Builder {
+    doc_requests: Vec<(DocType, BTreeMap<NameSpace, DataElements>)>,
+    namespace: Option<NameSpace>,
+    error: Option<(&'static str, &'static str)>,
+}
+
 impl DeviceRequest {
     pub const VERSION: &'static str = "1.0";
+
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+}
+
+impl ItemsRequest {
+    /// Whether the reader intends to retain `element_identifier` from `namespace`, or `None` if it
+    /// was not requested.
+    pub fn intent_to_retain(
+        &self,
+        namespace: &str,
+        element_identifier: &str,
+    ) -> Option<IntentToRetain> {
+        let elements = self.namespaces.get(namespace)?;
+        elements
+            .get(element_identifier)
+            .or_else(|| elements.get(WILDCARD))
+            .copied()
+    }
+
+    /// The requested elements that the reader intends to retain, by namespace and element
+    /// identifier. A wildcard is yielded as [WILDCARD].
+    pub fn retained_elements(&self) -> impl Iterator<Item = (&NameSpace, &DataElementIdentifier)> {
+        self.namespaces.iter().flat_map(|(namespace, elements)| {
+            elements
+                .iter()
+                .filter(|(_, intent_to_retain)| **intent_to_retain)
+                .map(move |(element_identifier, _)| (namespace, element_identifier))
+        })
+    }
+}
+
+impl Builder {
+    /// Start a request for a document of `doc_type`.
+    pub fn doc_request(mut self, doc_type: DocType) -> Self {
+        self.doc_requests.push((doc_type, BTreeMap::new()));
+        self.namespace = None;
+        self
+    }
+
+    /// Start requesting elements from `namespace` of the current document.
+    pub fn namespace(mut self, namespace: NameSpace) -> Self {
+        match self.doc_requests.last_mut() {
+            Some((_, namespaces)) => {
+                namespaces
+                    .entry(namespace.clone())
+                    .or_insert_with(|| DataElements(BTreeMap::new()));
+                self.namespace = Some(namespace);
+            }
+            None => {
+                self.error
+                    .get_or_insert(("a namespace", "document request"));
+            }
+        }
+        self
+    }
+
+    /// Request every element of `namespace` of the current document, with `intent_to_retain`.
+    ///
+    /// Elements can not be added to `namespace` afterwards, as all of them are requested.
+    pub fn request_all(mut self, namespace: NameSpace, intent_to_retain: IntentToRetain) -> Self {
+        match self.doc_requests.last_mut() {
+            Some((_, namespaces)) => {
+                namespaces.insert(namespace, DataElements::all(intent_to_retain));
+            }
+            None => {
+                self.error
+                    .get_or_insert(("a namespace", "document request"));
+            }
+        }
+        self.namespace = None;
+        self
+    }
+
+    /// Request `element_identifier` from the current namespace.
+    pub fn element(
+        mut self,
+        element_identifier: DataElementIdentifier,
+        intent_to_retain: IntentToRetain,
+    ) -> Self {
+        let elements = self
+            .namespace
+            .as_ref()
+            .zip(self.doc_requests.last_mut())
+            .and_then(|(namespace, (_, namespaces))| namespaces.get_mut(namespace))
+            .filter(|elements| !elements.is_wildcard());
+        match elements {
+            Some(elements) => {
+                elements.insert(element_identifier, intent_to_retain);
+            }
+            None => {
+                self.error.get_or_insert(("an element", "namespace"));
+            }
+        }
+        self
+    }
+
+    pub fn build(self) -> Result<DeviceRequest, Error> {
+        if let Some((added, missing)) = self.error {
+            return Err(Error::OutOfOrder(added, missing));
+        }
+        let doc_requests = self
+            .doc_requests
+            .into_iter()
+            .map(|(doc_type, namespaces)| {
+                let namespaces = namespaces
+                    .into_iter()
+                    .map(|(namespace, elements)| match elements.is_empty() {
+                        true => Err(Error::Empty(namespace)),
+                        false => Ok((namespace, elements)),
+                    })
+                    .collect::<Result<BTreeMap<_, _>, _>>()?;
+                let namespaces =
+                    NonEmptyMap::maybe_new(namespaces).ok_or(Error::Empty(doc_type.clone()))?;
+                Ok(DocRequest {
+                    items_request: Tag24::new(ItemsRequest {
+                        doc_type,
+                        namespaces,
+                        request_info: None,
+                    })?,
+                    reader_auth: None,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(DeviceRequest {
+            version: DeviceRequest::VERSION.to_string(),
+            doc_requests: doc_requests.try_into().map_err(|_| Error::NoDocRequests)?,
+        })
+    }
+}
+
+impl DataElements {
+    pub fn new(
+        element_identifier: DataElementIdentifier,
+        intent_to_retain: IntentToRetain,
+    ) -> Self {
+        Self(BTreeMap::from([(element_identifier, intent_to_retain)]))
+    }
+
+    /// Request every element of the namespace, with `intent_to_retain`.
+    pub fn all(intent_to_retain: IntentToRetain) -> Self {
+        Self::new(WILDCARD.into(), intent_to_retain)
+    }
+
+    /// Whether every element of the namespace is requested.
+    pub fn is_wildcard(&self) -> bool {
+        self.0.contains_key(WILDCARD)
+    }
+
+    /// Whether `element_identifier` is requested, explicitly or by a wildcard.
+    pub fn includes(&self, element_identifier: &str) -> bool {
+        self.is_wildcard() || self.0.contains_key(element_identifier)
+    }
+
+    pub fn insert(
+        &mut self,
+        element_identifier: DataElementIdentifier,
+        intent_to_retain: IntentToRetain,
+    ) -> Option<IntentToRetain> {
+        self.0.insert(element_identifier, intent_to_retain)
+    }
+
+    pub fn into_inner(self) -> BTreeMap<DataElementIdentifier, IntentToRetain> {
+        self.0
+    }
+
+    /// Request the `age_over_NN` element of the mDL namespace, for `nn` from 0 to 99.
+    pub fn age_over(nn: u8, intent_to_retain: IntentToRetain) -> Result<Self, Error> {
+        Ok(Self::new(age_over_element(nn)?, intent_to_retain))
+    }
+}
+
+/// The identifier of the `age_over_NN` element of the mDL namespace, for `nn` from 0 to 99.
+pub fn age_over_element(nn: u8) -> Result<DataElementIdentifier, Error> {
+    if nn > MAX_AGE_OVER {
+        return Err(Error::AgeOutOfRange(nn));
+    }
+    Ok(format!("age_over_{nn:02}"))
+}
+
+impl<S: SessionTranscript> ReaderAuthentication<S> {
+    pub fn new(transcript: S, items_request: ItemsRequestBytes) -> Self {
+        Self("ReaderAuthentication", transcript, items_request)
+    }
 }
 
 #[cfg(test)]
@@ -71,4 +319,151 @@ mod test {
         let roundtripped = serde_cbor::to_vec(&req).unwrap();
         assert_eq!(bytes, roundtripped);
     }
+
+    #[test]
+    fn age_over() {
+        let elements = DataElements::age_over(18, false).unwrap();
+        assert_eq!(elements.get("age_over_18"), Some(&false));
+        assert_eq!(age_over_element(65).unwrap(), "age_over_65");
+        assert_eq!(age_over_element(5).unwrap(), "age_over_05");
+        assert!(DataElements::age_over(100, false).is_err());
+    }
+
+    #[test]
+    fn builder() {
+        let request = DeviceRequest::builder()
+            .doc_request("org.iso.18013.5.1.mDL".into())
+            .namespace("org.iso.18013.5.1".into())
+            .element("family_name".into(), false)
+            .element("given_name".into(), false)
+            .element("portrait".into(), true)
+            .namespace("org.iso.18013.5.1.aamva".into())
+            .element("DHS_compliance".into(), false)
+            .element("organ_donor".into(), true)
+            .build()
+            .unwrap();
+
+        assert_eq!(request.doc_requests.len(), 1);
+        let items_request = request.doc_requests.first().items_request.as_ref();
+        assert_eq!(items_request.doc_type, "org.iso.18013.5.1.mDL");
+        let elements = items_request
+            .namespaces
+            .values()
+            .map(|elements| elements.len())
+            .sum::<usize>();
+        assert_eq!(elements, 5);
+        assert_eq!(
+            items_request.namespaces["org.iso.18013.5.1"].get("portrait"),
+            Some(&true)
+        );
+        assert_eq!(
+            items_request.namespaces["org.iso.18013.5.1.aamva"].get("DHS_compliance"),
+            Some(&false)
+        );
+
+        let bytes = serde_cbor::to_vec(&request).unwrap();
+        let roundtripped: DeviceRequest = serde_cbor::from_slice(&bytes).unwrap();
+        assert_eq!(serde_cbor::to_vec(&roundtripped).unwrap(), bytes);
+    }
+
+    #[test]
+    fn intent_to_retain() {
+        let request = DeviceRequest::builder()
+            .doc_request("org.iso.18013.5.1.mDL".into())
+            .namespace("org.iso.18013.5.1".into())
+            .element("age_over_21".into(), false)
+            .element("family_name".into(), true)
+            .build()
+            .unwrap();
+
+        let items_request = request.doc_requests.first().items_request.as_ref();
+        assert_eq!(
+            items_request.intent_to_retain("org.iso.18013.5.1", "age_over_21"),
+            Some(false)
+        );
+        assert_eq!(
+            items_request.intent_to_retain("org.iso.18013.5.1", "family_name"),
+            Some(true)
+        );
+        assert_eq!(
+            items_request.intent_to_retain("org.iso.18013.5.1", "portrait"),
+            None
+        );
+        assert_eq!(
+            items_request.retained_elements().collect::<Vec<_>>(),
+            vec![(&"org.iso.18013.5.1".to_string(), &"family_name".to_string())]
+        );
+    }
+
+    #[test]
+    fn request_all() {
+        let request = DeviceRequest::builder()
+            .doc_request("org.iso.18013.5.1.mDL".into())
+            .request_all("org.iso.18013.5.1".into(), true)
+            .namespace("org.iso.18013.5.1.aamva".into())
+            .element("DHS_compliance".into(), false)
+            .build()
+            .unwrap();
+
+        let items_request = request.doc_requests.first().items_request.as_ref();
+        let elements = &items_request.namespaces["org.iso.18013.5.1"];
+        assert!(elements.is_wildcard());
+        assert!(elements.includes("family_name"));
+        assert_eq!(
+            items_request.intent_to_retain("org.iso.18013.5.1", "portrait"),
+            Some(true)
+        );
+        assert!(!items_request.namespaces["org.iso.18013.5.1.aamva"].includes("organ_donor"));
+
+        // A wildcard is encoded as the element identifier "*".
+        let bytes = serde_cbor::to_vec(&DataElements::all(false)).unwrap();
+        assert_eq!(bytes, [0xa1, 0x61, b'*', 0xf4]);
+        let roundtripped: DeviceRequest =
+            serde_cbor::from_slice(&serde_cbor::to_vec(&request).unwrap()).unwrap();
+        assert!(roundtripped
+            .doc_requests
+            .first()
+            .items_request
+            .as_ref()
+            .namespaces["org.iso.18013.5.1"]
+            .is_wildcard());
+
+        // Elements can not be added to a namespace whose elements are all requested.
+        assert!(matches!(
+            DeviceRequest::builder()
+                .doc_request("org.iso.18013.5.1.mDL".into())
+                .request_all("org.iso.18013.5.1".into(), false)
+                .element("family_name".into(), false)
+                .build(),
+            Err(Error::OutOfOrder(..))
+        ));
+    }
+
+    #[test]
+    fn invalid_builder() {
+        assert!(matches!(
+            DeviceRequest::builder().build(),
+            Err(Error::NoDocRequests)
+        ));
+        assert!(matches!(
+            DeviceRequest::builder()
+                .namespace("org.iso.18013.5.1".into())
+                .build(),
+            Err(Error::OutOfOrder(..))
+        ));
+        assert!(matches!(
+            DeviceRequest::builder()
+                .doc_request("org.iso.18013.5.1.mDL".into())
+                .element("family_name".into(), false)
+                .build(),
+            Err(Error::OutOfOrder(..))
+        ));
+        assert!(matches!(
+            DeviceRequest::builder()
+                .doc_request("org.iso.18013.5.1.mDL".into())
+                .namespace("org.iso.18013.5.1".into())
+                .build(),
+            Err(Error::Empty(_))
+        ));
+    }
 }
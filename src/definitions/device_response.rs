@@ -32,14 +32,14 @@ pub type Errors = NonEmptyMap<String, NonEmptyMap<String, DocumentErrorCode>>;
 pub type DocumentErrors = NonEmptyVec<DocumentError>;
 pub type DocumentError = BTreeMap<String, DocumentErrorCode>;
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(try_from = "i128", into = "i128")]
 pub enum DocumentErrorCode {
     DataNotReturned,
     ApplicationSpecific(i128),
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(try_from = "u64", into = "u64")]
 pub enum Status {
     OK,
@@ -50,6 +50,21 @@ pub enum Status {
 
 impl DeviceResponse {
     pub const VERSION: &'static str = "1.0";
+
+    /// The returned documents, empty if the device returned none.
+    pub fn documents(&self) -> &[Document] {
+        self.documents.as_deref().unwrap_or_default()
+    }
+
+    /// The requested documents that the device did not return, with the reason for each.
+    pub fn document_errors(&self) -> &[DocumentError] {
+        self.document_errors.as_deref().unwrap_or_default()
+    }
+
+    /// The status of the response as a whole.
+    pub fn status(&self) -> Status {
+        self.status
+    }
 }
 
 impl From<DocumentErrorCode> for i128 {
@@ -100,7 +115,8 @@ impl TryFrom<u64> for Status {
 
 #[cfg(test)]
 mod test {
-    use super::DeviceResponse;
+    use super::{DeviceResponse, DocumentErrorCode, Status};
+    use crate::definitions::helpers::NonEmptyVec;
     use hex::FromHex;
 
     static DEVICE_RESPONSE_CBOR: &str = include_str!("../../test/definitions/device_response.cbor");
@@ -118,4 +134,43 @@ mod test {
             "original cbor and re-serialized DeviceResponse do not match"
         );
     }
+
+    #[test]
+    fn iterate_documents() {
+        let cbor_bytes =
+            <Vec<u8>>::from_hex(DEVICE_RESPONSE_CBOR).expect("unable to convert cbor hex to bytes");
+        let mut response: DeviceResponse =
+            serde_cbor::from_slice(&cbor_bytes).expect("unable to decode cbor as a DeviceResponse");
+        assert_eq!(response.status(), Status::OK);
+        assert_eq!(response.documents().len(), 1);
+        assert!(response.document_errors().is_empty());
+
+        let mut vehicle = response.documents()[0].clone();
+        vehicle.doc_type = "org.iso.7367.1.mVRC".into();
+        let mut documents = NonEmptyVec::new(response.documents()[0].clone());
+        documents.push(vehicle);
+        response.documents = Some(documents);
+        response.document_errors = Some(NonEmptyVec::new(
+            [(
+                "org.iso.23220.photoid.1".to_string(),
+                DocumentErrorCode::DataNotReturned,
+            )]
+            .into_iter()
+            .collect(),
+        ));
+
+        let doc_types: Vec<&str> = response
+            .documents()
+            .iter()
+            .map(|document| document.doc_type.as_str())
+            .collect();
+        assert_eq!(doc_types, ["org.iso.18013.5.1.mDL", "org.iso.7367.1.mVRC"]);
+        assert_eq!(
+            response.document_errors()[0]["org.iso.23220.photoid.1"],
+            DocumentErrorCode::DataNotReturned
+        );
+
+        response.documents = None;
+        assert!(response.documents().is_empty());
+    }
 }
@@ -3,8 +3,10 @@ use crate::definitions::{
     session::SessionTranscript,
 };
 use cose_rs::sign1::CoseSign1;
+use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
 use serde_cbor::{Error as CborError, Value as CborValue};
+use sha2::Sha256;
 use std::collections::BTreeMap;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -50,8 +52,138 @@ impl<S: SessionTranscript> DeviceAuthentication<S> {
     }
 }
 
+/// The COSE algorithm identifier of HMAC 256/256.
+const HMAC_256_256: i128 = 5;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("Unable to encode value as CBOR: {0}")]
     UnableToEncode(CborError),
+    #[error("Invalid COSE_Mac0: {0}")]
+    InvalidMac0(&'static str),
+    #[error("Unable to verify the device MAC")]
+    MacMismatch,
+}
+
+/// Create a COSE_Mac0 over the detached `device_authentication_bytes`, with HMAC 256/256 keyed by
+/// `emac_key`.
+pub fn device_mac(emac_key: &[u8], device_authentication_bytes: &[u8]) -> Result<CborValue, Error> {
+    let protected = protected_header()?;
+    let tag = mac0(emac_key, &protected, device_authentication_bytes)?
+        .finalize()
+        .into_bytes()
+        .to_vec();
+    Ok(CborValue::Array(vec![
+        CborValue::Bytes(protected),
+        CborValue::Map(BTreeMap::new()),
+        CborValue::Null,
+        CborValue::Bytes(tag),
+    ]))
+}
+
+/// Verify a COSE_Mac0, created as by [device_mac], over the detached
+/// `device_authentication_bytes`.
+pub fn verify_device_mac(
+    device_mac: &CborValue,
+    emac_key: &[u8],
+    device_authentication_bytes: &[u8],
+) -> Result<(), Error> {
+    let (protected, tag) = match device_mac {
+        CborValue::Array(fields) => match fields.as_slice() {
+            [CborValue::Bytes(protected), CborValue::Map(_), CborValue::Null, CborValue::Bytes(tag)] => {
+                (protected, tag)
+            }
+            _ => {
+                return Err(Error::InvalidMac0(
+                    "expected a COSE_Mac0 with a detached payload",
+                ))
+            }
+        },
+        _ => return Err(Error::InvalidMac0("expected an array")),
+    };
+    if protected != &protected_header()? {
+        return Err(Error::InvalidMac0("unsupported algorithm"));
+    }
+    mac0(emac_key, protected, device_authentication_bytes)?
+        .verify_slice(tag)
+        .map_err(|_| Error::MacMismatch)
+}
+
+fn protected_header() -> Result<Vec<u8>, Error> {
+    let header: BTreeMap<CborValue, CborValue> =
+        [(CborValue::Integer(1), CborValue::Integer(HMAC_256_256))]
+            .into_iter()
+            .collect();
+    serde_cbor::to_vec(&header).map_err(Error::UnableToEncode)
+}
+
+/// HMAC 256/256 over the MAC_structure of a COSE_Mac0, as defined in RFC 8152 section 6.3.
+fn mac0(key: &[u8], protected: &[u8], payload: &[u8]) -> Result<Hmac<Sha256>, Error> {
+    let mac_structure = CborValue::Array(vec![
+        CborValue::Text("MAC0".into()),
+        CborValue::Bytes(protected.to_vec()),
+        CborValue::Bytes(vec![]),
+        CborValue::Bytes(payload.to_vec()),
+    ]);
+    let mut mac = Hmac::<Sha256>::new_from_slice(key)
+        .map_err(|_| Error::InvalidMac0("invalid MAC key length"))?;
+    mac.update(&serde_cbor::to_vec(&mac_structure).map_err(Error::UnableToEncode)?);
+    Ok(mac)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn device_mac_round_trip() {
+        let key = [7u8; 32];
+        let mac = device_mac(&key, b"payload").expect("unable to create device mac");
+        verify_device_mac(&mac, &key, b"payload").expect("unable to verify device mac");
+        assert!(matches!(
+            verify_device_mac(&mac, &key, b"tampered"),
+            Err(Error::MacMismatch)
+        ));
+        assert!(matches!(
+            verify_device_mac(&mac, &[8u8; 32], b"payload"),
+            Err(Error::MacMismatch)
+        ));
+    }
+
+    #[test]
+    fn altered_tag() {
+        let key = [7u8; 32];
+        let mac = device_mac(&key, b"payload").expect("unable to create device mac");
+        let CborValue::Array(fields) = mac else {
+            panic!("device mac is not an array");
+        };
+        let with_tag = |tag: Vec<u8>| {
+            let mut fields = fields.clone();
+            fields[3] = CborValue::Bytes(tag);
+            CborValue::Array(fields)
+        };
+        let CborValue::Bytes(tag) = &fields[3] else {
+            panic!("device mac has no tag");
+        };
+
+        // Any difference from the expected tag, wherever it is, fails verification, as does a
+        // tag of another length.
+        for index in [0, tag.len() - 1] {
+            let mut altered = tag.clone();
+            altered[index] ^= 1;
+            assert!(matches!(
+                verify_device_mac(&with_tag(altered), &key, b"payload"),
+                Err(Error::MacMismatch)
+            ));
+        }
+        for altered in [
+            tag[..tag.len() - 1].to_vec(),
+            [tag.as_slice(), &[0]].concat(),
+        ] {
+            assert!(matches!(
+                verify_device_mac(&with_tag(altered), &key, b"payload"),
+                Err(Error::MacMismatch)
+            ));
+        }
+    }
 }
@@ -0,0 +1,160 @@
+//! Rendering of CBOR structures in the diagnostic notation of RFC 8949, section 8, for debugging.
+use crate::definitions::device_request::DeviceRequest;
+use crate::definitions::{DeviceEngagement, DeviceResponse, SessionEstablishment};
+use serde::Serialize;
+use serde_cbor::Value as CborValue;
+use std::fmt::Write;
+
+/// The tag of an embedded CBOR data item, encoded in a byte string.
+const EMBEDDED_CBOR_TAG: u64 = 24;
+
+/// Render `value` in diagnostic notation, showing the CBOR embedded in each tag-24 byte string.
+pub fn diagnostic(value: &CborValue) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value);
+    out
+}
+
+fn to_diagnostic<T: Serialize>(value: &T) -> Result<String, serde_cbor::Error> {
+    let value: CborValue = serde_cbor::from_slice(&serde_cbor::to_vec(value)?)?;
+    Ok(diagnostic(&value))
+}
+
+fn write_value(out: &mut String, value: &CborValue) {
+    match value {
+        CborValue::Null => out.push_str("null"),
+        CborValue::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        CborValue::Integer(i) => {
+            let _ = write!(out, "{i}");
+        }
+        CborValue::Float(f) if f.is_nan() => out.push_str("NaN"),
+        CborValue::Float(f) if f.is_infinite() => {
+            out.push_str(if *f > 0.0 { "Infinity" } else { "-Infinity" })
+        }
+        CborValue::Float(f) => {
+            let _ = write!(out, "{f:?}");
+        }
+        CborValue::Bytes(bytes) => {
+            out.push_str("h'");
+            for byte in bytes {
+                let _ = write!(out, "{byte:02x}");
+            }
+            out.push('\'');
+        }
+        CborValue::Text(text) => {
+            out.push_str(&serde_json::to_string(text).unwrap_or_else(|_| format!("{text:?}")))
+        }
+        CborValue::Array(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_value(out, value);
+            }
+            out.push(']');
+        }
+        CborValue::Map(entries) => {
+            out.push('{');
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_value(out, key);
+                out.push_str(": ");
+                write_value(out, value);
+            }
+            out.push('}');
+        }
+        CborValue::Tag(EMBEDDED_CBOR_TAG, inner) => {
+            let embedded = match inner.as_ref() {
+                CborValue::Bytes(bytes) => serde_cbor::from_slice::<CborValue>(bytes).ok(),
+                _ => None,
+            };
+            out.push_str("24(");
+            match embedded {
+                Some(embedded) => {
+                    out.push_str("<<");
+                    write_value(out, &embedded);
+                    out.push_str(">>");
+                }
+                None => write_value(out, inner),
+            }
+            out.push(')');
+        }
+        CborValue::Tag(tag, inner) => {
+            let _ = write!(out, "{tag}(");
+            write_value(out, inner);
+            out.push(')');
+        }
+        _ => out.push_str("undefined"),
+    }
+}
+
+impl DeviceRequest {
+    /// This request in CBOR diagnostic notation.
+    pub fn diagnostic(&self) -> Result<String, serde_cbor::Error> {
+        to_diagnostic(self)
+    }
+}
+
+impl DeviceResponse {
+    /// This response in CBOR diagnostic notation.
+    pub fn diagnostic(&self) -> Result<String, serde_cbor::Error> {
+        to_diagnostic(self)
+    }
+}
+
+impl DeviceEngagement {
+    /// This device engagement in CBOR diagnostic notation.
+    pub fn diagnostic(&self) -> Result<String, serde_cbor::Error> {
+        to_diagnostic(self)
+    }
+}
+
+impl SessionEstablishment {
+    /// This session establishment message in CBOR diagnostic notation.
+    pub fn diagnostic(&self) -> Result<String, serde_cbor::Error> {
+        to_diagnostic(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn values() {
+        let embedded = serde_cbor::to_vec(&CborValue::Integer(1)).unwrap();
+        let value = CborValue::Array(vec![
+            CborValue::Integer(-10),
+            CborValue::Float(1.5),
+            CborValue::Bool(true),
+            CborValue::Null,
+            CborValue::Text("a\"b".into()),
+            CborValue::Bytes(vec![0x01, 0xff]),
+            CborValue::Map([(CborValue::Integer(1), CborValue::Text("one".into()))].into()),
+            CborValue::Tag(1004, Box::new(CborValue::Text("2020-01-01".into()))),
+            CborValue::Tag(24, Box::new(CborValue::Bytes(embedded))),
+            CborValue::Tag(24, Box::new(CborValue::Bytes(vec![0xff]))),
+        ]);
+        assert_eq!(
+            diagnostic(&value),
+            r#"[-10, 1.5, true, null, "a\"b", h'01ff', {1: "one"}, 1004("2020-01-01"), 24(<<1>>), 24(h'ff')]"#
+        );
+    }
+
+    #[test]
+    fn device_request() {
+        const HEX: &str = "A26776657273696F6E63312E306B646F63526571756573747381A16C6974656D7352657175657374D8185868A267646F6354797065756F72672E69736F2E31383031332E352E312E6D444C6A6E616D65537061636573A1716F72672E69736F2E31383031332E352E31A36B66616D696C795F6E616D65F46A676976656E5F6E616D65F46F646F63756D656E745F6E756D626572F4";
+        let request: DeviceRequest = serde_cbor::from_slice(&hex::decode(HEX).unwrap()).unwrap();
+        assert_eq!(
+            request.diagnostic().unwrap(),
+            concat!(
+                r#"{"version": "1.0", "docRequests": [{"itemsRequest": 24(<<{"docType": "#,
+                r#""org.iso.18013.5.1.mDL", "nameSpaces": {"org.iso.18013.5.1": {"#,
+                r#""given_name": false, "family_name": false, "document_number": false}}}>>)}]}"#
+            )
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! Canonical encoding of CBOR, as in
+//! [RFC 7049 section 3.9](https://www.rfc-editor.org/rfc/rfc7049#section-3.9), for structures
+//! that are signed or MACed, so that every implementation reconstructs the same bytes.
+use serde::Serialize;
+use serde_cbor::{Error, Value as CborValue};
+
+/// Encode `value` as canonical CBOR: integers, lengths and floats in their shortest form, only
+/// definite lengths, and the keys of each map sorted shortest first, then bytewise.
+///
+/// Embedded CBOR, in a byte string with tag number 24, is kept as it is.
+pub fn to_canonical_vec<T: Serialize>(value: &T) -> Result<Vec<u8>, Error> {
+    // serde_cbor already encodes integers, lengths and floats in their shortest form, and
+    // its `Value` sorts map keys in canonical order, whatever the order of the fields of `T`.
+    let value: CborValue = serde_cbor::from_slice(&serde_cbor::to_vec(value)?)?;
+    serde_cbor::to_vec(&value)
+}
+
+/// Whether `bytes` are a single item of canonical CBOR, i.e. they re-encode to themselves.
+///
+/// Embedded CBOR is not checked, and must be checked on its own.
+pub fn is_canonical(bytes: &[u8]) -> bool {
+    serde_cbor::from_slice::<CborValue>(bytes)
+        .and_then(|value| to_canonical_vec(&value))
+        .is_ok_and(|encoded| encoded == bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_canonical, to_canonical_vec};
+    use crate::definitions::helpers::Tag24;
+    use serde::Serialize;
+    use serde_cbor::Value as CborValue;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Unordered {
+        value_digests: u8,
+        doc_type: &'static str,
+        a: i64,
+    }
+
+    #[test]
+    fn map_keys_are_sorted() {
+        let encoded = to_canonical_vec(&Unordered {
+            value_digests: 1,
+            doc_type: "mDL",
+            a: -1,
+        })
+        .unwrap();
+        // {"a": -1, "docType": "mDL", "valueDigests": 1}
+        assert_eq!(
+            hex::encode(encoded),
+            [
+                "a3",
+                "616120",
+                "67646f6354797065636d444c",
+                "6c76616c756544696765737473",
+                "01"
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn integer_keys_are_sorted() {
+        let map: BTreeMap<i64, bool> = [(-1, true), (24, false), (1, true)].into();
+        // {1: true, 24: false, -1: true}
+        assert_eq!(
+            hex::encode(to_canonical_vec(&map).unwrap()),
+            "a301f51818f420f5"
+        );
+    }
+
+    #[test]
+    fn canonical() {
+        // {"a": true, "bb": 1}
+        assert!(is_canonical(&hex::decode("a26161f562626201").unwrap()));
+        // {"bb": 1, "a": true}
+        assert!(!is_canonical(&hex::decode("a2626262016161f5").unwrap()));
+        // 1, encoded in two bytes rather than one.
+        assert!(!is_canonical(&hex::decode("1801").unwrap()));
+        // An array of indefinite length.
+        assert!(!is_canonical(&hex::decode("9f01ff").unwrap()));
+        assert!(!is_canonical(&hex::decode("a1").unwrap()));
+    }
+
+    #[test]
+    fn embedded_cbor_is_kept() {
+        // An embedded map whose keys are not in canonical order.
+        let embedded = hex::decode("a2626262016161f5").unwrap();
+        let tagged: Tag24<CborValue> = Tag24::from_bytes(embedded.clone()).unwrap();
+        let encoded = to_canonical_vec(&tagged).unwrap();
+        let decoded: Tag24<CborValue> = serde_cbor::from_slice(&encoded).unwrap();
+        assert_eq!(decoded.inner_bytes, embedded);
+    }
+}
@@ -0,0 +1,102 @@
+//! Conversions between the elliptic curve keys used in ISO 18013-5 and JSON Web Keys.
+use crate::definitions::x509::x5chain::EcPublicKey;
+use elliptic_curve::JwkEcKey;
+
+/// An ECDSA signing key on one of the NIST curves permitted by ISO 18013-5.
+#[derive(Debug, Clone)]
+pub enum EcSigningKey {
+    P256(p256::ecdsa::SigningKey),
+    P384(p384::ecdsa::SigningKey),
+    P521(ecdsa::SigningKey<p521::NistP521>),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("the JWK is not a valid elliptic curve private key: {0}")]
+    InvalidJwk(elliptic_curve::Error),
+    #[error("unsupported JWK curve: {0}")]
+    UnsupportedCurve(String),
+}
+
+impl EcSigningKey {
+    /// The public key corresponding to this signing key.
+    pub fn public_key(&self) -> EcPublicKey {
+        match self {
+            EcSigningKey::P256(key) => EcPublicKey::P256(key.verifying_key().into()),
+            EcSigningKey::P384(key) => EcPublicKey::P384(key.verifying_key().into()),
+            EcSigningKey::P521(key) => EcPublicKey::P521(key.verifying_key().into()),
+        }
+    }
+}
+
+/// Parse a signing key from a JWK with the private key parameter `d`, on the P-256, P-384 or
+/// P-521 curve.
+pub fn signing_key_from_jwk(jwk: &str) -> Result<EcSigningKey, Error> {
+    let jwk: JwkEcKey = jwk.parse().map_err(Error::InvalidJwk)?;
+    match jwk.crv() {
+        "P-256" => p256::SecretKey::from_jwk(&jwk)
+            .map(|key| EcSigningKey::P256(key.into()))
+            .map_err(Error::InvalidJwk),
+        "P-384" => p384::SecretKey::from_jwk(&jwk)
+            .map(|key| EcSigningKey::P384(key.into()))
+            .map_err(Error::InvalidJwk),
+        "P-521" => p521::SecretKey::from_jwk(&jwk)
+            .map(|key| EcSigningKey::P521(key.into()))
+            .map_err(Error::InvalidJwk),
+        crv => Err(Error::UnsupportedCurve(crv.to_string())),
+    }
+}
+
+/// Encode a public key as a JWK.
+pub fn public_key_to_jwk(public_key: &EcPublicKey) -> String {
+    match public_key {
+        EcPublicKey::P256(key) => key.to_jwk_string(),
+        EcPublicKey::P384(key) => key.to_jwk_string(),
+        EcPublicKey::P521(key) => key.to_jwk_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use p256::pkcs8::DecodePrivateKey;
+
+    fn roundtrip(secret_key_jwk: &str, public_key: EcPublicKey) {
+        let signing_key = signing_key_from_jwk(secret_key_jwk).unwrap();
+        let jwk = public_key_to_jwk(&signing_key.public_key());
+        assert_eq!(jwk, public_key_to_jwk(&public_key));
+    }
+
+    #[test]
+    fn p256_roundtrip() {
+        let key =
+            p256::SecretKey::from_pkcs8_pem(include_str!("../../../test/issuance/256-key.pem"))
+                .unwrap();
+        roundtrip(&key.to_jwk_string(), EcPublicKey::P256(key.public_key()));
+    }
+
+    #[test]
+    fn p384_roundtrip() {
+        let key =
+            p384::SecretKey::from_pkcs8_pem(include_str!("../../../test/issuance/384-key.pem"))
+                .unwrap();
+        roundtrip(&key.to_jwk_string(), EcPublicKey::P384(key.public_key()));
+    }
+
+    #[test]
+    fn p521_roundtrip() {
+        let key =
+            p521::SecretKey::from_pkcs8_pem(include_str!("../../../test/issuance/521-key.pem"))
+                .unwrap();
+        roundtrip(&key.to_jwk_string(), EcPublicKey::P521(key.public_key()));
+    }
+
+    #[test]
+    fn unsupported_curve() {
+        let jwk = r#"{"kty":"EC","crv":"secp256k1","x":"AA","y":"AA","d":"AA"}"#;
+        assert!(matches!(
+            signing_key_from_jwk(jwk),
+            Err(Error::UnsupportedCurve(crv)) if crv == "secp256k1"
+        ));
+    }
+}
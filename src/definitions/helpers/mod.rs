@@ -1,9 +1,15 @@
 pub mod bytestr;
+pub mod cbor;
+#[cfg(feature = "jwk")]
+pub mod jwk;
 pub mod non_empty_map;
 pub mod non_empty_vec;
+pub mod ordered;
 pub mod tag24;
 
+pub use crate::definitions::namespaces::org_iso_18013_5_1::{FullDate, TDate};
 pub use bytestr::ByteStr;
 pub use non_empty_map::NonEmptyMap;
 pub use non_empty_vec::NonEmptyVec;
+pub use ordered::OrderedValue;
 pub use tag24::Tag24;
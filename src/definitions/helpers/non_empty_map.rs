@@ -7,8 +7,21 @@ pub struct NonEmptyMap<K: Ord + Eq + Clone, V: Clone>(BTreeMap<K, V>);
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error("cannot construct a non-empty vec from an empty vec")]
+    #[error("cannot construct a non-empty map from an empty map")]
     Empty,
+    #[error("a key is present in both maps being merged")]
+    Collision,
+}
+
+/// How [NonEmptyMap::merge] resolves a key that is present in both maps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Keep the value already in the map being merged into.
+    KeepExisting,
+    /// Replace the value with the one from the other map.
+    Overwrite,
+    /// Fail with [Error::Collision].
+    Error,
 }
 
 impl<K: Ord + Eq + Clone, V: Clone> NonEmptyMap<K, V> {
@@ -26,9 +39,28 @@ impl<K: Ord + Eq + Clone, V: Clone> NonEmptyMap<K, V> {
         self.0.insert(k, v)
     }
 
+    pub fn get_mut(&mut self, k: &K) -> Option<&mut V> {
+        self.0.get_mut(k)
+    }
+
     pub fn into_inner(self) -> BTreeMap<K, V> {
         self.0
     }
+
+    /// Merge the entries of `other` into this map, resolving keys present in both according to
+    /// `policy`.
+    pub fn merge(mut self, other: Self, policy: MergePolicy) -> Result<Self, Error> {
+        for (k, v) in other.0 {
+            match (self.0.contains_key(&k), policy) {
+                (true, MergePolicy::KeepExisting) => {}
+                (true, MergePolicy::Error) => return Err(Error::Collision),
+                _ => {
+                    self.0.insert(k, v);
+                }
+            }
+        }
+        Ok(self)
+    }
 }
 
 impl<K: Ord + Eq + Clone, V: Clone> TryFrom<BTreeMap<K, V>> for NonEmptyMap<K, V> {
@@ -61,3 +93,78 @@ impl<K: Ord + Eq + Clone, V: Clone> Deref for NonEmptyMap<K, V> {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{MergePolicy, NonEmptyMap};
+    use serde_cbor::Value as CborValue;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn cbor_roundtrip() {
+        let original = NonEmptyMap::new(String::from("family_name"), 1);
+        let cbor = serde_cbor::to_vec(&original).unwrap();
+        // Serialized as a plain map.
+        let map: BTreeMap<String, i32> = serde_cbor::from_slice(&cbor).unwrap();
+        assert_eq!(map, original.clone().into_inner());
+        let roundtripped: NonEmptyMap<String, i32> = serde_cbor::from_slice(&cbor).unwrap();
+        assert_eq!(original, roundtripped);
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        let original = NonEmptyMap::new(String::from("family_name"), 1);
+        let json = serde_json::to_value(&original).unwrap();
+        assert_eq!(json, serde_json::json!({ "family_name": 1 }));
+        let roundtripped: NonEmptyMap<String, i32> = serde_json::from_value(json).unwrap();
+        assert_eq!(original, roundtripped);
+    }
+
+    #[test]
+    fn empty() {
+        let cbor = serde_cbor::to_vec(&CborValue::Map(BTreeMap::new())).unwrap();
+        assert!(serde_cbor::from_slice::<NonEmptyMap<String, i32>>(&cbor).is_err());
+        assert!(serde_json::from_str::<NonEmptyMap<String, i32>>("{}").is_err());
+    }
+
+    fn maps() -> (
+        NonEmptyMap<&'static str, i32>,
+        NonEmptyMap<&'static str, i32>,
+    ) {
+        let mut first = NonEmptyMap::new("family_name", 1);
+        first.insert("given_name", 1);
+        let mut second = NonEmptyMap::new("given_name", 2);
+        second.insert("birth_date", 2);
+        (first, second)
+    }
+
+    #[test]
+    fn merge_keep_existing() {
+        let (first, second) = maps();
+        let merged = first.merge(second, MergePolicy::KeepExisting).unwrap();
+        assert_eq!(
+            merged.into_inner(),
+            [("birth_date", 2), ("family_name", 1), ("given_name", 1)].into()
+        );
+    }
+
+    #[test]
+    fn merge_overwrite() {
+        let (first, second) = maps();
+        let merged = first.merge(second, MergePolicy::Overwrite).unwrap();
+        assert_eq!(
+            merged.into_inner(),
+            [("birth_date", 2), ("family_name", 1), ("given_name", 2)].into()
+        );
+    }
+
+    #[test]
+    fn merge_error() {
+        let (first, second) = maps();
+        assert!(first.clone().merge(second, MergePolicy::Error).is_err());
+
+        let disjoint = NonEmptyMap::new("birth_date", 2);
+        let merged = first.merge(disjoint, MergePolicy::Error).unwrap();
+        assert_eq!(merged.len(), 3);
+    }
+}
@@ -20,10 +20,52 @@ impl<T: Clone> NonEmptyVec<T> {
         Self::try_from(v).ok()
     }
 
+    /// Append an element. Slice methods such as `iter` and `len` are available through `Deref`.
+    ///
+    /// ```
+    /// # use isomdl::definitions::helpers::NonEmptyVec;
+    /// let mut v = NonEmptyVec::new(1);
+    /// v.push(2);
+    /// assert_eq!(v.len(), 2);
+    /// assert_eq!(v.iter().sum::<i32>(), 3);
+    /// ```
     pub fn push(&mut self, t: T) {
         self.0.push(t)
     }
 
+    /// The first element, which always exists.
+    ///
+    /// ```
+    /// # use isomdl::definitions::helpers::NonEmptyVec;
+    /// let v: NonEmptyVec<_> = vec![1, 2, 3].try_into().unwrap();
+    /// assert_eq!(*v.first(), 1);
+    /// ```
+    pub fn first(&self) -> &T {
+        &self.0[0]
+    }
+
+    /// The last element, which always exists.
+    ///
+    /// ```
+    /// # use isomdl::definitions::helpers::NonEmptyVec;
+    /// let v: NonEmptyVec<_> = vec![1, 2, 3].try_into().unwrap();
+    /// assert_eq!(*v.last(), 3);
+    /// ```
+    pub fn last(&self) -> &T {
+        &self.0[self.0.len() - 1]
+    }
+
+    /// Apply `f` to each element, which preserves at least one element.
+    ///
+    /// ```
+    /// # use isomdl::definitions::helpers::NonEmptyVec;
+    /// let v = NonEmptyVec::new(1).map(|n| n.to_string());
+    /// assert_eq!(v.first(), "1");
+    /// ```
+    pub fn map<U: Clone>(self, f: impl FnMut(T) -> U) -> NonEmptyVec<U> {
+        NonEmptyVec(self.0.into_iter().map(f).collect())
+    }
+
     pub fn into_inner(self) -> Vec<T> {
         self.0
     }
@@ -58,6 +100,12 @@ impl<T: Clone> NonEmptyVec<T> {
     }
 }
 
+impl<T: Clone> Extend<T> for NonEmptyVec<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+
 impl<T: Clone> TryFrom<Vec<T>> for NonEmptyVec<T> {
     type Error = Error;
 
@@ -0,0 +1,287 @@
+//! CBOR values whose maps keep their entries in the order in which they were encoded.
+//!
+//! [CborValue] holds maps in a `BTreeMap`, so decoding into it reorders the entries, and
+//! encoding it again can change the bytes, and so invalidate a signature over them. An
+//! [OrderedValue] reproduces the bytes it was decoded from, for display in the issuer's order
+//! or for re-signing.
+use serde::{
+    de::{self, Deserializer, MapAccess, SeqAccess, Visitor},
+    ser::{SerializeMap, Serializer},
+    Deserialize, Serialize,
+};
+use serde_cbor::{tags::Tagged, Error, Value as CborValue};
+use std::fmt;
+
+/// A CBOR value, as [CborValue], but with the entries of each map in encoded order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderedValue {
+    Null,
+    Bool(bool),
+    Integer(i128),
+    Float(f64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<OrderedValue>),
+    Map(OrderedMap),
+    Tag(u64, Box<OrderedValue>),
+}
+
+/// The entries of a CBOR map, in the order in which they were encoded or inserted.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OrderedMap(Vec<(OrderedValue, OrderedValue)>);
+
+impl OrderedValue {
+    /// Decode `bytes`, keeping the order of each map.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        serde_cbor::from_slice(bytes)
+    }
+
+    pub fn as_map(&self) -> Option<&OrderedMap> {
+        match self {
+            Self::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+}
+
+impl OrderedMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert an entry after the existing entries, or replace the value of an existing key in
+    /// place, returning the value replaced.
+    pub fn insert(&mut self, key: OrderedValue, value: OrderedValue) -> Option<OrderedValue> {
+        match self.0.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => Some(std::mem::replace(v, value)),
+            None => {
+                self.0.push((key, value));
+                None
+            }
+        }
+    }
+
+    pub fn get(&self, key: &OrderedValue) -> Option<&OrderedValue> {
+        self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&OrderedValue, &OrderedValue)> {
+        self.0.iter().map(|(k, v)| (k, v))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &OrderedValue> {
+        self.0.iter().map(|(k, _)| k)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<&str> for OrderedValue {
+    fn from(s: &str) -> Self {
+        Self::Text(s.to_string())
+    }
+}
+
+impl From<CborValue> for OrderedValue {
+    /// Convert from a [CborValue], whose maps are in canonical order.
+    fn from(value: CborValue) -> Self {
+        match value {
+            CborValue::Null => Self::Null,
+            CborValue::Bool(b) => Self::Bool(b),
+            CborValue::Integer(i) => Self::Integer(i),
+            CborValue::Float(f) => Self::Float(f),
+            CborValue::Bytes(b) => Self::Bytes(b),
+            CborValue::Text(t) => Self::Text(t),
+            CborValue::Array(a) => Self::Array(a.into_iter().map(Into::into).collect()),
+            CborValue::Map(m) => Self::Map(OrderedMap(
+                m.into_iter().map(|(k, v)| (k.into(), v.into())).collect(),
+            )),
+            CborValue::Tag(tag, v) => Self::Tag(tag, Box::new((*v).into())),
+            _ => unreachable!("serde_cbor does not construct hidden values"),
+        }
+    }
+}
+
+impl From<OrderedValue> for CborValue {
+    /// Convert to a [CborValue], which sorts each map.
+    fn from(value: OrderedValue) -> Self {
+        match value {
+            OrderedValue::Null => Self::Null,
+            OrderedValue::Bool(b) => Self::Bool(b),
+            OrderedValue::Integer(i) => Self::Integer(i),
+            OrderedValue::Float(f) => Self::Float(f),
+            OrderedValue::Bytes(b) => Self::Bytes(b),
+            OrderedValue::Text(t) => Self::Text(t),
+            OrderedValue::Array(a) => Self::Array(a.into_iter().map(Into::into).collect()),
+            OrderedValue::Map(m) => {
+                Self::Map(m.0.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+            }
+            OrderedValue::Tag(tag, v) => Self::Tag(tag, Box::new((*v).into())),
+        }
+    }
+}
+
+impl Serialize for OrderedValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Null => serializer.serialize_unit(),
+            Self::Bool(b) => serializer.serialize_bool(*b),
+            Self::Integer(i) => serializer.serialize_i128(*i),
+            Self::Float(f) => serializer.serialize_f64(*f),
+            Self::Bytes(b) => serializer.serialize_bytes(b),
+            Self::Text(t) => serializer.serialize_str(t),
+            Self::Array(a) => a.serialize(serializer),
+            Self::Map(m) => m.serialize(serializer),
+            Self::Tag(tag, v) => Tagged::new(Some(*tag), v).serialize(serializer),
+        }
+    }
+}
+
+impl Serialize for OrderedMap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (k, v) in &self.0 {
+            map.serialize_entry(k, v)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for OrderedValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(OrderedValueVisitor)
+    }
+}
+
+struct OrderedValueVisitor;
+
+impl<'de> Visitor<'de> for OrderedValueVisitor {
+    type Value = OrderedValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any valid CBOR value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(OrderedValue::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(OrderedValue::Integer(v.into()))
+    }
+
+    fn visit_i128<E: de::Error>(self, v: i128) -> Result<Self::Value, E> {
+        Ok(OrderedValue::Integer(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(OrderedValue::Integer(v.into()))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(OrderedValue::Float(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(OrderedValue::Text(v.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        Ok(OrderedValue::Text(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(OrderedValue::Bytes(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(OrderedValue::Bytes(v))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(OrderedValue::Null)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(OrderedValue::Null)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(OrderedValue::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entries = OrderedMap::new();
+        while let Some((key, value)) = map.next_entry()? {
+            entries.insert(key, value);
+        }
+        Ok(OrderedValue::Map(entries))
+    }
+
+    // serde_cbor presents a tagged item as a newtype struct.
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        let tag = serde_cbor::tags::current_cbor_tag();
+        let value = deserializer.deserialize_any(self)?;
+        Ok(match tag {
+            Some(tag) => OrderedValue::Tag(tag, Box::new(value)),
+            None => value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::OrderedValue;
+    use crate::definitions::helpers::Tag24;
+    use serde_cbor::Value as CborValue;
+
+    // {"b": 1, "a": {"z": 1, "y": 24(<<{"d": 1, "c": 2}>>)}}, with no map in canonical order.
+    const UNORDERED: &str = "a26162016161a2617a016179d81847a2616401616302";
+
+    #[test]
+    fn map_order_is_preserved() {
+        let bytes = hex::decode(UNORDERED).unwrap();
+        let value = OrderedValue::from_slice(&bytes).unwrap();
+        let map = value.as_map().unwrap();
+        assert_eq!(
+            map.keys().cloned().collect::<Vec<_>>(),
+            vec!["b".into(), "a".into()]
+        );
+        let inner = map.get(&"a".into()).unwrap().as_map().unwrap();
+        assert_eq!(
+            inner.keys().cloned().collect::<Vec<_>>(),
+            vec!["z".into(), "y".into()]
+        );
+        assert_eq!(serde_cbor::to_vec(&value).unwrap(), bytes);
+
+        // A CborValue sorts the keys, and so changes the bytes.
+        let sorted: CborValue = serde_cbor::from_slice(&bytes).unwrap();
+        assert_ne!(serde_cbor::to_vec(&sorted).unwrap(), bytes);
+        assert_eq!(CborValue::from(value), sorted);
+    }
+
+    #[test]
+    fn embedded_order_is_preserved() {
+        let embedded = hex::decode("a2616401616302").unwrap();
+        let tagged: Tag24<CborValue> = Tag24::from_bytes(embedded.clone()).unwrap();
+        let value = tagged.ordered_value().unwrap();
+        assert_eq!(
+            value.as_map().unwrap().keys().cloned().collect::<Vec<_>>(),
+            vec!["d".into(), "c".into()]
+        );
+        assert_eq!(serde_cbor::to_vec(&value).unwrap(), embedded);
+    }
+}
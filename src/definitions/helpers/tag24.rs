@@ -1,11 +1,13 @@
 //! Support for embedded
 //! [CBOR Data Items](https://www.ietf.org/rfc/rfc8949.html#name-encoded-cbor-data-item),
 //! also known as a tagged data item with tag number 24.
+use super::cbor::to_canonical_vec;
+use super::ordered::OrderedValue;
 use serde::{
     de::{self, Error as DeError},
     ser, Deserialize, Serialize,
 };
-use serde_cbor::{from_slice, to_vec, Error as CborError, Value as CborValue};
+use serde_cbor::{from_slice, Error as CborError, Value as CborValue};
 
 /// A wrapper for a struct that is to be encoded as a CBOR tagged item, with tag number 24.
 ///
@@ -35,11 +37,19 @@ impl<T> Tag24<T> {
     pub fn into_inner(self) -> T {
         self.inner
     }
+
+    /// The embedded value with each map in the order in which it was encoded, which decoding
+    /// into `T` may not keep.
+    pub fn ordered_value(&self) -> Result<OrderedValue> {
+        from_slice(&self.inner_bytes).map_err(Error::UnableToDecode)
+    }
 }
 
 impl<T: Serialize> Tag24<T> {
+    /// Embed `inner`, encoded as canonical CBOR so that a signature or MAC over the embedded
+    /// bytes can be reproduced by any implementation.
     pub fn new(inner: T) -> Result<Tag24<T>> {
-        let inner_bytes = to_vec(&inner).map_err(Error::UnableToEncode)?;
+        let inner_bytes = to_canonical_vec(&inner).map_err(Error::UnableToEncode)?;
         Ok(Self { inner, inner_bytes })
     }
 }
@@ -103,6 +113,22 @@ impl<'de, T: de::DeserializeOwned> Deserialize<'de> for Tag24<T> {
 #[cfg(test)]
 mod test {
     use super::Tag24;
+    use crate::definitions::DeviceEngagement;
+
+    /// DeviceEngagementBytes from ISO/IEC 18013-5 Annex D.
+    const DEVICE_ENGAGEMENT_BYTES: &str = "d8185858a20063312e30018201d818584ba4010220012158205a88d182bce5f42efa59943f33359d2e8a968ff289d93e5fa444b624343167fe225820b16e8cf858ddc7690407ba61d4c338237a8cfcf3de6aa672fc60a557aa32fc67";
+
+    #[test]
+    fn device_engagement_roundtrip() {
+        let bytes = hex::decode(DEVICE_ENGAGEMENT_BYTES).unwrap();
+        let device_engagement: Tag24<DeviceEngagement> = serde_cbor::from_slice(&bytes).unwrap();
+        assert_eq!(device_engagement.as_ref().version, "1.0");
+        assert_eq!(serde_cbor::to_vec(&device_engagement).unwrap(), bytes);
+
+        // Wrapping the decoded structure again reproduces the bytes of the spec.
+        let rewrapped = Tag24::new(device_engagement.into_inner()).unwrap();
+        assert_eq!(serde_cbor::to_vec(&rewrapped).unwrap(), bytes);
+    }
 
     #[test]
     #[should_panic]
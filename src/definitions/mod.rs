@@ -3,6 +3,8 @@ pub mod device_key;
 pub mod device_request;
 pub mod device_response;
 pub mod device_signed;
+#[cfg(feature = "diagnostic")]
+pub mod diagnostic;
 pub mod helpers;
 pub mod issuer_signed;
 pub mod mso;
@@ -10,16 +12,23 @@ pub mod namespaces;
 pub mod session;
 pub mod traits;
 pub mod validity_info;
+pub mod x509;
 
+#[cfg(feature = "transports")]
+pub use device_engagement::{BleOptions, DeviceRetrievalMethod, NfcOptions, WifiOptions};
 pub use device_engagement::{
-    BleOptions, DeviceEngagement, DeviceRetrievalMethod, NfcOptions, Security, WifiOptions,
+    DeviceEngagement, OriginInfo, Security, ServerRetrievalInformation, ServerRetrievalMethods,
 };
 pub use device_key::cose_key::{EC2Curve, Error, EC2Y};
 pub use device_key::{CoseKey, DeviceKeyInfo, KeyAuthorizations};
 pub use device_request::DocRequest;
 pub use device_response::{DeviceResponse, Document};
 pub use device_signed::{DeviceAuth, DeviceSigned};
+#[cfg(feature = "jwk")]
+pub use helpers::jwk::{public_key_to_jwk, signing_key_from_jwk, EcSigningKey};
 pub use issuer_signed::{IssuerSigned, IssuerSignedItem};
 pub use mso::{DigestAlgorithm, DigestId, DigestIds, Mso};
-pub use session::{SessionData, SessionEstablishment, SessionTranscript180135};
-pub use validity_info::ValidityInfo;
+pub use session::{
+    OID4VPSessionTranscript, SessionData, SessionEstablishment, SessionTranscript180135,
+};
+pub use validity_info::{RefreshStatus, ValidityInfo};
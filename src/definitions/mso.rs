@@ -1,5 +1,6 @@
 use crate::definitions::{helpers::ByteStr, DeviceKeyInfo, ValidityInfo};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha384, Sha512};
 use std::collections::BTreeMap;
 
 /// DigestId is a unsigned integer between 0 and (2^31 - 1) inclusive.
@@ -20,7 +21,7 @@ pub struct Mso {
     pub validity_info: ValidityInfo,
 }
 
-#[derive(Clone, Debug, Copy, Deserialize, Serialize)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub enum DigestAlgorithm {
     #[serde(rename = "SHA-256")]
     SHA256,
@@ -30,6 +31,17 @@ pub enum DigestAlgorithm {
     SHA512,
 }
 
+impl DigestAlgorithm {
+    /// Compute the digest of `bytes` with this algorithm.
+    pub fn digest(&self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            DigestAlgorithm::SHA256 => Sha256::digest(bytes).to_vec(),
+            DigestAlgorithm::SHA384 => Sha384::digest(bytes).to_vec(),
+            DigestAlgorithm::SHA512 => Sha512::digest(bytes).to_vec(),
+        }
+    }
+}
+
 impl DigestId {
     pub fn new(i: i32) -> DigestId {
         DigestId(if i.is_negative() { -i } else { i })
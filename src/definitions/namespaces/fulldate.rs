@@ -1,4 +1,5 @@
-use anyhow::Error;
+use anyhow::{anyhow, Error};
+use serde::{Deserialize, Serialize};
 use serde_cbor::Value as Cbor;
 use serde_json::Value as Json;
 use std::{fmt, str::FromStr};
@@ -8,13 +9,44 @@ use crate::definitions::traits::{FromJson, FromJsonError};
 
 const FORMAT: &[FormatItem<'static>] = format_description!("[year]-[month]-[day]");
 
-/// `full-date` as defined in RFC3339.
-#[derive(Clone, Debug)]
+/// The CBOR tag of a `full-date`, as defined in RFC8943.
+pub const FULL_DATE_TAG: u64 = 1004;
+
+/// `full-date` as defined in RFC3339, encoded in CBOR as a text string with tag 1004.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Cbor", into = "Cbor")]
 pub struct FullDate(Date);
 
+impl FullDate {
+    pub fn date(&self) -> Date {
+        self.0
+    }
+}
+
+impl From<Date> for FullDate {
+    fn from(d: Date) -> FullDate {
+        FullDate(d)
+    }
+}
+
 impl From<FullDate> for Cbor {
     fn from(d: FullDate) -> Cbor {
-        Cbor::Tag(1004, Box::new(Cbor::Text(d.to_string())))
+        Cbor::Tag(FULL_DATE_TAG, Box::new(Cbor::Text(d.to_string())))
+    }
+}
+
+/// Decode a `full-date`, rejecting a date that is not tagged as one, such as a plain text string.
+impl TryFrom<Cbor> for FullDate {
+    type Error = Error;
+
+    fn try_from(v: Cbor) -> Result<FullDate, Error> {
+        match v {
+            Cbor::Tag(FULL_DATE_TAG, value) => match *value {
+                Cbor::Text(s) => s.parse(),
+                _ => Err(anyhow!("full-date is not a text string")),
+            },
+            _ => Err(anyhow!("expected a value with tag {FULL_DATE_TAG}")),
+        }
     }
 }
 
@@ -59,4 +91,19 @@ mod test {
         let fulldate = FullDate::from_str(DATESTR_HI).expect("unable to parse datestr");
         assert_eq!(DATESTR_HI, fulldate.to_string());
     }
+
+    #[test]
+    fn fulldate_cbor_roundtrip() {
+        let fulldate = FullDate::from_str("1980-01-02").unwrap();
+        let bytes = serde_cbor::to_vec(&fulldate).unwrap();
+        // 1004("1980-01-02")
+        assert_eq!(hex::encode(&bytes), "d903ec6a313938302d30312d3032");
+        let roundtripped: FullDate = serde_cbor::from_slice(&bytes).unwrap();
+        assert_eq!(roundtripped, fulldate);
+
+        let untagged = serde_cbor::to_vec(&"1980-01-02").unwrap();
+        assert!(serde_cbor::from_slice::<FullDate>(&untagged).is_err());
+        let wrong_tag = Cbor::Tag(0, Box::new(Cbor::Text("1980-01-02".into())));
+        assert!(FullDate::try_from(wrong_tag).is_err());
+    }
 }
@@ -4,11 +4,22 @@ use crate::{
     macros::{FromJson, ToCbor},
 };
 use serde_cbor::Value as Cbor;
+use std::ops::Deref;
 
 /// `driving_privileges` in the org.iso.18013.5.1 namespace.
+///
+/// A reader decodes a disclosed value with [FromCbor](crate::definitions::traits::FromCbor).
 #[derive(Clone, Debug, FromJson)]
 pub struct DrivingPrivileges(Vec<DrivingPrivilege>);
 
+impl Deref for DrivingPrivileges {
+    type Target = [DrivingPrivilege];
+
+    fn deref(&self) -> &[DrivingPrivilege] {
+        &self.0
+    }
+}
+
 impl From<DrivingPrivileges> for Cbor {
     fn from(d: DrivingPrivileges) -> Cbor {
         Cbor::Array(d.0.into_iter().map(ToCbor::to_cbor).collect())
@@ -26,6 +37,14 @@ pub struct DrivingPrivilege {
 #[derive(Clone, Debug, FromJson)]
 pub struct Codes(NonEmptyVec<Code>);
 
+impl Deref for Codes {
+    type Target = [Code];
+
+    fn deref(&self) -> &[Code] {
+        &self.0
+    }
+}
+
 impl From<Codes> for Cbor {
     fn from(c: Codes) -> Cbor {
         Cbor::Array(c.0.into_inner().into_iter().map(ToCbor::to_cbor).collect())
@@ -38,3 +57,74 @@ pub struct Code {
     pub sign: Option<String>,
     pub value: Option<String>,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::definitions::traits::FromCbor;
+
+    #[test]
+    fn decode_two_categories() {
+        let full_date = |date: &str| Cbor::Tag(1004, Box::new(Cbor::Text(date.into())));
+        let text = |s: &str| Cbor::Text(s.into());
+        let cbor = Cbor::Array(vec![
+            Cbor::Map(
+                [
+                    (text("vehicle_category_code"), text("A")),
+                    (text("issue_date"), full_date("2018-08-09")),
+                    (text("expiry_date"), full_date("2024-10-20")),
+                ]
+                .into(),
+            ),
+            Cbor::Map(
+                [
+                    (text("vehicle_category_code"), text("B")),
+                    (text("issue_date"), full_date("2017-02-23")),
+                    (text("expiry_date"), full_date("2024-10-20")),
+                    (
+                        text("codes"),
+                        Cbor::Array(vec![Cbor::Map(
+                            [
+                                (text("code"), text("S01")),
+                                (text("sign"), text("<=")),
+                                (text("value"), text("2500")),
+                            ]
+                            .into(),
+                        )]),
+                    ),
+                ]
+                .into(),
+            ),
+        ]);
+
+        let privileges = DrivingPrivileges::from_cbor(&cbor).unwrap();
+        assert_eq!(privileges.len(), 2);
+        assert_eq!(privileges[0].vehicle_category_code, "A");
+        assert_eq!(
+            privileges[0].issue_date.as_ref().map(ToString::to_string),
+            Some("2018-08-09".into())
+        );
+        assert!(privileges[0].codes.is_none());
+        assert_eq!(privileges[1].vehicle_category_code, "B");
+        assert_eq!(
+            privileges[1].expiry_date.as_ref().map(ToString::to_string),
+            Some("2024-10-20".into())
+        );
+        let codes = privileges[1].codes.as_ref().unwrap();
+        assert_eq!(codes[0].code, "S01");
+        assert_eq!(codes[0].value.as_deref(), Some("2500"));
+
+        // The same array decodes into a plain list of privileges.
+        let privileges = Vec::<DrivingPrivilege>::from_cbor(&cbor).unwrap();
+        assert_eq!(privileges.len(), 2);
+
+        let invalid_date = Cbor::Array(vec![Cbor::Map(
+            [
+                (text("vehicle_category_code"), text("A")),
+                (text("issue_date"), full_date("not a date")),
+            ]
+            .into(),
+        )]);
+        assert!(DrivingPrivileges::from_cbor(&invalid_date).is_err());
+    }
+}
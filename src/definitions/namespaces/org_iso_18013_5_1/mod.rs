@@ -5,6 +5,7 @@ mod driving_privileges;
 mod eye_colour;
 mod hair_colour;
 mod issuing_jurisdiction;
+mod portrait;
 mod sex;
 mod tdate;
 mod un_distinguishing_sign;
@@ -18,6 +19,7 @@ pub use driving_privileges::*;
 pub use eye_colour::EyeColour;
 pub use hair_colour::HairColour;
 pub use issuing_jurisdiction::IssuingJurisdiction;
+pub use portrait::{ImageFormat, Portrait, PortraitError, DEFAULT_MAX_PORTRAIT_BYTES};
 pub use sex::Sex;
 pub use tdate::{TDate, TDateOrFullDate};
 pub use un_distinguishing_sign::UNDistinguishingSign;
@@ -0,0 +1,143 @@
+use serde_cbor::Value as Cbor;
+
+/// The largest portrait accepted by [Portrait::new] with the default limit, in bytes.
+pub const DEFAULT_MAX_PORTRAIT_BYTES: usize = 512 * 1024;
+
+const JPEG_SOI: [u8; 2] = [0xFF, 0xD8];
+const JPEG_EOI: [u8; 2] = [0xFF, 0xD9];
+const JPEG_2000_SIGNATURE: [u8; 12] = [
+    0x00, 0x00, 0x00, 0x0C, 0x6A, 0x50, 0x20, 0x20, 0x0D, 0x0A, 0x87, 0x0A,
+];
+
+/// `portrait` in the org.iso.18013.5.1 namespace: a JPEG or JPEG 2000 image of the holder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Portrait(Vec<u8>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    Jpeg2000,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PortraitError {
+    #[error("the portrait is not a byte string")]
+    NotAByteString,
+    #[error("the portrait is not a well-formed JPEG or JPEG 2000 image")]
+    NotAnImage,
+    #[error("the portrait is {size} bytes, more than the limit of {max}")]
+    TooLarge { size: usize, max: usize },
+}
+
+impl Portrait {
+    /// Check that `image` is a well-formed JPEG or JPEG 2000 image of at most
+    /// [DEFAULT_MAX_PORTRAIT_BYTES].
+    pub fn new(image: Vec<u8>) -> Result<Self, PortraitError> {
+        Self::with_max_bytes(image, DEFAULT_MAX_PORTRAIT_BYTES)
+    }
+
+    /// As [Portrait::new], accepting images of at most `max_bytes`.
+    pub fn with_max_bytes(image: Vec<u8>, max_bytes: usize) -> Result<Self, PortraitError> {
+        if image.len() > max_bytes {
+            return Err(PortraitError::TooLarge {
+                size: image.len(),
+                max: max_bytes,
+            });
+        }
+        image_format(&image).ok_or(PortraitError::NotAnImage)?;
+        Ok(Self(image))
+    }
+
+    /// Decode a disclosed `portrait`, accepting images of at most `max_bytes`.
+    pub fn from_cbor(value: &Cbor, max_bytes: usize) -> Result<Self, PortraitError> {
+        match value {
+            Cbor::Bytes(image) => Self::with_max_bytes(image.clone(), max_bytes),
+            _ => Err(PortraitError::NotAByteString),
+        }
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        // Unwrap safety: the format was checked when the portrait was constructed.
+        image_format(&self.0).unwrap()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+/// The format of `image`, from its start and end markers. A JPEG 2000 image is recognized by its
+/// signature box, and its codestream also ends with the EOI marker.
+fn image_format(image: &[u8]) -> Option<ImageFormat> {
+    if !image.ends_with(&JPEG_EOI) {
+        return None;
+    }
+    if image.starts_with(&JPEG_2000_SIGNATURE) {
+        Some(ImageFormat::Jpeg2000)
+    } else if image.starts_with(&JPEG_SOI) && image.len() >= JPEG_SOI.len() + JPEG_EOI.len() {
+        Some(ImageFormat::Jpeg)
+    } else {
+        None
+    }
+}
+
+impl From<Portrait> for Cbor {
+    fn from(p: Portrait) -> Cbor {
+        Cbor::Bytes(p.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static JPEG_2000: &[u8] = include_bytes!("../../../../test/issuance/portrait.jpg");
+
+    fn jpeg() -> Vec<u8> {
+        let mut jpeg = JPEG_SOI.to_vec();
+        // APP0 segment of a JFIF file.
+        jpeg.extend_from_slice(&[0xFF, 0xE0, 0x00, 0x10]);
+        jpeg.extend_from_slice(b"JFIF\0\x01\x01\x00\x00\x01\x00\x01\x00\x00");
+        jpeg.extend_from_slice(&JPEG_EOI);
+        jpeg
+    }
+
+    #[test]
+    fn valid_images() {
+        let portrait = Portrait::new(jpeg()).unwrap();
+        assert_eq!(portrait.format(), ImageFormat::Jpeg);
+        let decoded = Portrait::from_cbor(&portrait.clone().into(), 1024).unwrap();
+        assert_eq!(decoded.as_bytes(), jpeg());
+
+        let portrait = Portrait::new(JPEG_2000.to_vec()).unwrap();
+        assert_eq!(portrait.format(), ImageFormat::Jpeg2000);
+    }
+
+    #[test]
+    fn invalid_images() {
+        let oversized = Portrait::with_max_bytes(JPEG_2000.to_vec(), 64 * 1024);
+        assert_eq!(
+            oversized,
+            Err(PortraitError::TooLarge {
+                size: JPEG_2000.len(),
+                max: 64 * 1024
+            })
+        );
+
+        let mut truncated = jpeg();
+        truncated.pop();
+        assert_eq!(Portrait::new(truncated), Err(PortraitError::NotAnImage));
+        assert_eq!(
+            Portrait::new(b"\x89PNG\r\n\x1a\n\xFF\xD9".to_vec()),
+            Err(PortraitError::NotAnImage)
+        );
+        assert_eq!(
+            Portrait::from_cbor(&Cbor::Text("portrait".into()), 1024),
+            Err(PortraitError::NotAByteString)
+        );
+    }
+}
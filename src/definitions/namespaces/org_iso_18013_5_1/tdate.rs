@@ -1,32 +1,44 @@
 pub use super::FullDate;
 
 use crate::definitions::traits::{FromJson, FromJsonError};
-use anyhow::anyhow;
+use anyhow::{anyhow, Error};
+use serde::{Deserialize, Serialize};
 use serde_cbor::Value as Cbor;
 use serde_json::Value as Json;
+use std::{fmt, str::FromStr};
 use time::{format_description::well_known::Rfc3339, OffsetDateTime, UtcOffset};
 
-/// `tdate` as per RFC8610 and restrictions in 18013-5.
-#[derive(Debug, Clone)]
+/// The CBOR tag of a `tdate`, as defined in RFC8949.
+pub const TDATE_TAG: u64 = 0;
+
+/// `tdate` as per RFC8610 and restrictions in 18013-5, encoded in CBOR as a text string with
+/// tag 0.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Cbor", into = "Cbor")]
 pub struct TDate(String);
 
-/// `tdate` or `full-date`.
-#[derive(Debug, Clone)]
-pub enum TDateOrFullDate {
-    TDate(TDate),
-    FullDate(FullDate),
+impl TDate {
+    pub fn date_time(&self) -> OffsetDateTime {
+        // Unwrap safety: the string was formatted as RFC3339 when the TDate was constructed.
+        OffsetDateTime::parse(&self.0, &Rfc3339).unwrap()
+    }
 }
 
-impl FromJson for TDate {
-    fn from_json(v: &Json) -> Result<Self, FromJsonError> {
-        let date_str = String::from_json(v)?;
+impl fmt::Display for TDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
 
+impl FromStr for TDate {
+    type Err = Error;
+
+    fn from_str(date_str: &str) -> Result<Self, Error> {
         // 18013-5 asks for dates to be in RFC3339 format with no milliseconds, and with no UTC
         // offset.
         Ok(Self(
-            OffsetDateTime::parse(&date_str, &Rfc3339)
-                .map_err(|e| anyhow!("date not in RFC3339 format: {}", e))
-                .map_err(FromJsonError::Parsing)?
+            OffsetDateTime::parse(date_str, &Rfc3339)
+                .map_err(|e| anyhow!("date not in RFC3339 format: {}", e))?
                 .to_offset(UtcOffset::UTC)
                 .replace_millisecond(0)
                 // Unwrap safety: 0 is a valid millisecond.
@@ -38,6 +50,36 @@ impl FromJson for TDate {
     }
 }
 
+/// Decode a `tdate`, rejecting a date-time that is not tagged as one, such as a plain text string.
+impl TryFrom<Cbor> for TDate {
+    type Error = Error;
+
+    fn try_from(v: Cbor) -> Result<TDate, Error> {
+        match v {
+            Cbor::Tag(TDATE_TAG, value) => match *value {
+                Cbor::Text(s) => s.parse(),
+                _ => Err(anyhow!("tdate is not a text string")),
+            },
+            _ => Err(anyhow!("expected a value with tag {TDATE_TAG}")),
+        }
+    }
+}
+
+/// `tdate` or `full-date`.
+#[derive(Debug, Clone)]
+pub enum TDateOrFullDate {
+    TDate(TDate),
+    FullDate(FullDate),
+}
+
+impl FromJson for TDate {
+    fn from_json(v: &Json) -> Result<Self, FromJsonError> {
+        String::from_json(v)?
+            .parse()
+            .map_err(FromJsonError::Parsing)
+    }
+}
+
 impl FromJson for TDateOrFullDate {
     fn from_json(v: &Json) -> Result<Self, FromJsonError> {
         if let Ok(td) = TDate::from_json(v) {
@@ -54,7 +96,7 @@ impl FromJson for TDateOrFullDate {
 
 impl From<TDate> for Cbor {
     fn from(t: TDate) -> Cbor {
-        Cbor::Tag(0, Box::new(t.0.into()))
+        Cbor::Tag(TDATE_TAG, Box::new(t.0.into()))
     }
 }
 
@@ -66,3 +108,21 @@ impl From<TDateOrFullDate> for Cbor {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tdate_cbor_roundtrip() {
+        let tdate: TDate = "2024-02-03T04:05:06.789+01:00".parse().unwrap();
+        assert_eq!(tdate.to_string(), "2024-02-03T03:05:06Z");
+        let bytes = serde_cbor::to_vec(&tdate).unwrap();
+        let roundtripped: TDate = serde_cbor::from_slice(&bytes).unwrap();
+        assert_eq!(roundtripped, tdate);
+        assert_eq!(roundtripped.date_time().hour(), 3);
+
+        let untagged = serde_cbor::to_vec(&"2024-02-03T03:05:06Z").unwrap();
+        assert!(serde_cbor::from_slice::<TDate>(&untagged).is_err());
+    }
+}
@@ -5,7 +5,7 @@ use std::str::FromStr;
 
 /// `DHS_compliance` in the org.iso.18013.5.1.aamva namespace, as per the AAMVA mDL Implementation
 /// Guidelines (Version 1.0).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DHSCompliance {
     F,
     N,
@@ -18,6 +18,13 @@ pub enum Error {
 }
 
 impl DHSCompliance {
+    /// Whether the credential is fully compliant with the REAL ID Act, as marked on a REAL ID
+    /// card. AAMVA carries REAL ID status in `DHS_compliance` rather than in an element of its
+    /// own.
+    pub fn is_real_id(&self) -> bool {
+        matches!(self, Self::F)
+    }
+
     pub fn to_str(&self) -> &'static str {
         match self {
             Self::F => "F",
@@ -4,12 +4,21 @@ use crate::{
     macros::{FromJson, ToCbor},
 };
 use serde_cbor::Value as Cbor;
+use std::ops::Deref;
 
 /// `domestic_driving_privileges` in the org.iso.18013.5.1.aamva namespace, as per the AAMVA mDL Implementation
 /// Guidelines (Version 1.0).
 #[derive(Clone, Debug, FromJson)]
 pub struct DomesticDrivingPrivileges(Vec<DomesticDrivingPrivilege>);
 
+impl Deref for DomesticDrivingPrivileges {
+    type Target = [DomesticDrivingPrivilege];
+
+    fn deref(&self) -> &[DomesticDrivingPrivilege] {
+        &self.0
+    }
+}
+
 impl ToCbor for DomesticDrivingPrivileges {
     fn to_cbor(self) -> Cbor {
         Cbor::Array(self.0.into_iter().map(ToCbor::to_cbor).collect())
@@ -34,6 +43,14 @@ pub struct DomesticVehicleClass {
 #[derive(Clone, Debug, FromJson)]
 pub struct DomesticVehicleRestrictions(NonEmptyVec<DomesticVehicleRestriction>);
 
+impl Deref for DomesticVehicleRestrictions {
+    type Target = [DomesticVehicleRestriction];
+
+    fn deref(&self) -> &[DomesticVehicleRestriction] {
+        &self.0
+    }
+}
+
 impl ToCbor for DomesticVehicleRestrictions {
     fn to_cbor(self) -> Cbor {
         Cbor::Array(
@@ -55,6 +72,14 @@ pub struct DomesticVehicleRestriction {
 #[derive(Clone, Debug, FromJson)]
 pub struct DomesticVehicleEndorsements(NonEmptyVec<DomesticVehicleEndorsement>);
 
+impl Deref for DomesticVehicleEndorsements {
+    type Target = [DomesticVehicleEndorsement];
+
+    fn deref(&self) -> &[DomesticVehicleEndorsement] {
+        &self.0
+    }
+}
+
 impl ToCbor for DomesticVehicleEndorsements {
     fn to_cbor(self) -> Cbor {
         Cbor::Array(
@@ -23,6 +23,35 @@ pub use weight_range::WeightRange;
 
 use crate::macros::{FromJson, ToCbor};
 
+/// The identifier of the `org.iso.18013.5.1.aamva` namespace.
+pub const NAMESPACE: &str = "org.iso.18013.5.1.aamva";
+
+/// The identifiers of the elements in the `org.iso.18013.5.1.aamva` namespace, for use in
+/// requests.
+pub mod elements {
+    pub const DOMESTIC_DRIVING_PRIVILEGES: &str = "domestic_driving_privileges";
+    pub const NAME_SUFFIX: &str = "name_suffix";
+    pub const ORGAN_DONOR: &str = "organ_donor";
+    pub const VETERAN: &str = "veteran";
+    pub const FAMILY_NAME_TRUNCATION: &str = "family_name_truncation";
+    pub const GIVEN_NAME_TRUNCATION: &str = "given_name_truncation";
+    pub const AKA_FAMILY_NAME_V2: &str = "aka_family_name.v2";
+    pub const AKA_GIVEN_NAME_V2: &str = "aka_given_name.v2";
+    pub const AKA_SUFFIX: &str = "aka_suffix";
+    pub const WEIGHT_RANGE: &str = "weight_range";
+    pub const RACE_ETHNICITY: &str = "race_ethnicity";
+    pub const EDL_CREDENTIAL: &str = "EDL_credential";
+    pub const SEX: &str = "sex";
+    /// Whether the credential is REAL ID compliant, see
+    /// [DHSCompliance::is_real_id](super::DHSCompliance::is_real_id).
+    pub const DHS_COMPLIANCE: &str = "DHS_compliance";
+    pub const RESIDENT_COUNTY: &str = "resident_county";
+    pub const HAZMAT_ENDORSEMENT_EXPIRATION_DATE: &str = "hazmat_endorsement_expiration_date";
+    pub const CDL_INDICATOR: &str = "CDL_indicator";
+    pub const DHS_COMPLIANCE_TEXT: &str = "DHS_compliance_text";
+    pub const DHS_TEMPORARY_LAWFUL_STATUS: &str = "DHS_temporary_lawful_status";
+}
+
 /// `org.iso.18013.5.1.aamva` namespace, as per the AAMVA mDL Implementation
 /// Guidelines (Version 1.2).
 #[derive(Debug, Clone, FromJson, ToCbor)]
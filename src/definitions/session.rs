@@ -25,6 +25,7 @@ use p384::NistP384;
 use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
 
 pub type EReaderKey = CoseKey;
 pub type EDeviceKey = CoseKey;
@@ -47,7 +48,17 @@ pub struct SessionData {
     pub status: Option<Status>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+impl SessionData {
+    /// A message carrying only a status code, to report an error or end the session.
+    pub fn from_status(status: Status) -> Self {
+        Self {
+            data: None,
+            status: Some(status),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(try_from = "u64", into = "u64")]
 pub enum Status {
     SessionEncryptionError,
@@ -78,6 +89,36 @@ impl TryFrom<u64> for Status {
     }
 }
 
+/// A failure to exchange session data, which should be reported to the peer with the
+/// corresponding [Status].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum SessionError {
+    #[error("session data could not be decrypted")]
+    Encryption,
+    #[error("session data could not be decoded")]
+    CborDecoding,
+    #[error("the session message counter is exhausted")]
+    CounterExhausted,
+}
+
+impl SessionError {
+    /// The status code reporting this error to the peer.
+    pub fn status(&self) -> Status {
+        match self {
+            SessionError::Encryption | SessionError::CounterExhausted => {
+                Status::SessionEncryptionError
+            }
+            SessionError::CborDecoding => Status::CborDecodingError,
+        }
+    }
+
+    /// The encoded session data reporting this error to the peer.
+    pub fn to_session_data(&self) -> Vec<u8> {
+        // Safe to unwrap as a status code always encodes to CBOR.
+        serde_cbor::to_vec(&SessionData::from_status(self.status())).unwrap()
+    }
+}
+
 pub trait SessionTranscript: Serialize + for<'a> Deserialize<'a> {}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -89,6 +130,40 @@ pub struct SessionTranscript180135(
 
 impl SessionTranscript for SessionTranscript180135 {}
 
+/// The session transcript of a presentation over OpenID4VP, as in the ISO 18013-7 profile, which
+/// has no device engagement or reader key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OID4VPSessionTranscript((), (), pub OID4VPHandover);
+
+impl SessionTranscript for OID4VPSessionTranscript {}
+
+/// Binds the presentation to the verifier's authorization request: the SHA-256 digests of the
+/// client id and of the response URI, each encoded with the mdoc generated nonce, and the nonce
+/// of the request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OID4VPHandover(pub ByteStr, pub ByteStr, pub String);
+
+impl OID4VPSessionTranscript {
+    /// Build the session transcript for the authorization request of `client_id`, answered at
+    /// `response_uri`.
+    pub fn new_oid4vp(
+        client_id: &str,
+        response_uri: &str,
+        nonce: &str,
+        mdoc_generated_nonce: &str,
+    ) -> Result<Self, serde_cbor::Error> {
+        let hash = |value: &str| -> Result<ByteStr, serde_cbor::Error> {
+            let bytes = serde_cbor::to_vec(&(value, mdoc_generated_nonce))?;
+            Ok(Sha256::digest(bytes).to_vec().into())
+        };
+        Ok(Self(
+            (),
+            (),
+            OID4VPHandover(hash(client_id)?, hash(response_uri)?, nonce.to_string()),
+        ))
+    }
+}
+
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum Error {
     #[error("Curve not supported for DH exchange")]
@@ -155,43 +230,97 @@ pub fn get_shared_secret(
     e_device_key_priv: &p256::NonZeroScalar,
 ) -> Result<SharedSecret<NistP256>> {
     let encoded_point: EncodedPoint<NistP256> = EncodedPoint::<NistP256>::try_from(cose_key)?;
-    let public_key_opt = p256::PublicKey::from_encoded_point(&encoded_point);
-    if public_key_opt.is_none().into() {
-        return Err(anyhow::anyhow!(
-            "reader's public key could not be constructed"
-        ));
-    }
-    let public_key = public_key_opt.unwrap();
+    let public_key: p256::PublicKey =
+        Option::from(p256::PublicKey::from_encoded_point(&encoded_point))
+            .ok_or_else(|| anyhow::anyhow!("reader's public key could not be constructed"))?;
     let shared_secret = p256::ecdh::diffie_hellman(e_device_key_priv, public_key.as_affine());
     Ok(shared_secret)
 }
 
+/// The session keys derived for a session, to check the key derivation against test vectors.
+#[cfg(feature = "test-vectors")]
+#[derive(Debug, Clone, PartialEq, Eq, zeroize::Zeroize, zeroize::ZeroizeOnDrop)]
+pub struct SessionKeys {
+    pub sk_device: [u8; 32],
+    pub sk_reader: [u8; 32],
+}
+
+/// The salt and info with which the session keys are derived from the shared secret by HKDF.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyDerivationProfile {
+    /// ISO/IEC 18013-5 proximity presentation: salted with the SHA-256 hash of the session
+    /// transcript bytes, with info "SKReader" and "SKDevice".
+    #[default]
+    Proximity,
+    /// A remote presentation profile, such as those of ISO/IEC 18013-7, with its own info for
+    /// each key. Without a salt of its own, it is salted as in proximity presentation.
+    Remote {
+        salt: Option<Vec<u8>>,
+        reader_info: String,
+        device_info: String,
+    },
+}
+
+impl KeyDerivationProfile {
+    /// Derive the key with which the reader (if `reader`) or the device encrypts its messages.
+    pub fn derive_session_key(
+        &self,
+        shared_secret: &SharedSecret<NistP256>,
+        session_transcript: &SessionTranscriptBytes,
+        reader: bool,
+    ) -> Result<GenericArray<u8, U32>> {
+        let salt = match self {
+            Self::Remote {
+                salt: Some(salt), ..
+            } => salt.clone(),
+            _ => Sha256::digest(serde_cbor::to_vec(session_transcript)?).to_vec(),
+        };
+        let info = match (self, reader) {
+            (Self::Proximity, true) => "SKReader",
+            (Self::Proximity, false) => "SKDevice",
+            (Self::Remote { reader_info, .. }, true) => reader_info,
+            (Self::Remote { device_info, .. }, false) => device_info,
+        };
+        let hkdf = shared_secret.extract::<Sha256>(Some(&salt));
+        let mut okm = Zeroizing::new([0u8; 32]);
+
+        // Safe to unwrap as error will only occur if okm.len() is greater than 255 * 32;
+        Hkdf::expand(&hkdf, info.as_bytes(), &mut *okm).unwrap();
+
+        Ok((*okm).into())
+    }
+}
+
+/// Derive a session key as in ISO/IEC 18013-5 proximity presentation.
 pub fn derive_session_key(
     shared_secret: &SharedSecret<NistP256>,
     session_transcript: &SessionTranscriptBytes,
     reader: bool,
+) -> Result<GenericArray<u8, U32>> {
+    KeyDerivationProfile::Proximity.derive_session_key(shared_secret, session_transcript, reader)
+}
+
+/// Derive the key for MAC device authentication from the agreement between the device key and
+/// the reader's ephemeral key.
+pub fn derive_emac_key(
+    shared_secret: &SharedSecret<NistP256>,
+    session_transcript: &SessionTranscriptBytes,
 ) -> Result<GenericArray<u8, U32>> {
     let salt = Sha256::digest(serde_cbor::to_vec(session_transcript)?);
     let hkdf = shared_secret.extract::<Sha256>(Some(salt.as_ref()));
-    let mut okm = [0u8; 32];
-    let sk_device = "SKDevice".as_bytes();
-    let sk_reader = "SKReader".as_bytes();
+    let mut okm = Zeroizing::new([0u8; 32]);
 
     // Safe to unwrap as error will only occur if okm.len() is greater than 255 * 32;
-    if reader {
-        Hkdf::expand(&hkdf, sk_reader, &mut okm).unwrap();
-    } else {
-        Hkdf::expand(&hkdf, sk_device, &mut okm).unwrap();
-    }
+    Hkdf::expand(&hkdf, "EMacKey".as_bytes(), &mut *okm).unwrap();
 
-    Ok(okm.into())
+    Ok((*okm).into())
 }
 
 pub fn encrypt_device_data(
     sk_device: &GenericArray<u8, U32>,
     plaintext: &[u8],
     message_count: &mut u32,
-) -> Result<Vec<u8>, aes_gcm::Error> {
+) -> Result<Vec<u8>, SessionError> {
     encrypt(sk_device, plaintext, message_count, false)
 }
 
@@ -199,26 +328,32 @@ pub fn encrypt_reader_data(
     sk_reader: &GenericArray<u8, U32>,
     plaintext: &[u8],
     message_count: &mut u32,
-) -> Result<Vec<u8>, aes_gcm::Error> {
+) -> Result<Vec<u8>, SessionError> {
     encrypt(sk_reader, plaintext, message_count, true)
 }
 
+/// Encrypt the next message, with the counter following `message_count`, which is advanced only
+/// if encryption succeeds.
 fn encrypt(
     session_key: &GenericArray<u8, U32>,
     plaintext: &[u8],
     message_count: &mut u32,
     reader: bool,
-) -> Result<Vec<u8>, aes_gcm::Error> {
-    let initialization_vector = get_initialization_vector(message_count, reader);
+) -> Result<Vec<u8>, SessionError> {
+    let (next_count, initialization_vector) = next_initialization_vector(*message_count, reader)?;
     let nonce = Nonce::from(initialization_vector);
-    Aes256Gcm::new(session_key).encrypt(&nonce, plaintext)
+    let ciphertext = Aes256Gcm::new(session_key)
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| SessionError::Encryption)?;
+    *message_count = next_count;
+    Ok(ciphertext)
 }
 
 pub fn decrypt_device_data(
     sk_device: &GenericArray<u8, U32>,
     ciphertext: &[u8],
     message_count: &mut u32,
-) -> Result<Vec<u8>, aes_gcm::Error> {
+) -> Result<Vec<u8>, SessionError> {
     decrypt(sk_device, ciphertext, message_count, false)
 }
 
@@ -226,19 +361,40 @@ pub fn decrypt_reader_data(
     sk_reader: &GenericArray<u8, U32>,
     ciphertext: &[u8],
     message_count: &mut u32,
-) -> Result<Vec<u8>, aes_gcm::Error> {
+) -> Result<Vec<u8>, SessionError> {
     decrypt(sk_reader, ciphertext, message_count, true)
 }
 
+/// Decrypt the next message, which must have been encrypted with the counter following
+/// `message_count`. The counter is advanced only if decryption succeeds, so a replayed or
+/// reordered message is rejected without desynchronising the session.
 fn decrypt(
     session_key: &GenericArray<u8, U32>,
     ciphertext: &[u8],
     message_count: &mut u32,
     reader: bool,
-) -> Result<Vec<u8>, aes_gcm::Error> {
-    let initialization_vector = get_initialization_vector(message_count, reader);
+) -> Result<Vec<u8>, SessionError> {
+    let (next_count, initialization_vector) = next_initialization_vector(*message_count, reader)?;
     let nonce = Nonce::from(initialization_vector);
-    Aes256Gcm::new(session_key).decrypt(&nonce, ciphertext)
+    // The authentication tag is checked by aes-gcm, which compares it in constant time.
+    let plaintext = Aes256Gcm::new(session_key)
+        .decrypt(&nonce, ciphertext)
+        .map_err(|_| SessionError::Encryption)?;
+    *message_count = next_count;
+    Ok(plaintext)
+}
+
+/// The counter and initialization vector of the message following `message_count`. The counter
+/// must not wrap around, as that would reuse a nonce.
+fn next_initialization_vector(
+    mut message_count: u32,
+    reader: bool,
+) -> Result<(u32, [u8; 12]), SessionError> {
+    if message_count == u32::MAX {
+        return Err(SessionError::CounterExhausted);
+    }
+    let initialization_vector = get_initialization_vector(&mut message_count, reader);
+    Ok((message_count, initialization_vector))
 }
 
 pub fn get_initialization_vector(message_count: &mut u32, reader: bool) -> [u8; 12] {
@@ -372,6 +528,28 @@ mod test {
         }
     }
 
+    #[test]
+    fn oid4vp_session_transcript() {
+        let session_transcript = OID4VPSessionTranscript::new_oid4vp(
+            "example.com",
+            "https://example.com/12345/response",
+            "abcdefgh1234567890",
+            "1234567890abcdefgh",
+        )
+        .unwrap();
+        // [null, null, [clientIdHash, responseUriHash, nonce]]
+        let expected = hex::decode(concat!(
+            "83f6f683",
+            "5820da25c527e5fb75bc2dd31267c02237c4462ba0c1bf37071f692e7dd93b10ad0b",
+            "5820f6ed8e3220d3c59a5f17eb45f48ab70aeecf9ee21744b1014982350bd96ac0c5",
+            "72616263646566676831323334353637383930"
+        ))
+        .unwrap();
+        assert_eq!(serde_cbor::to_vec(&session_transcript).unwrap(), expected);
+        let roundtripped: OID4VPSessionTranscript = serde_cbor::from_slice(&expected).unwrap();
+        assert_eq!(roundtripped.2 .2, "abcdefgh1234567890");
+    }
+
     #[test]
     fn key_generation() {
         //todo fully test the exchange of keys and the resulting session keys e2e
@@ -398,9 +576,11 @@ mod test {
         let device_engagement = DeviceEngagement {
             version: "1.0".into(),
             security: Security(1, device_key_bytes),
+            #[cfg(feature = "transports")]
             device_retrieval_methods: None,
             server_retrieval_methods: None,
             protocol_info: None,
+            origin_infos: None,
         };
 
         let device_engagement_bytes = Tag24::new(device_engagement).unwrap();
@@ -431,6 +611,51 @@ mod test {
         assert_eq!(plaintext, decrypted_plaintext);
     }
 
+    #[test]
+    fn replayed_message() {
+        let session_key = GenericArray::from([7u8; 32]);
+        let mut sent = 0;
+        let first = encrypt_reader_data(&session_key, b"first", &mut sent).unwrap();
+        let second = encrypt_reader_data(&session_key, b"second", &mut sent).unwrap();
+        assert_eq!(sent, 2);
+
+        let mut received = 0;
+        decrypt_reader_data(&session_key, &first, &mut received).unwrap();
+        assert_eq!(
+            decrypt_reader_data(&session_key, &first, &mut received),
+            Err(SessionError::Encryption)
+        );
+        // The rejected message does not advance the counter.
+        assert_eq!(received, 1);
+        let plaintext = decrypt_reader_data(&session_key, &second, &mut received).unwrap();
+        assert_eq!(plaintext, b"second");
+        assert_eq!(received, 2);
+    }
+
+    #[test]
+    fn message_counter_exhausted() {
+        let session_key = GenericArray::from([7u8; 32]);
+        let mut sent = u32::MAX - 1;
+        let last = encrypt_device_data(&session_key, b"last", &mut sent).unwrap();
+        assert_eq!(sent, u32::MAX);
+        assert_eq!(
+            encrypt_device_data(&session_key, b"one too many", &mut sent),
+            Err(SessionError::CounterExhausted)
+        );
+        assert_eq!(sent, u32::MAX);
+
+        let mut received = u32::MAX - 1;
+        decrypt_device_data(&session_key, &last, &mut received).unwrap();
+        assert_eq!(
+            decrypt_device_data(&session_key, &last, &mut received),
+            Err(SessionError::CounterExhausted)
+        );
+        assert_eq!(
+            SessionError::CounterExhausted.status(),
+            Status::SessionEncryptionError
+        );
+    }
+
     #[test]
     fn handle_session_establishment_and_decrypt_device_request() {
         const E_DEVICE_KEY: &str = include_str!("../../test/definitions/session/e_device_key.cbor");
@@ -471,4 +696,85 @@ mod test {
             decrypt_reader_data(&session_key, encrypted_request.as_ref(), &mut 0).unwrap();
         let _device_request: DeviceRequest = serde_cbor::from_slice(&plaintext).unwrap();
     }
+
+    #[test]
+    fn key_derivation_profiles() {
+        const E_DEVICE_KEY: &str = include_str!("../../test/definitions/session/e_device_key.cbor");
+        const SESSION_ESTABLISHMENT: &str =
+            include_str!("../../test/definitions/session/session_establishment.cbor");
+        const SESSION_TRANSCRIPT: &str =
+            include_str!("../../test/definitions/session/session_transcript.cbor");
+        const READER_SESSION_KEY: &str =
+            include_str!("../../test/definitions/session/reader_session_key.cbor");
+
+        let e_device_key =
+            p256::SecretKey::from_slice(&hex::decode(E_DEVICE_KEY).unwrap()).unwrap();
+        let session_establishment: SessionEstablishment =
+            serde_cbor::from_slice(&hex::decode(SESSION_ESTABLISHMENT).unwrap()).unwrap();
+        let shared_secret = get_shared_secret(
+            session_establishment.e_reader_key.into_inner(),
+            &e_device_key.to_nonzero_scalar(),
+        )
+        .unwrap();
+        let session_transcript: SessionTranscriptBytes =
+            serde_cbor::from_slice(&hex::decode(SESSION_TRANSCRIPT).unwrap()).unwrap();
+        let derive = |profile: &KeyDerivationProfile, reader| {
+            hex::encode(
+                profile
+                    .derive_session_key(&shared_secret, &session_transcript, reader)
+                    .unwrap(),
+            )
+        };
+
+        let proximity = KeyDerivationProfile::default();
+        assert_eq!(derive(&proximity, true), READER_SESSION_KEY);
+        assert_eq!(
+            derive(&proximity, false),
+            "81d170e07fbdac93c1a676242c2576124a380d87bb73ed9ce4834de2272cf409"
+        );
+
+        // The Remote keys below are regression vectors produced by this implementation, not
+        // published test vectors; they pin the derivation so that changes to it are noticed.
+
+        // Salted with the hash of the session transcript, as in proximity presentation.
+        let remote = KeyDerivationProfile::Remote {
+            salt: None,
+            reader_info: "SKReaderRemote".into(),
+            device_info: "SKDeviceRemote".into(),
+        };
+        assert_eq!(
+            derive(&remote, true),
+            "95a0f22e56a17817db90d417f48fd1e32abf1a7f803f9e4de5cfe4c41f8c3e36"
+        );
+        assert_eq!(
+            derive(&remote, false),
+            "f9033428b597d9d27f59da4eeb30cbf41be24700cc8d69ac63034799f4268145"
+        );
+
+        let remote = KeyDerivationProfile::Remote {
+            salt: Some(b"remote salt".to_vec()),
+            reader_info: "SKReaderRemote".into(),
+            device_info: "SKDeviceRemote".into(),
+        };
+        assert_eq!(
+            derive(&remote, true),
+            "ecc2bd74ca39bae9294465f46e6b326e75ab780ccaed68ecad2a54bfd216499f"
+        );
+        assert_eq!(
+            derive(&remote, false),
+            "e08b9976fb3f0dd864859a9db9458be8a532b933b62d29b48f47287272f4a268"
+        );
+    }
+
+    #[test]
+    fn session_error_status() {
+        // Status 10: error, session encryption.
+        let encoded = SessionError::Encryption.to_session_data();
+        assert_eq!(hex::encode(encoded), "a1667374617475730a");
+        // Status 11: error, CBOR decoding.
+        let session_data: SessionData =
+            serde_cbor::from_slice(&SessionError::CborDecoding.to_session_data()).unwrap();
+        assert!(session_data.data.is_none());
+        assert_eq!(session_data.status, Some(Status::CborDecodingError));
+    }
 }
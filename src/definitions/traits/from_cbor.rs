@@ -0,0 +1,89 @@
+use super::{FromJson, FromJsonError};
+use serde_cbor::Value as CborValue;
+use serde_json::{json, Value};
+
+/// Decode a disclosed element value into its typed definition, through the same JSON mapping as
+/// at issuance. Tagged values, such as full-dates, decode from their content, and byte strings
+/// from base64.
+pub trait FromCbor: Sized {
+    fn from_cbor(v: &CborValue) -> Result<Self, FromJsonError>;
+}
+
+impl<T: FromJson> FromCbor for T {
+    fn from_cbor(v: &CborValue) -> Result<Self, FromJsonError> {
+        T::from_json(&cbor_to_json(v))
+    }
+}
+
+/// Convert a disclosed element value to JSON, the inverse of its mapping from JSON at issuance.
+pub(crate) fn cbor_to_json(value: &CborValue) -> Value {
+    match value {
+        CborValue::Null => Value::Null,
+        CborValue::Bool(b) => json!(b),
+        CborValue::Integer(i) => i64::try_from(*i)
+            .map(|i| json!(i))
+            .or_else(|_| u64::try_from(*i).map(|i| json!(i)))
+            .unwrap_or_else(|_| json!(i.to_string())),
+        CborValue::Float(f) => json!(f),
+        CborValue::Bytes(b) => json!(base64::encode(b)),
+        CborValue::Text(s) => json!(s),
+        CborValue::Array(values) => Value::Array(values.iter().map(cbor_to_json).collect()),
+        CborValue::Map(entries) => Value::Object(
+            entries
+                .iter()
+                .filter_map(|(key, value)| {
+                    let key = match key {
+                        CborValue::Text(key) => key.clone(),
+                        CborValue::Integer(key) => key.to_string(),
+                        _ => return None,
+                    };
+                    Some((key, cbor_to_json(value)))
+                })
+                .collect(),
+        ),
+        CborValue::Tag(_, value) => cbor_to_json(value),
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cbor_to_json_mapping() {
+        let value = CborValue::Map(
+            [
+                (
+                    CborValue::Text("birth_date".into()),
+                    CborValue::Tag(1004, Box::new(CborValue::Text("1980-01-01".into()))),
+                ),
+                (
+                    CborValue::Text("portrait".into()),
+                    CborValue::Bytes(vec![0xff, 0xd8, 0xff]),
+                ),
+                (
+                    CborValue::Text("driving_privileges".into()),
+                    CborValue::Array(vec![CborValue::Map(
+                        [(
+                            CborValue::Text("vehicle_category_code".into()),
+                            CborValue::Text("A".into()),
+                        )]
+                        .into(),
+                    )]),
+                ),
+                (CborValue::Integer(1), CborValue::Integer(-1)),
+                (CborValue::Text("age_over_21".into()), CborValue::Bool(true)),
+            ]
+            .into(),
+        );
+        let expected = json!({
+            "birth_date": "1980-01-01",
+            "portrait": "/9j/",
+            "driving_privileges": [{ "vehicle_category_code": "A" }],
+            "1": -1,
+            "age_over_21": true,
+        });
+        assert_eq!(cbor_to_json(&value), expected);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::definitions::helpers::{ByteStr, NonEmptyVec};
+use serde_cbor::Value as CborValue;
 use serde_json::{Map, Value};
 use std::collections::BTreeMap;
 
@@ -113,6 +114,39 @@ impl FromJson for ByteStr {
     }
 }
 
+/// Maps JSON onto CBOR structurally, for element values that have no dedicated type, such as those
+/// of namespaces other than the mDL's.
+impl FromJson for CborValue {
+    fn from_json(v: &Value) -> Result<Self, FromJsonError> {
+        match v {
+            Value::Null => Ok(CborValue::Null),
+            Value::Bool(b) => Ok(CborValue::Bool(*b)),
+            Value::Number(n) => {
+                if let Some(i) = n.as_i64() {
+                    Ok(CborValue::Integer(i.into()))
+                } else if let Some(u) = n.as_u64() {
+                    Ok(CborValue::Integer(u.into()))
+                } else {
+                    n.as_f64()
+                        .map(CborValue::Float)
+                        .ok_or(FromJsonError::UnexpectedType("number", "float"))
+                }
+            }
+            Value::String(s) => Ok(CborValue::Text(s.clone())),
+            Value::Array(v) => v
+                .iter()
+                .map(CborValue::from_json)
+                .collect::<Result<_, _>>()
+                .map(CborValue::Array),
+            Value::Object(m) => m
+                .iter()
+                .map(|(k, v)| Ok((CborValue::Text(k.clone()), CborValue::from_json(v)?)))
+                .collect::<Result<_, _>>()
+                .map(CborValue::Map),
+        }
+    }
+}
+
 impl<T> FromJson for BTreeMap<String, T>
 where
     T: FromJson,
@@ -191,6 +225,43 @@ mod tests {
         assert!(s.a.is_none());
     }
 
+    #[test]
+    fn cbor_value() {
+        let v: Value =
+            json!({ "a": [1, -1, 1.5, "b", true, null], "c": { "d": 18446744073709551615u64 } });
+        let cbor = CborValue::from_json(&v).unwrap();
+
+        let expected = CborValue::Map(
+            [
+                (
+                    CborValue::Text("a".into()),
+                    CborValue::Array(vec![
+                        CborValue::Integer(1),
+                        CborValue::Integer(-1),
+                        CborValue::Float(1.5),
+                        CborValue::Text("b".into()),
+                        CborValue::Bool(true),
+                        CborValue::Null,
+                    ]),
+                ),
+                (
+                    CborValue::Text("c".into()),
+                    CborValue::Map(
+                        [(
+                            CborValue::Text("d".into()),
+                            CborValue::Integer(u64::MAX.into()),
+                        )]
+                        .into_iter()
+                        .collect(),
+                    ),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(cbor, expected);
+    }
+
     #[test]
     fn int_as_some() {
         let v: Value = json!({ "a": 11 });
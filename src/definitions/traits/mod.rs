@@ -1,5 +1,8 @@
+mod from_cbor;
 mod from_json;
 mod to_cbor;
 
+pub(crate) use from_cbor::cbor_to_json;
+pub use from_cbor::FromCbor;
 pub use from_json::{FromJson, FromJsonError, FromJsonMap};
 pub use to_cbor::{ToCbor, ToCborError, ToCborMap, ToNamespaceMap};
@@ -6,7 +6,7 @@ use serde_cbor::Value as CborValue;
 use std::collections::BTreeMap;
 use time::{
     error::Format as FormatError, error::Parse as ParseError,
-    format_description::well_known::Rfc3339, OffsetDateTime, UtcOffset,
+    format_description::well_known::Rfc3339, Duration, OffsetDateTime, UtcOffset,
 };
 
 #[derive(Clone, Debug, Deserialize)]
@@ -36,6 +36,112 @@ pub enum Error {
     UnableToFormatDate(#[from] FormatError),
     #[error("Failed to parse date string as rfc3339 date: {0}")]
     UnableToParseDate(#[from] ParseError),
+    #[error("missing parameter: '{0}'")]
+    MissingParameter(&'static str),
+    #[error("'{0}' must not be earlier than '{1}'")]
+    OutOfOrder(&'static str, &'static str),
+}
+
+/// Builds a [ValidityInfo], where `signed` defaults to now and `validFrom` defaults to `signed`.
+#[derive(Debug, Clone, Default)]
+pub struct Builder {
+    signed: Option<OffsetDateTime>,
+    valid_from: Option<OffsetDateTime>,
+    valid_until: Option<OffsetDateTime>,
+    valid_for: Option<Duration>,
+    expected_update: Option<OffsetDateTime>,
+}
+
+/// Whether a document should be refreshed from its issuer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshStatus {
+    Current,
+    /// The issuer expected to have updated the document by now.
+    UpdateDue,
+    /// The document is past its `validUntil`.
+    Expired,
+}
+
+impl ValidityInfo {
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
+    /// Whether the document should be refreshed at `now`. A document that is not yet valid is
+    /// current.
+    pub fn refresh_status(&self, now: OffsetDateTime) -> RefreshStatus {
+        if now > self.valid_until {
+            RefreshStatus::Expired
+        } else if self
+            .expected_update
+            .is_some_and(|expected_update| now > expected_update)
+        {
+            RefreshStatus::UpdateDue
+        } else {
+            RefreshStatus::Current
+        }
+    }
+}
+
+impl Builder {
+    /// Set when the mobile security object was signed.
+    pub fn signed(mut self, signed: OffsetDateTime) -> Self {
+        self.signed = Some(signed);
+        self
+    }
+
+    /// Set the start of the validity window.
+    pub fn valid_from(mut self, valid_from: OffsetDateTime) -> Self {
+        self.valid_from = Some(valid_from);
+        self
+    }
+
+    /// Set the end of the validity window.
+    pub fn valid_until(mut self, valid_until: OffsetDateTime) -> Self {
+        self.valid_until = Some(valid_until);
+        self.valid_for = None;
+        self
+    }
+
+    /// Set the end of the validity window to `duration` after its start.
+    pub fn valid_for(mut self, duration: Duration) -> Self {
+        self.valid_for = Some(duration);
+        self.valid_until = None;
+        self
+    }
+
+    /// Set when the issuer expects to re-sign the mobile security object.
+    pub fn expected_update(mut self, expected_update: OffsetDateTime) -> Self {
+        self.expected_update = Some(expected_update);
+        self
+    }
+
+    pub fn build(self) -> Result<ValidityInfo> {
+        let signed = self.signed.unwrap_or_else(OffsetDateTime::now_utc);
+        let valid_from = self.valid_from.unwrap_or(signed);
+        let valid_until = self
+            .valid_until
+            .or_else(|| self.valid_for.map(|duration| valid_from + duration))
+            .ok_or(Error::MissingParameter("valid_until"))?;
+        if valid_from < signed {
+            return Err(Error::OutOfOrder("validFrom", "signed"));
+        }
+        if valid_until < valid_from {
+            return Err(Error::OutOfOrder("validUntil", "validFrom"));
+        }
+        if self
+            .expected_update
+            .is_some_and(|expected_update| expected_update < signed)
+        {
+            return Err(Error::OutOfOrder("expectedUpdate", "signed"));
+        }
+        Ok(ValidityInfo {
+            signed,
+            valid_from,
+            valid_until,
+            expected_update: self.expected_update,
+        })
+    }
 }
 
 impl TryFrom<ValidityInfo> for CborValue {
@@ -156,4 +262,70 @@ mod test {
         let trimmed = hex::decode("A3667369676E6564C074323032302D30312D30315430303A30303A30305A6976616C696446726F6DC074323032302D30312D30315430303A30303A30305A6A76616C6964556E74696CC074323032302D30312D30315430303A30303A30305A").unwrap();
         assert_eq!(trimmed, roundtripped);
     }
+
+    #[test]
+    fn builder() {
+        let signed = OffsetDateTime::parse("2020-01-01T00:00:00Z", &Rfc3339).unwrap();
+        let validity_info = ValidityInfo::builder()
+            .signed(signed)
+            .valid_from(signed + Duration::days(1))
+            .valid_for(Duration::days(365))
+            .build()
+            .unwrap();
+        assert_eq!(validity_info.signed, signed);
+        assert_eq!(validity_info.valid_from, signed + Duration::days(1));
+        assert_eq!(validity_info.valid_until, signed + Duration::days(366));
+        assert_eq!(validity_info.expected_update, None);
+
+        assert!(matches!(
+            ValidityInfo::builder().signed(signed).build(),
+            Err(Error::MissingParameter(_))
+        ));
+        assert!(matches!(
+            ValidityInfo::builder()
+                .signed(signed)
+                .valid_from(signed - Duration::days(1))
+                .valid_for(Duration::days(365))
+                .build(),
+            Err(Error::OutOfOrder("validFrom", "signed"))
+        ));
+        assert!(matches!(
+            ValidityInfo::builder()
+                .signed(signed)
+                .valid_until(signed - Duration::days(1))
+                .build(),
+            Err(Error::OutOfOrder("validUntil", "validFrom"))
+        ));
+    }
+
+    #[test]
+    fn refresh_status() {
+        let signed = OffsetDateTime::parse("2020-01-01T00:00:00Z", &Rfc3339).unwrap();
+        let validity_info = ValidityInfo::builder()
+            .signed(signed)
+            .valid_for(Duration::days(365))
+            .expected_update(signed + Duration::days(300))
+            .build()
+            .unwrap();
+        let status_after = |days| validity_info.refresh_status(signed + Duration::days(days));
+        assert_eq!(status_after(-1), RefreshStatus::Current);
+        assert_eq!(status_after(300), RefreshStatus::Current);
+        assert_eq!(status_after(301), RefreshStatus::UpdateDue);
+        assert_eq!(status_after(365), RefreshStatus::UpdateDue);
+        assert_eq!(status_after(366), RefreshStatus::Expired);
+
+        // Without an expected update, a document is current until it expires.
+        let validity_info = ValidityInfo {
+            expected_update: None,
+            ..validity_info
+        };
+        assert_eq!(
+            validity_info.refresh_status(signed + Duration::days(365)),
+            RefreshStatus::Current
+        );
+        assert_eq!(
+            validity_info.refresh_status(signed + Duration::days(366)),
+            RefreshStatus::Expired
+        );
+    }
 }
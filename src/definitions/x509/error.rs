@@ -0,0 +1,24 @@
+/// Errors that can occur when validating an x5chain.
+#[derive(Debug, Clone, thiserror::Error, PartialEq, Eq)]
+pub enum X509Error {
+    #[error("certificate {0} is not signed by the next certificate in the chain: {1}")]
+    SignatureVerification(usize, String),
+    #[error("certificate {0} could not be parsed: {1}")]
+    Malformed(usize, String),
+    #[error("certificate {0} is not yet valid")]
+    NotYetValid(usize),
+    #[error("certificate {0} has expired")]
+    Expired(usize),
+    #[error("certificate {0} has invalid key usage: {1}")]
+    KeyUsage(usize, String),
+    #[error("trust anchor does not conform to the IACA profile: {0}")]
+    IacaProfile(String),
+    #[error("certificate {0} has been revoked")]
+    Revoked(usize),
+    #[error("the revocation status of certificate {0} is unknown")]
+    RevocationUnknown(usize),
+    #[error("unable to check the revocation status of certificate {0}: {1}")]
+    RevocationCheck(usize, String),
+    #[error("no trust anchor found for certificate {0}")]
+    NoTrustAnchor(usize),
+}
@@ -0,0 +1,13 @@
+pub mod error;
+#[cfg(feature = "ocsp")]
+pub mod ocsp;
+pub mod revocation;
+pub mod trust_anchor;
+pub mod vical;
+pub mod x5chain;
+
+pub use error::X509Error;
+pub use revocation::RevocationStatus;
+pub use trust_anchor::{KeyUsageProfile, TrustAnchorRegistry};
+pub use vical::Vical;
+pub use x5chain::{X5Chain, X509};
@@ -0,0 +1,393 @@
+//! OCSP revocation checking for x5chain certificates (RFC 6960).
+use crate::definitions::x509::{
+    x5chain::{check_signature, verify_signature},
+    RevocationStatus, X509,
+};
+use anyhow::{anyhow, Result};
+use der::{
+    asn1::{Any, BitString, GeneralizedTime, Null, OctetString},
+    oid::db::{rfc5280, rfc5912, rfc6960},
+    Decode, Encode, Enumerated, Sequence, Tag, TagNumber, Tagged,
+};
+use sha1::{Digest, Sha1};
+use std::{io::Read, time::Duration};
+use time::OffsetDateTime;
+use x509_cert::{
+    ext::{
+        pkix::{name::GeneralName, AuthorityInfoAccessSyntax, ExtendedKeyUsage},
+        Extensions,
+    },
+    serial_number::SerialNumber,
+    spki::AlgorithmIdentifierOwned,
+    Certificate,
+};
+
+/// The largest OCSP response read from a responder. A basic response for a single certificate,
+/// with the responder's certificate, is a few kilobytes.
+const MAX_OCSP_RESPONSE_LEN: u64 = 64 * 1024;
+
+/// Checks the revocation status of certificates against the OCSP responder named in their
+/// authority information access extension.
+#[derive(Debug, Clone)]
+pub struct OcspClient {
+    agent: ureq::Agent,
+}
+
+impl Default for OcspClient {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(10))
+    }
+}
+
+impl OcspClient {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new().timeout(timeout).build(),
+        }
+    }
+
+    /// Fetch the revocation status of `cert` from its OCSP responder. `issuer` must be the
+    /// certificate that issued `cert`.
+    pub fn check(&self, cert: &X509, issuer: &X509) -> Result<RevocationStatus> {
+        let url = responder_url(cert)?
+            .ok_or_else(|| anyhow!("certificate does not name an OCSP responder"))?;
+        let request = build_request(cert, issuer)?;
+        let mut response = vec![];
+        self.agent
+            .post(&url)
+            .set("Content-Type", "application/ocsp-request")
+            .send_bytes(&request)
+            .map_err(|e| anyhow!("OCSP request to {} failed: {}", url, e))?
+            .into_reader()
+            .take(MAX_OCSP_RESPONSE_LEN + 1)
+            .read_to_end(&mut response)?;
+        if response.len() as u64 > MAX_OCSP_RESPONSE_LEN {
+            return Err(anyhow!(
+                "OCSP response from {} exceeds {} bytes",
+                url,
+                MAX_OCSP_RESPONSE_LEN
+            ));
+        }
+        parse_response(&response, cert, issuer)
+    }
+}
+
+/// The OCSP responder URL from the authority information access extension of `cert`.
+pub fn responder_url(cert: &X509) -> Result<Option<String>> {
+    let aia = cert
+        .certificate()?
+        .tbs_certificate
+        .get::<AuthorityInfoAccessSyntax>()
+        .map_err(|e| anyhow!("unable to parse authorityInfoAccess extension: {}", e))?;
+    Ok(aia.and_then(|(_critical, aia)| {
+        aia.0.into_iter().find_map(|description| {
+            match (description.access_method, description.access_location) {
+                (rfc5280::ID_AD_OCSP, GeneralName::UniformResourceIdentifier(uri)) => {
+                    Some(uri.to_string())
+                }
+                _ => None,
+            }
+        })
+    }))
+}
+
+/// Encode an OCSP request for `cert`, without a nonce.
+pub fn build_request(cert: &X509, issuer: &X509) -> Result<Vec<u8>> {
+    let request = OcspRequest {
+        tbs_request: TbsRequest {
+            request_list: vec![Request {
+                req_cert: cert_id(&cert.certificate()?, &issuer.certificate()?)?,
+            }],
+        },
+    };
+    request
+        .to_der()
+        .map_err(|e| anyhow!("unable to encode OCSP request: {}", e))
+}
+
+/// Parse an OCSP response for `cert` and check that it was signed by `issuer`, or by a
+/// responder that `issuer` delegated to.
+pub fn parse_response(response: &[u8], cert: &X509, issuer: &X509) -> Result<RevocationStatus> {
+    parse_response_at(response, cert, issuer, OffsetDateTime::now_utc())
+}
+
+fn parse_response_at(
+    response: &[u8],
+    cert: &X509,
+    issuer: &X509,
+    now: OffsetDateTime,
+) -> Result<RevocationStatus> {
+    let response = OcspResponse::from_der(response)
+        .map_err(|e| anyhow!("unable to parse OCSP response: {}", e))?;
+    if response.response_status != OcspResponseStatus::Successful {
+        return Err(anyhow!(
+            "OCSP responder returned {:?}",
+            response.response_status
+        ));
+    }
+    let response_bytes = response
+        .response_bytes
+        .ok_or_else(|| anyhow!("OCSP response has no response bytes"))?;
+    if response_bytes.response_type != rfc6960::ID_PKIX_OCSP_BASIC {
+        return Err(anyhow!(
+            "unsupported OCSP response type: {}",
+            response_bytes.response_type
+        ));
+    }
+    let basic = BasicOcspResponse::from_der(response_bytes.response.as_bytes())
+        .map_err(|e| anyhow!("unable to parse basic OCSP response: {}", e))?;
+
+    let tbs = basic
+        .tbs_response_data
+        .to_der()
+        .map_err(|e| anyhow!("unable to encode OCSP response data: {}", e))?;
+    let signature = basic
+        .signature
+        .as_bytes()
+        .ok_or_else(|| anyhow!("OCSP response signature is not octet aligned"))?;
+    verify_response_signature(&basic, &tbs, signature, issuer)?;
+
+    let response_data: ResponseData = basic
+        .tbs_response_data
+        .decode_as()
+        .map_err(|e| anyhow!("unable to parse OCSP response data: {}", e))?;
+    let cert_id = cert_id(&cert.certificate()?, &issuer.certificate()?)?;
+    let single = response_data
+        .responses
+        .into_iter()
+        .find(|single| single.cert_id == cert_id)
+        .ok_or_else(|| anyhow!("OCSP response does not cover the certificate"))?;
+
+    if now < OffsetDateTime::UNIX_EPOCH + single.this_update.to_unix_duration() {
+        return Err(anyhow!("OCSP response is not yet valid"));
+    }
+    if let Some(next_update) = single.next_update {
+        if now > OffsetDateTime::UNIX_EPOCH + next_update.to_unix_duration() {
+            return Err(anyhow!("OCSP response has expired"));
+        }
+    }
+
+    match single.cert_status.tag() {
+        Tag::ContextSpecific { number, .. } if number == TagNumber::N0 => {
+            Ok(RevocationStatus::Good)
+        }
+        Tag::ContextSpecific { number, .. } if number == TagNumber::N1 => {
+            Ok(RevocationStatus::Revoked)
+        }
+        Tag::ContextSpecific { number, .. } if number == TagNumber::N2 => {
+            Ok(RevocationStatus::Unknown)
+        }
+        tag => Err(anyhow!("unexpected OCSP certificate status: {}", tag)),
+    }
+}
+
+/// Check that an OCSP response was signed by `issuer`, or by a delegated responder whose
+/// certificate is included in the response and was issued by `issuer` for OCSP signing.
+fn verify_response_signature(
+    basic: &BasicOcspResponse,
+    tbs: &[u8],
+    signature: &[u8],
+    issuer: &X509,
+) -> Result<()> {
+    if verify_signature(issuer, &basic.signature_algorithm, tbs, signature).is_ok() {
+        return Ok(());
+    }
+    basic
+        .certs
+        .iter()
+        .flatten()
+        .filter_map(|cert| cert.to_der().ok().and_then(|der| X509::from_der(&der).ok()))
+        .find(|responder| {
+            let ocsp_signing = responder
+                .certificate()
+                .ok()
+                .and_then(|cert| cert.tbs_certificate.get::<ExtendedKeyUsage>().ok())
+                .flatten()
+                .map(|(_critical, eku)| eku.0.contains(&rfc5280::ID_KP_OCSP_SIGNING))
+                .unwrap_or(false);
+            ocsp_signing
+                && check_signature(responder, issuer).is_ok()
+                && verify_signature(responder, &basic.signature_algorithm, tbs, signature).is_ok()
+        })
+        .map(|_responder| ())
+        .ok_or_else(|| anyhow!("OCSP response is not signed by the issuer or its delegate"))
+}
+
+fn cert_id(cert: &Certificate, issuer: &Certificate) -> Result<CertId> {
+    let issuer_name = issuer
+        .tbs_certificate
+        .subject
+        .to_der()
+        .map_err(|e| anyhow!("unable to encode issuer name: {}", e))?;
+    let issuer_key = issuer
+        .tbs_certificate
+        .subject_public_key_info
+        .subject_public_key
+        .raw_bytes();
+    Ok(CertId {
+        hash_algorithm: AlgorithmIdentifierOwned {
+            oid: rfc5912::ID_SHA_1,
+            parameters: Some(Null.into()),
+        },
+        issuer_name_hash: OctetString::new(Sha1::digest(issuer_name).to_vec())?,
+        issuer_key_hash: OctetString::new(Sha1::digest(issuer_key).to_vec())?,
+        serial_number: cert.tbs_certificate.serial_number.clone(),
+    })
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct OcspRequest {
+    tbs_request: TbsRequest,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct TbsRequest {
+    request_list: Vec<Request>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct Request {
+    req_cert: CertId,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct CertId {
+    hash_algorithm: AlgorithmIdentifierOwned,
+    issuer_name_hash: OctetString,
+    issuer_key_hash: OctetString,
+    serial_number: SerialNumber,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Enumerated)]
+#[repr(u32)]
+enum OcspResponseStatus {
+    Successful = 0,
+    MalformedRequest = 1,
+    InternalError = 2,
+    TryLater = 3,
+    SigRequired = 5,
+    Unauthorized = 6,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct OcspResponse {
+    response_status: OcspResponseStatus,
+    #[asn1(context_specific = "0", optional = "true", tag_mode = "EXPLICIT")]
+    response_bytes: Option<ResponseBytes>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct ResponseBytes {
+    response_type: der::oid::ObjectIdentifier,
+    response: OctetString,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct BasicOcspResponse {
+    // Kept undecoded so the signature is checked over the bytes as received.
+    tbs_response_data: Any,
+    signature_algorithm: AlgorithmIdentifierOwned,
+    signature: BitString,
+    #[asn1(context_specific = "0", optional = "true", tag_mode = "EXPLICIT")]
+    certs: Option<Vec<Certificate>>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct ResponseData {
+    #[asn1(context_specific = "0", optional = "true", tag_mode = "EXPLICIT")]
+    version: Option<u8>,
+    responder_id: Any,
+    produced_at: GeneralizedTime,
+    responses: Vec<SingleResponse>,
+    #[asn1(context_specific = "1", optional = "true", tag_mode = "EXPLICIT")]
+    response_extensions: Option<Extensions>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct SingleResponse {
+    cert_id: CertId,
+    cert_status: Any,
+    this_update: GeneralizedTime,
+    #[asn1(context_specific = "0", optional = "true", tag_mode = "EXPLICIT")]
+    next_update: Option<GeneralizedTime>,
+    #[asn1(context_specific = "1", optional = "true", tag_mode = "EXPLICIT")]
+    single_extensions: Option<Extensions>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use time::macros::datetime;
+
+    static RSA_ROOT: &[u8] = include_bytes!("../../../test/issuance/rsa-root-cert.pem");
+    static GOOD_LEAF: &[u8] =
+        include_bytes!("../../../test/definitions/x509/ocsp/good-leaf-cert.pem");
+    static REVOKED_LEAF: &[u8] =
+        include_bytes!("../../../test/definitions/x509/ocsp/revoked-leaf-cert.pem");
+    static GOOD_REQUEST: &[u8] =
+        include_bytes!("../../../test/definitions/x509/ocsp/good-request.der");
+    static GOOD_RESPONSE: &[u8] =
+        include_bytes!("../../../test/definitions/x509/ocsp/good-response.der");
+    static REVOKED_RESPONSE: &[u8] =
+        include_bytes!("../../../test/definitions/x509/ocsp/revoked-response.der");
+    static CERT_256: &[u8] = include_bytes!("../../../test/issuance/256-cert.pem");
+
+    fn x509(pem: &[u8]) -> X509 {
+        X509::from_pem(pem).expect("unable to parse cert")
+    }
+
+    #[test]
+    fn responder_url_from_aia() {
+        assert_eq!(
+            responder_url(&x509(GOOD_LEAF)).expect("unable to read AIA"),
+            Some("http://ocsp.example.com".into())
+        );
+        assert_eq!(
+            responder_url(&x509(RSA_ROOT)).expect("unable to read AIA"),
+            None
+        );
+    }
+
+    #[test]
+    fn request_matches_openssl() {
+        let request =
+            build_request(&x509(GOOD_LEAF), &x509(RSA_ROOT)).expect("unable to build request");
+        assert_eq!(request, GOOD_REQUEST);
+    }
+
+    #[test]
+    fn good_and_revoked_responses() {
+        let root = x509(RSA_ROOT);
+        assert_eq!(
+            parse_response(GOOD_RESPONSE, &x509(GOOD_LEAF), &root)
+                .expect("unable to parse response"),
+            RevocationStatus::Good
+        );
+        assert_eq!(
+            parse_response(REVOKED_RESPONSE, &x509(REVOKED_LEAF), &root)
+                .expect("unable to parse response"),
+            RevocationStatus::Revoked
+        );
+    }
+
+    #[test]
+    fn response_for_another_certificate() {
+        assert!(parse_response(GOOD_RESPONSE, &x509(REVOKED_LEAF), &x509(RSA_ROOT)).is_err());
+    }
+
+    #[test]
+    fn response_not_signed_by_issuer() {
+        assert!(parse_response(GOOD_RESPONSE, &x509(GOOD_LEAF), &x509(CERT_256)).is_err());
+    }
+
+    #[test]
+    fn response_not_yet_valid() {
+        assert!(parse_response_at(
+            GOOD_RESPONSE,
+            &x509(GOOD_LEAF),
+            &x509(RSA_ROOT),
+            datetime!(2020-01-01 0:00 UTC)
+        )
+        .is_err());
+    }
+}
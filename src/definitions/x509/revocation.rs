@@ -0,0 +1,246 @@
+use crate::definitions::x509::{x5chain::verify_signature, X509Error, X509};
+use anyhow::{anyhow, Result};
+use time::OffsetDateTime;
+use x509_cert::{crl::CertificateList, der::Encode, Certificate};
+
+/// The revocation status of a certificate, as reported by its issuer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevocationStatus {
+    Good,
+    Revoked,
+    Unknown,
+}
+
+/// Look up the serial number of `cert` in `crl`.
+///
+/// The status is `Unknown` if the CRL was not issued by the issuer of `cert`, or is outside
+/// its `thisUpdate`/`nextUpdate` window. The signature of the CRL is not checked here.
+pub fn check_revocation_against_crl(cert: &Certificate, crl: &CertificateList) -> RevocationStatus {
+    check_revocation_against_crl_at(cert, crl, OffsetDateTime::now_utc())
+}
+
+fn check_revocation_against_crl_at(
+    cert: &Certificate,
+    crl: &CertificateList,
+    now: OffsetDateTime,
+) -> RevocationStatus {
+    let tbs = &crl.tbs_cert_list;
+    if tbs.issuer != cert.tbs_certificate.issuer || !is_current(crl, now) {
+        return RevocationStatus::Unknown;
+    }
+    let revoked = tbs
+        .revoked_certificates
+        .iter()
+        .flatten()
+        .any(|revoked| revoked.serial_number == cert.tbs_certificate.serial_number);
+    if revoked {
+        RevocationStatus::Revoked
+    } else {
+        RevocationStatus::Good
+    }
+}
+
+/// Whether `now` is within the `thisUpdate`/`nextUpdate` window of `crl`.
+fn is_current(crl: &CertificateList, now: OffsetDateTime) -> bool {
+    let tbs = &crl.tbs_cert_list;
+    let this_update = OffsetDateTime::UNIX_EPOCH + tbs.this_update.to_unix_duration();
+    let expired = tbs.next_update.is_some_and(|next_update| {
+        now > OffsetDateTime::UNIX_EPOCH + next_update.to_unix_duration()
+    });
+    now >= this_update && !expired
+}
+
+/// Check that `crl` was signed by `issuer`.
+fn verify_crl_signature(crl: &CertificateList, issuer: &X509) -> Result<()> {
+    let tbs = crl
+        .tbs_cert_list
+        .to_der()
+        .map_err(|e| anyhow!("unable to encode tbs cert list: {}", e))?;
+    let signature = crl
+        .signature
+        .as_bytes()
+        .ok_or_else(|| anyhow!("CRL signature is not octet aligned"))?;
+    verify_signature(issuer, &crl.signature_algorithm, &tbs, signature)
+        .map_err(|e| anyhow!("unable to verify CRL: {}", e))
+}
+
+/// Check the revocation status of `cert` against the most recent CRL in `crls` that was
+/// issued, and signed, by `issuer` and has not expired.
+pub(crate) fn check_revocation_against_crls(
+    cert: &X509,
+    issuer: &X509,
+    crls: &[CertificateList],
+) -> Result<RevocationStatus> {
+    check_revocation_against_crls_at(cert, issuer, crls, OffsetDateTime::now_utc())
+}
+
+fn check_revocation_against_crls_at(
+    cert: &X509,
+    issuer: &X509,
+    crls: &[CertificateList],
+    now: OffsetDateTime,
+) -> Result<RevocationStatus> {
+    let cert = cert.certificate()?;
+    let mut current: Vec<&CertificateList> = crls
+        .iter()
+        .filter(|crl| crl.tbs_cert_list.issuer == cert.tbs_certificate.issuer)
+        .filter(|crl| is_current(crl, now))
+        .collect();
+    current.sort_by_key(|crl| std::cmp::Reverse(crl.tbs_cert_list.this_update.to_unix_duration()));
+
+    // A CRL that does not verify is skipped in favour of an older one that does.
+    let mut error = anyhow!("no current CRL supplied for the certificate issuer");
+    for crl in current {
+        match verify_crl_signature(crl, issuer) {
+            Ok(()) => return Ok(check_revocation_against_crl_at(&cert, crl, now)),
+            Err(e) => error = e,
+        }
+    }
+    Err(error)
+}
+
+/// Convert the outcome of a revocation check on the certificate at `index` into an error, if
+/// the certificate cannot be shown to be unrevoked.
+pub(crate) fn revocation_error(
+    index: usize,
+    status: Result<RevocationStatus>,
+) -> Option<X509Error> {
+    match status {
+        Ok(RevocationStatus::Good) => None,
+        Ok(RevocationStatus::Revoked) => Some(X509Error::Revoked(index)),
+        Ok(RevocationStatus::Unknown) => Some(X509Error::RevocationUnknown(index)),
+        Err(e) => Some(X509Error::RevocationCheck(index, e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use time::macros::datetime;
+    use x509_cert::der::Decode;
+
+    static RSA_ROOT: &[u8] = include_bytes!("../../../test/issuance/rsa-root-cert.pem");
+    static CERT_256_ISSUED_BY_384: &[u8] =
+        include_bytes!("../../../test/issuance/256-issued-by-384-cert.pem");
+    static GOOD_LEAF: &[u8] =
+        include_bytes!("../../../test/definitions/x509/ocsp/good-leaf-cert.pem");
+    static REVOKED_LEAF: &[u8] =
+        include_bytes!("../../../test/definitions/x509/ocsp/revoked-leaf-cert.pem");
+    static RSA_ROOT_CRL: &[u8] =
+        include_bytes!("../../../test/definitions/x509/crl/rsa-root-crl.der");
+    static CERT_384: &[u8] = include_bytes!("../../../test/issuance/384-cert.pem");
+    // Issued by the P-384 key on 2024-01-01 until 2030, revoking nothing.
+    static CRL_2024: &[u8] = include_bytes!("../../../test/definitions/x509/crl/384-crl-2024.der");
+    // Issued by the P-384 key on 2025-01-01 until 2026, revoking CERT_256_ISSUED_BY_384.
+    static CRL_2025: &[u8] = include_bytes!("../../../test/definitions/x509/crl/384-crl-2025.der");
+
+    fn certificate(pem: &[u8]) -> Certificate {
+        X509::from_pem(pem)
+            .and_then(|x509| x509.certificate())
+            .expect("unable to parse cert")
+    }
+
+    fn crl() -> CertificateList {
+        CertificateList::from_der(RSA_ROOT_CRL).expect("unable to parse crl")
+    }
+
+    #[test]
+    fn serial_lookup() {
+        assert_eq!(
+            check_revocation_against_crl(&certificate(GOOD_LEAF), &crl()),
+            RevocationStatus::Good
+        );
+        assert_eq!(
+            check_revocation_against_crl(&certificate(REVOKED_LEAF), &crl()),
+            RevocationStatus::Revoked
+        );
+        assert_eq!(
+            check_revocation_against_crl(&certificate(CERT_256_ISSUED_BY_384), &crl()),
+            RevocationStatus::Unknown
+        );
+    }
+
+    #[test]
+    fn update_window() {
+        assert_eq!(
+            check_revocation_against_crl_at(
+                &certificate(REVOKED_LEAF),
+                &crl(),
+                datetime!(2020-01-01 0:00 UTC)
+            ),
+            RevocationStatus::Unknown
+        );
+        assert_eq!(
+            check_revocation_against_crl_at(
+                &certificate(REVOKED_LEAF),
+                &crl(),
+                datetime!(2200-01-01 0:00 UTC)
+            ),
+            RevocationStatus::Unknown
+        );
+    }
+
+    #[test]
+    fn crl_signature() {
+        let root = X509::from_pem(RSA_ROOT).expect("unable to parse cert");
+        let leaf = X509::from_pem(REVOKED_LEAF).expect("unable to parse cert");
+        assert_eq!(
+            check_revocation_against_crls(&leaf, &root, &[crl()]).expect("unable to check crl"),
+            RevocationStatus::Revoked
+        );
+
+        let other = X509::from_pem(CERT_256_ISSUED_BY_384).expect("unable to parse cert");
+        assert!(check_revocation_against_crls(&leaf, &other, &[crl()]).is_err());
+        assert!(check_revocation_against_crls(&leaf, &root, &[]).is_err());
+    }
+
+    #[test]
+    fn most_recent_crl() {
+        let leaf = X509::from_pem(CERT_256_ISSUED_BY_384).expect("unable to parse cert");
+        let issuer = X509::from_pem(CERT_384).expect("unable to parse cert");
+        let crls = [CRL_2024, CRL_2025]
+            .map(|der| CertificateList::from_der(der).expect("unable to parse crl"));
+
+        // Both CRLs are current, and the later one revokes the certificate.
+        assert_eq!(
+            check_revocation_against_crls_at(&leaf, &issuer, &crls, datetime!(2025-06-01 0:00 UTC))
+                .expect("unable to check crl"),
+            RevocationStatus::Revoked
+        );
+        // The later CRL has expired, so the earlier one is used.
+        assert_eq!(
+            check_revocation_against_crls_at(&leaf, &issuer, &crls, datetime!(2027-01-01 0:00 UTC))
+                .expect("unable to check crl"),
+            RevocationStatus::Good
+        );
+        // Neither CRL is current.
+        assert!(check_revocation_against_crls_at(
+            &leaf,
+            &issuer,
+            &crls,
+            datetime!(2031-01-01 0:00 UTC)
+        )
+        .is_err());
+
+        // A later CRL with an invalid signature is skipped.
+        let mut forged = crls[1].clone();
+        forged.tbs_cert_list.revoked_certificates = None;
+        assert_eq!(
+            check_revocation_against_crls_at(
+                &leaf,
+                &issuer,
+                &[crls[0].clone(), forged.clone()],
+                datetime!(2025-06-01 0:00 UTC)
+            )
+            .expect("unable to check crl"),
+            RevocationStatus::Good
+        );
+        assert!(check_revocation_against_crls_at(
+            &leaf,
+            &issuer,
+            &[forged],
+            datetime!(2025-06-01 0:00 UTC)
+        )
+        .is_err());
+    }
+}
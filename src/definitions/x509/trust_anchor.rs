@@ -0,0 +1,456 @@
+use crate::definitions::x509::{
+    vical::{CertificateInfo, Vical},
+    x5chain::{check_signature, is_issued_by},
+    X509Error, X509,
+};
+use anyhow::{anyhow, Result};
+use cose_rs::algorithm::SignatureAlgorithm;
+use signature::Verifier;
+use std::{collections::HashMap, fmt::Display, time::Duration};
+use time::OffsetDateTime;
+use x509_cert::{
+    der::{oid::ObjectIdentifier, Encode},
+    ext::pkix::{BasicConstraints, CrlDistributionPoints, ExtendedKeyUsage, KeyUsage},
+    Certificate,
+};
+
+/// The longest validity period permitted for an IACA certificate (ISO 18013-5 Annex B).
+const IACA_MAX_VALIDITY: Duration = Duration::from_secs(7305 * 24 * 60 * 60);
+
+/// The mdlDS extended key usage required on document signer certificates (ISO 18013-5 Annex B).
+pub const MDL_DS_EXTENDED_KEY_USAGE: ObjectIdentifier =
+    ObjectIdentifier::new_unwrap("1.0.18013.5.1.2");
+
+/// The mdlReaderAuth extended key usage required on reader authentication certificates
+/// (ISO 18013-5 Annex B).
+pub const MDL_READER_AUTH_EXTENDED_KEY_USAGE: ObjectIdentifier =
+    ObjectIdentifier::new_unwrap("1.0.18013.5.1.6");
+
+/// The keyUsage and extendedKeyUsage a certificate must carry for its role in the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyUsageProfile {
+    /// `digitalSignature`, and the mdlDS extended key usage.
+    DocumentSigner,
+    /// `digitalSignature`, and the mdlReaderAuth extended key usage.
+    ReaderAuth,
+    /// `keyCertSign` and `cRLSign`.
+    Iaca,
+}
+
+/// The set of certificates a relying party trusts to issue document signer certificates, e.g.
+/// IACA roots or the contents of a VICAL.
+#[derive(Debug, Clone, Default)]
+pub struct TrustAnchorRegistry {
+    anchors: Vec<X509>,
+    doc_types: HashMap<X509, Vec<String>>,
+}
+
+impl TrustAnchorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Populate a registry from the trust store of the operating system.
+    ///
+    /// Certificates that cannot be loaded or parsed are skipped.
+    #[cfg(feature = "native-roots")]
+    pub fn from_system_roots() -> Self {
+        let native = rustls_native_certs::load_native_certs();
+        #[cfg(feature = "tracing")]
+        for error in &native.errors {
+            tracing::warn!("unable to load system root certificates: {}", error);
+        }
+        let mut registry = Self::new();
+        for cert in native.certs {
+            match X509::from_der(&cert) {
+                Ok(x509) => registry.add(x509),
+                Err(_e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!("skipping system root certificate: {}", _e);
+                }
+            }
+        }
+        #[cfg(feature = "tracing")]
+        tracing::info!("loaded {} system root certificates", registry.anchors.len());
+        registry
+    }
+
+    /// Populate a registry from a VICAL, once its signature has been verified with `verifier`.
+    ///
+    /// Each certificate is restricted to the document types declared for it in the VICAL.
+    /// Certificates that have expired are skipped.
+    pub fn from_vical<V, S>(cbor: &[u8], verifier: &V) -> Result<Self>
+    where
+        V: Verifier<S> + SignatureAlgorithm,
+        S: for<'a> TryFrom<&'a [u8]>,
+        for<'a> <S as TryFrom<&'a [u8]>>::Error: Display,
+    {
+        let vical = Vical::from_cose_sign1(cbor, verifier)?;
+        Self::from_certificate_infos(vical.certificate_infos, OffsetDateTime::now_utc())
+    }
+
+    fn from_certificate_infos(infos: Vec<CertificateInfo>, now: OffsetDateTime) -> Result<Self> {
+        let mut registry = Self::new();
+        for info in infos {
+            let anchor = X509::from_der(info.certificate.as_ref())?;
+            let not_after = match info.not_after {
+                Some(not_after) => not_after,
+                None => {
+                    let validity = anchor.certificate()?.tbs_certificate.validity;
+                    OffsetDateTime::UNIX_EPOCH + validity.not_after.to_unix_duration()
+                }
+            };
+            if not_after < now {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    "skipping expired VICAL certificate issued to {:?}",
+                    info.issuing_authority
+                );
+                continue;
+            }
+            registry.add_with_doc_types(anchor, info.doc_type);
+        }
+        Ok(registry)
+    }
+
+    pub fn add(&mut self, anchor: X509) {
+        self.anchors.push(anchor);
+    }
+
+    /// Add a trust anchor that may only issue documents of the given `doc_types`.
+    pub fn add_with_doc_types(&mut self, anchor: X509, doc_types: Vec<String>) {
+        self.doc_types.insert(anchor.clone(), doc_types);
+        self.add(anchor);
+    }
+
+    /// The document types `anchor` may issue, or `None` if it is not restricted.
+    pub fn doc_types(&self, anchor: &X509) -> Option<&[String]> {
+        self.doc_types.get(anchor).map(Vec::as_slice)
+    }
+
+    pub fn with_pem(mut self, data: &[u8]) -> Result<Self> {
+        self.add(X509::from_pem(data)?);
+        Ok(self)
+    }
+
+    pub fn with_der(mut self, data: &[u8]) -> Result<Self> {
+        self.add(X509::from_der(data)?);
+        Ok(self)
+    }
+
+    pub fn anchors(&self) -> &[X509] {
+        &self.anchors
+    }
+
+    /// Find the trust anchor for `cert`: either the anchor that is `cert` itself, or an anchor
+    /// whose subject matches the issuer of `cert`. Where `cert` carries an authority key
+    /// identifier and the anchor a subject key identifier, these must match as well.
+    ///
+    /// The signature of `cert` is not checked here.
+    pub fn find_anchor(&self, cert: &X509) -> Option<&X509> {
+        if let Some(anchor) = self.anchors.iter().find(|anchor| *anchor == cert) {
+            return Some(anchor);
+        }
+        let cert = cert.certificate().ok()?;
+        self.anchors.iter().find(|anchor| {
+            anchor
+                .certificate()
+                .map(|anchor| is_issued_by(&cert, &anchor))
+                .unwrap_or(false)
+        })
+    }
+}
+
+/// Check that `cert` carries the key usages required by `expected`.
+pub fn check_key_usage(cert: &Certificate, expected: KeyUsageProfile) -> Result<()> {
+    let key_usage = cert
+        .tbs_certificate
+        .get::<KeyUsage>()
+        .map_err(|e| anyhow!("unable to parse keyUsage extension: {}", e))?
+        .map(|(_critical, key_usage)| key_usage)
+        .ok_or_else(|| anyhow!("missing keyUsage extension"))?;
+    match expected {
+        KeyUsageProfile::DocumentSigner => {
+            check_extended_key_usage(cert, &key_usage, MDL_DS_EXTENDED_KEY_USAGE, "mdlDS")?
+        }
+        KeyUsageProfile::ReaderAuth => check_extended_key_usage(
+            cert,
+            &key_usage,
+            MDL_READER_AUTH_EXTENDED_KEY_USAGE,
+            "mdlReaderAuth",
+        )?,
+        KeyUsageProfile::Iaca => {
+            if !key_usage.key_cert_sign() {
+                return Err(anyhow!("keyUsage does not include keyCertSign"));
+            }
+            if !key_usage.crl_sign() {
+                return Err(anyhow!("keyUsage does not include cRLSign"));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_extended_key_usage(
+    cert: &Certificate,
+    key_usage: &KeyUsage,
+    expected: ObjectIdentifier,
+    name: &str,
+) -> Result<()> {
+    if !key_usage.digital_signature() {
+        return Err(anyhow!("keyUsage does not include digitalSignature"));
+    }
+    let extended_key_usage = cert
+        .tbs_certificate
+        .get::<ExtendedKeyUsage>()
+        .map_err(|e| anyhow!("unable to parse extendedKeyUsage extension: {}", e))?
+        .map(|(_critical, extended_key_usage)| extended_key_usage)
+        .ok_or_else(|| anyhow!("missing extendedKeyUsage extension"))?;
+    if !extended_key_usage.0.contains(&expected) {
+        return Err(anyhow!("extendedKeyUsage does not include {}", name));
+    }
+    Ok(())
+}
+
+/// Check that `cert` conforms to the IACA root certificate profile (ISO 18013-5 Annex B).
+pub fn validate_iaca_profile(cert: &Certificate) -> Vec<X509Error> {
+    let mut errors = vec![];
+    let tbs = &cert.tbs_certificate;
+
+    let self_signed = tbs.issuer == tbs.subject
+        && cert
+            .to_der()
+            .map_err(|e| anyhow!("unable to encode certificate: {}", e))
+            .and_then(|der| X509::from_der(&der))
+            .and_then(|x509| check_signature(&x509, &x509))
+            .is_ok();
+    if !self_signed {
+        errors.push(X509Error::IacaProfile(
+            "certificate is not self-signed".into(),
+        ));
+    }
+
+    match tbs.get::<BasicConstraints>() {
+        Ok(Some((
+            _critical,
+            BasicConstraints {
+                ca: true,
+                path_len_constraint: Some(0),
+            },
+        ))) => {}
+        Ok(Some((_critical, BasicConstraints { ca: false, .. }))) => errors.push(
+            X509Error::IacaProfile("basicConstraints does not allow CA=true".into()),
+        ),
+        Ok(Some(_)) => errors.push(X509Error::IacaProfile(
+            "basicConstraints pathLenConstraint must be 0".into(),
+        )),
+        Ok(None) => errors.push(X509Error::IacaProfile(
+            "missing basicConstraints extension".into(),
+        )),
+        Err(e) => errors.push(X509Error::IacaProfile(format!(
+            "unable to parse basicConstraints extension: {}",
+            e
+        ))),
+    }
+
+    match tbs.get::<CrlDistributionPoints>() {
+        Ok(Some((_critical, points))) if !points.0.is_empty() => {}
+        Ok(_) => errors.push(X509Error::IacaProfile(
+            "missing cRLDistributionPoints extension".into(),
+        )),
+        Err(e) => errors.push(X509Error::IacaProfile(format!(
+            "unable to parse cRLDistributionPoints extension: {}",
+            e
+        ))),
+    }
+
+    let not_before = tbs.validity.not_before.to_unix_duration();
+    let not_after = tbs.validity.not_after.to_unix_duration();
+    if not_after.saturating_sub(not_before) > IACA_MAX_VALIDITY {
+        errors.push(X509Error::IacaProfile(
+            "validity period exceeds 20 years".into(),
+        ));
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::definitions::x509::{
+        x5chain::{EcPublicKey, PublicKeyKind},
+        X5Chain,
+    };
+    use time::macros::datetime;
+
+    static CERT_256: &[u8] = include_bytes!("../../../test/issuance/256-cert.pem");
+    static ED25519_ROOT: &[u8] = include_bytes!("../../../test/issuance/ed25519-cert.pem");
+    static CERT_256_ISSUED_BY_384: &[u8] =
+        include_bytes!("../../../test/issuance/256-issued-by-384-cert.pem");
+    static CERT_384_ISSUED_BY_521: &[u8] =
+        include_bytes!("../../../test/issuance/384-issued-by-521-cert.pem");
+    static CERT_384: &[u8] = include_bytes!("../../../test/issuance/384-cert.pem");
+    static CERT_521: &[u8] = include_bytes!("../../../test/issuance/521-cert.pem");
+    static RSA_ROOT: &[u8] = include_bytes!("../../../test/issuance/rsa-root-cert.pem");
+    static ED25519_ISSUED_LEAF: &[u8] =
+        include_bytes!("../../../test/issuance/ed25519-issued-leaf-cert.pem");
+    static VICAL_SIGNER: &[u8] = include_bytes!("../../../test/issuance/256-cert.pem");
+    static VICAL: &[u8] = include_bytes!("../../../test/definitions/x509/vical/vical.cbor");
+    static VICAL_PAYLOAD: &[u8] =
+        include_bytes!("../../../test/definitions/x509/vical/vical-payload.cbor");
+    static READER_CERT: &[u8] = include_bytes!("../../../test/presentation/reader-cert.pem");
+    static PATH_LENGTH_IACA: &[u8] =
+        include_bytes!("../../../test/issuance/iaca-path-length-cert.pem");
+    static WRONG_KEY_USAGE: &[u8] =
+        include_bytes!("../../../test/issuance/256-wrong-key-usage-cert.pem");
+
+    #[test]
+    fn find_anchor() {
+        let registry = TrustAnchorRegistry::new()
+            .with_pem(CERT_256)
+            .expect("unable to add anchor")
+            .with_pem(CERT_521)
+            .expect("unable to add anchor");
+
+        let root = X509::from_pem(CERT_521).expect("unable to parse cert");
+        let intermediate = X509::from_pem(CERT_384_ISSUED_BY_521).expect("unable to parse cert");
+        let leaf = X509::from_pem(CERT_256_ISSUED_BY_384).expect("unable to parse cert");
+        let unknown = X509::from_pem(ED25519_ROOT).expect("unable to parse cert");
+
+        assert_eq!(registry.find_anchor(&root), Some(&root));
+        assert_eq!(registry.find_anchor(&intermediate), Some(&root));
+        assert_eq!(registry.find_anchor(&leaf), None);
+        assert_eq!(registry.find_anchor(&unknown), None);
+    }
+
+    #[test]
+    fn vical() {
+        let verifier: p256::ecdsa::VerifyingKey = match X509::from_pem(VICAL_SIGNER)
+            .and_then(|x509| x509.public_key_kind())
+            .expect("unable to parse vical signer key")
+        {
+            PublicKeyKind::Ec(EcPublicKey::P256(key)) => key.into(),
+            _ => panic!("vical signer key is not a P-256 key"),
+        };
+        let registry =
+            TrustAnchorRegistry::from_vical::<_, p256::ecdsa::Signature>(VICAL, &verifier)
+                .expect("unable to load vical");
+
+        let rsa_root = X509::from_pem(RSA_ROOT).expect("unable to parse cert");
+        let ed25519_root = X509::from_pem(ED25519_ROOT).expect("unable to parse cert");
+        assert_eq!(
+            registry.anchors(),
+            &[rsa_root.clone(), ed25519_root.clone()]
+        );
+        assert_eq!(
+            registry.doc_types(&ed25519_root),
+            Some(&["org.iso.18013.5.1.mDL".to_string()][..])
+        );
+
+        let chain = X5Chain::builder()
+            .with_pem(ED25519_ISSUED_LEAF)
+            .expect("unable to add leaf cert")
+            .build()
+            .expect("unable to build x5chain");
+        assert_eq!(chain.validate(&registry), vec![]);
+    }
+
+    #[test]
+    fn vical_payload() {
+        let vical: Vical =
+            serde_cbor::from_slice(VICAL_PAYLOAD).expect("unable to parse vical payload");
+        assert_eq!(vical.vical_provider, "Spruce Systems Test VICAL Provider");
+        assert_eq!(vical.certificate_infos.len(), 3);
+
+        let registry = TrustAnchorRegistry::from_certificate_infos(
+            vical.certificate_infos,
+            datetime!(2024-01-01 0:00 UTC),
+        )
+        .expect("unable to load certificate infos");
+        // 521-cert.pem has expired.
+        assert_eq!(registry.anchors().len(), 2);
+        assert_eq!(
+            registry.find_anchor(&X509::from_pem(RSA_ROOT).expect("unable to parse cert")),
+            registry.anchors().first()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "native-roots")]
+    fn system_roots() {
+        let registry = TrustAnchorRegistry::from_system_roots();
+        for anchor in registry.anchors() {
+            anchor.certificate().expect("unable to parse system root");
+        }
+    }
+
+    #[test]
+    fn find_anchor_by_key_identifier() {
+        // 384-cert.pem and 521-cert.pem share a subject DN but not a key.
+        let registry = TrustAnchorRegistry::new()
+            .with_pem(CERT_384)
+            .expect("unable to add anchor")
+            .with_pem(CERT_521)
+            .expect("unable to add anchor");
+
+        let root = X509::from_pem(CERT_521).expect("unable to parse cert");
+        let intermediate = X509::from_pem(CERT_384_ISSUED_BY_521).expect("unable to parse cert");
+
+        assert_eq!(registry.find_anchor(&intermediate), Some(&root));
+    }
+
+    #[test]
+    fn key_usage_profiles() {
+        let certificate = |pem| {
+            X509::from_pem(pem)
+                .and_then(|x509| x509.certificate())
+                .expect("unable to parse cert")
+        };
+        let document_signer = certificate(CERT_256_ISSUED_BY_384);
+        let iaca = certificate(CERT_384_ISSUED_BY_521);
+        let wrong_key_usage = certificate(WRONG_KEY_USAGE);
+
+        check_key_usage(&document_signer, KeyUsageProfile::DocumentSigner)
+            .expect("document signer profile not satisfied");
+        check_key_usage(&iaca, KeyUsageProfile::Iaca).expect("iaca profile not satisfied");
+        check_key_usage(&certificate(READER_CERT), KeyUsageProfile::ReaderAuth)
+            .expect("reader auth profile not satisfied");
+        assert!(check_key_usage(&document_signer, KeyUsageProfile::ReaderAuth).is_err());
+        assert!(check_key_usage(&document_signer, KeyUsageProfile::Iaca).is_err());
+        assert!(check_key_usage(&iaca, KeyUsageProfile::DocumentSigner).is_err());
+
+        let error = check_key_usage(&wrong_key_usage, KeyUsageProfile::DocumentSigner)
+            .expect_err("wrong key usage accepted");
+        assert_eq!(
+            error.to_string(),
+            "keyUsage does not include digitalSignature"
+        );
+    }
+
+    #[test]
+    fn iaca_profile() {
+        let certificate = |pem| {
+            X509::from_pem(pem)
+                .and_then(|x509| x509.certificate())
+                .expect("unable to parse cert")
+        };
+
+        assert_eq!(validate_iaca_profile(&certificate(RSA_ROOT)), vec![]);
+        assert_eq!(validate_iaca_profile(&certificate(ED25519_ROOT)), vec![]);
+        assert_eq!(
+            validate_iaca_profile(&certificate(PATH_LENGTH_IACA)),
+            vec![X509Error::IacaProfile(
+                "basicConstraints pathLenConstraint must be 0".into()
+            )]
+        );
+        assert_eq!(
+            validate_iaca_profile(&certificate(CERT_384_ISSUED_BY_521)),
+            vec![
+                X509Error::IacaProfile("certificate is not self-signed".into()),
+                X509Error::IacaProfile("missing cRLDistributionPoints extension".into()),
+                X509Error::IacaProfile("validity period exceeds 20 years".into()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use crate::definitions::helpers::ByteStr;
+use anyhow::{anyhow, Result};
+use cose_rs::{algorithm::SignatureAlgorithm, CoseSign1};
+use serde::{Deserialize, Deserializer};
+use serde_cbor::Value as CborValue;
+use signature::Verifier;
+use std::fmt::Display;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+/// A Verified Issuer Certificate Authority List, as distributed by a VICAL provider.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Vical {
+    pub version: String,
+    pub vical_provider: String,
+    #[serde(deserialize_with = "tdate")]
+    pub date: OffsetDateTime,
+    #[serde(rename = "vicalIssueID")]
+    pub vical_issue_id: Option<u64>,
+    #[serde(default, deserialize_with = "optional_tdate")]
+    pub next_update: Option<OffsetDateTime>,
+    pub certificate_infos: Vec<CertificateInfo>,
+}
+
+/// An IACA certificate listed in a [Vical], and the document types it may issue.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificateInfo {
+    pub certificate: ByteStr,
+    pub serial_number: CborValue,
+    pub ski: ByteStr,
+    pub doc_type: Vec<String>,
+    pub issuing_authority: Option<String>,
+    pub issuing_country: Option<String>,
+    #[serde(default, deserialize_with = "optional_tdate")]
+    pub not_before: Option<OffsetDateTime>,
+    #[serde(default, deserialize_with = "optional_tdate")]
+    pub not_after: Option<OffsetDateTime>,
+}
+
+impl Vical {
+    /// Verify the COSE_Sign1 signature over a VICAL with `verifier`, and parse its payload.
+    pub fn from_cose_sign1<V, S>(cbor: &[u8], verifier: &V) -> Result<Vical>
+    where
+        V: Verifier<S> + SignatureAlgorithm,
+        S: for<'a> TryFrom<&'a [u8]>,
+        for<'a> <S as TryFrom<&'a [u8]>>::Error: Display,
+    {
+        let cose_sign1: CoseSign1 = serde_cbor::from_slice(cbor)
+            .map_err(|e| anyhow!("unable to parse VICAL as COSE_Sign1: {}", e))?;
+        if !cose_sign1.verify::<V, S>(verifier, None, None).success() {
+            return Err(anyhow!("unable to verify VICAL signature"));
+        }
+        let payload = cose_sign1
+            .payload()
+            .ok_or_else(|| anyhow!("VICAL has no payload"))?;
+        serde_cbor::from_slice(payload).map_err(|e| anyhow!("unable to parse VICAL: {}", e))
+    }
+}
+
+fn tdate<'de, D>(d: D) -> Result<OffsetDateTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match CborValue::deserialize(d)? {
+        CborValue::Tag(0, inner) => match *inner {
+            CborValue::Text(date) => {
+                OffsetDateTime::parse(&date, &Rfc3339).map_err(serde::de::Error::custom)
+            }
+            v => Err(serde::de::Error::custom(format!(
+                "expected a text string, received: {:?}",
+                v
+            ))),
+        },
+        v => Err(serde::de::Error::custom(format!(
+            "expected a tdate, received: {:?}",
+            v
+        ))),
+    }
+}
+
+fn optional_tdate<'de, D>(d: D) -> Result<Option<OffsetDateTime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    tdate(d).map(Some)
+}
@@ -0,0 +1,1234 @@
+use crate::definitions::{
+    helpers::NonEmptyVec,
+    x509::{
+        revocation::{check_revocation_against_crls, revocation_error},
+        trust_anchor::{check_key_usage, validate_iaca_profile, KeyUsageProfile},
+        TrustAnchorRegistry, X509Error,
+    },
+};
+use anyhow::{anyhow, Result};
+use pem_rfc7468::LineEnding;
+use rsa::{
+    pkcs1::{DecodeRsaPublicKey, RsaPssParams},
+    pkcs1v15, pss, RsaPublicKey,
+};
+use serde_cbor::Value as CborValue;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use signature::{hazmat::PrehashVerifier, Verifier};
+use std::{fs::File, io::Read};
+use time::OffsetDateTime;
+use x509_cert::{
+    certificate::Certificate,
+    crl::CertificateList,
+    der::{
+        oid::{
+            db::{rfc5912, rfc8410},
+            ObjectIdentifier,
+        },
+        Decode, Encode,
+    },
+    ext::pkix::{AuthorityKeyIdentifier, SubjectKeyIdentifier},
+    spki::AlgorithmIdentifierOwned,
+};
+
+pub const X5CHAIN_HEADER_LABEL: i128 = 33;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct X509 {
+    bytes: Vec<u8>,
+}
+
+/// The subject public key of a certificate, tagged by key algorithm.
+#[derive(Debug, Clone)]
+pub enum PublicKeyKind {
+    Ec(EcPublicKey),
+    Rsa(RsaPublicKey),
+    Ed25519(ed25519_dalek::VerifyingKey),
+}
+
+/// An elliptic curve public key on one of the NIST curves permitted by ISO 18013-5.
+#[derive(Debug, Clone)]
+pub enum EcPublicKey {
+    P256(p256::PublicKey),
+    P384(p384::PublicKey),
+    P521(p521::PublicKey),
+}
+
+#[derive(Debug, Clone)]
+pub struct X5Chain(NonEmptyVec<X509>);
+
+impl X509 {
+    pub fn from_pem(data: &[u8]) -> Result<X509> {
+        let bytes = pem_rfc7468::decode_vec(data)
+            .map_err(|e| anyhow!("unable to parse pem: {}", e))?
+            .1;
+        X509::from_der(&bytes)
+    }
+
+    pub fn from_der(data: &[u8]) -> Result<X509> {
+        let cert: Certificate = Certificate::from_der(data)
+            .map_err(|e| anyhow!("unable to parse certificate from der encoding: {}", e))?;
+        X509::from_certificate(&cert)
+    }
+
+    fn from_certificate(cert: &Certificate) -> Result<X509> {
+        Ok(X509 {
+            bytes: cert
+                .to_der()
+                .map_err(|e| anyhow!("unable to convert certificate to bytes: {}", e))?,
+        })
+    }
+
+    pub fn certificate(&self) -> Result<Certificate> {
+        Certificate::from_der(&self.bytes)
+            .map_err(|e| anyhow!("unable to parse certificate from der encoding: {}", e))
+    }
+
+    /// Parse the subject public key, dispatching on the algorithm OID in the
+    /// certificate's `subject_public_key_info`.
+    pub fn public_key_kind(&self) -> Result<PublicKeyKind> {
+        let spki = self.certificate()?.tbs_certificate.subject_public_key_info;
+        let key_bytes = spki
+            .subject_public_key
+            .as_bytes()
+            .ok_or_else(|| anyhow!("subject public key is not octet aligned"))?;
+        match spki.algorithm.oid {
+            rfc5912::ID_EC_PUBLIC_KEY => {
+                let curve: ObjectIdentifier = spki
+                    .algorithm
+                    .parameters
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("missing named curve for ec public key"))?
+                    .decode_as()
+                    .map_err(|e| anyhow!("unable to parse named curve: {}", e))?;
+                let key = match curve {
+                    rfc5912::SECP_256_R_1 => {
+                        p256::PublicKey::from_sec1_bytes(key_bytes).map(EcPublicKey::P256)
+                    }
+                    rfc5912::SECP_384_R_1 => {
+                        p384::PublicKey::from_sec1_bytes(key_bytes).map(EcPublicKey::P384)
+                    }
+                    rfc5912::SECP_521_R_1 => {
+                        p521::PublicKey::from_sec1_bytes(key_bytes).map(EcPublicKey::P521)
+                    }
+                    oid => return Err(anyhow!("unsupported named curve: {}", oid)),
+                };
+                key.map(PublicKeyKind::Ec)
+                    .map_err(|e| anyhow!("unable to parse ec public key: {}", e))
+            }
+            rfc5912::RSA_ENCRYPTION => RsaPublicKey::from_pkcs1_der(key_bytes)
+                .map(PublicKeyKind::Rsa)
+                .map_err(|e| anyhow!("unable to parse rsa public key: {}", e)),
+            rfc8410::ID_ED_25519 => ed25519_dalek::VerifyingKey::try_from(key_bytes)
+                .map(PublicKeyKind::Ed25519)
+                .map_err(|e| anyhow!("unable to parse ed25519 public key: {}", e)),
+            oid => Err(anyhow!("unsupported public key algorithm: {}", oid)),
+        }
+    }
+
+    pub fn ed25519_public_key(&self) -> Result<ed25519_dalek::VerifyingKey> {
+        match self.public_key_kind()? {
+            PublicKeyKind::Ed25519(key) => Ok(key),
+            _ => Err(anyhow!(
+                "certificate does not contain an ed25519 public key"
+            )),
+        }
+    }
+}
+
+/// Check that `target` was signed by the private key belonging to `issuer`.
+pub fn check_signature(target: &X509, issuer: &X509) -> Result<()> {
+    let target = target.certificate()?;
+    let tbs = target
+        .tbs_certificate
+        .to_der()
+        .map_err(|e| anyhow!("unable to encode tbs certificate: {}", e))?;
+    let signature = target
+        .signature
+        .as_bytes()
+        .ok_or_else(|| anyhow!("certificate signature is not octet aligned"))?;
+    verify_signature(issuer, &target.signature_algorithm, &tbs, signature)
+}
+
+/// Check `signature` over `message` against the public key of `signer`, using the signature
+/// `algorithm` declared by the signed structure, e.g. a certificate, CRL or OCSP response.
+pub(crate) fn verify_signature(
+    signer: &X509,
+    algorithm: &AlgorithmIdentifierOwned,
+    message: &[u8],
+    signature: &[u8],
+) -> Result<()> {
+    match signer.public_key_kind()? {
+        PublicKeyKind::Ec(key) => check_ecdsa_signature(key, algorithm, message, signature),
+        PublicKeyKind::Rsa(key) => check_rsa_signature(key, algorithm, message, signature),
+        PublicKeyKind::Ed25519(key) => {
+            if algorithm.oid != rfc8410::ID_ED_25519 {
+                return Err(anyhow!(
+                    "unsupported signature algorithm for ed25519 key: {}",
+                    algorithm.oid
+                ));
+            }
+            let signature = ed25519_dalek::Signature::from_slice(signature)
+                .map_err(|e| anyhow!("unable to parse ed25519 signature: {}", e))?;
+            key.verify_strict(message, &signature)
+                .map_err(|e| anyhow!("invalid ed25519 signature: {}", e))
+        }
+    }
+}
+
+fn check_ecdsa_signature(
+    key: EcPublicKey,
+    algorithm: &AlgorithmIdentifierOwned,
+    message: &[u8],
+    signature: &[u8],
+) -> Result<()> {
+    // The hash is chosen by the issuer and need not match the curve, e.g. a P-521 key signing
+    // with ecdsa-with-SHA256.
+    let prehash = match algorithm.oid {
+        rfc5912::ECDSA_WITH_SHA_256 => Sha256::digest(message).to_vec(),
+        rfc5912::ECDSA_WITH_SHA_384 => Sha384::digest(message).to_vec(),
+        rfc5912::ECDSA_WITH_SHA_512 => Sha512::digest(message).to_vec(),
+        oid => return Err(anyhow!("unsupported ecdsa signature algorithm: {}", oid)),
+    };
+    let result = match key {
+        EcPublicKey::P256(key) => p256::ecdsa::VerifyingKey::from(key)
+            .verify_prehash(&prehash, &p256::ecdsa::Signature::from_der(signature)?),
+        EcPublicKey::P384(key) => p384::ecdsa::VerifyingKey::from(key)
+            .verify_prehash(&prehash, &p384::ecdsa::Signature::from_der(signature)?),
+        EcPublicKey::P521(key) => {
+            // The ecdsa crate rejects prehashes shorter than half the field size (33 bytes for
+            // P-521). Left-padding with zeroes leaves the integer value of the hash unchanged.
+            let mut padded = vec![0; 33usize.saturating_sub(prehash.len())];
+            padded.extend_from_slice(&prehash);
+            ecdsa::VerifyingKey::<p521::NistP521>::from(key)
+                .verify_prehash(&padded, &p521::ecdsa::Signature::from_der(signature)?)
+        }
+    };
+    result.map_err(|e| anyhow!("invalid ecdsa signature: {}", e))
+}
+
+fn check_rsa_signature(
+    key: RsaPublicKey,
+    algorithm: &AlgorithmIdentifierOwned,
+    message: &[u8],
+    signature: &[u8],
+) -> Result<()> {
+    let result = match algorithm.oid {
+        rfc5912::SHA_256_WITH_RSA_ENCRYPTION => {
+            pkcs1v15::VerifyingKey::<Sha256>::new(key).verify(message, &signature.try_into()?)
+        }
+        rfc5912::SHA_384_WITH_RSA_ENCRYPTION => {
+            pkcs1v15::VerifyingKey::<Sha384>::new(key).verify(message, &signature.try_into()?)
+        }
+        rfc5912::SHA_512_WITH_RSA_ENCRYPTION => {
+            pkcs1v15::VerifyingKey::<Sha512>::new(key).verify(message, &signature.try_into()?)
+        }
+        rfc5912::ID_RSASSA_PSS => {
+            let params: RsaPssParams = algorithm
+                .parameters
+                .as_ref()
+                .ok_or_else(|| anyhow!("missing rsassa-pss parameters"))?
+                .decode_as()
+                .map_err(|e| anyhow!("unable to parse rsassa-pss parameters: {}", e))?;
+            let salt_len = params.salt_len as usize;
+            let signature: pss::Signature = signature.try_into()?;
+            match params.hash.oid {
+                rfc5912::ID_SHA_256 => {
+                    pss::VerifyingKey::<Sha256>::new_with_salt_len(key, salt_len)
+                        .verify(message, &signature)
+                }
+                rfc5912::ID_SHA_384 => {
+                    pss::VerifyingKey::<Sha384>::new_with_salt_len(key, salt_len)
+                        .verify(message, &signature)
+                }
+                rfc5912::ID_SHA_512 => {
+                    pss::VerifyingKey::<Sha512>::new_with_salt_len(key, salt_len)
+                        .verify(message, &signature)
+                }
+                oid => return Err(anyhow!("unsupported rsassa-pss hash algorithm: {}", oid)),
+            }
+        }
+        oid => return Err(anyhow!("unsupported rsa signature algorithm: {}", oid)),
+    };
+    result.map_err(|e| anyhow!("invalid rsa signature: {}", e))
+}
+
+impl From<NonEmptyVec<X509>> for X5Chain {
+    fn from(v: NonEmptyVec<X509>) -> Self {
+        Self(v)
+    }
+}
+
+impl X5Chain {
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
+    /// The first certificate in the chain, i.e. the document signer certificate.
+    ///
+    /// ```
+    /// # use isomdl::definitions::x509::{x5chain::PublicKeyKind, X5Chain};
+    /// # let pem = include_bytes!("../../../test/issuance/256-cert.pem");
+    /// let x5chain = X5Chain::builder().with_pem(pem)?.build()?;
+    /// let signer_key = x5chain.leaf().public_key_kind()?;
+    /// assert!(matches!(signer_key, PublicKeyKind::Ec(_)));
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn leaf(&self) -> &X509 {
+        self.0.first()
+    }
+
+    /// The last certificate in the chain, which is issued by, or is, a trust anchor.
+    pub fn root(&self) -> &X509 {
+        self.0.last()
+    }
+
+    /// The certificates in the chain, leaf first.
+    ///
+    /// ```
+    /// # use isomdl::definitions::x509::X5Chain;
+    /// # let leaf = include_bytes!("../../../test/issuance/256-issued-by-384-cert.pem");
+    /// # let intermediate = include_bytes!("../../../test/issuance/384-issued-by-521-cert.pem");
+    /// let x5chain = X5Chain::builder()
+    ///     .with_pem(leaf)?
+    ///     .with_pem(intermediate)?
+    ///     .build()?;
+    /// let subjects = x5chain
+    ///     .iter()
+    ///     .map(|x509| Ok(x509.certificate()?.tbs_certificate.subject.to_string()))
+    ///     .collect::<anyhow::Result<Vec<_>>>()?;
+    /// assert_eq!(subjects[0], "CN=P-256 Leaf,O=Spruce Systems Ltd,C=AU");
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn iter(&self) -> std::slice::Iter<'_, X509> {
+        self.0.iter()
+    }
+
+    /// The number of certificates in the chain, which is never zero.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&X509> {
+        self.0.get(index)
+    }
+
+    /// Validate the chain against `registry`, returning every error found.
+    pub fn validate(&self, registry: &TrustAnchorRegistry) -> Vec<X509Error> {
+        self.validate_detailed(registry).errors()
+    }
+
+    /// Validate the chain against `registry` as in [X5Chain::validate], then check the
+    /// revocation status of each certificate with the OCSP responder named in it.
+    #[cfg(feature = "ocsp")]
+    pub fn validate_with_revocation(
+        &self,
+        registry: &TrustAnchorRegistry,
+        ocsp: &super::ocsp::OcspClient,
+    ) -> Vec<X509Error> {
+        let mut errors = self.validate(registry);
+        errors.extend(
+            self.issued_by(registry)
+                .into_iter()
+                .filter_map(|(index, cert, issuer)| {
+                    revocation_error(index, ocsp.check(cert, issuer))
+                }),
+        );
+        errors
+    }
+
+    /// Validate the chain against `registry` as in [X5Chain::validate], then check the
+    /// revocation status of each certificate against the CRL published by its issuer.
+    ///
+    /// Certificates whose issuer has no CRL in `crls` are reported as
+    /// [X509Error::RevocationCheck].
+    pub fn validate_with_crls(
+        &self,
+        registry: &TrustAnchorRegistry,
+        crls: &[CertificateList],
+    ) -> Vec<X509Error> {
+        let mut errors = self.validate(registry);
+        errors.extend(
+            self.issued_by(registry)
+                .into_iter()
+                .filter_map(|(index, cert, issuer)| {
+                    revocation_error(index, check_revocation_against_crls(cert, issuer, crls))
+                }),
+        );
+        errors
+    }
+
+    /// Each certificate in the chain paired with its issuer: the next certificate in the chain,
+    /// or the trust anchor for the last certificate. Trust anchors themselves are omitted.
+    fn issued_by<'a>(
+        &'a self,
+        registry: &'a TrustAnchorRegistry,
+    ) -> Vec<(usize, &'a X509, &'a X509)> {
+        self.0
+            .iter()
+            .enumerate()
+            .filter_map(|(index, cert)| {
+                let issuer = self
+                    .0
+                    .get(index + 1)
+                    .or_else(|| registry.find_anchor(cert))?;
+                (issuer != cert).then_some((index, cert, issuer))
+            })
+            .collect()
+    }
+
+    /// Validate the chain against `registry`, recording the outcome of each check per
+    /// certificate.
+    ///
+    /// Each certificate must be signed by the certificate that follows it, be within its
+    /// validity period, and carry the key usages for its role: the first certificate is the
+    /// document signer, and any others are IACA certificates. The last certificate must either
+    /// be a trust anchor in `registry`, or be signed by one, and that trust anchor must conform
+    /// to the IACA profile.
+    pub fn validate_detailed(&self, registry: &TrustAnchorRegistry) -> ValidationReport {
+        self.validate_detailed_at(registry, OffsetDateTime::now_utc())
+    }
+
+    /// Validate a reader authentication chain against `registry`, the trust anchors of the
+    /// reader PKI.
+    ///
+    /// The checks are those of [X5Chain::validate_detailed], except that the first certificate
+    /// must be a reader authentication certificate, and the trust anchor is not checked against
+    /// the IACA profile.
+    pub fn validate_reader_auth(&self, registry: &TrustAnchorRegistry) -> Vec<X509Error> {
+        self.validate_detailed_for(
+            registry,
+            OffsetDateTime::now_utc(),
+            KeyUsageProfile::ReaderAuth,
+        )
+        .errors()
+    }
+
+    fn validate_detailed_at(
+        &self,
+        registry: &TrustAnchorRegistry,
+        now: OffsetDateTime,
+    ) -> ValidationReport {
+        self.validate_detailed_for(registry, now, KeyUsageProfile::DocumentSigner)
+    }
+
+    fn validate_detailed_for(
+        &self,
+        registry: &TrustAnchorRegistry,
+        now: OffsetDateTime,
+        leaf_profile: KeyUsageProfile,
+    ) -> ValidationReport {
+        let certificates = self
+            .0
+            .iter()
+            .enumerate()
+            .map(|(index, x509)| {
+                let (issuer, trust_anchor) = match self.0.get(index + 1) {
+                    Some(next) => (Some(next), None),
+                    None => match registry.find_anchor(x509) {
+                        Some(anchor) => (Some(anchor), Some(Ok(()))),
+                        None => (None, Some(Err(X509Error::NoTrustAnchor(index)))),
+                    },
+                };
+                let profile = match index {
+                    0 => leaf_profile,
+                    _ => KeyUsageProfile::Iaca,
+                };
+                CertificateReport {
+                    index,
+                    signature: issuer.map(|issuer| {
+                        check_signature(x509, issuer)
+                            .map_err(|e| X509Error::SignatureVerification(index, e.to_string()))
+                    }),
+                    validity: check_validity_period(index, x509, now),
+                    key_usage: x509
+                        .certificate()
+                        .and_then(|cert| check_key_usage(&cert, profile))
+                        .map_err(|e| X509Error::KeyUsage(index, e.to_string())),
+                    trust_anchor,
+                }
+            })
+            .collect();
+        let iaca_profile = Some(self.0.last())
+            .filter(|_| leaf_profile == KeyUsageProfile::DocumentSigner)
+            .and_then(|last| registry.find_anchor(last))
+            .map(|anchor| match anchor.certificate() {
+                Ok(cert) => validate_iaca_profile(&cert),
+                Err(e) => vec![X509Error::IacaProfile(e.to_string())],
+            })
+            .unwrap_or_default();
+        ValidationReport {
+            certificates,
+            iaca_profile,
+        }
+    }
+
+    /// Encode the chain as concatenated `CERTIFICATE` PEM blocks, leaf first.
+    pub fn to_pem_bundle(&self) -> Result<String> {
+        self.iter()
+            .map(|x509| {
+                pem_rfc7468::encode_string("CERTIFICATE", LineEnding::LF, &x509.bytes)
+                    .map_err(|e| anyhow!("unable to encode pem: {}", e))
+            })
+            .collect()
+    }
+
+    /// The DER encoding of each certificate in the chain, leaf first.
+    pub fn to_der_chain(&self) -> Vec<Vec<u8>> {
+        self.iter().map(|x509| x509.bytes.clone()).collect()
+    }
+
+    /// Parse an x5chain header value, either a single certificate or an array of them.
+    pub fn from_cbor(cbor: &CborValue) -> Result<X5Chain> {
+        match cbor {
+            CborValue::Bytes(bytes) => X5Chain::builder().with_der(bytes)?.build(),
+            CborValue::Array(certs) => certs
+                .iter()
+                .try_fold(X5Chain::builder(), |builder, cert| match cert {
+                    CborValue::Bytes(bytes) => builder.with_der(bytes),
+                    _ => Err(anyhow!("x5chain array contains a non-bstr element")),
+                })?
+                .build(),
+            _ => Err(anyhow!("x5chain is neither a bstr nor an array")),
+        }
+    }
+
+    pub fn into_cbor(&self) -> CborValue {
+        match &self.0.as_ref() {
+            &[cert] => CborValue::Bytes(cert.bytes.clone()),
+            certs => CborValue::Array(
+                certs
+                    .iter()
+                    .cloned()
+                    .map(|x509| x509.bytes)
+                    .map(CborValue::Bytes)
+                    .collect::<Vec<CborValue>>(),
+            ),
+        }
+    }
+}
+
+/// The outcome of validating an [X5Chain], with one entry per certificate in chain order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub certificates: Vec<CertificateReport>,
+    /// Deviations of the trust anchor from the IACA profile, if a trust anchor was found.
+    pub iaca_profile: Vec<X509Error>,
+}
+
+/// The outcome of each check performed on the certificate at `index` in the chain.
+///
+/// `signature` is checked against the next certificate in the chain, or against the trust
+/// anchor for the last certificate, and is `None` if no trust anchor was found.
+/// `trust_anchor` is only checked for the last certificate in the chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateReport {
+    pub index: usize,
+    pub signature: Option<Result<(), X509Error>>,
+    pub validity: Result<(), X509Error>,
+    pub key_usage: Result<(), X509Error>,
+    pub trust_anchor: Option<Result<(), X509Error>>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.iaca_profile.is_empty()
+            && self
+                .certificates
+                .iter()
+                .all(|cert| cert.errors().is_empty())
+    }
+
+    pub fn errors(&self) -> Vec<X509Error> {
+        self.certificates
+            .iter()
+            .flat_map(|cert| cert.errors())
+            .chain(self.iaca_profile.iter().cloned())
+            .collect()
+    }
+}
+
+impl CertificateReport {
+    pub fn errors(&self) -> Vec<X509Error> {
+        [
+            self.signature.clone(),
+            Some(self.validity.clone()),
+            Some(self.key_usage.clone()),
+            self.trust_anchor.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(Result::err)
+        .collect()
+    }
+}
+
+fn check_validity_period(index: usize, x509: &X509, now: OffsetDateTime) -> Result<(), X509Error> {
+    let validity = x509
+        .certificate()
+        .map_err(|e| X509Error::Malformed(index, e.to_string()))?
+        .tbs_certificate
+        .validity;
+    if now < OffsetDateTime::UNIX_EPOCH + validity.not_before.to_unix_duration() {
+        return Err(X509Error::NotYetValid(index));
+    }
+    if now > OffsetDateTime::UNIX_EPOCH + validity.not_after.to_unix_duration() {
+        return Err(X509Error::Expired(index));
+    }
+    Ok(())
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct Builder {
+    certs: Vec<X509>,
+}
+
+impl Builder {
+    pub fn with_pem(mut self, data: &[u8]) -> Result<Builder> {
+        self.certs.push(X509::from_pem(data)?);
+        Ok(self)
+    }
+    pub fn with_der(mut self, data: &[u8]) -> Result<Builder> {
+        self.certs.push(X509::from_der(data)?);
+        Ok(self)
+    }
+    /// Add every certificate in a buffer of concatenated PEM blocks, in order.
+    pub fn with_pem_chain(mut self, data: &[u8]) -> Result<Builder> {
+        let certs = Certificate::load_pem_chain(data)
+            .map_err(|e| anyhow!("unable to parse pem chain: {}", e))?;
+        for cert in certs {
+            self.certs.push(X509::from_certificate(&cert)?);
+        }
+        Ok(self)
+    }
+    /// Add every certificate in a DER encoded `SEQUENCE OF Certificate`, in order.
+    pub fn with_der_sequence(mut self, data: &[u8]) -> Result<Builder> {
+        let certs = Vec::<Certificate>::from_der(data)
+            .map_err(|e| anyhow!("unable to parse certificate sequence: {}", e))?;
+        for cert in certs {
+            self.certs.push(X509::from_certificate(&cert)?);
+        }
+        Ok(self)
+    }
+    pub fn with_pem_from_file(self, mut f: File) -> Result<Builder> {
+        let mut data: Vec<u8> = vec![];
+        f.read_to_end(&mut data)?;
+        self.with_pem(&data)
+    }
+    pub fn with_der_from_file(self, mut f: File) -> Result<Builder> {
+        let mut data: Vec<u8> = vec![];
+        f.read_to_end(&mut data)?;
+        self.with_der(&data)
+    }
+    pub fn build(self) -> Result<X5Chain> {
+        Ok(X5Chain(self.certs.try_into().map_err(|_| {
+            anyhow!("at least one certificate must be given to the builder")
+        })?))
+    }
+
+    /// Build the chain regardless of the order the certificates were added in, placing the leaf
+    /// first and each certificate's issuer after it.
+    ///
+    /// Fails if the certificates do not form a single chain.
+    pub fn build_sorted(self) -> Result<X5Chain> {
+        let mut remaining = self
+            .certs
+            .into_iter()
+            .map(|x509| Ok((x509.certificate()?, x509)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut leaves = (0..remaining.len()).filter(|&i| {
+            !remaining
+                .iter()
+                .enumerate()
+                .any(|(j, (cert, _))| i != j && is_issued_by(cert, &remaining[i].0))
+        });
+        let leaf = match (leaves.next(), leaves.next()) {
+            (Some(leaf), None) => leaf,
+            (None, _) => return Err(anyhow!("certificates contain no leaf certificate")),
+            (Some(_), Some(_)) => return Err(anyhow!("certificates do not form a single chain")),
+        };
+
+        let mut sorted = vec![remaining.swap_remove(leaf)];
+        while !remaining.is_empty() {
+            let (current, _) = &sorted[sorted.len() - 1];
+            let next = remaining
+                .iter()
+                .position(|(cert, _)| is_issued_by(current, cert))
+                .ok_or_else(|| anyhow!("certificates do not form a single chain"))?;
+            sorted.push(remaining.swap_remove(next));
+        }
+
+        Builder {
+            certs: sorted.into_iter().map(|(_, x509)| x509).collect(),
+        }
+        .build()
+    }
+}
+
+/// Whether `subject` names `issuer` as its issuer, by DN and, where both certificates carry
+/// them, by authority and subject key identifier.
+pub(crate) fn is_issued_by(subject: &Certificate, issuer: &Certificate) -> bool {
+    if subject == issuer || subject.tbs_certificate.issuer != issuer.tbs_certificate.subject {
+        return false;
+    }
+    let authority_key_id = subject
+        .tbs_certificate
+        .get::<AuthorityKeyIdentifier>()
+        .ok()
+        .flatten()
+        .and_then(|(_critical, aki)| aki.key_identifier);
+    let subject_key_id = issuer
+        .tbs_certificate
+        .get::<SubjectKeyIdentifier>()
+        .ok()
+        .flatten()
+        .map(|(_critical, ski)| ski.0);
+    match (authority_key_id, subject_key_id) {
+        (Some(authority_key_id), Some(subject_key_id)) => authority_key_id == subject_key_id,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use time::macros::datetime;
+
+    static CERT_256: &[u8] = include_bytes!("../../../test/issuance/256-cert.pem");
+    static CERT_384: &[u8] = include_bytes!("../../../test/issuance/384-cert.pem");
+    static CERT_521: &[u8] = include_bytes!("../../../test/issuance/521-cert.pem");
+    static CERT_256_ISSUED_BY_384: &[u8] =
+        include_bytes!("../../../test/issuance/256-issued-by-384-cert.pem");
+    static CERT_384_ISSUED_BY_521: &[u8] =
+        include_bytes!("../../../test/issuance/384-issued-by-521-cert.pem");
+    static ED25519_ROOT: &[u8] = include_bytes!("../../../test/issuance/ed25519-cert.pem");
+    static ED25519_ISSUED_LEAF: &[u8] =
+        include_bytes!("../../../test/issuance/ed25519-issued-leaf-cert.pem");
+    static WRONG_KEY_USAGE: &[u8] =
+        include_bytes!("../../../test/issuance/256-wrong-key-usage-cert.pem");
+    static GOOD_LEAF: &[u8] =
+        include_bytes!("../../../test/definitions/x509/ocsp/good-leaf-cert.pem");
+    static REVOKED_LEAF: &[u8] =
+        include_bytes!("../../../test/definitions/x509/ocsp/revoked-leaf-cert.pem");
+    static RSA_ROOT_CRL: &[u8] =
+        include_bytes!("../../../test/definitions/x509/crl/rsa-root-crl.der");
+    static RSA_ROOT: &[u8] = include_bytes!("../../../test/issuance/rsa-root-cert.pem");
+    static NONCONFORMING_IACA: &[u8] =
+        include_bytes!("../../../test/issuance/iaca-nonconforming-cert.pem");
+    static NONCONFORMING_IACA_ISSUED_DS: &[u8] =
+        include_bytes!("../../../test/issuance/iaca-nonconforming-issued-ds-cert.pem");
+    static CHAIN_256_384_PEM: &[u8] = include_bytes!("../../../test/issuance/256-384-chain.pem");
+    static CHAIN_256_384_DER: &[u8] = include_bytes!("../../../test/issuance/256-384-chain.der");
+    static RSA_ISSUED_LEAF: &[u8] =
+        include_bytes!("../../../test/issuance/rsa-issued-leaf-cert.pem");
+    static RSA_PSS_ISSUED_LEAF: &[u8] =
+        include_bytes!("../../../test/issuance/rsa-pss-issued-leaf-cert.pem");
+
+    #[test]
+    pub fn self_signed_es256() {
+        let x5chain = X5Chain::builder()
+            .with_pem(CERT_256)
+            .expect("unable to add cert")
+            .build()
+            .expect("unable to build x5chain");
+
+        let self_signed = x5chain.leaf();
+        assert!(matches!(
+            self_signed
+                .public_key_kind()
+                .expect("unable to parse public key"),
+            PublicKeyKind::Ec(_)
+        ));
+        check_signature(self_signed, self_signed).expect("unable to verify self signed cert");
+
+        //let self_signed = &x5chain[0];
+
+        //assert!(self_signed.issued(self_signed) == CertificateVerifyResult::OK);
+        //assert!(self_signed
+        //    .verify(
+        //        &self_signed
+        //            .public_key()
+        //            .expect("unable to get public key of cert")
+        //    )
+        //    .expect("unable to verify public key of cert"));
+
+        //assert!(matches!(
+        //    x5chain
+        //        .key_algorithm()
+        //        .expect("unable to retrieve public key algorithm"),
+        //    Algorithm::ES256
+        //));
+    }
+
+    #[test]
+    pub fn self_signed_es384() {
+        let x5chain = X5Chain::builder()
+            .with_pem(CERT_384)
+            .expect("unable to add cert")
+            .build()
+            .expect("unable to build x5chain");
+
+        let self_signed = x5chain.leaf();
+        check_signature(self_signed, self_signed).expect("unable to verify self signed cert");
+
+        //let self_signed = &x5chain[0];
+
+        //assert!(self_signed.issued(self_signed) == CertificateVerifyResult::OK);
+        //assert!(self_signed
+        //    .verify(
+        //        &self_signed
+        //            .public_key()
+        //            .expect("unable to get public key of cert")
+        //    )
+        //    .expect("unable to verify public key of cert"));
+
+        //assert!(matches!(
+        //    x5chain
+        //        .key_algorithm()
+        //        .expect("unable to retrieve public key algorithm"),
+        //    Algorithm::ES384
+        //));
+    }
+
+    #[test]
+    pub fn self_signed_es512() {
+        let x5chain = X5Chain::builder()
+            .with_pem(CERT_521)
+            .expect("unable to add cert")
+            .build()
+            .expect("unable to build x5chain");
+
+        let self_signed = x5chain.leaf();
+        check_signature(self_signed, self_signed).expect("unable to verify self signed cert");
+
+        //let self_signed = &x5chain[0];
+
+        //assert!(self_signed.issued(self_signed) == CertificateVerifyResult::OK);
+        //assert!(self_signed
+        //    .verify(
+        //        &self_signed
+        //            .public_key()
+        //            .expect("unable to get public key of cert")
+        //    )
+        //    .expect("unable to verify public key of cert"));
+
+        //assert!(matches!(
+        //    x5chain
+        //        .key_algorithm()
+        //        .expect("unable to retrieve public key algorithm"),
+        //    Algorithm::ES512
+        //));
+    }
+
+    #[test]
+    pub fn mixed_curve_chain() {
+        let x5chain = X5Chain::builder()
+            .with_pem(CERT_256_ISSUED_BY_384)
+            .expect("unable to add leaf cert")
+            .with_pem(CERT_384_ISSUED_BY_521)
+            .expect("unable to add intermediate cert")
+            .with_pem(CERT_521)
+            .expect("unable to add root cert")
+            .build()
+            .expect("unable to build x5chain");
+
+        assert!(matches!(
+            x5chain
+                .get(1)
+                .expect("missing intermediate cert")
+                .public_key_kind()
+                .expect("unable to parse key"),
+            PublicKeyKind::Ec(EcPublicKey::P384(_))
+        ));
+        assert!(matches!(
+            x5chain
+                .root()
+                .public_key_kind()
+                .expect("unable to parse key"),
+            PublicKeyKind::Ec(EcPublicKey::P521(_))
+        ));
+
+        let registry = TrustAnchorRegistry::new()
+            .with_pem(CERT_521)
+            .expect("unable to add trust anchor");
+        let report = x5chain.validate_detailed(&registry);
+        assert!(!report.is_valid());
+        assert_eq!(report.certificates[2].validity, Err(X509Error::Expired(2)));
+        // The self-signed P-521 fixture predates the IACA profile and carries no keyUsage.
+        assert!(matches!(
+            report.certificates[2].key_usage,
+            Err(X509Error::KeyUsage(2, _))
+        ));
+        assert!(report.iaca_profile.contains(&X509Error::IacaProfile(
+            "missing cRLDistributionPoints extension".into()
+        )));
+        assert_eq!(report.certificates[2].trust_anchor, Some(Ok(())));
+        assert_eq!(report.certificates[2].signature, Some(Ok(())));
+    }
+
+    #[test]
+    pub fn untrusted_chain() {
+        let x5chain = X5Chain::builder()
+            .with_pem(CERT_256_ISSUED_BY_384)
+            .expect("unable to add leaf cert")
+            .with_pem(CERT_384_ISSUED_BY_521)
+            .expect("unable to add intermediate cert")
+            .build()
+            .expect("unable to build x5chain");
+
+        let registry = TrustAnchorRegistry::new()
+            .with_pem(RSA_ROOT)
+            .expect("unable to add trust anchor");
+        let report = x5chain.validate_detailed(&registry);
+        assert!(!report.is_valid());
+        assert_eq!(report.certificates[1].signature, None);
+        assert_eq!(
+            x5chain.validate(&registry),
+            vec![X509Error::NoTrustAnchor(1)]
+        );
+    }
+
+    #[test]
+    pub fn document_signer_key_usage() {
+        let x5chain = X5Chain::builder()
+            .with_pem(WRONG_KEY_USAGE)
+            .expect("unable to add leaf cert")
+            .with_pem(CERT_384_ISSUED_BY_521)
+            .expect("unable to add intermediate cert")
+            .build()
+            .expect("unable to build x5chain");
+
+        let registry = TrustAnchorRegistry::new()
+            .with_pem(CERT_521)
+            .expect("unable to add trust anchor");
+        let report = x5chain.validate_detailed(&registry);
+        assert_eq!(
+            report.certificates[0].key_usage,
+            Err(X509Error::KeyUsage(
+                0,
+                "keyUsage does not include digitalSignature".into()
+            ))
+        );
+        assert_eq!(report.certificates[1].key_usage, Ok(()));
+    }
+
+    #[test]
+    pub fn unrelated_self_signed_chain() {
+        let x5chain = X5Chain::builder()
+            .with_pem(CERT_256)
+            .expect("unable to add cert")
+            .with_pem(CERT_384)
+            .expect("unable to add cert")
+            .with_pem(CERT_521)
+            .expect("unable to add cert")
+            .build()
+            .expect("unable to build x5chain");
+
+        let registry = TrustAnchorRegistry::new()
+            .with_pem(CERT_521)
+            .expect("unable to add trust anchor");
+        let report = x5chain.validate_detailed_at(&registry, datetime!(2022-10-01 0:00 UTC));
+        assert!(matches!(
+            report.certificates[0].signature,
+            Some(Err(X509Error::SignatureVerification(0, _)))
+        ));
+        assert!(matches!(
+            report.certificates[1].signature,
+            Some(Err(X509Error::SignatureVerification(1, _)))
+        ));
+        assert_eq!(report.certificates[2].signature, Some(Ok(())));
+        assert!(report.certificates.iter().all(|cert| cert.validity.is_ok()));
+    }
+
+    #[test]
+    pub fn self_signed_ed25519() {
+        let x5chain = X5Chain::builder()
+            .with_pem(ED25519_ROOT)
+            .expect("unable to add cert")
+            .build()
+            .expect("unable to build x5chain");
+
+        let self_signed = x5chain.leaf();
+        self_signed
+            .ed25519_public_key()
+            .expect("unable to parse ed25519 public key");
+        check_signature(self_signed, self_signed).expect("unable to verify self signed cert");
+
+        let registry = TrustAnchorRegistry::new()
+            .with_pem(ED25519_ROOT)
+            .expect("unable to add trust anchor");
+        let report = x5chain.validate_detailed(&registry);
+        assert_eq!(report.certificates[0].signature, Some(Ok(())));
+        assert_eq!(report.certificates[0].validity, Ok(()));
+        assert_eq!(report.certificates[0].trust_anchor, Some(Ok(())));
+    }
+
+    #[test]
+    pub fn nonconforming_iaca() {
+        let x5chain = X5Chain::builder()
+            .with_pem(NONCONFORMING_IACA_ISSUED_DS)
+            .expect("unable to add leaf cert")
+            .build()
+            .expect("unable to build x5chain");
+
+        // The trust anchor allows a path length of 1, but every other check passes.
+        let registry = TrustAnchorRegistry::new()
+            .with_pem(NONCONFORMING_IACA)
+            .expect("unable to add trust anchor");
+        let report = x5chain.validate_detailed(&registry);
+        assert!(report
+            .certificates
+            .iter()
+            .all(|cert| cert.errors().is_empty()));
+        let profile_error =
+            X509Error::IacaProfile("basicConstraints pathLenConstraint must be 0".into());
+        assert_eq!(report.iaca_profile, vec![profile_error.clone()]);
+        assert!(!report.is_valid());
+        assert_eq!(x5chain.validate(&registry), vec![profile_error]);
+    }
+
+    #[test]
+    pub fn ed25519_chain() {
+        let x5chain = X5Chain::builder()
+            .with_pem(ED25519_ISSUED_LEAF)
+            .expect("unable to add leaf cert")
+            .with_pem(ED25519_ROOT)
+            .expect("unable to add root cert")
+            .build()
+            .expect("unable to build x5chain");
+
+        let registry = TrustAnchorRegistry::new()
+            .with_pem(ED25519_ROOT)
+            .expect("unable to add trust anchor");
+        assert!(x5chain.validate_detailed(&registry).is_valid());
+
+        let reversed = X5Chain::builder()
+            .with_pem(ED25519_ROOT)
+            .expect("unable to add root cert")
+            .with_pem(ED25519_ISSUED_LEAF)
+            .expect("unable to add leaf cert")
+            .build()
+            .expect("unable to build x5chain");
+
+        let report = reversed.validate_detailed(&registry);
+        assert!(!report.is_valid());
+        assert!(matches!(
+            report.certificates[0].signature,
+            Some(Err(X509Error::SignatureVerification(0, _)))
+        ));
+    }
+
+    #[test]
+    pub fn rsa_issuer_pkcs1v15() {
+        let x5chain = X5Chain::builder()
+            .with_pem(RSA_ISSUED_LEAF)
+            .expect("unable to add leaf cert")
+            .with_pem(RSA_ROOT)
+            .expect("unable to add root cert")
+            .build()
+            .expect("unable to build x5chain");
+
+        let leaf = x5chain.leaf();
+        let root = x5chain.root();
+        assert!(matches!(
+            leaf.public_key_kind().expect("unable to parse leaf key"),
+            PublicKeyKind::Ec(_)
+        ));
+        assert!(matches!(
+            root.public_key_kind().expect("unable to parse root key"),
+            PublicKeyKind::Rsa(_)
+        ));
+        check_signature(root, root).expect("unable to verify self signed rsa root");
+        check_signature(leaf, root).expect("unable to verify rsa signed leaf");
+        assert!(check_signature(root, leaf).is_err());
+    }
+
+    #[test]
+    pub fn rsa_issuer_pss() {
+        let x5chain = X5Chain::builder()
+            .with_pem(RSA_PSS_ISSUED_LEAF)
+            .expect("unable to add leaf cert")
+            .with_pem(RSA_ROOT)
+            .expect("unable to add root cert")
+            .build()
+            .expect("unable to build x5chain");
+
+        check_signature(x5chain.leaf(), x5chain.root())
+            .expect("unable to verify rsa-pss signed leaf");
+    }
+
+    #[test]
+    pub fn wrong_issuer() {
+        let leaf = X5Chain::builder()
+            .with_pem(RSA_ISSUED_LEAF)
+            .expect("unable to add leaf cert")
+            .build()
+            .expect("unable to build x5chain");
+        let other = X5Chain::builder()
+            .with_pem(CERT_256)
+            .expect("unable to add cert")
+            .build()
+            .expect("unable to build x5chain");
+
+        assert!(check_signature(&leaf.0[0], &other.0[0]).is_err());
+    }
+
+    #[test]
+    pub fn validate_with_crls() {
+        let registry = TrustAnchorRegistry::new()
+            .with_pem(RSA_ROOT)
+            .expect("unable to add trust anchor");
+        let crls = [CertificateList::from_der(RSA_ROOT_CRL).expect("unable to parse crl")];
+
+        let good = X5Chain::builder()
+            .with_pem(GOOD_LEAF)
+            .expect("unable to add leaf cert")
+            .build()
+            .expect("unable to build x5chain");
+        assert_eq!(good.validate_with_crls(&registry, &crls), vec![]);
+
+        let revoked = X5Chain::builder()
+            .with_pem(REVOKED_LEAF)
+            .expect("unable to add leaf cert")
+            .build()
+            .expect("unable to build x5chain");
+        assert_eq!(
+            revoked.validate_with_crls(&registry, &crls),
+            vec![X509Error::Revoked(0)]
+        );
+        assert!(matches!(
+            revoked.validate_with_crls(&registry, &[])[..],
+            [X509Error::RevocationCheck(0, _)]
+        ));
+    }
+
+    #[test]
+    pub fn build_sorted() {
+        let orders: [[&[u8]; 3]; 3] = [
+            [CERT_521, CERT_384_ISSUED_BY_521, CERT_256_ISSUED_BY_384],
+            [CERT_384_ISSUED_BY_521, CERT_256_ISSUED_BY_384, CERT_521],
+            [CERT_256_ISSUED_BY_384, CERT_521, CERT_384_ISSUED_BY_521],
+        ];
+        let expected = X5Chain::builder()
+            .with_pem(CERT_256_ISSUED_BY_384)
+            .and_then(|b| b.with_pem(CERT_384_ISSUED_BY_521))
+            .and_then(|b| b.with_pem(CERT_521))
+            .and_then(|b| b.build())
+            .expect("unable to build x5chain");
+
+        for order in orders {
+            let x5chain = order
+                .into_iter()
+                .try_fold(X5Chain::builder(), |builder, pem| builder.with_pem(pem))
+                .and_then(|builder| builder.build_sorted())
+                .expect("unable to sort x5chain");
+            assert_eq!(x5chain.0, expected.0);
+        }
+    }
+
+    #[test]
+    pub fn build_sorted_disjoint() {
+        X5Chain::builder()
+            .with_pem(CERT_256_ISSUED_BY_384)
+            .and_then(|b| b.with_pem(ED25519_ROOT))
+            .and_then(|b| b.build_sorted())
+            .expect_err("disjoint certificates sorted into a chain");
+        X5Chain::builder()
+            .with_pem(CERT_256_ISSUED_BY_384)
+            .and_then(|b| b.with_pem(CERT_521))
+            .and_then(|b| b.build_sorted())
+            .expect_err("certificates with a missing link sorted into a chain");
+    }
+
+    #[test]
+    pub fn accessors() {
+        let x5chain = X5Chain::builder()
+            .with_pem(CERT_256_ISSUED_BY_384)
+            .expect("unable to add leaf cert")
+            .with_pem(CERT_384_ISSUED_BY_521)
+            .expect("unable to add intermediate cert")
+            .build()
+            .expect("unable to build x5chain");
+        let leaf = X509::from_pem(CERT_256_ISSUED_BY_384).expect("unable to parse cert");
+        let intermediate = X509::from_pem(CERT_384_ISSUED_BY_521).expect("unable to parse cert");
+
+        assert_eq!(x5chain.len(), 2);
+        assert_eq!(x5chain.leaf(), &leaf);
+        assert_eq!(x5chain.root(), &intermediate);
+        assert_eq!(x5chain.get(1), Some(&intermediate));
+        assert_eq!(x5chain.get(2), None);
+        assert_eq!(
+            x5chain.iter().collect::<Vec<_>>(),
+            vec![&leaf, &intermediate]
+        );
+    }
+
+    #[test]
+    pub fn pem_bundle_round_trip() {
+        let x5chain = X5Chain::builder()
+            .with_pem(CERT_256_ISSUED_BY_384)
+            .expect("unable to add leaf cert")
+            .with_pem(CERT_384_ISSUED_BY_521)
+            .expect("unable to add intermediate cert")
+            .with_pem(CERT_521)
+            .expect("unable to add root cert")
+            .build()
+            .expect("unable to build x5chain");
+
+        let bundle = x5chain
+            .to_pem_bundle()
+            .expect("unable to encode pem bundle");
+        let reimported = X5Chain::builder()
+            .with_pem_chain(bundle.as_bytes())
+            .and_then(|builder| builder.build())
+            .expect("unable to re-import pem bundle");
+
+        assert_eq!(reimported.len(), 3);
+        assert_eq!(reimported.to_der_chain(), x5chain.to_der_chain());
+    }
+
+    #[test]
+    pub fn multi_certificate_input() {
+        let expected = X5Chain::builder()
+            .with_pem(CERT_256_ISSUED_BY_384)
+            .expect("unable to add leaf cert")
+            .with_pem(CERT_384_ISSUED_BY_521)
+            .expect("unable to add intermediate cert")
+            .build()
+            .expect("unable to build x5chain");
+
+        let from_pem = X5Chain::builder()
+            .with_pem_chain(CHAIN_256_384_PEM)
+            .expect("unable to add pem chain")
+            .build()
+            .expect("unable to build x5chain");
+        assert_eq!(from_pem.to_der_chain(), expected.to_der_chain());
+
+        let from_der = X5Chain::builder()
+            .with_der_sequence(CHAIN_256_384_DER)
+            .expect("unable to add der sequence")
+            .build()
+            .expect("unable to build x5chain");
+        assert_eq!(from_der.to_der_chain(), expected.to_der_chain());
+    }
+
+    #[test]
+    pub fn cbor_round_trip() {
+        for pems in [
+            &[CERT_256][..],
+            &[CERT_256_ISSUED_BY_384, CERT_384_ISSUED_BY_521][..],
+        ] {
+            let x5chain = pems
+                .iter()
+                .try_fold(X5Chain::builder(), |builder, pem| builder.with_pem(pem))
+                .and_then(|builder| builder.build())
+                .expect("unable to build x5chain");
+            let roundtripped =
+                X5Chain::from_cbor(&x5chain.into_cbor()).expect("unable to parse x5chain");
+            assert_eq!(roundtripped.to_der_chain(), x5chain.to_der_chain());
+        }
+        assert!(X5Chain::from_cbor(&CborValue::Text("cert".into())).is_err());
+    }
+}
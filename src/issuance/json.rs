@@ -0,0 +1,164 @@
+use crate::{
+    definitions::{
+        namespaces::{
+            org_iso_18013_5_1::OrgIso1801351,
+            org_iso_18013_5_1_aamva::{OrgIso1801351Aamva, NAMESPACE as AAMVA_NAMESPACE},
+        },
+        traits::{FromJson, ToNamespaceMap},
+        DeviceKeyInfo, DigestAlgorithm, ValidityInfo,
+    },
+    issuance::{mdoc::Namespaces, x5chain::X5Chain, Mdoc},
+};
+use anyhow::{anyhow, Context, Result};
+use cose_rs::algorithm::SignatureAlgorithm;
+use serde_cbor::Value as CborValue;
+use serde_json::Value;
+use signature::{SignatureEncoding, Signer};
+use std::collections::BTreeMap;
+
+const MDL_NAMESPACE: &str = "org.iso.18013.5.1";
+
+/// Map a JSON object of namespace, to element identifier, to value, onto the elements of an mdoc.
+///
+/// The elements of the mDL and AAMVA namespaces are checked against their definitions, and
+/// encoded accordingly. The elements of any other namespace are mapped onto CBOR structurally.
+pub fn namespaces_from_json(json: &Value) -> Result<Namespaces> {
+    let namespaces = json
+        .as_object()
+        .ok_or_else(|| anyhow!("expected a JSON object of namespaces"))?;
+    namespaces
+        .iter()
+        .map(|(namespace, elements)| {
+            let elements = match namespace.as_str() {
+                MDL_NAMESPACE => OrgIso1801351::from_json(elements).map(|ns| ns.to_ns_map()),
+                AAMVA_NAMESPACE => OrgIso1801351Aamva::from_json(elements).map(|ns| ns.to_ns_map()),
+                _ => BTreeMap::<String, CborValue>::from_json(elements),
+            }
+            .with_context(|| format!("invalid elements in namespace {namespace}"))?;
+            Ok((namespace.clone(), elements))
+        })
+        .collect()
+}
+
+/// Issue an mdoc of `doc_type` from the JSON elements of each of its namespaces, as in
+/// [namespaces_from_json], digested with SHA-256 and signed by `signer`, whose certificate is
+/// the first in `x5chain`.
+pub fn issue_from_json<S, Sig>(
+    doc_type: String,
+    namespaces: &Value,
+    device_key_info: DeviceKeyInfo,
+    validity_info: ValidityInfo,
+    x5chain: X5Chain,
+    signer: S,
+) -> Result<Mdoc>
+where
+    S: Signer<Sig> + SignatureAlgorithm,
+    Sig: SignatureEncoding,
+{
+    Mdoc::builder()
+        .doc_type(doc_type)
+        .namespaces(namespaces_from_json(namespaces)?)
+        .validity_info(validity_info)
+        .digest_algorithm(DigestAlgorithm::SHA256)
+        .device_key_info(device_key_info)
+        .issue(x5chain, signer)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::definitions::{
+        helpers::{FullDate, TDate},
+        CoseKey, EC2Curve, EC2Y,
+    };
+    use elliptic_curve::sec1::ToEncodedPoint;
+    use p256::ecdsa::{Signature, SigningKey};
+    use p256::pkcs8::DecodePrivateKey;
+    use time::{Duration, OffsetDateTime};
+
+    static ISSUER_CERT: &[u8] = include_bytes!("../../test/issuance/issuer-cert.pem");
+    static ISSUER_KEY: &str = include_str!("../../test/issuance/issuer-key.pem");
+    static MDL_JSON: &str = include_str!("../../test/issuance/mdl.json");
+
+    fn device_key_info() -> DeviceKeyInfo {
+        let point = p256::SecretKey::random(&mut rand::thread_rng())
+            .public_key()
+            .to_encoded_point(false);
+        DeviceKeyInfo {
+            device_key: CoseKey::EC2 {
+                crv: EC2Curve::P256,
+                x: point.x().unwrap().to_vec(),
+                y: EC2Y::Value(point.y().unwrap().to_vec()),
+            },
+            key_authorizations: None,
+            key_info: None,
+        }
+    }
+
+    #[test]
+    fn namespaces() {
+        let json: Value = serde_json::from_str(MDL_JSON).unwrap();
+        let namespaces = namespaces_from_json(&json).unwrap();
+
+        assert_eq!(
+            namespaces[MDL_NAMESPACE]["family_name"],
+            CborValue::Text("Smith".into())
+        );
+        // Dates are encoded as full-dates, rather than as text.
+        assert!(matches!(
+            namespaces[MDL_NAMESPACE]["birth_date"],
+            CborValue::Tag(1004, _)
+        ));
+        assert!(namespaces[AAMVA_NAMESPACE].contains_key("DHS_compliance"));
+        assert_eq!(
+            namespaces["org.example.loyalty"]["points"],
+            CborValue::Integer(1200)
+        );
+
+        // Dates are tagged as full-dates or tdates, as defined for each element.
+        let birth_date =
+            FullDate::try_from(namespaces[MDL_NAMESPACE]["birth_date"].clone()).unwrap();
+        assert_eq!(birth_date.to_string(), "1980-01-01");
+        let portrait_capture_date =
+            TDate::try_from(namespaces[MDL_NAMESPACE]["portrait_capture_date"].clone()).unwrap();
+        assert_eq!(portrait_capture_date.to_string(), "2020-01-01T12:00:00Z");
+
+        let invalid = serde_json::json!({ MDL_NAMESPACE: { "family_name": 1 } });
+        assert!(namespaces_from_json(&invalid).is_err());
+    }
+
+    #[test]
+    fn issue() {
+        let json: Value = serde_json::from_str(MDL_JSON).unwrap();
+        let x5chain = X5Chain::builder()
+            .with_pem(ISSUER_CERT)
+            .unwrap()
+            .build()
+            .unwrap();
+        let signer: SigningKey = p256::SecretKey::from_pkcs8_pem(ISSUER_KEY).unwrap().into();
+        let validity_info = ValidityInfo::builder()
+            .valid_for(Duration::days(365))
+            .build()
+            .unwrap();
+
+        let mdoc = issue_from_json::<_, Signature>(
+            "org.iso.18013.5.1.mDL".to_string(),
+            &json,
+            device_key_info(),
+            validity_info,
+            x5chain,
+            signer,
+        )
+        .unwrap();
+
+        assert_eq!(mdoc.namespaces.len(), 3);
+        // Decoy digests are added by default, so each namespace has more digests than items.
+        for (namespace, items) in mdoc.namespaces.iter() {
+            let digests = &mdoc.mso.value_digests[namespace];
+            assert!(items
+                .iter()
+                .all(|item| digests.contains_key(&item.as_ref().digest_id)));
+        }
+        assert!(mdoc.mso.validity_info.valid_until > OffsetDateTime::now_utc());
+    }
+}
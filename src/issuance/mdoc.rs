@@ -1,8 +1,10 @@
 use crate::{
     definitions::{
         helpers::{NonEmptyMap, NonEmptyVec, Tag24},
-        issuer_signed::{IssuerNamespaces, IssuerSignedItemBytes},
-        DeviceKeyInfo, DigestAlgorithm, DigestId, DigestIds, IssuerSignedItem, Mso, ValidityInfo,
+        issuer_signed::{IssuerNamespaces, IssuerSigned, IssuerSignedItemBytes},
+        namespaces::org_iso_18013_5_1::Portrait,
+        DeviceKeyInfo, DigestAlgorithm, DigestId, DigestIds, IssuerSignedItem, KeyAuthorizations,
+        Mso, ValidityInfo,
     },
     issuance::x5chain::{X5Chain, X5CHAIN_HEADER_LABEL},
 };
@@ -12,15 +14,19 @@ use cose_rs::{
     algorithm::{Algorithm, SignatureAlgorithm},
     sign1::{CoseSign1, PreparedCoseSign1},
 };
-use rand::Rng;
+use rand::{CryptoRng, Rng, RngCore};
 use serde::{Deserialize, Serialize};
 use serde_cbor::Value as CborValue;
-use sha2::{Digest, Sha256, Sha384, Sha512};
 use signature::{SignatureEncoding, Signer};
 use std::collections::{BTreeMap, HashSet};
 
 pub type Namespaces = BTreeMap<String, BTreeMap<String, CborValue>>;
 
+/// The default length in bytes of the random salt of each element.
+pub const DEFAULT_SALT_LENGTH: usize = 16;
+const MIN_SALT_LENGTH: usize = 16;
+const MDL_NAMESPACE: &str = "org.iso.18013.5.1";
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 /// A signed mdoc.
@@ -47,7 +53,11 @@ pub struct Builder {
     validity_info: Option<ValidityInfo>,
     digest_algorithm: Option<DigestAlgorithm>,
     device_key_info: Option<DeviceKeyInfo>,
+    key_authorizations: Option<KeyAuthorizations>,
     enable_decoy_digests: Option<bool>,
+    salt_length: Option<usize>,
+    portrait: Option<Portrait>,
+    detached_payload: Option<bool>,
 }
 
 impl Mdoc {
@@ -65,28 +75,52 @@ impl Mdoc {
         signature_algorithm: Algorithm,
         enable_decoy_digests: bool,
     ) -> Result<PreparedMdoc> {
-        if let Some(authorizations) = &device_key_info.key_authorizations {
-            authorizations.validate()?;
-        }
-
-        let issuer_namespaces = to_issuer_namespaces(namespaces)?;
-        let value_digests =
-            digest_namespaces(&issuer_namespaces, digest_algorithm, enable_decoy_digests)?;
-
-        let mso = Mso {
-            version: "1.0".to_string(),
+        Self::prepare_salted(
+            doc_type,
+            namespaces,
+            validity_info,
             digest_algorithm,
-            value_digests,
             device_key_info,
-            doc_type: doc_type.clone(),
+            signature_algorithm,
+            enable_decoy_digests,
+            DEFAULT_SALT_LENGTH,
+            false,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn prepare_salted(
+        doc_type: String,
+        namespaces: Namespaces,
+        validity_info: ValidityInfo,
+        digest_algorithm: DigestAlgorithm,
+        device_key_info: DeviceKeyInfo,
+        signature_algorithm: Algorithm,
+        enable_decoy_digests: bool,
+        salt_length: usize,
+        detached_payload: bool,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<PreparedMdoc> {
+        let mut mso = Mso::new(
+            doc_type.clone(),
+            device_key_info,
             validity_info,
-        };
+            digest_algorithm,
+        )?;
+        let issuer_namespaces = to_issuer_namespaces(namespaces, salt_length, rng)?;
+        mso.value_digests =
+            digest_namespaces(&issuer_namespaces, digest_algorithm, enable_decoy_digests)?;
 
         let mso_bytes = serde_cbor::to_vec(&Tag24::new(&mso)?)?;
 
-        let prepared_sig = CoseSign1::builder()
+        let mut builder = CoseSign1::builder()
             .payload(mso_bytes)
-            .signature_algorithm(signature_algorithm)
+            .signature_algorithm(signature_algorithm);
+        if detached_payload {
+            builder = builder.detached();
+        }
+        let prepared_sig = builder
             .prepare()
             .map_err(|e| anyhow!("error preparing cosesign1: {}", e))?;
 
@@ -100,6 +134,21 @@ impl Mdoc {
         Ok(preparation_mdoc)
     }
 
+    /// The tagged encoding of the mobile security object, which is the payload of the issuer auth.
+    ///
+    /// A reader needs these bytes to verify an issuer auth whose payload is detached.
+    pub fn mso_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_cbor::to_vec(&Tag24::new(&self.mso)?)?)
+    }
+
+    /// The issuer signed elements and issuer auth, as provisioned to the holder.
+    pub fn issuer_signed(&self) -> IssuerSigned {
+        IssuerSigned {
+            namespaces: Some(self.namespaces.clone()),
+            issuer_auth: self.issuer_auth.clone(),
+        }
+    }
+
     /// Directly sign and issue an mdoc.
     #[allow(clippy::too_many_arguments)]
     pub fn issue<S, Sig>(
@@ -233,35 +282,85 @@ impl Builder {
         self
     }
 
+    /// Restrict the namespaces and data elements that the device key may sign over, replacing any
+    /// authorizations in the device key information.
+    pub fn key_authorizations(mut self, key_authorizations: KeyAuthorizations) -> Self {
+        self.key_authorizations = Some(key_authorizations);
+        self
+    }
+
     /// Enable the use of decoy digests.
     pub fn enable_decoy_digests(mut self, enable_decoy_digests: bool) -> Self {
         self.enable_decoy_digests = Some(enable_decoy_digests);
         self
     }
 
+    /// Set the length in bytes of the random salt of each element, at least 16. Defaults to
+    /// [DEFAULT_SALT_LENGTH].
+    pub fn salt_length(mut self, salt_length: usize) -> Self {
+        self.salt_length = Some(salt_length);
+        self
+    }
+
+    /// Set the `portrait` element of the org.iso.18013.5.1 namespace, replacing any in the
+    /// namespaces.
+    pub fn portrait(mut self, portrait: Portrait) -> Self {
+        self.portrait = Some(portrait);
+        self
+    }
+
+    /// Detach the mobile security object from the issuer auth, for profiles that store it
+    /// separately. Defaults to `false`, embedding it as the payload.
+    ///
+    /// See [Mdoc::mso_bytes] for the payload to supply when verifying the signature.
+    pub fn detached_payload(mut self, detached_payload: bool) -> Self {
+        self.detached_payload = Some(detached_payload);
+        self
+    }
+
     /// Prepare the mdoc for remote signing.
     ///
     /// The signature algorithm which the mdoc will be signed with must be known ahead of time as
     /// it is a required field in the signature headers.
     pub fn prepare(self, signature_algorithm: Algorithm) -> Result<PreparedMdoc> {
+        self.prepare_with_rng(signature_algorithm, &mut rand::thread_rng())
+    }
+
+    /// As [Builder::prepare], drawing the digest ids and salts of the elements from `rng`.
+    pub fn prepare_with_rng(
+        self,
+        signature_algorithm: Algorithm,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<PreparedMdoc> {
         let doc_type = self
             .doc_type
             .ok_or_else(|| anyhow!("missing parameter: 'doc_type'"))?;
-        let namespaces = self
+        let mut namespaces = self
             .namespaces
             .ok_or_else(|| anyhow!("missing parameter: 'namespaces'"))?;
+        if let Some(portrait) = self.portrait {
+            namespaces
+                .entry(MDL_NAMESPACE.to_string())
+                .or_default()
+                .insert("portrait".to_string(), portrait.into());
+        }
         let validity_info = self
             .validity_info
             .ok_or_else(|| anyhow!("missing parameter: 'validity_info'"))?;
         let digest_algorithm = self
             .digest_algorithm
             .ok_or_else(|| anyhow!("missing parameter: 'digest_algorithm'"))?;
-        let device_key_info = self
+        let mut device_key_info = self
             .device_key_info
             .ok_or_else(|| anyhow!("missing parameter: 'device_key_info'"))?;
+        if let Some(key_authorizations) = self.key_authorizations {
+            device_key_info.key_authorizations = Some(key_authorizations);
+        }
         let enable_decoy_digests = self.enable_decoy_digests.unwrap_or(true);
+        let salt_length = self.salt_length.unwrap_or(DEFAULT_SALT_LENGTH);
+        let detached_payload = self.detached_payload.unwrap_or(false);
 
-        Mdoc::prepare(
+        Mdoc::prepare_salted(
             doc_type,
             namespaces,
             validity_info,
@@ -269,6 +368,9 @@ impl Builder {
             device_key_info,
             signature_algorithm,
             enable_decoy_digests,
+            salt_length,
+            detached_payload,
+            rng,
         )
     }
 
@@ -278,33 +380,12 @@ impl Builder {
         S: Signer<Sig> + SignatureAlgorithm,
         Sig: SignatureEncoding,
     {
-        let doc_type = self
-            .doc_type
-            .ok_or_else(|| anyhow!("missing parameter: 'doc_type'"))?;
-        let namespaces = self
-            .namespaces
-            .ok_or_else(|| anyhow!("missing parameter: 'namespaces'"))?;
-        let validity_info = self
-            .validity_info
-            .ok_or_else(|| anyhow!("missing parameter: 'validity_info'"))?;
-        let digest_algorithm = self
-            .digest_algorithm
-            .ok_or_else(|| anyhow!("missing parameter: 'digest_algorithm'"))?;
-        let device_key_info = self
-            .device_key_info
-            .ok_or_else(|| anyhow!("missing parameter: 'device_key_info'"))?;
-        let enable_decoy_digests = self.enable_decoy_digests.unwrap_or(true);
-
-        Mdoc::issue(
-            doc_type,
-            namespaces,
-            validity_info,
-            digest_algorithm,
-            device_key_info,
-            x5chain,
-            enable_decoy_digests,
-            signer,
-        )
+        let prepared_mdoc = self.prepare(signer.algorithm())?;
+        let signature = signer
+            .try_sign(prepared_mdoc.signature_payload())
+            .map_err(|e| anyhow!("error signing cosesign1: {}", e))?
+            .to_vec();
+        Ok(prepared_mdoc.complete(x5chain, signature))
     }
 
     /// Directly issue an mdoc.
@@ -313,42 +394,32 @@ impl Builder {
         S: AsyncSigner<Sig> + SignatureAlgorithm,
         Sig: SignatureEncoding + Send + 'static,
     {
-        let doc_type = self
-            .doc_type
-            .ok_or_else(|| anyhow!("missing parameter: 'doc_type'"))?;
-        let namespaces = self
-            .namespaces
-            .ok_or_else(|| anyhow!("missing parameter: 'namespaces'"))?;
-        let validity_info = self
-            .validity_info
-            .ok_or_else(|| anyhow!("missing parameter: 'validity_info'"))?;
-        let digest_algorithm = self
-            .digest_algorithm
-            .ok_or_else(|| anyhow!("missing parameter: 'digest_algorithm'"))?;
-        let device_key_info = self
-            .device_key_info
-            .ok_or_else(|| anyhow!("missing parameter: 'device_key_info'"))?;
-        let enable_decoy_digests = self.enable_decoy_digests.unwrap_or(true);
-
-        Mdoc::issue_async(
-            doc_type,
-            namespaces,
-            validity_info,
-            digest_algorithm,
-            device_key_info,
-            x5chain,
-            enable_decoy_digests,
-            signer,
-        )
-        .await
+        let prepared_mdoc = self.prepare(signer.algorithm())?;
+        let signature = signer
+            .sign_async(prepared_mdoc.signature_payload())
+            .await
+            .map_err(|e| anyhow!("error signing cosesign1: {}", e))?
+            .to_vec();
+        Ok(prepared_mdoc.complete(x5chain, signature))
     }
 }
 
-fn to_issuer_namespaces(namespaces: Namespaces) -> Result<IssuerNamespaces> {
+fn to_issuer_namespaces(
+    namespaces: Namespaces,
+    salt_length: usize,
+    rng: &mut (impl RngCore + CryptoRng),
+) -> Result<IssuerNamespaces> {
+    if salt_length < MIN_SALT_LENGTH {
+        return Err(anyhow!(
+            "the random salt of each element must be at least {} bytes",
+            MIN_SALT_LENGTH
+        ));
+    }
     namespaces
         .into_iter()
         .map(|(name, elements)| {
-            to_issuer_signed_items(elements)
+            to_issuer_signed_items(elements, salt_length, rng)
+                .into_iter()
                 .map(Tag24::new)
                 .collect::<Result<Vec<Tag24<IssuerSignedItem>>, _>>()
                 .map_err(|err| anyhow!("unable to encode IssuerSignedItem as cbor: {}", err))
@@ -365,20 +436,28 @@ fn to_issuer_namespaces(namespaces: Namespaces) -> Result<IssuerNamespaces> {
         })
 }
 
+/// Each element is salted with `salt_length` fresh random bytes, so that its digest cannot be
+/// correlated with its value.
 fn to_issuer_signed_items(
     elements: BTreeMap<String, CborValue>,
-) -> impl Iterator<Item = IssuerSignedItem> {
+    salt_length: usize,
+    rng: &mut (impl RngCore + CryptoRng),
+) -> Vec<IssuerSignedItem> {
     let mut used_ids = HashSet::new();
-    elements.into_iter().map(move |(key, value)| {
-        let digest_id = generate_digest_id(&mut used_ids);
-        let random = Vec::from(rand::thread_rng().gen::<[u8; 16]>()).into();
-        IssuerSignedItem {
-            digest_id,
-            random,
-            element_identifier: key,
-            element_value: value,
-        }
-    })
+    elements
+        .into_iter()
+        .map(|(key, value)| {
+            let digest_id = generate_digest_id(&mut used_ids, rng);
+            let mut random = vec![0u8; salt_length];
+            rng.fill_bytes(&mut random);
+            IssuerSignedItem {
+                digest_id,
+                random: random.into(),
+                element_identifier: key,
+                element_value: value,
+            }
+        })
+        .collect()
 }
 
 fn digest_namespaces(
@@ -397,7 +476,7 @@ fn digest_namespaces(
         .collect()
 }
 
-fn digest_namespace(
+pub(super) fn digest_namespace(
     elements: &[IssuerSignedItemBytes],
     digest_algorithm: DigestAlgorithm,
     enable_decoy_digests: bool,
@@ -408,7 +487,8 @@ fn digest_namespace(
         .collect();
 
     // Generate X random digests to avoid leaking information.
-    let random_ids = std::iter::repeat_with(|| generate_digest_id(&mut used_ids));
+    let random_ids =
+        std::iter::repeat_with(|| generate_digest_id(&mut used_ids, &mut rand::thread_rng()));
     let random_bytes = std::iter::repeat_with(|| {
         std::iter::repeat_with(|| rand::thread_rng().gen::<u8>())
             .take(512)
@@ -429,20 +509,15 @@ fn digest_namespace(
         .chain(random_digests)
         .map(|result| {
             let (digest_id, bytes) = result?;
-            let digest = match digest_algorithm {
-                DigestAlgorithm::SHA256 => Sha256::digest(bytes).to_vec(),
-                DigestAlgorithm::SHA384 => Sha384::digest(bytes).to_vec(),
-                DigestAlgorithm::SHA512 => Sha512::digest(bytes).to_vec(),
-            };
-            Ok((digest_id, digest.into()))
+            Ok((digest_id, digest_algorithm.digest(&bytes).into()))
         })
         .collect()
 }
 
-fn generate_digest_id(used_ids: &mut HashSet<DigestId>) -> DigestId {
+fn generate_digest_id(used_ids: &mut HashSet<DigestId>, rng: &mut impl Rng) -> DigestId {
     let mut digest_id;
     loop {
-        digest_id = DigestId::new(rand::thread_rng().gen());
+        digest_id = DigestId::new(rng.gen());
         if used_ids.insert(digest_id) {
             break;
         }
@@ -699,4 +774,109 @@ pub mod test {
                 .fold(0, |acc, x| acc + x.len()),
         );
     }
+
+    fn digests(prepared_mdoc: &PreparedMdoc) -> HashSet<Vec<u8>> {
+        prepared_mdoc
+            .mso
+            .value_digests
+            .values()
+            .flat_map(|digests| digests.values())
+            .map(|digest| digest.as_ref().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn salted_digests() {
+        let mdoc_builder = minimal_test_mdoc_builder().enable_decoy_digests(false);
+        let first = mdoc_builder.clone().prepare(Algorithm::ES256).unwrap();
+        let second = mdoc_builder.prepare(Algorithm::ES256).unwrap();
+
+        assert!(first
+            .namespaces
+            .values()
+            .flat_map(|items| items.iter())
+            .all(|item| item.as_ref().random.as_ref().len() == DEFAULT_SALT_LENGTH));
+        assert!(digests(&first).is_disjoint(&digests(&second)));
+    }
+
+    #[test]
+    fn portrait() {
+        let image = include_bytes!("../../test/issuance/portrait.jpg").to_vec();
+        let prepared = minimal_test_mdoc_builder()
+            .portrait(Portrait::new(image.clone()).unwrap())
+            .prepare(Algorithm::ES256)
+            .unwrap();
+
+        let portrait = prepared.namespaces[MDL_NAMESPACE]
+            .iter()
+            .map(|item| item.as_ref())
+            .find(|item| item.element_identifier == "portrait")
+            .expect("portrait was not issued");
+        assert_eq!(portrait.element_value, CborValue::Bytes(image));
+    }
+
+    #[test]
+    fn deterministic_salts() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mdoc_builder = minimal_test_mdoc_builder()
+            .enable_decoy_digests(false)
+            .salt_length(32);
+        let first = mdoc_builder
+            .clone()
+            .prepare_with_rng(Algorithm::ES256, &mut StdRng::seed_from_u64(1))
+            .unwrap();
+        let second = mdoc_builder
+            .clone()
+            .prepare_with_rng(Algorithm::ES256, &mut StdRng::seed_from_u64(1))
+            .unwrap();
+
+        assert!(first
+            .namespaces
+            .values()
+            .flat_map(|items| items.iter())
+            .all(|item| item.as_ref().random.as_ref().len() == 32));
+        assert_eq!(digests(&first), digests(&second));
+
+        assert!(mdoc_builder
+            .salt_length(8)
+            .prepare(Algorithm::ES256)
+            .is_err());
+    }
+
+    #[test]
+    fn key_authorizations() {
+        let key_authorizations = KeyAuthorizations {
+            namespaces: None,
+            data_elements: Some(NonEmptyMap::new(
+                "org.iso.18013.5.1".to_string(),
+                NonEmptyVec::new("family_name".to_string()),
+            )),
+        };
+        let prepared = minimal_test_mdoc_builder()
+            .key_authorizations(key_authorizations)
+            .prepare(Algorithm::ES256)
+            .unwrap();
+
+        let mso: Mso = serde_cbor::from_slice(&serde_cbor::to_vec(&prepared.mso).unwrap()).unwrap();
+        let authorizations = mso.device_key_info.key_authorizations.unwrap();
+        assert!(
+            authorizations.permitted(&"org.iso.18013.5.1".to_string(), &"family_name".to_string())
+        );
+        assert!(
+            !authorizations.permitted(&"org.iso.18013.5.1".to_string(), &"given_name".to_string())
+        );
+
+        let double_authorized = KeyAuthorizations {
+            namespaces: Some(NonEmptyVec::new("org.iso.18013.5.1".to_string())),
+            data_elements: Some(NonEmptyMap::new(
+                "org.iso.18013.5.1".to_string(),
+                NonEmptyVec::new("family_name".to_string()),
+            )),
+        };
+        assert!(minimal_test_mdoc_builder()
+            .key_authorizations(double_authorized)
+            .prepare(Algorithm::ES256)
+            .is_err());
+    }
 }
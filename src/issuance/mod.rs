@@ -1,5 +1,11 @@
+mod json;
 pub mod mdoc;
-pub mod x5chain;
+mod mso;
+mod verify;
 
+pub use crate::definitions::x509::x5chain;
+pub use crate::definitions::Mso;
+pub use json::{issue_from_json, namespaces_from_json};
 pub use mdoc::{Mdoc, Namespaces};
+pub use verify::{verify_issuer_signed, IssuerSignedReport};
 pub use x5chain::{Builder, X5Chain};
@@ -0,0 +1,198 @@
+use crate::{
+    definitions::{
+        helpers::Tag24, issuer_signed::IssuerSignedItemBytes, DeviceKeyInfo, DigestAlgorithm, Mso,
+        ValidityInfo,
+    },
+    issuance::{
+        mdoc::digest_namespace,
+        x5chain::{X5Chain, X5CHAIN_HEADER_LABEL},
+    },
+};
+use anyhow::{anyhow, Result};
+use cose_rs::{algorithm::SignatureAlgorithm, sign1::CoseSign1};
+use signature::{SignatureEncoding, Signer};
+
+impl Mso {
+    /// Create a mobile security object with no value digests, for an issuer that builds the
+    /// issuer signed items itself. Add the digests of each namespace with [Mso::add_namespace].
+    pub fn new(
+        doc_type: String,
+        device_key_info: DeviceKeyInfo,
+        validity_info: ValidityInfo,
+        digest_algorithm: DigestAlgorithm,
+    ) -> Result<Self> {
+        if let Some(authorizations) = &device_key_info.key_authorizations {
+            authorizations.validate()?;
+        }
+        Ok(Self {
+            version: "1.0".to_string(),
+            digest_algorithm,
+            value_digests: Default::default(),
+            device_key_info,
+            doc_type,
+            validity_info,
+        })
+    }
+
+    /// Digest each of `items`, the issuer signed items of `namespace`.
+    pub fn add_namespace(
+        &mut self,
+        namespace: String,
+        items: &[IssuerSignedItemBytes],
+    ) -> Result<()> {
+        if self.value_digests.contains_key(&namespace) {
+            return Err(anyhow!("namespace {} has already been digested", namespace));
+        }
+        let digests = digest_namespace(items, self.digest_algorithm, false)?;
+        if digests.len() != items.len() {
+            return Err(anyhow!(
+                "digest ids are not unique in namespace {}",
+                namespace
+            ));
+        }
+        self.value_digests.insert(namespace, digests);
+        Ok(())
+    }
+
+    /// Sign the mobile security object, embedding `x5chain`, whose first certificate is that of
+    /// `signer`.
+    pub fn sign<S, Sig>(&self, x5chain: X5Chain, signer: S) -> Result<CoseSign1>
+    where
+        S: Signer<Sig> + SignatureAlgorithm,
+        Sig: SignatureEncoding,
+    {
+        let prepared_sig = CoseSign1::builder()
+            .payload(serde_cbor::to_vec(&Tag24::new(self)?)?)
+            .signature_algorithm(signer.algorithm())
+            .prepare()
+            .map_err(|e| anyhow!("error preparing cosesign1: {}", e))?;
+        let signature = signer
+            .try_sign(prepared_sig.signature_payload())
+            .map_err(|e| anyhow!("error signing cosesign1: {}", e))?
+            .to_vec();
+        let mut issuer_auth = prepared_sig.finalize(signature);
+        issuer_auth
+            .unprotected_mut()
+            .insert_i(X5CHAIN_HEADER_LABEL, x5chain.into_cbor());
+        Ok(issuer_auth)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::definitions::{CoseKey, DigestId, EC2Curve, IssuerSignedItem, EC2Y};
+    use elliptic_curve::sec1::ToEncodedPoint;
+    use p256::ecdsa::{Signature, SigningKey};
+    use p256::pkcs8::DecodePrivateKey;
+    use serde_cbor::Value as CborValue;
+    use time::OffsetDateTime;
+
+    static ISSUER_CERT: &[u8] = include_bytes!("../../test/issuance/issuer-cert.pem");
+    static ISSUER_KEY: &str = include_str!("../../test/issuance/issuer-key.pem");
+
+    fn items() -> Vec<IssuerSignedItemBytes> {
+        [("family_name", "Doe"), ("given_name", "John")]
+            .into_iter()
+            .enumerate()
+            .map(|(id, (element_identifier, value))| {
+                Tag24::new(IssuerSignedItem {
+                    digest_id: DigestId::new(id as i32),
+                    random: vec![id as u8; 16].into(),
+                    element_identifier: element_identifier.to_string(),
+                    element_value: CborValue::Text(value.to_string()),
+                })
+                .unwrap()
+            })
+            .collect()
+    }
+
+    fn mso() -> Mso {
+        let device_key = p256::SecretKey::random(&mut rand::thread_rng())
+            .public_key()
+            .to_encoded_point(false);
+        let device_key = CoseKey::EC2 {
+            crv: EC2Curve::P256,
+            x: device_key.x().unwrap().to_vec(),
+            y: EC2Y::Value(device_key.y().unwrap().to_vec()),
+        };
+        let now = OffsetDateTime::now_utc();
+        Mso::new(
+            "org.iso.18013.5.1.mDL".to_string(),
+            DeviceKeyInfo {
+                device_key,
+                key_authorizations: None,
+                key_info: None,
+            },
+            ValidityInfo {
+                signed: now,
+                valid_from: now,
+                valid_until: now,
+                expected_update: None,
+            },
+            DigestAlgorithm::SHA256,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn value_digests() {
+        let mut mso = mso();
+        let items = items();
+        mso.add_namespace("org.iso.18013.5.1".to_string(), &items)
+            .unwrap();
+
+        let digests = &mso.value_digests["org.iso.18013.5.1"];
+        assert_eq!(digests.len(), items.len());
+        for item in items.iter() {
+            let digest = DigestAlgorithm::SHA256.digest(&serde_cbor::to_vec(item).unwrap());
+            assert_eq!(
+                digests[&item.as_ref().digest_id].as_ref(),
+                digest.as_slice()
+            );
+        }
+
+        assert!(mso
+            .add_namespace("org.iso.18013.5.1".to_string(), &items)
+            .is_err());
+    }
+
+    #[test]
+    fn canonical_encoding() {
+        let mut mso = mso();
+        mso.add_namespace("org.iso.18013.5.1".to_string(), &items())
+            .unwrap();
+        let encoded = Tag24::new(&mso).unwrap();
+        // The keys are in canonical order, shortest first, rather than in the order of the
+        // fields of the MSO.
+        assert!(encoded.inner_bytes[1..].starts_with(b"\x67docType"));
+
+        let parsed: Tag24<Mso> = Tag24::from_bytes(encoded.inner_bytes.clone()).unwrap();
+        let reencoded = Tag24::new(parsed.into_inner()).unwrap();
+        assert_eq!(reencoded.inner_bytes, encoded.inner_bytes);
+    }
+
+    #[test]
+    fn sign_mso() {
+        let mut mso = mso();
+        mso.add_namespace("org.iso.18013.5.1".to_string(), &items())
+            .unwrap();
+        let x5chain = X5Chain::builder()
+            .with_pem(ISSUER_CERT)
+            .unwrap()
+            .build()
+            .unwrap();
+        let signer: SigningKey = p256::SecretKey::from_pkcs8_pem(ISSUER_KEY).unwrap().into();
+
+        let issuer_auth = mso.sign::<SigningKey, Signature>(x5chain, signer).unwrap();
+        assert!(issuer_auth
+            .unprotected()
+            .get_i(X5CHAIN_HEADER_LABEL)
+            .is_some());
+        let signed: Tag24<Mso> = serde_cbor::from_slice(issuer_auth.payload().unwrap()).unwrap();
+        assert_eq!(
+            serde_cbor::to_vec(signed.as_ref()).unwrap(),
+            serde_cbor::to_vec(&mso).unwrap()
+        );
+    }
+}
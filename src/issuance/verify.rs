@@ -0,0 +1,48 @@
+//! Verification of an issued document on its own, so that an issuer can check its output before
+//! the document is provisioned to a holder.
+use crate::definitions::{x509::TrustAnchorRegistry, IssuerSigned};
+use crate::presentation::reader::{self, AuthenticationStatus, DigestMismatch};
+
+/// The outcome of verifying an [IssuerSigned] with [verify_issuer_signed].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssuerSignedReport {
+    /// The document type of the mobile security object, or `None` if it could not be decoded.
+    pub doc_type: Option<String>,
+    pub issuer_auth: AuthenticationStatus,
+    pub digest_mismatches: Vec<DigestMismatch>,
+}
+
+impl IssuerSignedReport {
+    /// Whether the issuer auth is valid, and every element matches its digest.
+    pub fn is_valid(&self) -> bool {
+        self.issuer_auth == AuthenticationStatus::Valid && self.digest_mismatches.is_empty()
+    }
+}
+
+/// Verify `issuer_signed` without a presentation session: the issuer auth against the issuers
+/// trusted in `registry`, and each element against its digest in the mobile security object.
+pub fn verify_issuer_signed(
+    issuer_signed: &IssuerSigned,
+    registry: &TrustAnchorRegistry,
+) -> IssuerSignedReport {
+    let issuer_auth = &issuer_signed.issuer_auth;
+    let mso = match reader::parse_mso(issuer_auth) {
+        Ok(mso) => mso,
+        Err(e) => {
+            return IssuerSignedReport {
+                doc_type: None,
+                issuer_auth: AuthenticationStatus::Invalid(format!(
+                    "unable to decode the mobile security object: {}",
+                    e
+                )),
+                digest_mismatches: Vec::new(),
+            }
+        }
+    };
+    IssuerSignedReport {
+        issuer_auth: reader::verify_issuer_auth(issuer_auth, &mso, &mso.doc_type, registry, None)
+            .into(),
+        digest_mismatches: reader::digest_mismatches(issuer_signed, &mso),
+        doc_type: Some(mso.doc_type),
+    }
+}
@@ -1,5 +1,3 @@
-use std::collections::BTreeMap;
-
 use anyhow::{Context, Error};
 use clap::Parser;
 use clap_stdin::MaybeStdin;
@@ -30,11 +28,7 @@ fn main() -> Result<(), Error> {
 fn print_namespaces(mdl: String) -> Result<(), Error> {
     let claims = Document::parse(mdl)
         .context("could not parse mdl")?
-        .namespaces
-        .into_inner()
-        .into_iter()
-        .map(|(ns, inner)| (ns, inner.into_inner().into_keys().collect()))
-        .collect::<BTreeMap<String, Vec<String>>>();
+        .available_elements();
     println!("{}", serde_json::to_string_pretty(&claims)?);
     Ok(())
 }
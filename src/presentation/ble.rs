@@ -0,0 +1,183 @@
+//! Fragmentation and reassembly of messages sent over BLE, where each write to the
+//! Server2Client or Client2Server characteristic carries only part of a message.
+//!
+//! As in ISO/IEC 18013-5 8.3.3.1.1.6, each fragment starts with a byte that is
+//! [MORE_FRAGMENTS] if more of the message follows, or [LAST_FRAGMENT] if it completes the
+//! message. The transport, and any timeout between fragments, is left to the caller.
+
+use super::DecodeLimits;
+use crate::definitions::SessionData;
+
+/// The first byte of a fragment that is followed by more of the message.
+pub const MORE_FRAGMENTS: u8 = 0x01;
+/// The first byte of the fragment that completes the message.
+pub const LAST_FRAGMENT: u8 = 0x00;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("received a fragment without a header byte")]
+    EmptyFragment,
+    #[error("unrecognised fragment header: {0:#04x}")]
+    UnknownHeader(u8),
+    #[error("the message exceeds the limit of {0} bytes")]
+    TooLarge(usize),
+    #[error("unable to decode the reassembled message: {0}")]
+    Cbor(#[from] serde_cbor::Error),
+}
+
+/// Reassembles a message from the fragments in which it was received.
+#[derive(Debug, Clone)]
+pub struct MessageAssembler {
+    buffer: Vec<u8>,
+    max_message_bytes: usize,
+}
+
+impl Default for MessageAssembler {
+    fn default() -> Self {
+        Self::new(DecodeLimits::default().max_message_bytes)
+    }
+}
+
+impl MessageAssembler {
+    /// An assembler that rejects messages longer than `max_message_bytes`.
+    pub fn new(max_message_bytes: usize) -> Self {
+        Self {
+            buffer: Vec::new(),
+            max_message_bytes,
+        }
+    }
+
+    /// Add the next fragment, returning the message if the fragment completes it.
+    ///
+    /// On an error, the partially received message is discarded.
+    pub fn push_bytes(&mut self, fragment: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        let result = self.append(fragment);
+        if !matches!(result, Ok(None)) {
+            self.buffer.clear();
+        }
+        result
+    }
+
+    /// Add the next fragment, returning the session data if the fragment completes it.
+    pub fn push(&mut self, fragment: &[u8]) -> Result<Option<SessionData>, Error> {
+        self.push_bytes(fragment)?
+            .map(|message| serde_cbor::from_slice(&message).map_err(Error::from))
+            .transpose()
+    }
+
+    /// The number of bytes received of the current message.
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Discard the partially received message, e.g. when the peer has timed out.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+    }
+
+    fn append(&mut self, fragment: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        let (&header, data) = fragment.split_first().ok_or(Error::EmptyFragment)?;
+        if header != MORE_FRAGMENTS && header != LAST_FRAGMENT {
+            return Err(Error::UnknownHeader(header));
+        }
+        if self.buffer.len() + data.len() > self.max_message_bytes {
+            return Err(Error::TooLarge(self.max_message_bytes));
+        }
+        self.buffer.extend_from_slice(data);
+        if header == MORE_FRAGMENTS {
+            return Ok(None);
+        }
+        Ok(Some(std::mem::take(&mut self.buffer)))
+    }
+}
+
+/// Split `message` into fragments of at most `max_fragment_len` bytes, including the header
+/// byte of each.
+///
+/// Each fragment carries at least one byte of the message, so a `max_fragment_len` below two is
+/// treated as two.
+pub fn fragment(message: &[u8], max_fragment_len: usize) -> Vec<Vec<u8>> {
+    let chunk_len = max_fragment_len.saturating_sub(1).max(1);
+    if message.is_empty() {
+        return vec![vec![LAST_FRAGMENT]];
+    }
+    let chunks = message.chunks(chunk_len).count();
+    message
+        .chunks(chunk_len)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let header = if index + 1 == chunks {
+                LAST_FRAGMENT
+            } else {
+                MORE_FRAGMENTS
+            };
+            [&[header], chunk].concat()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::definitions::helpers::ByteStr;
+
+    fn session_data() -> Vec<u8> {
+        let session_data = SessionData {
+            data: Some(ByteStr::from(
+                (0..=255).cycle().take(1000).collect::<Vec<u8>>(),
+            )),
+            status: None,
+        };
+        serde_cbor::to_vec(&session_data).unwrap()
+    }
+
+    #[test]
+    fn reassemble() {
+        let message = session_data();
+        let fragments = fragment(&message, 20);
+        assert_eq!(fragments.len(), message.len().div_ceil(19));
+        assert!(fragments.iter().all(|fragment| fragment.len() <= 20));
+
+        let mut assembler = MessageAssembler::default();
+        let (last, rest) = fragments.split_last().unwrap();
+        for fragment in rest {
+            assert!(assembler.push(fragment).unwrap().is_none());
+        }
+        assert_eq!(assembler.buffered_len(), message.len() - (last.len() - 1));
+        let session_data = assembler.push(last).unwrap().unwrap();
+        assert_eq!(serde_cbor::to_vec(&session_data).unwrap(), message);
+        assert_eq!(assembler.buffered_len(), 0);
+    }
+
+    #[test]
+    fn too_large() {
+        let message = session_data();
+        let mut assembler = MessageAssembler::new(message.len() - 1);
+        let result = fragment(&message, 20)
+            .iter()
+            .try_for_each(|fragment| assembler.push_bytes(fragment).map(|_| ()));
+        assert!(matches!(result, Err(Error::TooLarge(_))));
+        assert_eq!(assembler.buffered_len(), 0);
+    }
+
+    #[test]
+    fn invalid_fragments() {
+        let mut assembler = MessageAssembler::default();
+        assert!(matches!(assembler.push(&[]), Err(Error::EmptyFragment)));
+        assembler.push_bytes(&[MORE_FRAGMENTS, 0xa0]).unwrap();
+        assert!(matches!(
+            assembler.push(&[0x02, 0xa0]),
+            Err(Error::UnknownHeader(0x02))
+        ));
+        assert_eq!(assembler.buffered_len(), 0);
+        assert_eq!(
+            assembler.push_bytes(&[LAST_FRAGMENT]).unwrap(),
+            Some(Vec::new())
+        );
+        assert_eq!(fragment(&[], 20), [[LAST_FRAGMENT]]);
+        assert_eq!(
+            fragment(&[0xa0, 0xa1], 0),
+            [[MORE_FRAGMENTS, 0xa0], [LAST_FRAGMENT, 0xa1]]
+        );
+    }
+}
@@ -1,55 +1,117 @@
+#[cfg(feature = "transports")]
+use crate::definitions::DeviceRetrievalMethod;
 use crate::definitions::IssuerSignedItem;
 use crate::{
     definitions::{
-        device_engagement::{DeviceRetrievalMethod, Security, ServerRetrievalMethods},
-        device_request::{DeviceRequest, DocRequest, ItemsRequest},
+        device_engagement::{self, OriginInfo, Security, ServerRetrievalMethods},
+        device_request::{
+            DataElements, DeviceRequest, DocRequest, IntentToRetain, ItemsRequest,
+            ItemsRequestBytes, ReaderAuth, ReaderAuthentication, WILDCARD,
+        },
         device_response::{
             Document as DeviceResponseDoc, DocumentError, DocumentErrorCode, DocumentErrors,
             Errors as NamespaceErrors, Status,
         },
-        device_signed::{DeviceAuth, DeviceAuthentication, DeviceNamespacesBytes, DeviceSigned},
+        device_signed::{
+            self, DeviceAuth, DeviceAuthentication, DeviceNamespaces, DeviceNamespacesBytes,
+            DeviceSigned,
+        },
         helpers::{tag24, NonEmptyMap, NonEmptyVec, Tag24},
         issuer_signed::{IssuerSigned, IssuerSignedItemBytes},
         session::{
-            self, derive_session_key, get_shared_secret, Handover, SessionData, SessionTranscript,
+            self, get_shared_secret, Handover, KeyDerivationProfile, SessionData, SessionTranscript,
+        },
+        x509::{
+            x5chain::{ValidationReport, X5Chain, X509, X5CHAIN_HEADER_LABEL},
+            TrustAnchorRegistry,
         },
-        CoseKey, DeviceEngagement, DeviceResponse, Mso, SessionEstablishment,
+        CoseKey, DeviceEngagement, DeviceResponse, Mso, RefreshStatus, SessionEstablishment,
     },
     issuance::Mdoc,
+    presentation::{DecodeLimits, LimitExceeded, Stringify},
 };
+#[cfg(feature = "async")]
+use async_signature::AsyncSigner;
+use cose_rs::algorithm::Algorithm;
 use cose_rs::sign1::{CoseSign1, PreparedCoseSign1};
-use p256::FieldBytes;
 use serde::{Deserialize, Serialize};
 use serde_cbor::Value as CborValue;
 use session::SessionTranscript180135;
+#[cfg(feature = "async")]
+use signature::SignatureEncoding;
 use std::collections::BTreeMap;
 use std::num::ParseIntError;
+use time::OffsetDateTime;
 use uuid::Uuid;
+use zeroize::Zeroizing;
+
+mod disclosure;
+mod documents;
+mod signer;
+mod storage;
+mod wallet;
+pub use disclosure::DisclosurePolicy;
+pub use documents::Documents;
+pub use signer::MdocSigner;
+pub use storage::{StorageError, StoredCredential, STORED_CREDENTIAL_VERSION};
+pub use wallet::{EngagedWallet, Wallet};
 
 #[derive(Serialize, Deserialize)]
 pub struct SessionManagerInit {
     documents: Documents,
-    e_device_key: Vec<u8>,
+    e_device_key: Zeroizing<Vec<u8>>,
     device_engagement: Tag24<DeviceEngagement>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct SessionManagerEngaged {
     documents: Documents,
-    e_device_key: Vec<u8>,
+    e_device_key: Zeroizing<Vec<u8>>,
     device_engagement: Tag24<DeviceEngagement>,
     handover: Handover,
+    /// Bounds on the size of requests from the reader.
+    #[serde(default)]
+    limits: DecodeLimits,
+    /// How the session keys are derived.
+    #[serde(default)]
+    key_derivation: KeyDerivationProfile,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct SessionManager {
     documents: Documents,
     session_transcript: SessionTranscript180135,
-    sk_device: [u8; 32],
+    sk_device: Zeroizing<[u8; 32]>,
+    /// The counter of the last message sent by the device.
     device_message_counter: u32,
-    sk_reader: [u8; 32],
+    sk_reader: Zeroizing<[u8; 32]>,
+    /// The counter of the last message received from the reader.
     reader_message_counter: u32,
     state: State,
+    /// Each document request in the last request received, with its reader authentication.
+    #[serde(default)]
+    reader_auth: Vec<(ItemsRequestBytes, Option<ReaderAuth>)>,
+    /// Bounds on the size of requests from the reader.
+    #[serde(default)]
+    limits: DecodeLimits,
+    /// The elements offered as asserted by the device, where the issuer did not sign them.
+    #[serde(default)]
+    device_asserted: DeviceAssertedItems,
+    /// The id of the document selected for each doc type in the last request received.
+    #[serde(default)]
+    selected: BTreeMap<DocType, Uuid>,
+}
+
+/// A session whose transcript is established by the transport, such as OpenID4VP, rather than by
+/// device engagement. Responses are not encrypted with session keys, as the transport protects
+/// them.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TranscriptSession<ST> {
+    documents: Documents,
+    session_transcript: ST,
+    /// The elements offered as asserted by the device, where the issuer did not sign them.
+    #[serde(default)]
+    device_asserted: DeviceAssertedItems,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -58,6 +120,7 @@ pub enum State {
     AwaitingRequest,
     Signing(PreparedDeviceResponse),
     ReadyToRespond(Vec<u8>),
+    Terminated,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -68,6 +131,14 @@ pub enum Error {
     Tag24CborEncoding(tag24::Error),
     #[error("unable to generate shared secret: {0}")]
     SharedSecretGeneration(anyhow::Error),
+    #[error("the stored ephemeral device key is invalid")]
+    InvalidEDeviceKey,
+    #[error("the ephemeral device key is not supported: {0}")]
+    UnsupportedEDeviceKey(device_engagement::Error),
+    #[error("unable to derive session keys: {0}")]
+    SessionKeyDerivation(anyhow::Error),
+    #[error("no mdoc requests received, assume session can be terminated")]
+    NoRequest,
     #[error("error encoding value to CBOR: {0}")]
     CborEncoding(serde_cbor::Error),
     #[error("session manager was used incorrectly")]
@@ -76,11 +147,31 @@ pub enum Error {
     ParsingError(#[from] ParseIntError),
     #[error("age_over element identifier is malformed")]
     PrefixError,
+    #[error("no signature payload is pending with id {0}")]
+    UnknownPayloadId(PayloadId),
+    #[error("more than one signature was submitted for payload {0}")]
+    DuplicateSignature(PayloadId),
+    #[error("no signature was submitted for payload {0}")]
+    MissingSignature(PayloadId),
+    #[error("the signer uses {signer:?}, but payload {id} must be signed with {expected:?}")]
+    SignerAlgorithm {
+        id: PayloadId,
+        signer: Algorithm,
+        expected: Algorithm,
+    },
+    #[error("the session has been terminated")]
+    SessionEnded,
+    #[error(transparent)]
+    Session(#[from] session::SessionError),
+    #[error("the request is too large: {0}")]
+    LimitExceeded(#[from] LimitExceeded),
 }
 
-pub type Documents = NonEmptyMap<DocType, Document>;
 type DocType = String;
 
+/// Identifies a signature payload, by the id of the document it authenticates.
+pub type PayloadId = Uuid;
+
 /// Device-internal document datatype.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Document {
@@ -112,45 +203,119 @@ type Namespaces = NonEmptyMap<Namespace, NonEmptyMap<ElementIdentifier, IssuerSi
 type Namespace = String;
 type ElementIdentifier = String;
 
+/// How the device authenticates the documents in its response.
+#[derive(Debug, Clone)]
+pub enum DeviceAuthMode {
+    /// Sign each document with its device key, through
+    /// [SessionManager::get_next_signature_payload] and [SessionManager::submit_next_signature].
+    Signature,
+    /// MAC each document with a key agreed between the given device key and the reader's
+    /// ephemeral key.
+    Mac(p256::SecretKey),
+}
+
 pub type RequestedItems = Vec<ItemsRequest>;
 pub type PermittedItems = BTreeMap<DocType, BTreeMap<Namespace, Vec<ElementIdentifier>>>;
 
+/// Elements that the device asserts itself, rather than the issuer, by document type, then
+/// namespace, then element identifier. They are returned in `deviceSigned`, authenticated only by
+/// the device key.
+pub type DeviceAssertedItems =
+    BTreeMap<DocType, BTreeMap<Namespace, BTreeMap<ElementIdentifier, CborValue>>>;
+
+/// An element requested by the reader, presented to the holder for consent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestedItem<'a> {
+    pub doc_type: &'a str,
+    pub namespace: &'a str,
+    pub element_identifier: &'a str,
+    /// Whether the reader intends to retain the element.
+    pub intent_to_retain: bool,
+}
+
+/// The holder's decision on sharing a [RequestedItem].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Allow,
+    Deny,
+    /// Share the element only if the reader does not intend to retain it.
+    AllowWithConstraint,
+}
+
 impl SessionManagerInit {
     /// Initialise the SessionManager.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     pub fn initialise(
         documents: Documents,
-        device_retrieval_methods: Option<NonEmptyVec<DeviceRetrievalMethod>>,
+        #[cfg(feature = "transports")] device_retrieval_methods: Option<
+            NonEmptyVec<DeviceRetrievalMethod>,
+        >,
         server_retrieval_methods: Option<ServerRetrievalMethods>,
     ) -> Result<Self, Error> {
         let (e_device_key, e_device_key_pub) =
             session::create_p256_ephemeral_keys().map_err(Error::EKeyGeneration)?;
         let e_device_key_bytes =
             Tag24::<CoseKey>::new(e_device_key_pub).map_err(Error::Tag24CborEncoding)?;
-        let security = Security(1, e_device_key_bytes);
+        let security = Security::new(e_device_key_bytes).map_err(Error::UnsupportedEDeviceKey)?;
 
         let device_engagement = DeviceEngagement {
             version: "1.0".to_string(),
             security,
+            #[cfg(feature = "transports")]
             device_retrieval_methods,
             server_retrieval_methods,
             protocol_info: None,
+            origin_infos: None,
         };
 
         let device_engagement =
             Tag24::<DeviceEngagement>::new(device_engagement).map_err(Error::Tag24CborEncoding)?;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            documents = documents.iter().count(),
+            device_engagement_len = device_engagement.inner_bytes.len(),
+            "generated device engagement"
+        );
 
         Ok(Self {
             documents,
-            e_device_key: e_device_key.to_bytes().to_vec(),
+            e_device_key: Zeroizing::new(e_device_key.to_bytes().to_vec()),
             device_engagement,
         })
     }
 
+    /// Bind the engagement to `origin_infos`, for presentations made through a browser.
+    pub fn with_origin_infos(
+        mut self,
+        origin_infos: NonEmptyVec<OriginInfo>,
+    ) -> Result<Self, Error> {
+        let mut device_engagement = self.device_engagement.into_inner();
+        device_engagement.origin_infos = Some(origin_infos);
+        self.device_engagement = Tag24::new(device_engagement).map_err(Error::Tag24CborEncoding)?;
+        Ok(self)
+    }
+
+    /// The value of the Ident characteristic, which the mdoc exposes in peripheral server
+    /// mode so the reader can check it has connected to the right device.
     pub fn ble_ident(&self) -> anyhow::Result<[u8; 16]> {
         super::calculate_ble_ident(&self.device_engagement.as_ref().security.1)
     }
 
+    /// The service UUID to advertise in mdoc peripheral server mode, if that mode is offered.
+    #[cfg(feature = "transports")]
+    pub fn ble_peripheral_server_uuid(&self) -> Option<&Uuid> {
+        super::first_peripheral_server_uuid(self.device_engagement.as_ref())
+    }
+
+    /// The service UUID the reader will advertise in mdoc central client mode, if that mode is
+    /// offered.
+    #[cfg(feature = "transports")]
+    pub fn ble_central_client_uuid(&self) -> Option<&Uuid> {
+        super::first_central_client_uuid(self.device_engagement.as_ref())
+    }
+
     /// Begin device engagement using QR code.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     pub fn qr_engagement(self) -> anyhow::Result<(SessionManagerEngaged, String)> {
         let qr_code_uri = self.device_engagement.to_qr_code_uri()?;
         let sm = SessionManagerEngaged {
@@ -158,12 +323,96 @@ impl SessionManagerInit {
             device_engagement: self.device_engagement,
             e_device_key: self.e_device_key,
             handover: Handover::QR,
+            limits: DecodeLimits::default(),
+            key_derivation: KeyDerivationProfile::default(),
         };
         Ok((sm, qr_code_uri))
     }
+
+    /// The encoded device engagement, to send to the reader in the handover select message of
+    /// NFC engagement.
+    pub fn device_engagement_bytes(&self) -> &[u8] {
+        &self.device_engagement.inner_bytes
+    }
+
+    /// Begin device engagement using NFC, where the device engagement was sent in
+    /// `handover_select`, in response to `handover_request` if the handover was negotiated.
+    pub fn nfc_engagement(
+        self,
+        handover_select: Vec<u8>,
+        handover_request: Option<Vec<u8>>,
+    ) -> SessionManagerEngaged {
+        SessionManagerEngaged {
+            documents: self.documents,
+            device_engagement: self.device_engagement,
+            e_device_key: self.e_device_key,
+            handover: Handover::NFC(handover_select.into(), handover_request.map(Into::into)),
+            limits: DecodeLimits::default(),
+            key_derivation: KeyDerivationProfile::default(),
+        }
+    }
 }
 
 impl SessionManagerEngaged {
+    /// Check that the engagement is bound to the website `expected_origin` before responding to
+    /// a request that arrived through it.
+    pub fn verify_origin(&self, expected_origin: &str) -> Result<(), device_engagement::Error> {
+        self.device_engagement
+            .as_ref()
+            .verify_origin(expected_origin)
+    }
+
+    /// Reject requests from the reader that exceed `limits`, rather than the defaults, for the
+    /// rest of the session.
+    pub fn set_decode_limits(&mut self, limits: DecodeLimits) {
+        self.limits = limits;
+    }
+
+    /// Derive the session keys under `profile`, rather than as in proximity presentation.
+    pub fn set_key_derivation_profile(&mut self, profile: KeyDerivationProfile) {
+        self.key_derivation = profile;
+    }
+
+    /// Render the QR code engagement URI as an SVG image.
+    #[cfg(feature = "qr")]
+    pub fn qr_code_svg(&self) -> anyhow::Result<String> {
+        Ok(self
+            .qr_code()?
+            .render::<qrcode::render::svg::Color>()
+            .build())
+    }
+
+    /// Render the QR code engagement URI as a PNG image, with each module `module_size` pixels
+    /// square.
+    #[cfg(feature = "qr")]
+    pub fn qr_code_png(&self, module_size: u32) -> anyhow::Result<Vec<u8>> {
+        let image = self
+            .qr_code()?
+            .render::<image::Luma<u8>>()
+            .module_dimensions(module_size, module_size)
+            .build();
+        let mut png = std::io::Cursor::new(vec![]);
+        image.write_to(&mut png, image::ImageFormat::Png)?;
+        Ok(png.into_inner())
+    }
+
+    /// Encode the engagement URI at the highest error correction level that still fits a
+    /// version 10 QR code, so that it remains easy to scan from a screen.
+    #[cfg(feature = "qr")]
+    fn qr_code(&self) -> anyhow::Result<qrcode::QrCode> {
+        use qrcode::{EcLevel, QrCode, Version};
+
+        let qr_code_uri = self.device_engagement.to_qr_code_uri()?;
+        [EcLevel::H, EcLevel::Q, EcLevel::M]
+            .into_iter()
+            .filter_map(|ec_level| QrCode::with_error_correction_level(&qr_code_uri, ec_level).ok())
+            .find(|code| matches!(code.version(), Version::Normal(version) if version <= 10))
+            .map(Ok)
+            .unwrap_or_else(|| QrCode::with_error_correction_level(&qr_code_uri, EcLevel::L))
+            .map_err(|e| anyhow::anyhow!("unable to encode qr code: {}", e))
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     pub fn process_session_establishment(
         self,
         session_establishment: SessionEstablishment,
@@ -174,14 +423,26 @@ impl SessionManagerEngaged {
         let session_transcript_bytes =
             Tag24::new(session_transcript.clone()).map_err(Error::Tag24CborEncoding)?;
 
-        let e_device_key = p256::SecretKey::from_bytes(FieldBytes::from_slice(&self.e_device_key))?;
+        let e_device_key = p256::SecretKey::from_slice(&self.e_device_key)
+            .map_err(|_| Error::InvalidEDeviceKey)?;
 
         let shared_secret = get_shared_secret(e_reader_key.into_inner(), &e_device_key.into())
             .map_err(Error::SharedSecretGeneration)?;
 
-        let sk_reader = derive_session_key(&shared_secret, &session_transcript_bytes, true)?.into();
-        let sk_device =
-            derive_session_key(&shared_secret, &session_transcript_bytes, false)?.into();
+        let sk_reader = Zeroizing::new(
+            self.key_derivation
+                .derive_session_key(&shared_secret, &session_transcript_bytes, true)
+                .map_err(Error::SessionKeyDerivation)?
+                .into(),
+        );
+        let sk_device = Zeroizing::new(
+            self.key_derivation
+                .derive_session_key(&shared_secret, &session_transcript_bytes, false)
+                .map_err(Error::SessionKeyDerivation)?
+                .into(),
+        );
+        #[cfg(feature = "tracing")]
+        tracing::debug!("derived session keys");
 
         let mut sm = SessionManager {
             documents: self.documents,
@@ -191,6 +452,10 @@ impl SessionManagerEngaged {
             sk_reader,
             reader_message_counter: 0,
             state: State::AwaitingRequest,
+            reader_auth: vec![],
+            limits: self.limits,
+            device_asserted: DeviceAssertedItems::new(),
+            selected: BTreeMap::new(),
         };
 
         let requested_data = sm.handle_decoded_request(SessionData {
@@ -200,56 +465,204 @@ impl SessionManagerEngaged {
 
         Ok((sm, requested_data))
     }
+
+    /// Process the session establishment as in
+    /// [SessionManagerEngaged::process_session_establishment], additionally verifying the
+    /// reader authentication on the request against the reader trust anchors in `registry`.
+    pub fn process_session_establishment_with_reader_auth(
+        self,
+        session_establishment: SessionEstablishment,
+        registry: &TrustAnchorRegistry,
+    ) -> anyhow::Result<(SessionManager, RequestedItems, ReaderAuthOutcome)> {
+        let (session_manager, requested_items) =
+            self.process_session_establishment(session_establishment)?;
+        let outcome = match session_manager.reader_x5chain() {
+            Some(Ok(x5chain)) => ReaderAuthOutcome {
+                authenticated: x5chain.validate_reader_auth(registry).is_empty(),
+                certificate: Some(x5chain.leaf().clone()),
+            },
+            Some(Err(_)) | None => ReaderAuthOutcome {
+                authenticated: false,
+                certificate: None,
+            },
+        };
+        Ok((session_manager, requested_items, outcome))
+    }
+}
+
+/// The outcome of verifying the reader authentication on a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReaderAuthOutcome {
+    /// Whether every document request was signed by the same reader certificate, and it chains
+    /// to a trust anchor.
+    pub authenticated: bool,
+    /// The reader certificate, if the request was signed by it, whether or not it is trusted.
+    pub certificate: Option<X509>,
 }
 
 impl SessionManager {
     fn parse_request(&self, request: &[u8]) -> Result<DeviceRequest, PreparedDeviceResponse> {
-        let request: CborValue = serde_cbor::from_slice(request).map_err(|_| {
-            // tracing::error!("unable to decode DeviceRequest bytes as cbor: {}", error);
+        let request: CborValue = serde_cbor::from_slice(request).map_err(|_error| {
+            #[cfg(feature = "tracing")]
+            tracing::error!("unable to decode DeviceRequest bytes as cbor: {}", _error);
             PreparedDeviceResponse::empty(Status::CborDecodingError)
         })?;
 
-        serde_cbor::value::from_value(request).map_err(|_| {
-            // tracing::error!("unable to validate DeviceRequest cbor: {}", error);
+        serde_cbor::value::from_value(request).map_err(|_error| {
+            #[cfg(feature = "tracing")]
+            tracing::error!("unable to validate DeviceRequest cbor: {}", _error);
             PreparedDeviceResponse::empty(Status::CborValidationError)
         })
     }
 
+    /// Check the version of `request`, and select the document with which to respond to each
+    /// document request, expanding any wildcards to the elements it holds.
     fn validate_request(
-        &self,
+        &mut self,
         request: DeviceRequest,
     ) -> Result<Vec<ItemsRequest>, PreparedDeviceResponse> {
         if request.version != DeviceRequest::VERSION {
-            // tracing::error!(
-            //     "unsupported DeviceRequest version: {} ({} is supported)",
-            //     request.version,
-            //     DeviceRequest::VERSION
-            // );
+            #[cfg(feature = "tracing")]
+            tracing::error!(
+                "unsupported DeviceRequest version: {} ({} is supported)",
+                request.version,
+                DeviceRequest::VERSION
+            );
             return Err(PreparedDeviceResponse::empty(Status::GeneralError));
         }
+        self.selected.clear();
         Ok(request
             .doc_requests
             .into_inner()
             .into_iter()
-            .map(|DocRequest { items_request, .. }| items_request.into_inner())
+            .map(|DocRequest { items_request, .. }| {
+                let items_request = items_request.into_inner();
+                let requested = items_request
+                    .namespaces
+                    .iter()
+                    .map(|(namespace, elements)| {
+                        (namespace.clone(), elements.keys().cloned().collect())
+                    })
+                    .collect();
+                match self.documents.select(&items_request.doc_type, &requested) {
+                    Some(document) => {
+                        self.selected
+                            .insert(items_request.doc_type.clone(), document.id);
+                        expand_wildcards(document, items_request)
+                    }
+                    None => items_request,
+                }
+            })
             .collect())
     }
 
+    /// Offer `items` as asserted by the device, to be returned in the `deviceSigned` namespaces
+    /// of later responses when they are requested and permitted. An element that the issuer
+    /// signed is returned as issuer signed instead.
+    pub fn set_device_asserted_items(&mut self, items: DeviceAssertedItems) {
+        self.device_asserted = items;
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     pub fn prepare_response(&mut self, requests: &RequestedItems, permitted: PermittedItems) {
         let prepared_response = DeviceSession::prepare_response(self, requests, permitted);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            requested_documents = requests.len(),
+            status = ?prepared_response.status,
+            "prepared response"
+        );
         self.state = State::Signing(prepared_response);
     }
 
+    /// Prepare a response as in [SessionManager::prepare_response], permitting the elements
+    /// that `consent` decides to share, asking once for each requested element.
+    pub fn prepare_response_with(
+        &mut self,
+        requests: &RequestedItems,
+        consent: impl FnMut(&RequestedItem) -> Decision,
+    ) {
+        let permitted = permitted_items(requests, consent);
+        self.prepare_response(requests, permitted);
+    }
+
+    /// Prepare a response as in [SessionManager::prepare_response], signing each document with
+    /// `signer` in turn, e.g. a remote key in an HSM.
+    #[cfg(feature = "async")]
+    pub async fn prepare_response_async<S, Sig>(
+        &mut self,
+        requests: &RequestedItems,
+        permitted: PermittedItems,
+        signer: &S,
+    ) -> anyhow::Result<()>
+    where
+        S: AsyncSigner<Sig>,
+        Sig: SignatureEncoding + Send + 'static,
+    {
+        self.prepare_response(requests, permitted);
+        while let Some((_, payload)) = self.get_next_signature_payload() {
+            let signature = signer
+                .sign_async(payload)
+                .await
+                .map_err(|e| anyhow::anyhow!("error signing device authentication: {}", e))?
+                .to_vec();
+            self.submit_next_signature(signature)?;
+        }
+        // A response without documents to sign is complete as soon as it is prepared.
+        match std::mem::take(&mut self.state) {
+            State::Signing(p) => self.respond(p),
+            state => {
+                self.state = state;
+                Ok(())
+            }
+        }
+    }
+
+    /// Prepare a response as in [SessionManager::prepare_response], authenticating the documents
+    /// as selected by `mode`.
+    ///
+    /// With [DeviceAuthMode::Mac] the response is ready immediately, and there are no signature
+    /// payloads to submit.
+    pub fn prepare_response_with_mode(
+        &mut self,
+        requests: &RequestedItems,
+        permitted: PermittedItems,
+        mode: DeviceAuthMode,
+    ) -> anyhow::Result<()> {
+        let mut prepared_response = DeviceSession::prepare_response(self, requests, permitted);
+        match mode {
+            DeviceAuthMode::Signature => {
+                self.state = State::Signing(prepared_response);
+                Ok(())
+            }
+            DeviceAuthMode::Mac(device_key) => {
+                let shared_secret = get_shared_secret(
+                    self.session_transcript.1.as_ref().clone(),
+                    &device_key.to_nonzero_scalar(),
+                )?;
+                let emac_key = session::derive_emac_key(
+                    &shared_secret,
+                    &Tag24::new(self.session_transcript.clone())?,
+                )?;
+                prepared_response.authenticate_with_mac(&emac_key, &self.session_transcript)?;
+                self.respond(prepared_response)
+            }
+        }
+    }
+
     fn handle_decoded_request(&mut self, request: SessionData) -> anyhow::Result<RequestedItems> {
-        let data = request.data.ok_or_else(|| {
-            anyhow::anyhow!("no mdoc requests received, assume session can be terminated")
-        })?;
+        let data = request.data.ok_or(Error::NoRequest)?;
+        self.limits
+            .check_message(data.as_ref())
+            .map_err(Error::LimitExceeded)?;
         let decrypted_request = session::decrypt_reader_data(
-            &self.sk_reader.into(),
+            &(*self.sk_reader).into(),
             data.as_ref(),
             &mut self.reader_message_counter,
         )
-        .map_err(|e| anyhow::anyhow!("unable to decrypt request: {}", e))?;
+        .map_err(Error::Session)?;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(request_len = decrypted_request.len(), "decrypted request");
         let request = match self.parse_request(&decrypted_request) {
             Ok(r) => r,
             Err(e) => {
@@ -257,6 +670,19 @@ impl SessionManager {
                 return Ok(Default::default());
             }
         };
+        self.limits
+            .check_request(&request)
+            .map_err(Error::LimitExceeded)?;
+        self.reader_auth = request
+            .doc_requests
+            .iter()
+            .map(|doc_request| {
+                (
+                    doc_request.items_request.clone(),
+                    doc_request.reader_auth.clone(),
+                )
+            })
+            .collect();
         let request = match self.validate_request(request) {
             Ok(r) => r,
             Err(e) => {
@@ -267,12 +693,69 @@ impl SessionManager {
         Ok(request)
     }
 
+    /// Verify the reader authentication on the last request received, returning the x5chain
+    /// of the reader, or `None` if no document request in it was authenticated.
+    ///
+    /// Every document request must be authenticated under the same x5chain: if only some of
+    /// them are, or they name different readers, an error is returned. The x5chain is not
+    /// validated against any trust anchors here.
+    pub fn reader_x5chain(&self) -> Option<anyhow::Result<X5Chain>> {
+        if self
+            .reader_auth
+            .iter()
+            .all(|(_, reader_auth)| reader_auth.is_none())
+        {
+            return None;
+        }
+        let x5chains = self
+            .reader_auth
+            .iter()
+            .map(|(items_request, reader_auth)| {
+                let reader_auth = reader_auth.as_ref().ok_or_else(|| {
+                    anyhow::anyhow!("a document request is not authenticated by the reader")
+                })?;
+                verify_reader_auth(&self.session_transcript, items_request, reader_auth)
+            })
+            .collect::<anyhow::Result<Vec<X5Chain>>>();
+        let mut x5chains = match x5chains {
+            Ok(x5chains) => x5chains.into_iter(),
+            Err(e) => return Some(Err(e)),
+        };
+        let x5chain = x5chains.next()?;
+        if x5chains.any(|other| !other.iter().eq(x5chain.iter())) {
+            return Some(Err(anyhow::anyhow!(
+                "the document requests are authenticated by different readers"
+            )));
+        }
+        Some(Ok(x5chain))
+    }
+
     /// Handle a request from the reader.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(request_len = request.len()))
+    )]
     pub fn handle_request(&mut self, request: &[u8]) -> anyhow::Result<RequestedItems> {
-        let session_data: SessionData = serde_cbor::from_slice(request)?;
+        if matches!(self.state, State::Terminated) {
+            return Err(Error::SessionEnded.into());
+        }
+        self.limits
+            .check_message(request)
+            .map_err(Error::LimitExceeded)?;
+        let session_data: SessionData = serde_cbor::from_slice(request)
+            .map_err(|_| Error::Session(session::SessionError::CborDecoding))?;
         self.handle_decoded_request(session_data)
     }
 
+    /// The session keys derived from the ephemeral keys and the session transcript.
+    #[cfg(feature = "test-vectors")]
+    pub fn session_keys(&self) -> session::SessionKeys {
+        session::SessionKeys {
+            sk_device: *self.sk_device,
+            sk_reader: *self.sk_reader,
+        }
+    }
+
     /// Get the next payload for signing.
     pub fn get_next_signature_payload(&self) -> Option<(Uuid, &[u8])> {
         match &self.state {
@@ -281,34 +764,92 @@ impl SessionManager {
         }
     }
 
+    /// The algorithm with which to sign the payload `id`, that of the device key of the
+    /// document it authenticates. The algorithm is already in the protected header of the
+    /// signature, so the signature must be made with it.
+    pub fn signature_algorithm(&self, id: PayloadId) -> Option<Algorithm> {
+        self.documents
+            .get_by_id(id)?
+            .mso
+            .device_key_info
+            .device_key
+            .signature_algorithm()
+    }
+
+    /// All signature payloads still to be signed, so that they can be signed in one batch and
+    /// submitted with [SessionManager::submit_signatures].
+    pub fn pending_signature_payloads(&self) -> Vec<(PayloadId, Vec<u8>)> {
+        match &self.state {
+            State::Signing(p) => p.pending_signature_payloads(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Submit a signature for every pending signature payload at once.
+    ///
+    /// Nothing is submitted unless there is exactly one signature for each pending payload.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(signatures = signatures.len()))
+    )]
+    pub fn submit_signatures(
+        &mut self,
+        signatures: Vec<(PayloadId, Vec<u8>)>,
+    ) -> anyhow::Result<()> {
+        match std::mem::take(&mut self.state) {
+            State::Signing(mut p) => {
+                if let Err(e) = p.submit_signatures(signatures) {
+                    self.state = State::Signing(p);
+                    return Err(e.into());
+                }
+                self.respond(p)
+            }
+            state => {
+                self.state = state;
+                Err(Error::ApiMisuse.into())
+            }
+        }
+    }
+
+    /// Sign every pending signature payload with `signer` and submit the signatures, completing
+    /// the response.
+    ///
+    /// Nothing is signed if `signer` does not use the algorithm of the device key of each
+    /// document.
+    pub fn sign_response(&mut self, signer: &impl MdocSigner) -> anyhow::Result<()> {
+        let payloads = self.pending_signature_payloads();
+        for (id, _) in &payloads {
+            match self.signature_algorithm(*id) {
+                Some(expected) if expected != signer.algorithm() => {
+                    return Err(Error::SignerAlgorithm {
+                        id: *id,
+                        signer: signer.algorithm(),
+                        expected,
+                    }
+                    .into())
+                }
+                _ => (),
+            }
+        }
+        let signatures = payloads
+            .into_iter()
+            .map(|(id, payload)| Ok((id, signer.sign(&payload)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        self.submit_signatures(signatures)
+    }
+
     /// Submit the externally signed signature.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(signature_len = signature.len()))
+    )]
     pub fn submit_next_signature(&mut self, signature: Vec<u8>) -> anyhow::Result<()> {
         if matches!(self.state, State::Signing(_)) {
             match std::mem::take(&mut self.state) {
                 State::Signing(mut p) => {
                     p.submit_next_signature(signature);
                     if p.is_complete() {
-                        let response = p.finalize_response();
-                        let mut status: Option<session::Status> = None;
-                        let response_bytes = serde_cbor::to_vec(&response)?;
-                        let encrypted_response = session::encrypt_device_data(
-                            &self.sk_device.into(),
-                            &response_bytes,
-                            &mut self.device_message_counter,
-                        )
-                        .unwrap_or_else(|_e| {
-                            //tracing::warn!("unable to encrypt response: {}", e);
-                            status = Some(session::Status::SessionEncryptionError);
-                            Default::default()
-                        });
-                        let data = if status.is_some() {
-                            None
-                        } else {
-                            Some(encrypted_response.into())
-                        };
-                        let session_data = SessionData { status, data };
-                        let encoded_response = serde_cbor::to_vec(&session_data)?;
-                        self.state = State::ReadyToRespond(encoded_response);
+                        self.respond(p)?;
                     } else {
                         self.state = State::Signing(p)
                     }
@@ -319,6 +860,40 @@ impl SessionManager {
         Ok(())
     }
 
+    /// Finalize and encrypt a response whose documents have all been authorized.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    fn respond(&mut self, prepared_response: PreparedDeviceResponse) -> anyhow::Result<()> {
+        let response = prepared_response.finalize_response();
+        let mut status: Option<session::Status> = None;
+        let response_bytes = serde_cbor::to_vec(&response)?;
+        let encrypted_response = session::encrypt_device_data(
+            &(*self.sk_device).into(),
+            &response_bytes,
+            &mut self.device_message_counter,
+        )
+        .unwrap_or_else(|_e| {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("unable to encrypt response: {}", _e);
+            status = Some(session::Status::SessionEncryptionError);
+            Default::default()
+        });
+        let data = if status.is_some() {
+            None
+        } else {
+            Some(encrypted_response.into())
+        };
+        let session_data = SessionData { status, data };
+        let encoded_response = serde_cbor::to_vec(&session_data)?;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            response_len = encoded_response.len(),
+            status = ?session_data.status,
+            "response ready"
+        );
+        self.state = State::ReadyToRespond(encoded_response);
+        Ok(())
+    }
+
     /// Identifies that the response is ready.
     pub fn response_ready(&self) -> bool {
         matches!(self.state, State::ReadyToRespond(_))
@@ -338,6 +913,16 @@ impl SessionManager {
             None
         }
     }
+
+    /// End the session, returning the message that informs the reader of the termination.
+    ///
+    /// Any further requests received in this session are rejected.
+    pub fn terminate(&mut self) -> Vec<u8> {
+        self.state = State::Terminated;
+        let session_data = SessionData::from_status(session::Status::SessionTermination);
+        // Safe to unwrap as a status code always encodes to CBOR.
+        serde_cbor::to_vec(&session_data).unwrap()
+    }
 }
 
 impl PreparedDeviceResponse {
@@ -363,22 +948,84 @@ impl PreparedDeviceResponse {
             .map(|doc| (doc.id, doc.prepared_cose_sign1.signature_payload()))
     }
 
+    pub fn pending_signature_payloads(&self) -> Vec<(PayloadId, Vec<u8>)> {
+        self.prepared_documents
+            .iter()
+            .rev()
+            .map(|doc| (doc.id, doc.prepared_cose_sign1.signature_payload().to_vec()))
+            .collect()
+    }
+
+    /// Submit a signature for each prepared document, identified by [PayloadId].
+    pub fn submit_signatures(
+        &mut self,
+        signatures: Vec<(PayloadId, Vec<u8>)>,
+    ) -> Result<(), Error> {
+        let mut by_id = BTreeMap::new();
+        for (id, signature) in signatures {
+            if !self.prepared_documents.iter().any(|doc| doc.id == id) {
+                return Err(Error::UnknownPayloadId(id));
+            }
+            if by_id.insert(id, signature).is_some() {
+                return Err(Error::DuplicateSignature(id));
+            }
+        }
+        if let Some(doc) = self
+            .prepared_documents
+            .iter()
+            .find(|doc| !by_id.contains_key(&doc.id))
+        {
+            return Err(Error::MissingSignature(doc.id));
+        }
+        while let Some(doc) = self.prepared_documents.pop() {
+            // Safe to unwrap as every prepared document has a signature.
+            let signature = by_id.remove(&doc.id).unwrap();
+            self.signed_documents.push(doc.finalize(signature));
+        }
+        Ok(())
+    }
+
     pub fn submit_next_signature(&mut self, signature: Vec<u8>) {
         let signed_doc = match self.prepared_documents.pop() {
             Some(doc) => doc.finalize(signature),
             None => {
-                //tracing::error!(
-                //    "received a signature for finalising when there are no more prepared docs"
-                //);
+                #[cfg(feature = "tracing")]
+                tracing::error!(
+                    "received a signature for finalising when there are no more prepared docs"
+                );
                 return;
             }
         };
         self.signed_documents.push(signed_doc);
     }
 
+    /// Authenticate all prepared documents with a MAC keyed by `emac_key`, in place of a
+    /// signature.
+    fn authenticate_with_mac(
+        &mut self,
+        emac_key: &[u8],
+        session_transcript: &SessionTranscript180135,
+    ) -> anyhow::Result<()> {
+        while let Some(doc) = self.prepared_documents.pop() {
+            let device_auth = Tag24::new(DeviceAuthentication::new(
+                session_transcript.clone(),
+                doc.doc_type.clone(),
+                doc.device_namespaces.clone(),
+            ))?;
+            let device_mac =
+                device_signed::device_mac(emac_key, &serde_cbor::to_vec(&device_auth)?)?;
+            self.signed_documents
+                .push(doc.finalize_with(DeviceAuth::Mac { device_mac }));
+        }
+        Ok(())
+    }
+
     pub fn finalize_response(self) -> DeviceResponse {
         if !self.is_complete() {
-            //tracing::warn!("attempt to finalize PreparedDeviceResponse before all prepared documents had been authorized");
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                "attempt to finalize PreparedDeviceResponse before all prepared documents had been authorized"
+            );
             return PreparedDeviceResponse::empty(Status::GeneralError).finalize_response();
         }
 
@@ -393,20 +1040,21 @@ impl PreparedDeviceResponse {
 
 impl PreparedDocument {
     fn finalize(self, signature: Vec<u8>) -> DeviceResponseDoc {
+        let device_signature = self.prepared_cose_sign1.clone().finalize(signature);
+        self.finalize_with(DeviceAuth::Signature { device_signature })
+    }
+
+    fn finalize_with(self, device_auth: DeviceAuth) -> DeviceResponseDoc {
         let Self {
             issuer_signed,
             device_namespaces,
-            prepared_cose_sign1,
             errors,
             doc_type,
             ..
         } = self;
-        let cose_sign1 = prepared_cose_sign1.finalize(signature);
         let device_signed = DeviceSigned {
             namespaces: device_namespaces,
-            device_auth: DeviceAuth::Signature {
-                device_signature: cose_sign1,
-            },
+            device_auth,
         };
         DeviceResponseDoc {
             doc_type,
@@ -422,6 +1070,16 @@ pub trait DeviceSession {
 
     fn documents(&self) -> &Documents;
     fn session_transcript(&self) -> Self::ST;
+    /// The elements offered as asserted by the device, returned where the issuer did not sign
+    /// them.
+    fn device_asserted_items(&self) -> Option<&DeviceAssertedItems> {
+        None
+    }
+    /// The id of the document selected to respond to the request for `doc_type` when the request
+    /// was received, if one was. Otherwise the document is selected on the permitted elements.
+    fn selected_document(&self, _doc_type: &str) -> Option<Uuid> {
+        None
+    }
     fn prepare_response(
         &self,
         requests: &RequestedItems,
@@ -431,18 +1089,23 @@ pub trait DeviceSession {
         let mut document_errors: Vec<DocumentError> = Vec::new();
 
         for (doc_type, namespaces) in filter_permitted(requests, permitted).into_iter() {
-            let document = match self.documents().get(&doc_type) {
-                Some(doc) => doc,
-                None => {
-                    // tracing::error!("holder owns no documents of type {}", doc_type);
-                    let error: DocumentError =
-                        [(doc_type.clone(), DocumentErrorCode::DataNotReturned)]
-                            .into_iter()
-                            .collect();
-                    document_errors.push(error);
-                    continue;
-                }
-            };
+            let selected = self
+                .selected_document(&doc_type)
+                .and_then(|id| self.documents().get_by_id(id));
+            let document =
+                match selected.or_else(|| self.documents().select(&doc_type, &namespaces)) {
+                    Some(doc) => doc,
+                    None => {
+                        #[cfg(feature = "tracing")]
+                        tracing::error!("holder owns no documents of type {}", doc_type);
+                        let error: DocumentError =
+                            [(doc_type.clone(), DocumentErrorCode::DataNotReturned)]
+                                .into_iter()
+                                .collect();
+                        document_errors.push(error);
+                        continue;
+                    }
+                };
             let signature_algorithm = match document
                 .mso
                 .device_key_info
@@ -451,10 +1114,11 @@ pub trait DeviceSession {
             {
                 Some(alg) => alg,
                 None => {
-                    //tracing::error!(
-                    //    "device key for document '{}' cannot perform signing",
-                    //    document.id
-                    //);
+                    #[cfg(feature = "tracing")]
+                    tracing::error!(
+                        "device key for document '{}' cannot perform signing",
+                        document.id
+                    );
                     let error: DocumentError =
                         [(doc_type.clone(), DocumentErrorCode::DataNotReturned)]
                             .into_iter()
@@ -464,49 +1128,91 @@ pub trait DeviceSession {
                 }
             };
 
+            let device_asserted = self
+                .device_asserted_items()
+                .and_then(|items| items.get(&doc_type));
             let mut issuer_namespaces: BTreeMap<String, NonEmptyVec<IssuerSignedItemBytes>> =
                 Default::default();
+            let mut device_elements: BTreeMap<String, BTreeMap<String, CborValue>> =
+                Default::default();
             let mut errors: BTreeMap<String, NonEmptyMap<String, DocumentErrorCode>> =
                 Default::default();
 
             for (namespace, elements) in namespaces.into_iter() {
-                if let Some(issuer_items) = document.namespaces.get(&namespace) {
-                    for element_identifier in elements.into_iter() {
-                        if let Some(item) = issuer_items.get(&element_identifier) {
-                            if let Some(returned_items) = issuer_namespaces.get_mut(&namespace) {
-                                returned_items.push(item.clone());
-                            } else {
-                                let returned_items = NonEmptyVec::new(item.clone());
-                                issuer_namespaces.insert(namespace.clone(), returned_items);
+                let issuer_items = document.namespaces.get(&namespace);
+                let asserted_items = device_asserted.and_then(|items| items.get(&namespace));
+                for element_identifier in elements.into_iter() {
+                    let item = issuer_items.and_then(|issuer_items| {
+                        issuer_items.get(&element_identifier).cloned().or_else(|| {
+                            nearest_age_attestation(
+                                element_identifier.clone(),
+                                issuer_items.clone(),
+                            )
+                            .ok()
+                            .flatten()
+                        })
+                    });
+                    if let Some(item) = item {
+                        if let Some(returned_items) = issuer_namespaces.get_mut(&namespace) {
+                            // Several age_over_NN requests may be answered by one statement.
+                            if !returned_items.iter().any(|returned| {
+                                returned.as_ref().element_identifier
+                                    == item.as_ref().element_identifier
+                            }) {
+                                returned_items.push(item);
                             }
-                        } else if let Some(returned_errors) = errors.get_mut(&namespace) {
-                            returned_errors
-                                .insert(element_identifier, DocumentErrorCode::DataNotReturned);
                         } else {
-                            let returned_errors = NonEmptyMap::new(
-                                element_identifier,
-                                DocumentErrorCode::DataNotReturned,
-                            );
-                            errors.insert(namespace.clone(), returned_errors);
+                            let returned_items = NonEmptyVec::new(item);
+                            issuer_namespaces.insert(namespace.clone(), returned_items);
                         }
+                    } else if let Some(value) =
+                        asserted_items.and_then(|items| items.get(&element_identifier))
+                    {
+                        device_elements
+                            .entry(namespace.clone())
+                            .or_default()
+                            .insert(element_identifier, value.clone());
+                    } else {
+                        record_element_error(&mut errors, &namespace, element_identifier);
                     }
-                } else {
-                    for element_identifier in elements.into_iter() {
-                        if let Some(returned_errors) = errors.get_mut(&namespace) {
-                            returned_errors
-                                .insert(element_identifier, DocumentErrorCode::DataNotReturned);
-                        } else {
-                            let returned_errors = NonEmptyMap::new(
-                                element_identifier,
-                                DocumentErrorCode::DataNotReturned,
+                }
+            }
+
+            // Requested elements that this document does not contain are reported as not
+            // returned, whether or not they were permitted.
+            for request in requests
+                .iter()
+                .filter(|request| request.doc_type == doc_type)
+            {
+                for (namespace, elements) in request.namespaces.iter() {
+                    let issuer_items = document.namespaces.get(namespace);
+                    let asserted_items = device_asserted.and_then(|items| items.get(namespace));
+                    for element_identifier in elements.keys() {
+                        if !issuer_items.is_some_and(|items| {
+                            items.contains_key(element_identifier)
+                                || nearest_age_attestation(
+                                    element_identifier.clone(),
+                                    items.clone(),
+                                )
+                                .is_ok_and(|item| item.is_some())
+                        }) && !asserted_items
+                            .is_some_and(|items| items.contains_key(element_identifier))
+                        {
+                            record_element_error(
+                                &mut errors,
+                                namespace,
+                                element_identifier.clone(),
                             );
-                            errors.insert(namespace.clone(), returned_errors);
                         }
                     }
                 }
             }
 
-            let device_namespaces = match Tag24::new(Default::default()) {
+            let device_namespaces: DeviceNamespaces = device_elements
+                .into_iter()
+                .filter_map(|(namespace, elements)| Some((namespace, elements.try_into().ok()?)))
+                .collect();
+            let device_namespaces = match Tag24::new(device_namespaces) {
                 Ok(dp) => dp,
                 Err(_e) => {
                     let error: DocumentError =
@@ -590,6 +1296,104 @@ impl DeviceSession for SessionManager {
     fn session_transcript(&self) -> SessionTranscript180135 {
         self.session_transcript.clone()
     }
+
+    fn device_asserted_items(&self) -> Option<&DeviceAssertedItems> {
+        Some(&self.device_asserted)
+    }
+
+    fn selected_document(&self, doc_type: &str) -> Option<Uuid> {
+        self.selected.get(doc_type).copied()
+    }
+}
+
+impl<ST: SessionTranscript + Clone> TranscriptSession<ST> {
+    /// Initialise a session over `session_transcript`, such as an
+    /// [OID4VPSessionTranscript](crate::definitions::OID4VPSessionTranscript).
+    ///
+    /// The response is prepared with [DeviceSession::prepare_response], and once signed, returned
+    /// with [PreparedDeviceResponse::finalize_response].
+    pub fn new(documents: Documents, session_transcript: ST) -> Self {
+        Self {
+            documents,
+            session_transcript,
+            device_asserted: DeviceAssertedItems::new(),
+        }
+    }
+
+    /// Offer `items` as asserted by the device, as in [SessionManager::set_device_asserted_items].
+    pub fn set_device_asserted_items(&mut self, items: DeviceAssertedItems) {
+        self.device_asserted = items;
+    }
+}
+
+impl<ST: SessionTranscript + Clone> DeviceSession for TranscriptSession<ST> {
+    type ST = ST;
+
+    fn documents(&self) -> &Documents {
+        &self.documents
+    }
+
+    fn session_transcript(&self) -> ST {
+        self.session_transcript.clone()
+    }
+
+    fn device_asserted_items(&self) -> Option<&DeviceAssertedItems> {
+        Some(&self.device_asserted)
+    }
+}
+
+impl Document {
+    /// Decode a document as in [Stringify::parse], and validate the x5chain of its document
+    /// signer against `registry`.
+    ///
+    /// The document is returned whatever the outcome of the validation, e.g. so that a wallet
+    /// can warn the holder that the chain has expired.
+    pub fn parse_validated(
+        encoded: String,
+        registry: &TrustAnchorRegistry,
+    ) -> anyhow::Result<(Self, ValidationReport)> {
+        let document = Self::parse(encoded)?;
+        let report = document
+            .issuer_x5chain()
+            .ok_or_else(|| anyhow::anyhow!("issuer auth has no valid x5chain"))?
+            .validate_detailed(registry);
+        Ok((document, report))
+    }
+
+    /// The identifiers of the issued elements, by namespace, without their values.
+    pub fn available_elements(&self) -> BTreeMap<Namespace, Vec<ElementIdentifier>> {
+        self.namespaces
+            .iter()
+            .map(|(namespace, elements)| (namespace.clone(), elements.keys().cloned().collect()))
+            .collect()
+    }
+
+    /// The x5chain of the document signer, from the unprotected header of the issuer auth.
+    pub fn issuer_x5chain(&self) -> Option<X5Chain> {
+        self.issuer_auth
+            .unprotected()
+            .get_i(X5CHAIN_HEADER_LABEL)
+            .and_then(|x5chain| X5Chain::from_cbor(x5chain).ok())
+    }
+
+    /// The certificate of the document signer, at the head of its x5chain.
+    ///
+    /// The certificate is not validated against any trust anchors here.
+    pub fn issuer_certificate(&self) -> Option<X509> {
+        Some(self.issuer_x5chain()?.leaf().clone())
+    }
+
+    /// The subject DN of the document signer, e.g. to show who issued the document.
+    pub fn issuer_subject(&self) -> Option<String> {
+        let certificate = self.issuer_certificate()?.certificate().ok()?;
+        Some(certificate.tbs_certificate.subject.to_string())
+    }
+
+    /// Whether the document should be refreshed from its issuer at `now`, by the validity info
+    /// of its mobile security object.
+    pub fn refresh_status(&self, now: OffsetDateTime) -> RefreshStatus {
+        self.mso.validity_info.refresh_status(now)
+    }
 }
 
 impl From<Mdoc> for Document {
@@ -630,6 +1434,85 @@ impl From<Mdoc> for Document {
     }
 }
 
+/// Record that `element_identifier` in `namespace` was not returned.
+fn record_element_error(
+    errors: &mut BTreeMap<String, NonEmptyMap<String, DocumentErrorCode>>,
+    namespace: &str,
+    element_identifier: String,
+) {
+    if let Some(returned_errors) = errors.get_mut(namespace) {
+        returned_errors.insert(element_identifier, DocumentErrorCode::DataNotReturned);
+    } else {
+        let returned_errors =
+            NonEmptyMap::new(element_identifier, DocumentErrorCode::DataNotReturned);
+        errors.insert(namespace.to_string(), returned_errors);
+    }
+}
+
+/// The items permitted by `consent`, asked once for each element in `requests`.
+pub fn permitted_items(
+    requests: &RequestedItems,
+    mut consent: impl FnMut(&RequestedItem) -> Decision,
+) -> PermittedItems {
+    let mut permitted = PermittedItems::new();
+    for request in requests {
+        for (namespace, elements) in request.namespaces.iter() {
+            for (element_identifier, intent_to_retain) in elements.iter() {
+                let item = RequestedItem {
+                    doc_type: &request.doc_type,
+                    namespace,
+                    element_identifier,
+                    intent_to_retain: *intent_to_retain,
+                };
+                let allowed = match consent(&item) {
+                    Decision::Allow => true,
+                    Decision::Deny => false,
+                    Decision::AllowWithConstraint => !item.intent_to_retain,
+                };
+                if allowed {
+                    permitted
+                        .entry(request.doc_type.clone())
+                        .or_default()
+                        .entry(namespace.clone())
+                        .or_default()
+                        .push(element_identifier.clone());
+                }
+            }
+        }
+    }
+    permitted
+}
+
+/// Replace each wildcard in `request` with the elements that `document` holds in that namespace,
+/// each with the intent to retain of the wildcard, so that the holder is asked to consent to each
+/// of them.
+fn expand_wildcards(document: &Document, mut request: ItemsRequest) -> ItemsRequest {
+    let wildcards: Vec<(Namespace, IntentToRetain)> = request
+        .namespaces
+        .iter()
+        .filter_map(|(namespace, elements)| {
+            let intent_to_retain = elements.get(WILDCARD)?;
+            Some((namespace.clone(), *intent_to_retain))
+        })
+        .collect();
+    for (namespace, intent_to_retain) in wildcards {
+        let elements: BTreeMap<_, _> = document
+            .namespaces
+            .get(&namespace)
+            .map(|items| {
+                items
+                    .keys()
+                    .map(|id| (id.clone(), intent_to_retain))
+                    .collect()
+            })
+            .unwrap_or_default();
+        request
+            .namespaces
+            .insert(namespace, DataElements::from(elements));
+    }
+    request
+}
+
 /// Filter permitted items to only permit the items that were requested.
 fn filter_permitted(request: &RequestedItems, permitted: PermittedItems) -> PermittedItems {
     permitted
@@ -647,7 +1530,7 @@ fn filter_permitted(request: &RequestedItems, permitted: PermittedItems) -> Perm
                                 .map(|req_elems| {
                                     elems
                                         .into_iter()
-                                        .filter(|elem| req_elems.contains_key(elem))
+                                        .filter(|elem| req_elems.includes(elem))
                                         .collect()
                                 })
                                 .map(|e| (ns, e))
@@ -729,6 +1612,27 @@ impl TryFrom<String> for AgeOver {
     }
 }
 
+/// Verify `reader_auth` over the ReaderAuthentication structure for `items_request`, using the
+/// certificate at the head of the x5chain in its unprotected header.
+fn verify_reader_auth(
+    session_transcript: &SessionTranscript180135,
+    items_request: &ItemsRequestBytes,
+    reader_auth: &ReaderAuth,
+) -> anyhow::Result<X5Chain> {
+    let x5chain = reader_auth
+        .unprotected()
+        .get_i(X5CHAIN_HEADER_LABEL)
+        .ok_or_else(|| anyhow::anyhow!("reader auth has no x5chain"))
+        .and_then(X5Chain::from_cbor)?;
+    let payload = serde_cbor::to_vec(&Tag24::new(ReaderAuthentication::new(
+        session_transcript.clone(),
+        items_request.clone(),
+    ))?)?;
+    super::verify_with_certificate(reader_auth, x5chain.leaf(), Some(payload))
+        .map_err(|e| anyhow::anyhow!("unable to verify reader auth signature: {}", e))?;
+    Ok(x5chain)
+}
+
 #[cfg(test)]
 mod test {
     use crate::definitions::helpers::ByteStr;
@@ -737,6 +1641,43 @@ mod test {
     use crate::definitions::mso::DigestId;
     use serde_json::json;
 
+    static MDL: &str = include_str!("../../tests/data/stringified-mdl.txt");
+
+    #[test]
+    #[cfg(feature = "test-vectors")]
+    fn session_keys() {
+        static E_DEVICE_KEY: &str =
+            include_str!("../../test/definitions/session/e_device_key.cbor");
+        static SESSION_ESTABLISHMENT: &str =
+            include_str!("../../test/definitions/session/session_establishment.cbor");
+        static SESSION_TRANSCRIPT: &str =
+            include_str!("../../test/definitions/session/session_transcript.cbor");
+        static READER_SESSION_KEY: &str =
+            include_str!("../../test/definitions/session/reader_session_key.cbor");
+        let session_transcript: session::SessionTranscriptBytes =
+            serde_cbor::from_slice(&hex::decode(SESSION_TRANSCRIPT).unwrap()).unwrap();
+        let SessionTranscript180135(device_engagement, _, handover) =
+            session_transcript.into_inner();
+        let document = Document::parse(MDL.to_string()).unwrap();
+        let engaged = SessionManagerEngaged {
+            documents: Documents::new(document.mso.doc_type.clone(), document),
+            e_device_key: Zeroizing::new(hex::decode(E_DEVICE_KEY).unwrap()),
+            device_engagement,
+            handover,
+            limits: DecodeLimits::default(),
+            key_derivation: KeyDerivationProfile::default(),
+        };
+        let session_establishment: SessionEstablishment =
+            serde_cbor::from_slice(&hex::decode(SESSION_ESTABLISHMENT).unwrap()).unwrap();
+        let (session_manager, _) = engaged
+            .process_session_establishment(session_establishment)
+            .unwrap();
+
+        let keys = session_manager.session_keys();
+        assert_eq!(hex::encode(keys.sk_reader), READER_SESSION_KEY);
+        assert_ne!(keys.sk_device, keys.sk_reader);
+    }
+
     #[test]
     fn filter_permitted() {
         let requested = serde_json::from_value(json!([
@@ -793,6 +1734,46 @@ mod test {
         assert_eq!(expected, filtered);
     }
 
+    #[test]
+    fn consent() {
+        let requested = serde_json::from_value(json!([
+            {
+                "docType": "org.iso.18013.5.1.mDL",
+                "nameSpaces": {
+                    "org.iso.18013.5.1": {
+                        "family_name": false,
+                        "resident_address": false,
+                        "portrait": true,
+                        "age_over_21": false,
+                    }
+                }
+            }
+        ]))
+        .unwrap();
+        let expected: PermittedItems = serde_json::from_value(json!({
+            "org.iso.18013.5.1.mDL": {
+                "org.iso.18013.5.1": [
+                    "age_over_21",
+                    "family_name",
+                ],
+            }
+        }))
+        .unwrap();
+
+        let mut asked = 0;
+        let permitted = permitted_items(&requested, |item| {
+            asked += 1;
+            match item.element_identifier {
+                "resident_address" => Decision::Deny,
+                "portrait" | "age_over_21" => Decision::AllowWithConstraint,
+                _ => Decision::Allow,
+            }
+        });
+
+        assert_eq!(asked, 4);
+        assert_eq!(expected, permitted);
+    }
+
     #[test]
     fn test_parse_age_from_element_identifier() {
         let element_identifier = "age_over_88".to_string();
@@ -849,4 +1830,68 @@ mod test {
 
         println!("{:?}", x);
     }
+
+    #[test]
+    fn available_elements() {
+        let document = Document::parse(MDL.to_string()).unwrap();
+        let available = document.available_elements();
+        assert_eq!(
+            available.keys().collect::<Vec<_>>(),
+            ["org.iso.18013.5.1", "org.iso.18013.5.1.aamva"]
+        );
+        assert_eq!(available["org.iso.18013.5.1"].len(), 30);
+        assert!(available["org.iso.18013.5.1"].contains(&"age_over_21".to_string()));
+        assert_eq!(available["org.iso.18013.5.1.aamva"].len(), 19);
+        assert!(available["org.iso.18013.5.1.aamva"].contains(&"DHS_compliance".to_string()));
+    }
+
+    #[test]
+    fn issuer_subject() {
+        let document = Document::parse(MDL.to_string()).unwrap();
+        let certificate = document.issuer_certificate().unwrap();
+        assert_eq!(
+            document
+                .issuer_x5chain()
+                .map(|x5chain| x5chain.leaf().clone()),
+            Some(certificate)
+        );
+        assert_eq!(document.issuer_subject().as_deref(), Some("CN=TEST"));
+    }
+
+    #[test]
+    fn parse_validated_expired() {
+        // The fixture was issued by a self-signed certificate that expired in July 2023.
+        let registry = TrustAnchorRegistry::new()
+            .with_pem(include_bytes!("../../test/presentation/iaca-cert.pem"))
+            .unwrap();
+        let (_document, report) = Document::parse_validated(MDL.to_string(), &registry).unwrap();
+        assert!(!report.is_valid());
+        assert_eq!(
+            report.certificates[0].validity,
+            Err(crate::definitions::x509::X509Error::Expired(0))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "qr")]
+    fn qr_code_images() {
+        let document = Document::parse(MDL.to_string()).unwrap();
+        let (engaged, _qr_code_uri) = SessionManagerInit::initialise(
+            Documents::new(document.mso.doc_type.clone(), document),
+            #[cfg(feature = "transports")]
+            None,
+            None,
+        )
+        .unwrap()
+        .qr_engagement()
+        .unwrap();
+
+        let svg = engaged.qr_code_svg().unwrap();
+        assert!(svg.contains("<svg"));
+
+        let small = image::load_from_memory(&engaged.qr_code_png(4).unwrap()).unwrap();
+        let large = image::load_from_memory(&engaged.qr_code_png(8).unwrap()).unwrap();
+        assert_eq!(large.width(), small.width() * 2);
+        assert_eq!(large.height(), small.height() * 2);
+    }
 }
@@ -0,0 +1,119 @@
+//! Selective disclosure of the requested items that a policy permits.
+
+use super::{
+    nearest_age_attestation, permitted_items, Decision, Documents, ElementIdentifier, Namespace,
+    PermittedItems, RequestedItems,
+};
+use std::collections::BTreeSet;
+
+/// Which namespaces and elements a holder is willing to disclose.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisclosurePolicy {
+    mode: Mode,
+    namespaces: BTreeSet<Namespace>,
+    elements: BTreeSet<(Namespace, ElementIdentifier)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Allow,
+    Deny,
+}
+
+impl DisclosurePolicy {
+    /// Disclose only the listed namespaces and elements.
+    pub fn allow_list() -> Self {
+        Self::new(Mode::Allow)
+    }
+
+    /// Disclose everything except the listed namespaces and elements.
+    pub fn deny_list() -> Self {
+        Self::new(Mode::Deny)
+    }
+
+    fn new(mode: Mode) -> Self {
+        Self {
+            mode,
+            namespaces: BTreeSet::new(),
+            elements: BTreeSet::new(),
+        }
+    }
+
+    /// List every element of `namespace`.
+    pub fn namespace(mut self, namespace: Namespace) -> Self {
+        self.namespaces.insert(namespace);
+        self
+    }
+
+    /// List `element_identifier` of `namespace`.
+    pub fn element(mut self, namespace: Namespace, element_identifier: ElementIdentifier) -> Self {
+        self.elements.insert((namespace, element_identifier));
+        self
+    }
+
+    /// Whether the policy permits disclosing `element_identifier` of `namespace`.
+    pub fn permits(&self, namespace: &str, element_identifier: &str) -> bool {
+        let listed = self.namespaces.contains(namespace)
+            || self
+                .elements
+                .contains(&(namespace.to_string(), element_identifier.to_string()));
+        match self.mode {
+            Mode::Allow => listed,
+            Mode::Deny => !listed,
+        }
+    }
+}
+
+impl Documents {
+    /// The items of `requested` that any of these documents hold and `policy` permits, to pass to
+    /// [SessionManager::prepare_response](super::SessionManager::prepare_response).
+    ///
+    /// A requested `age_over_NN` element is held if the document can answer it with its nearest
+    /// age attestation.
+    pub fn filter_disclosure(
+        &self,
+        requested: &RequestedItems,
+        policy: &DisclosurePolicy,
+    ) -> PermittedItems {
+        permitted_items(requested, |item| {
+            let held = self
+                .get_all(item.doc_type)
+                .filter_map(|document| document.namespaces.get(item.namespace))
+                .any(|items| {
+                    items.contains_key(item.element_identifier)
+                        || nearest_age_attestation(
+                            item.element_identifier.to_string(),
+                            items.clone(),
+                        )
+                        .is_ok_and(|item| item.is_some())
+                });
+            if held && policy.permits(item.namespace, item.element_identifier) {
+                Decision::Allow
+            } else {
+                Decision::Deny
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DisclosurePolicy;
+
+    #[test]
+    fn policies() {
+        let allow = DisclosurePolicy::allow_list()
+            .namespace("org.iso.18013.5.1.aamva".into())
+            .element("org.iso.18013.5.1".into(), "age_over_21".into());
+        assert!(allow.permits("org.iso.18013.5.1", "age_over_21"));
+        assert!(allow.permits("org.iso.18013.5.1.aamva", "DHS_compliance"));
+        assert!(!allow.permits("org.iso.18013.5.1", "family_name"));
+
+        let deny = DisclosurePolicy::deny_list()
+            .namespace("org.iso.18013.5.1.aamva".into())
+            .element("org.iso.18013.5.1".into(), "portrait".into());
+        assert!(deny.permits("org.iso.18013.5.1", "family_name"));
+        assert!(!deny.permits("org.iso.18013.5.1", "portrait"));
+        assert!(!deny.permits("org.iso.18013.5.1.aamva", "DHS_compliance"));
+    }
+}
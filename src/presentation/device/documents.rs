@@ -0,0 +1,171 @@
+//! The documents held by a wallet, from which the document to present for each request is
+//! selected.
+
+use super::{DocType, Document, ElementIdentifier, Namespace};
+use crate::definitions::device_request::WILDCARD;
+use crate::definitions::helpers::{NonEmptyMap, NonEmptyVec};
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use uuid::Uuid;
+
+/// The documents held by a wallet, by doc type. A wallet may hold several documents of one doc
+/// type, such as the mDLs of two states.
+///
+/// Documents serialized when a wallet held one document of each doc type, as a map from doc type
+/// to document, are still accepted.
+#[derive(Debug, Clone, Serialize)]
+pub struct Documents(NonEmptyMap<DocType, NonEmptyVec<Document>>);
+
+/// The documents held of one doc type, deserialized from either a sequence of documents or a
+/// single document.
+#[derive(Clone)]
+struct Held(NonEmptyVec<Document>);
+
+impl Documents {
+    pub fn new(doc_type: DocType, document: Document) -> Self {
+        Self(NonEmptyMap::new(doc_type, NonEmptyVec::new(document)))
+    }
+
+    /// Add a document, after any already held of its doc type.
+    pub fn add(&mut self, doc_type: DocType, document: Document) {
+        match self.0.get_mut(&doc_type) {
+            Some(documents) => documents.push(document),
+            None => {
+                self.0.insert(doc_type, NonEmptyVec::new(document));
+            }
+        }
+    }
+
+    /// The first document held of `doc_type`.
+    pub fn get(&self, doc_type: &str) -> Option<&Document> {
+        self.0.get(doc_type).map(|documents| documents.first())
+    }
+
+    /// The documents held of `doc_type`, in the order in which they were added.
+    pub fn get_all(&self, doc_type: &str) -> impl Iterator<Item = &Document> {
+        self.0
+            .get(doc_type)
+            .into_iter()
+            .flat_map(|documents| documents.iter())
+    }
+
+    /// The document with the id `id`.
+    pub fn get_by_id(&self, id: Uuid) -> Option<&Document> {
+        self.iter()
+            .map(|(_, document)| document)
+            .find(|document| document.id == id)
+    }
+
+    /// Each document held, with its doc type.
+    pub fn iter(&self) -> impl Iterator<Item = (&DocType, &Document)> {
+        self.0.iter().flat_map(|(doc_type, documents)| {
+            documents.iter().map(move |document| (doc_type, document))
+        })
+    }
+
+    /// The document of `doc_type` with which to respond to a request for the `requested`
+    /// elements: the one that holds the most of them, or the first added of those that hold as
+    /// many. A [WILDCARD] counts every element held in its namespace.
+    pub fn select(
+        &self,
+        doc_type: &str,
+        requested: &BTreeMap<Namespace, Vec<ElementIdentifier>>,
+    ) -> Option<&Document> {
+        let held = |document: &Document| {
+            requested
+                .iter()
+                .filter_map(|(namespace, elements)| {
+                    document.namespaces.get(namespace).map(|items| {
+                        match elements.iter().any(|element| element == WILDCARD) {
+                            true => items.len(),
+                            false => elements.iter().filter(|e| items.contains_key(*e)).count(),
+                        }
+                    })
+                })
+                .sum::<usize>()
+        };
+        // max_by_key returns the last of equal maxima, so search from the last added.
+        self.get_all(doc_type)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .max_by_key(|document| held(document))
+    }
+}
+
+impl<'de> Deserialize<'de> for Documents {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let held = BTreeMap::<DocType, Held>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(doc_type, Held(documents))| (doc_type, documents))
+            .collect();
+        NonEmptyMap::maybe_new(held)
+            .map(Self)
+            .ok_or_else(|| de::Error::invalid_length(0, &"at least one doc type"))
+    }
+}
+
+impl<'de> Deserialize<'de> for Held {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct HeldVisitor;
+
+        impl<'de> Visitor<'de> for HeldVisitor {
+            type Value = Held;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a document or a sequence of documents")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Held, A::Error> {
+                NonEmptyVec::deserialize(SeqAccessDeserializer::new(seq)).map(Held)
+            }
+
+            // The documents are read from the map directly, rather than buffered, so that the
+            // CBOR tags of the issuer signed items are kept.
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Held, A::Error> {
+                Document::deserialize(MapAccessDeserializer::new(map))
+                    .map(|document| Held(NonEmptyVec::new(document)))
+            }
+        }
+
+        deserializer.deserialize_any(HeldVisitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::presentation::Stringify;
+
+    static MDL: &str = include_str!("../../../tests/data/stringified-mdl.txt");
+
+    #[test]
+    fn serde() {
+        let document = Document::parse(MDL.to_string()).unwrap();
+        let doc_type = document.mso.doc_type.clone();
+
+        // A map from doc type to a single document, as serialized by earlier versions.
+        let single =
+            serde_cbor::to_vec(&BTreeMap::from([(doc_type.clone(), document.clone())])).unwrap();
+        let mut documents: Documents = serde_cbor::from_slice(&single).unwrap();
+        assert_eq!(documents.get(&doc_type).unwrap().id, document.id);
+
+        let other = Document {
+            id: Uuid::new_v4(),
+            ..document.clone()
+        };
+        documents.add(doc_type.clone(), other.clone());
+        let roundtripped: Documents =
+            serde_cbor::from_slice(&serde_cbor::to_vec(&documents).unwrap()).unwrap();
+        assert_eq!(
+            roundtripped
+                .get_all(&doc_type)
+                .map(|document| document.id)
+                .collect::<Vec<_>>(),
+            [document.id, other.id]
+        );
+    }
+}
@@ -0,0 +1,78 @@
+//! Signing the device authentication of a response with a key of any kind, such as one held in
+//! an HSM or a cloud KMS.
+
+use cose_rs::algorithm::Algorithm;
+use signature::Signer;
+use std::sync::Arc;
+
+/// A device key that signs the device authentication of each document in a response.
+pub trait MdocSigner {
+    /// The algorithm of the signatures, which must be that of the device key in the MSO.
+    fn algorithm(&self) -> Algorithm;
+
+    /// Sign `payload`, returning the signature encoded as in COSE.
+    fn sign(&self, payload: &[u8]) -> anyhow::Result<Vec<u8>>;
+}
+
+macro_rules! impl_ecdsa_signer {
+    ($key:ty, $signature:ty, $algorithm:expr) => {
+        impl MdocSigner for $key {
+            fn algorithm(&self) -> Algorithm {
+                $algorithm
+            }
+
+            fn sign(&self, payload: &[u8]) -> anyhow::Result<Vec<u8>> {
+                let signature: $signature = self
+                    .try_sign(payload)
+                    .map_err(|e| anyhow::anyhow!("error signing device authentication: {}", e))?;
+                Ok(signature.to_vec())
+            }
+        }
+    };
+}
+
+impl_ecdsa_signer!(
+    p256::ecdsa::SigningKey,
+    p256::ecdsa::Signature,
+    Algorithm::ES256
+);
+impl_ecdsa_signer!(
+    p384::ecdsa::SigningKey,
+    p384::ecdsa::Signature,
+    Algorithm::ES384
+);
+impl_ecdsa_signer!(
+    p521::ecdsa::SigningKey,
+    p521::ecdsa::Signature,
+    Algorithm::ES512
+);
+
+impl<T: MdocSigner + ?Sized> MdocSigner for &T {
+    fn algorithm(&self) -> Algorithm {
+        (**self).algorithm()
+    }
+
+    fn sign(&self, payload: &[u8]) -> anyhow::Result<Vec<u8>> {
+        (**self).sign(payload)
+    }
+}
+
+impl<T: MdocSigner + ?Sized> MdocSigner for Box<T> {
+    fn algorithm(&self) -> Algorithm {
+        (**self).algorithm()
+    }
+
+    fn sign(&self, payload: &[u8]) -> anyhow::Result<Vec<u8>> {
+        (**self).sign(payload)
+    }
+}
+
+impl<T: MdocSigner + ?Sized> MdocSigner for Arc<T> {
+    fn algorithm(&self) -> Algorithm {
+        (**self).algorithm()
+    }
+
+    fn sign(&self, payload: &[u8]) -> anyhow::Result<Vec<u8>> {
+        (**self).sign(payload)
+    }
+}
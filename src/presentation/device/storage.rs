@@ -0,0 +1,206 @@
+//! A versioned envelope in which a wallet stores a document between sessions, and across
+//! versions of the wallet.
+
+use super::Document;
+use crate::definitions::{helpers::Tag24, IssuerSigned, Mso};
+use crate::presentation::reader::{digest_mismatches, DigestMismatch};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// The version of [StoredCredential] written by this release, and the only one it reads.
+pub const STORED_CREDENTIAL_VERSION: u32 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("the stored credential has no version, so it predates versioned storage")]
+    Unversioned,
+    #[error(
+        "the stored credential has version {found}, but only version {} is supported",
+        STORED_CREDENTIAL_VERSION
+    )]
+    UnsupportedVersion { found: u32 },
+    #[error("the stored credential discloses no elements")]
+    NoElements,
+    #[error("the issuer auth of the stored credential has no mobile security object")]
+    MissingMso,
+    #[error(
+        "the stored credential is for {envelope}, but its mobile security object is for {mso}"
+    )]
+    DocTypeMismatch { envelope: String, mso: String },
+    #[error("{} elements of the stored credential do not match their digests", .0.len())]
+    DigestMismatches(Vec<DigestMismatch>),
+    #[error("unable to encode or decode the stored credential: {0}")]
+    Cbor(#[from] serde_cbor::Error),
+}
+
+/// An issued document as a wallet stores it: the issuer signed elements and issuer auth,
+/// exactly as issued, tagged with the version of the envelope.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredCredential {
+    version: u32,
+    pub doc_type: String,
+    pub issuer_signed: IssuerSigned,
+}
+
+/// Just the version of a stored credential, read before the rest is decoded.
+#[derive(Deserialize)]
+struct Version {
+    version: Option<u32>,
+}
+
+impl StoredCredential {
+    pub fn new(doc_type: String, issuer_signed: IssuerSigned) -> Self {
+        Self {
+            version: STORED_CREDENTIAL_VERSION,
+            doc_type,
+            issuer_signed,
+        }
+    }
+
+    /// The version of the envelope.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Encode the credential for storage.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, StorageError> {
+        Ok(serde_cbor::to_vec(self)?)
+    }
+
+    /// Decode a stored credential, rejecting any version other than
+    /// [STORED_CREDENTIAL_VERSION] before decoding the rest, so that the layout of another
+    /// version is never mistaken for this one.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StorageError> {
+        let Version { version } = serde_cbor::from_slice(bytes)?;
+        match version {
+            Some(STORED_CREDENTIAL_VERSION) => Ok(serde_cbor::from_slice(bytes)?),
+            Some(found) => Err(StorageError::UnsupportedVersion { found }),
+            None => Err(StorageError::Unversioned),
+        }
+    }
+}
+
+impl From<&Document> for StoredCredential {
+    fn from(document: &Document) -> Self {
+        let namespaces = document
+            .namespaces
+            .iter()
+            .map(|(namespace, elements)| {
+                let items = elements
+                    .values()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .try_into()
+                    // Can unwrap as there is always at least one element in a NonEmptyMap.
+                    .unwrap();
+                (namespace.clone(), items)
+            })
+            .collect::<BTreeMap<_, _>>()
+            .try_into()
+            // Can unwrap as there is always at least one namespace in a NonEmptyMap.
+            .unwrap();
+        Self::new(
+            document.mso.doc_type.clone(),
+            IssuerSigned {
+                namespaces: Some(namespaces),
+                issuer_auth: document.issuer_auth.clone(),
+            },
+        )
+    }
+}
+
+/// Restore a stored document, checking that its doc type and elements match the mobile security
+/// object. The issuer signature is not verified.
+impl TryFrom<StoredCredential> for Document {
+    type Error = StorageError;
+
+    fn try_from(credential: StoredCredential) -> Result<Self, StorageError> {
+        let mso: Tag24<Mso> = serde_cbor::from_slice(
+            credential
+                .issuer_signed
+                .issuer_auth
+                .payload()
+                .ok_or(StorageError::MissingMso)?,
+        )?;
+        if credential.doc_type != mso.as_ref().doc_type {
+            return Err(StorageError::DocTypeMismatch {
+                envelope: credential.doc_type,
+                mso: mso.into_inner().doc_type,
+            });
+        }
+        let mismatches = digest_mismatches(&credential.issuer_signed, mso.as_ref());
+        if !mismatches.is_empty() {
+            return Err(StorageError::DigestMismatches(mismatches));
+        }
+        let IssuerSigned {
+            namespaces,
+            issuer_auth,
+        } = credential.issuer_signed;
+        let namespaces = namespaces
+            .ok_or(StorageError::NoElements)?
+            .into_inner()
+            .into_iter()
+            .map(|(namespace, items)| {
+                let elements = items
+                    .into_inner()
+                    .into_iter()
+                    .map(|item| (item.as_ref().element_identifier.clone(), item))
+                    .collect::<BTreeMap<_, _>>()
+                    .try_into()
+                    // Can unwrap as there is always at least one element in a NonEmptyVec.
+                    .unwrap();
+                (namespace, elements)
+            })
+            .collect::<BTreeMap<_, _>>()
+            .try_into()
+            // Can unwrap as there is always at least one namespace in a NonEmptyMap.
+            .unwrap();
+        Ok(Document {
+            id: Uuid::now_v1(&[0, 0, 0, 0, 0, 0]),
+            issuer_auth,
+            mso: mso.into_inner(),
+            namespaces,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{StorageError, StoredCredential};
+    use serde_cbor::Value as CborValue;
+    use std::collections::BTreeMap;
+
+    fn blob(version: Option<i128>) -> Vec<u8> {
+        let mut map = BTreeMap::new();
+        if let Some(version) = version {
+            map.insert(
+                CborValue::Text("version".into()),
+                CborValue::Integer(version),
+            );
+        }
+        map.insert(
+            CborValue::Text("docType".into()),
+            CborValue::Text("org.iso.18013.5.1.mDL".into()),
+        );
+        serde_cbor::to_vec(&CborValue::Map(map)).unwrap()
+    }
+
+    #[test]
+    fn other_versions() {
+        assert!(matches!(
+            StoredCredential::from_bytes(&blob(None)),
+            Err(StorageError::Unversioned)
+        ));
+        assert!(matches!(
+            StoredCredential::from_bytes(&blob(Some(2))),
+            Err(StorageError::UnsupportedVersion { found: 2 })
+        ));
+        // The current version is decoded in full, and this blob is incomplete.
+        assert!(matches!(
+            StoredCredential::from_bytes(&blob(Some(1))),
+            Err(StorageError::Cbor(_))
+        ));
+    }
+}
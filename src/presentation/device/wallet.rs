@@ -0,0 +1,133 @@
+//! A wallet holding documents and the device key they are bound to, presenting them over the
+//! [SessionManagerInit] state machine without exposing its signing steps.
+
+use super::{
+    Documents, Error, MdocSigner, PermittedItems, RequestedItems, SessionManager,
+    SessionManagerEngaged, SessionManagerInit,
+};
+#[cfg(feature = "transports")]
+use crate::definitions::device_engagement::DeviceRetrievalMethods;
+use crate::definitions::{device_engagement::ServerRetrievalMethods, SessionEstablishment};
+
+/// Documents, and the device key that signs their device authentication.
+#[derive(Clone)]
+pub struct Wallet<S> {
+    documents: Documents,
+    signer: S,
+    #[cfg(feature = "transports")]
+    device_retrieval_methods: Option<DeviceRetrievalMethods>,
+    server_retrieval_methods: Option<ServerRetrievalMethods>,
+}
+
+/// A wallet engaged with a reader, for the duration of one session.
+pub struct EngagedWallet<S> {
+    session: Session,
+    signer: S,
+    requested: RequestedItems,
+}
+
+enum Session {
+    Engaged(Box<SessionManagerEngaged>),
+    Established(Box<SessionManager>),
+}
+
+impl<S> Wallet<S>
+where
+    S: MdocSigner + Clone,
+{
+    pub fn new(documents: Documents, signer: S) -> Self {
+        Self {
+            documents,
+            signer,
+            #[cfg(feature = "transports")]
+            device_retrieval_methods: None,
+            server_retrieval_methods: None,
+        }
+    }
+
+    /// Offer `methods` to the reader in the device engagement.
+    #[cfg(feature = "transports")]
+    pub fn with_device_retrieval_methods(mut self, methods: DeviceRetrievalMethods) -> Self {
+        self.device_retrieval_methods = Some(methods);
+        self
+    }
+
+    /// Offer `methods` to the reader in the device engagement.
+    pub fn with_server_retrieval_methods(mut self, methods: ServerRetrievalMethods) -> Self {
+        self.server_retrieval_methods = Some(methods);
+        self
+    }
+
+    /// Begin a session with QR code engagement, returning the URI to show to the reader.
+    pub fn engage(&self) -> anyhow::Result<(EngagedWallet<S>, String)> {
+        let (engaged, qr_code_uri) = SessionManagerInit::initialise(
+            self.documents.clone(),
+            #[cfg(feature = "transports")]
+            self.device_retrieval_methods.clone(),
+            self.server_retrieval_methods.clone(),
+        )?
+        .qr_engagement()?;
+        let wallet = EngagedWallet {
+            session: Session::Engaged(Box::new(engaged)),
+            signer: self.signer.clone(),
+            requested: Vec::new(),
+        };
+        Ok((wallet, qr_code_uri))
+    }
+}
+
+impl<S> EngagedWallet<S>
+where
+    S: MdocSigner,
+{
+    /// Handle a message from the reader: the session establishment first, then any further
+    /// requests. Returns the requested items, to present to the holder for consent.
+    ///
+    /// If the session establishment is rejected, the wallet remains engaged, awaiting another.
+    pub fn receive_request(&mut self, request: &[u8]) -> anyhow::Result<&RequestedItems> {
+        match &mut self.session {
+            Session::Engaged(engaged) => {
+                let session_establishment: SessionEstablishment = serde_cbor::from_slice(request)?;
+                let (session_manager, requested) = engaged
+                    .as_ref()
+                    .clone()
+                    .process_session_establishment(session_establishment)?;
+                self.session = Session::Established(Box::new(session_manager));
+                self.requested = requested;
+            }
+            Session::Established(session_manager) => {
+                self.requested = session_manager.handle_request(request)?;
+            }
+        }
+        Ok(&self.requested)
+    }
+
+    /// Respond to the last request with the `permitted` elements, signing the device
+    /// authentication of each document. Returns the message to send to the reader.
+    pub fn respond(&mut self, permitted: PermittedItems) -> anyhow::Result<Vec<u8>> {
+        let Session::Established(session_manager) = &mut self.session else {
+            return Err(Error::ApiMisuse.into());
+        };
+        session_manager.prepare_response(&self.requested, permitted);
+        session_manager.sign_response(&self.signer)?;
+        session_manager
+            .retrieve_response()
+            .ok_or_else(|| Error::ApiMisuse.into())
+    }
+
+    /// End the session, returning the message that informs the reader of the termination.
+    pub fn terminate(&mut self) -> anyhow::Result<Vec<u8>> {
+        match &mut self.session {
+            Session::Established(session_manager) => Ok(session_manager.terminate()),
+            _ => Err(Error::ApiMisuse.into()),
+        }
+    }
+
+    /// The underlying session, once the reader has established it.
+    pub fn session_manager(&self) -> Option<&SessionManager> {
+        match &self.session {
+            Session::Established(session_manager) => Some(session_manager),
+            _ => None,
+        }
+    }
+}
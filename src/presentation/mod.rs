@@ -1,5 +1,9 @@
+#[cfg(feature = "transports")]
+pub mod ble;
 pub mod device;
 pub mod reader;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 use anyhow::Result;
 use base64::{decode, encode};
@@ -25,9 +29,149 @@ impl Stringify for device::SessionManagerEngaged {}
 impl Stringify for device::SessionManager {}
 impl Stringify for reader::SessionManager {}
 
-use crate::definitions::{device_key::cose_key::CoseKey, helpers::Tag24};
+use crate::definitions::{
+    device_key::cose_key::CoseKey,
+    device_request::DeviceRequest,
+    helpers::Tag24,
+    x509::x5chain::{EcPublicKey, PublicKeyKind, X509, X5CHAIN_HEADER_LABEL},
+    DeviceResponse,
+};
+#[cfg(feature = "transports")]
+use crate::definitions::{BleOptions, DeviceEngagement, DeviceRetrievalMethod};
+use cose_rs::{
+    algorithm::{Algorithm, SignatureAlgorithm},
+    CoseSign1,
+};
 use hkdf::Hkdf;
 use sha2::Sha256;
+#[cfg(feature = "transports")]
+use uuid::Uuid;
+
+/// Bounds on the size of messages received from the other party, checked before they are
+/// decrypted and again once they are decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodeLimits {
+    /// The largest encoded message accepted, in bytes.
+    pub max_message_bytes: usize,
+    /// The most documents, or document requests, in one message.
+    pub max_documents: usize,
+    /// The most data elements, across all documents, in one message.
+    pub max_elements: usize,
+    /// The most certificates in any one x5chain.
+    pub max_certificates: usize,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_message_bytes: 16 * 1024 * 1024,
+            max_documents: 32,
+            max_elements: 1024,
+            max_certificates: 8,
+        }
+    }
+}
+
+/// A message received from the other party exceeded one of its [DecodeLimits].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum LimitExceeded {
+    #[error("the message is {0} bytes, more than the limit")]
+    MessageBytes(usize),
+    #[error("the message contains {0} documents, more than the limit")]
+    Documents(usize),
+    #[error("the message contains {0} data elements, more than the limit")]
+    Elements(usize),
+    #[error("an x5chain contains {0} certificates, more than the limit")]
+    Certificates(usize),
+}
+
+impl DecodeLimits {
+    fn check_message(&self, message: &[u8]) -> Result<(), LimitExceeded> {
+        check(
+            message.len(),
+            self.max_message_bytes,
+            LimitExceeded::MessageBytes,
+        )
+    }
+
+    fn check_response(&self, response: &DeviceResponse) -> Result<(), LimitExceeded> {
+        let documents = response
+            .documents
+            .as_ref()
+            .map(|documents| &documents[..])
+            .unwrap_or_default();
+        check(
+            documents.len(),
+            self.max_documents,
+            LimitExceeded::Documents,
+        )?;
+        let elements = documents
+            .iter()
+            .map(|document| {
+                let issuer_signed = document
+                    .issuer_signed
+                    .namespaces
+                    .iter()
+                    .flat_map(|namespaces| namespaces.values())
+                    .map(|items| items.len())
+                    .sum::<usize>();
+                let device_signed = document
+                    .device_signed
+                    .namespaces
+                    .as_ref()
+                    .values()
+                    .map(|items| items.len())
+                    .sum::<usize>();
+                issuer_signed + device_signed
+            })
+            .sum();
+        check(elements, self.max_elements, LimitExceeded::Elements)?;
+        documents
+            .iter()
+            .try_for_each(|document| self.check_x5chain(&document.issuer_signed.issuer_auth))
+    }
+
+    fn check_request(&self, request: &DeviceRequest) -> Result<(), LimitExceeded> {
+        let doc_requests = &request.doc_requests;
+        check(
+            doc_requests.len(),
+            self.max_documents,
+            LimitExceeded::Documents,
+        )?;
+        let elements = doc_requests
+            .iter()
+            .flat_map(|doc_request| doc_request.items_request.as_ref().namespaces.values())
+            .map(|elements| elements.len())
+            .sum();
+        check(elements, self.max_elements, LimitExceeded::Elements)?;
+        doc_requests
+            .iter()
+            .filter_map(|doc_request| doc_request.reader_auth.as_ref())
+            .try_for_each(|reader_auth| self.check_x5chain(reader_auth))
+    }
+
+    fn check_x5chain(&self, cose_sign1: &CoseSign1) -> Result<(), LimitExceeded> {
+        match cose_sign1.unprotected().get_i(X5CHAIN_HEADER_LABEL) {
+            Some(serde_cbor::Value::Array(certificates)) => check(
+                certificates.len(),
+                self.max_certificates,
+                LimitExceeded::Certificates,
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn check(
+    count: usize,
+    limit: usize,
+    exceeded: fn(usize) -> LimitExceeded,
+) -> Result<(), LimitExceeded> {
+    if count > limit {
+        return Err(exceeded(count));
+    }
+    Ok(())
+}
 
 fn calculate_ble_ident(e_device_key: &Tag24<CoseKey>) -> Result<[u8; 16]> {
     let e_device_key_bytes = serde_cbor::to_vec(e_device_key)?;
@@ -39,3 +183,247 @@ fn calculate_ble_ident(e_device_key: &Tag24<CoseKey>) -> Result<[u8; 16]> {
 
     Ok(ble_ident)
 }
+
+/// The UUID of the first BLE retrieval method in `device_engagement` that offers the mode
+/// selected by `mode`.
+#[cfg(feature = "transports")]
+fn first_ble_uuid(
+    device_engagement: &DeviceEngagement,
+    mode: impl Fn(&BleOptions) -> Option<&Uuid>,
+) -> Option<&Uuid> {
+    device_engagement
+        .device_retrieval_methods
+        .as_ref()?
+        .iter()
+        .filter_map(|m| match m {
+            DeviceRetrievalMethod::BLE(opt) => mode(opt),
+            _ => None,
+        })
+        .next()
+}
+
+#[cfg(feature = "transports")]
+fn first_central_client_uuid(device_engagement: &DeviceEngagement) -> Option<&Uuid> {
+    first_ble_uuid(device_engagement, |opt| {
+        opt.central_client_mode.as_ref().map(|cc| &cc.uuid)
+    })
+}
+
+#[cfg(feature = "transports")]
+fn first_peripheral_server_uuid(device_engagement: &DeviceEngagement) -> Option<&Uuid> {
+    first_ble_uuid(device_engagement, |opt| {
+        opt.peripheral_server_mode.as_ref().map(|ps| &ps.uuid)
+    })
+}
+
+/// A P-521 verifying key, wrapped so that it can implement [SignatureAlgorithm].
+struct P521VerifyingKey(p521::ecdsa::VerifyingKey);
+
+impl signature::Verifier<p521::ecdsa::Signature> for P521VerifyingKey {
+    fn verify(
+        &self,
+        message: &[u8],
+        signature: &p521::ecdsa::Signature,
+    ) -> Result<(), signature::Error> {
+        self.0.verify(message, signature)
+    }
+}
+
+impl SignatureAlgorithm for P521VerifyingKey {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::ES512
+    }
+}
+
+/// The COSE header label of the signature algorithm.
+const ALG_HEADER_LABEL: i128 = 1;
+
+/// The algorithm declared in the protected header of a signature is not that of the key that
+/// verifies it.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("the signature declares algorithm {declared:?}, but is verified with an {expected:?} key")]
+pub struct AlgorithmMismatch {
+    /// The declared algorithm, or `None` if it is missing or unrecognised.
+    pub declared: Option<Algorithm>,
+    pub expected: Algorithm,
+}
+
+/// Check that the protected header of `cose_sign1` declares `expected`, the algorithm of the
+/// verifying key, before the signature is checked, so that a signature is never verified under
+/// an algorithm other than the one it claims.
+fn check_algorithm(cose_sign1: &CoseSign1, expected: Algorithm) -> Result<(), AlgorithmMismatch> {
+    let declared = match cose_sign1.protected().get_i(ALG_HEADER_LABEL) {
+        Some(serde_cbor::Value::Integer(alg)) => match alg {
+            -7 => Some(Algorithm::ES256),
+            -35 => Some(Algorithm::ES384),
+            -36 => Some(Algorithm::ES512),
+            -8 => Some(Algorithm::EdDSA),
+            -37 => Some(Algorithm::PS256),
+            -38 => Some(Algorithm::PS384),
+            -39 => Some(Algorithm::PS512),
+            _ => None,
+        },
+        _ => None,
+    };
+    if declared == Some(expected) {
+        Ok(())
+    } else {
+        Err(AlgorithmMismatch { declared, expected })
+    }
+}
+
+/// Verify `cose_sign1` with the public key of `certificate`, over `detached_payload` where the
+/// payload is detached.
+fn verify_with_certificate(
+    cose_sign1: &CoseSign1,
+    certificate: &X509,
+    detached_payload: Option<Vec<u8>>,
+) -> Result<()> {
+    let verified = match certificate.public_key_kind()? {
+        PublicKeyKind::Ec(EcPublicKey::P256(key)) => {
+            check_algorithm(cose_sign1, Algorithm::ES256)?;
+            cose_sign1
+                .verify::<p256::ecdsa::VerifyingKey, p256::ecdsa::Signature>(
+                    &key.into(),
+                    detached_payload,
+                    None,
+                )
+                .success()
+        }
+        PublicKeyKind::Ec(EcPublicKey::P384(key)) => {
+            check_algorithm(cose_sign1, Algorithm::ES384)?;
+            cose_sign1
+                .verify::<p384::ecdsa::VerifyingKey, p384::ecdsa::Signature>(
+                    &key.into(),
+                    detached_payload,
+                    None,
+                )
+                .success()
+        }
+        PublicKeyKind::Ec(EcPublicKey::P521(key)) => {
+            check_algorithm(cose_sign1, Algorithm::ES512)?;
+            cose_sign1
+                .verify::<P521VerifyingKey, p521::ecdsa::Signature>(
+                    &P521VerifyingKey(p521::ecdsa::VerifyingKey::from_affine(*key.as_affine())?),
+                    detached_payload,
+                    None,
+                )
+                .success()
+        }
+        _ => anyhow::bail!("unsupported signing key"),
+    };
+    if !verified {
+        anyhow::bail!("signature verification failed");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::definitions::{
+        device_request::{DataElements, DocRequest, ItemsRequest, Namespaces},
+        helpers::NonEmptyVec,
+    };
+
+    fn device_request(elements: usize) -> DeviceRequest {
+        let elements = (0..elements)
+            .map(|i| (format!("element_{i}"), false))
+            .collect::<std::collections::BTreeMap<_, _>>();
+        let items_request = ItemsRequest {
+            doc_type: "org.iso.18013.5.1.mDL".into(),
+            namespaces: Namespaces::new("org.iso.18013.5.1".into(), DataElements::from(elements)),
+            request_info: None,
+        };
+        DeviceRequest {
+            version: "1.0".into(),
+            doc_requests: NonEmptyVec::new(DocRequest {
+                items_request: Tag24::new(items_request).unwrap(),
+                reader_auth: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn request_limits() {
+        let limits = DecodeLimits {
+            max_documents: 1,
+            max_elements: 4,
+            ..Default::default()
+        };
+        assert!(limits.check_request(&device_request(4)).is_ok());
+        assert_eq!(
+            limits.check_request(&device_request(5)),
+            Err(LimitExceeded::Elements(5))
+        );
+
+        let mut request = device_request(1);
+        request.doc_requests.push(request.doc_requests[0].clone());
+        assert_eq!(
+            limits.check_request(&request),
+            Err(LimitExceeded::Documents(2))
+        );
+    }
+
+    #[test]
+    fn message_limit() {
+        let limits = DecodeLimits {
+            max_message_bytes: 8,
+            ..Default::default()
+        };
+        assert!(limits.check_message(&[0; 8]).is_ok());
+        assert_eq!(
+            limits.check_message(&[0; 9]),
+            Err(LimitExceeded::MessageBytes(9))
+        );
+    }
+
+    #[test]
+    fn algorithm_mismatch() {
+        use p384::pkcs8::DecodePrivateKey;
+        use signature::Signer;
+
+        let signer: p384::ecdsa::SigningKey =
+            p384::SecretKey::from_pkcs8_pem(include_str!("../../test/issuance/384-key.pem"))
+                .unwrap()
+                .into();
+        let certificate =
+            X509::from_pem(include_bytes!("../../test/issuance/384-cert.pem")).unwrap();
+        let sign = |algorithm| {
+            let prepared = CoseSign1::builder()
+                .payload(b"payload".to_vec())
+                .signature_algorithm(algorithm)
+                .prepare()
+                .unwrap();
+            let signature: p384::ecdsa::Signature = signer.sign(prepared.signature_payload());
+            prepared.finalize(signature.to_vec())
+        };
+
+        verify_with_certificate(&sign(Algorithm::ES384), &certificate, None).unwrap();
+        // Signed by the P-384 key, but declaring ES256.
+        let error = verify_with_certificate(&sign(Algorithm::ES256), &certificate, None)
+            .expect_err("mismatched algorithm accepted");
+        assert_eq!(
+            error.downcast_ref::<AlgorithmMismatch>(),
+            Some(&AlgorithmMismatch {
+                declared: Some(Algorithm::ES256),
+                expected: Algorithm::ES384,
+            })
+        );
+    }
+
+    #[test]
+    fn undeclared_algorithm() {
+        // A COSE_Sign1 whose protected header is an empty map.
+        let cose_sign1: CoseSign1 =
+            serde_cbor::from_slice(&[0x84, 0x41, 0xa0, 0xa0, 0x43, 0x01, 0x02, 0x03, 0x41, 0x00])
+                .unwrap();
+        assert_eq!(
+            check_algorithm(&cose_sign1, Algorithm::ES256),
+            Err(AlgorithmMismatch {
+                declared: None,
+                expected: Algorithm::ES256,
+            })
+        );
+    }
+}
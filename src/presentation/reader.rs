@@ -1,34 +1,136 @@
+use super::{DecodeLimits, LimitExceeded};
+#[cfg(feature = "transports")]
+use crate::definitions::DeviceRetrievalMethod;
 use crate::definitions::{
-    device_engagement::DeviceRetrievalMethod,
-    device_request::{self, DeviceRequest, DocRequest, ItemsRequest},
-    helpers::{NonEmptyVec, Tag24},
+    device_engagement::{self, ServerRetrievalMethods},
+    device_request::{
+        self, DeviceRequest, DocRequest, DocType, ItemsRequest, ItemsRequestBytes, ReaderAuth,
+        ReaderAuthentication,
+    },
+    device_response::{Document, DocumentErrorCode},
+    device_signed::{self, DeviceAuth, DeviceAuthentication},
+    helpers::{cbor::is_canonical, tag24, NonEmptyMap, Tag24},
+    namespaces::org_iso_18013_5_1::{Portrait, PortraitError},
     session::{
-        self, create_p256_ephemeral_keys, derive_session_key, get_shared_secret, Handover,
-        SessionEstablishment,
+        self, create_p256_ephemeral_keys, get_shared_secret, Handover, KeyDerivationProfile,
+        SessionError, SessionEstablishment, SessionTranscript, Status,
+    },
+    traits::{cbor_to_json, FromCbor, FromJsonError},
+    x509::{
+        x5chain::{X5Chain, X5CHAIN_HEADER_LABEL},
+        TrustAnchorRegistry,
     },
-    DeviceEngagement, DeviceResponse, SessionData, SessionTranscript180135,
+    CoseKey, DeviceEngagement, DeviceResponse, DigestId, EC2Curve, IssuerSigned, Mso, SessionData,
+    SessionTranscript180135, ValidityInfo,
 };
 use anyhow::{anyhow, Result};
+use cose_rs::{
+    algorithm::{Algorithm, SignatureAlgorithm},
+    CoseSign1,
+};
 use serde::{Deserialize, Serialize};
 use serde_cbor::Value as CborValue;
 use serde_json::json;
 use serde_json::Value;
+use signature::{SignatureEncoding, Signer};
 use std::collections::BTreeMap;
+use subtle::ConstantTimeEq;
+use time::OffsetDateTime;
+#[cfg(feature = "transports")]
 use uuid::Uuid;
+use zeroize::Zeroizing;
+
+mod verifier;
+pub use verifier::Verifier;
+
+/// The document type of an mDL.
+const MDL_DOC_TYPE: &str = "org.iso.18013.5.1.mDL";
+/// The namespace of the elements defined for an mDL.
+const MDL_NAMESPACE: &str = "org.iso.18013.5.1";
+
+/// The disclosed elements of an mDL, by namespace, then element identifier.
+pub type ParsedResponse = BTreeMap<String, BTreeMap<String, Value>>;
+
+/// Element-level errors reported by the device, by document type, then namespace, then element
+/// identifier.
+pub type ElementErrors = BTreeMap<String, BTreeMap<String, BTreeMap<String, DocumentErrorCode>>>;
 
 #[derive(Serialize, Deserialize)]
 pub struct SessionManager {
     session_transcript: SessionTranscript180135,
-    sk_device: [u8; 32],
+    sk_device: Zeroizing<[u8; 32]>,
+    /// The counter of the last message received from the device.
     device_message_counter: u32,
-    sk_reader: [u8; 32],
+    sk_reader: Zeroizing<[u8; 32]>,
+    /// The counter of the last message sent by the reader.
     reader_message_counter: u32,
+    /// The reader's ephemeral private key, for verifying MAC device authentication.
+    #[serde(default = "no_e_reader_key")]
+    e_reader_key: Zeroizing<[u8; 32]>,
+    /// Whether either party has ended the session.
+    #[serde(default)]
+    terminated: bool,
+    /// The elements last requested, by document type.
+    #[serde(default)]
+    requested: BTreeMap<DocType, device_request::Namespaces>,
+    /// Bounds on the size of responses from the device.
+    #[serde(default)]
+    limits: DecodeLimits,
+    /// Whether to reject a mobile security object that is not canonically encoded.
+    #[serde(default)]
+    strict_cbor: bool,
+    /// The issuers trusted when a response arrives. This is not persisted with the session.
+    #[serde(skip)]
+    trust_anchor_registry: Option<TrustAnchorRegistry>,
+}
+
+/// The ephemeral reader key of a session persisted before the key was kept, with which MAC device
+/// authentication cannot be verified.
+fn no_e_reader_key() -> Zeroizing<[u8; 32]> {
+    Zeroizing::new([0; 32])
 }
 
+/// A failure to establish a session from a device engagement.
 #[derive(Debug, thiserror::Error)]
-pub enum Error {
+pub enum EngagementError {
     #[error("the qr code had the wrong prefix or the contained data could not be decoded: {0}")]
     InvalidQrCode(anyhow::Error),
+    #[error("the device engagement could not be decoded: {0}")]
+    InvalidDeviceEngagement(tag24::Error),
+    #[error("the device engagement offers an unsupported cipher suite: {0}")]
+    UnsupportedCipherSuite(device_engagement::Error),
+    #[error("unable to generate ephemeral key: {0}")]
+    EKeyGeneration(session::Error),
+    #[error("unable to calculate the BLE ident: {0}")]
+    BleIdent(anyhow::Error),
+    #[error("unable to derive session keys: {0}")]
+    KeyDerivation(anyhow::Error),
+    #[error("error encoding value to CBOR: {0}")]
+    Tag24CborEncoding(tag24::Error),
+    #[error("error encoding value to CBOR: {0}")]
+    CborEncoding(serde_cbor::Error),
+    #[error(transparent)]
+    Request(#[from] RequestError),
+}
+
+/// A failure to build a device request.
+#[derive(Debug, thiserror::Error)]
+pub enum RequestError {
+    #[error("Request for data is invalid.")]
+    InvalidRequest,
+    #[error("error encoding value to CBOR: {0}")]
+    Tag24CborEncoding(tag24::Error),
+    #[error("error encoding value to CBOR: {0}")]
+    CborEncoding(serde_cbor::Error),
+    #[error("unable to authenticate request: {0}")]
+    ReaderAuth(anyhow::Error),
+    #[error("unable to encrypt request: {0}")]
+    Encryption(SessionError),
+}
+
+/// A failure to handle a device response.
+#[derive(Debug, thiserror::Error)]
+pub enum ResponseError {
     #[error("Device did not transmit any data.")]
     DeviceTransmissionError,
     #[error("Device did not transmit an mDL.")]
@@ -39,27 +141,51 @@ pub enum Error {
     IncorrectNamespace,
     #[error("device responded with an error.")]
     HolderError,
-    #[error("could not decrypt the response.")]
-    DecryptionError,
+    #[error("could not read the session data: {0}")]
+    Session(#[from] SessionError),
     #[error("Unexpected CBOR type for offered value")]
     CborDecodingError,
     #[error("not a valid JSON input.")]
     JsonError,
     #[error("Unexpected date type for data_element.")]
     ParsingError,
-    #[error("Request for data is invalid.")]
-    InvalidRequest,
+    #[error("the issuer authentication of a document has no mobile security object.")]
+    InvalidIssuerAuth,
+    #[error("disclosed elements do not match the digests signed by the issuer: {0:?}")]
+    DigestMismatch(Vec<DigestMismatch>),
+    #[error("the session has been terminated.")]
+    SessionEnded,
+    #[error("the message does not terminate the session.")]
+    NotATermination,
+    #[error("the issuer of a document is not trusted: {0}")]
+    UntrustedIssuer(String),
+    #[error("the issuer signature of a document is invalid: {0}")]
+    InvalidIssuerSignature(String),
+    /// The device signature or MAC over a document did not verify against the device key in
+    /// its mobile security object. The key is not compared with the EDeviceKey from the
+    /// engagement, which is ephemeral, so a device bound to another key, a corrupt signature
+    /// and a wrong transcript are all reported here; the cause is carried in the message.
+    #[error("device authentication of a document failed: {0}")]
+    DeviceAuthenticationFailed(String),
+    #[error("the response is too large: {0}")]
+    LimitExceeded(#[from] LimitExceeded),
+    #[error("no request has been made to the device.")]
+    NoSession,
+    #[error("the mobile security object is not canonically encoded.")]
+    NonCanonicalCbor,
+    #[error(transparent)]
+    Validity(#[from] ValidityError),
 }
 
-impl From<serde_cbor::Error> for Error {
+impl From<serde_cbor::Error> for ResponseError {
     fn from(_: serde_cbor::Error) -> Self {
-        Error::CborDecodingError
+        ResponseError::CborDecodingError
     }
 }
 
-impl From<serde_json::Error> for Error {
+impl From<serde_json::Error> for ResponseError {
     fn from(_: serde_json::Error) -> Self {
-        Error::JsonError
+        ResponseError::JsonError
     }
 }
 
@@ -67,40 +193,159 @@ impl SessionManager {
     pub fn establish_session(
         qr_code: String,
         namespaces: device_request::Namespaces,
-    ) -> Result<(Self, Vec<u8>, [u8; 16])> {
+    ) -> Result<(Self, Vec<u8>, [u8; 16]), EngagementError> {
+        Self::establish_session_with_reader_auth(qr_code, mdl_request(namespaces), |_, _| Ok(None))
+    }
+
+    /// Establish a session as in [SessionManager::establish_session], requesting elements from
+    /// each of several document types.
+    pub fn establish_session_for_documents(
+        qr_code: String,
+        requests: NonEmptyMap<DocType, device_request::Namespaces>,
+    ) -> Result<(Self, Vec<u8>, [u8; 16]), EngagementError> {
+        Self::establish_session_with_reader_auth(qr_code, requests, |_, _| Ok(None))
+    }
+
+    /// Establish a session with an mdoc engaged over NFC, from the device engagement in its
+    /// handover select message, and the handover request message sent to it, if any.
+    pub fn establish_session_nfc(
+        device_engagement_bytes: Vec<u8>,
+        handover_select: Vec<u8>,
+        handover_request: Option<Vec<u8>>,
+        namespaces: device_request::Namespaces,
+    ) -> Result<(Self, Vec<u8>, [u8; 16]), EngagementError> {
         let device_engagement_bytes =
-            Tag24::<DeviceEngagement>::from_qr_code_uri(&qr_code).map_err(Error::InvalidQrCode)?;
+            Tag24::<DeviceEngagement>::from_bytes(device_engagement_bytes)
+                .map_err(EngagementError::InvalidDeviceEngagement)?;
+        let handover = Handover::NFC(handover_select.into(), handover_request.map(Into::into));
+        Self::establish_session_with_handover(
+            device_engagement_bytes,
+            handover,
+            mdl_request(namespaces),
+            &KeyDerivationProfile::default(),
+            |_, _| Ok(None),
+        )
+    }
+
+    /// Establish a session as in [SessionManager::establish_session], deriving the session keys
+    /// under `profile`, such as that of a remote presentation, rather than as in proximity
+    /// presentation.
+    pub fn establish_session_with_key_derivation(
+        qr_code: String,
+        namespaces: device_request::Namespaces,
+        profile: &KeyDerivationProfile,
+    ) -> Result<(Self, Vec<u8>, [u8; 16]), EngagementError> {
+        let device_engagement_bytes = Tag24::<DeviceEngagement>::from_qr_code_uri(&qr_code)
+            .map_err(EngagementError::InvalidQrCode)?;
+        Self::establish_session_with_handover(
+            device_engagement_bytes,
+            Handover::QR,
+            mdl_request(namespaces),
+            profile,
+            |_, _| Ok(None),
+        )
+    }
+
+    /// Establish a session as in [SessionManager::establish_session], authenticating the
+    /// request with `signer`, whose certificate is the first in `x5chain`.
+    pub fn establish_session_signed<S, Sig>(
+        qr_code: String,
+        namespaces: device_request::Namespaces,
+        signer: S,
+        x5chain: X5Chain,
+    ) -> Result<(Self, Vec<u8>, [u8; 16]), EngagementError>
+    where
+        S: Signer<Sig> + SignatureAlgorithm,
+        Sig: SignatureEncoding,
+    {
+        Self::establish_session_with_reader_auth(
+            qr_code,
+            mdl_request(namespaces),
+            |session_transcript, items_request| {
+                sign_reader_auth(session_transcript, items_request, &signer, &x5chain).map(Some)
+            },
+        )
+    }
+
+    fn establish_session_with_reader_auth(
+        qr_code: String,
+        requests: NonEmptyMap<DocType, device_request::Namespaces>,
+        reader_auth: impl FnMut(
+            &SessionTranscript180135,
+            &ItemsRequestBytes,
+        ) -> Result<Option<ReaderAuth>>,
+    ) -> Result<(Self, Vec<u8>, [u8; 16]), EngagementError> {
+        let device_engagement_bytes = Tag24::<DeviceEngagement>::from_qr_code_uri(&qr_code)
+            .map_err(EngagementError::InvalidQrCode)?;
+        Self::establish_session_with_handover(
+            device_engagement_bytes,
+            Handover::QR,
+            requests,
+            &KeyDerivationProfile::default(),
+            reader_auth,
+        )
+    }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    fn establish_session_with_handover(
+        device_engagement_bytes: Tag24<DeviceEngagement>,
+        handover: Handover,
+        requests: NonEmptyMap<DocType, device_request::Namespaces>,
+        key_derivation: &KeyDerivationProfile,
+        reader_auth: impl FnMut(
+            &SessionTranscript180135,
+            &ItemsRequestBytes,
+        ) -> Result<Option<ReaderAuth>>,
+    ) -> Result<(Self, Vec<u8>, [u8; 16]), EngagementError> {
         //generate own keys
-        let key_pair = create_p256_ephemeral_keys()?;
+        let key_pair = create_p256_ephemeral_keys().map_err(EngagementError::EKeyGeneration)?;
         let e_reader_key_private = key_pair.0;
-        let e_reader_key_public = Tag24::new(key_pair.1)?;
+        let e_reader_key_public =
+            Tag24::new(key_pair.1).map_err(EngagementError::Tag24CborEncoding)?;
 
         //decode device_engagement
         let device_engagement = device_engagement_bytes.as_ref();
+        device_engagement
+            .security
+            .validate()
+            .map_err(EngagementError::UnsupportedCipherSuite)?;
         let e_device_key = &device_engagement.security.1;
 
         // calculate ble Ident value
-        let ble_ident = super::calculate_ble_ident(e_device_key)?;
+        let ble_ident =
+            super::calculate_ble_ident(e_device_key).map_err(EngagementError::BleIdent)?;
 
         // derive shared secret
         let shared_secret = get_shared_secret(
             e_device_key.clone().into_inner(),
-            &e_reader_key_private.into(),
-        )?;
+            &e_reader_key_private.to_nonzero_scalar(),
+        )
+        .map_err(EngagementError::KeyDerivation)?;
 
         let session_transcript = SessionTranscript180135(
             device_engagement_bytes,
             e_reader_key_public.clone(),
-            Handover::QR,
+            handover,
         );
 
-        let session_transcript_bytes = Tag24::new(session_transcript.clone())?;
+        let session_transcript_bytes =
+            Tag24::new(session_transcript.clone()).map_err(EngagementError::Tag24CborEncoding)?;
 
         //derive session keys
-        let sk_reader = derive_session_key(&shared_secret, &session_transcript_bytes, true)?.into();
-        let sk_device =
-            derive_session_key(&shared_secret, &session_transcript_bytes, false)?.into();
+        let sk_reader = Zeroizing::new(
+            key_derivation
+                .derive_session_key(&shared_secret, &session_transcript_bytes, true)
+                .map_err(EngagementError::KeyDerivation)?
+                .into(),
+        );
+        let sk_device = Zeroizing::new(
+            key_derivation
+                .derive_session_key(&shared_secret, &session_transcript_bytes, false)
+                .map_err(EngagementError::KeyDerivation)?
+                .into(),
+        );
+        #[cfg(feature = "tracing")]
+        tracing::debug!("derived session keys");
 
         let mut session_manager = Self {
             session_transcript,
@@ -108,148 +353,1103 @@ impl SessionManager {
             device_message_counter: 0,
             sk_reader,
             reader_message_counter: 0,
+            e_reader_key: Zeroizing::new(e_reader_key_private.to_bytes().into()),
+            terminated: false,
+            requested: BTreeMap::new(),
+            limits: DecodeLimits::default(),
+            strict_cbor: false,
+            trust_anchor_registry: None,
         };
 
-        let request = session_manager.build_request(namespaces)?;
+        let request = session_manager.build_request_with_reader_auth(requests, reader_auth)?;
         let session = SessionEstablishment {
             data: request.into(),
             e_reader_key: e_reader_key_public,
         };
-        let session_request = serde_cbor::to_vec(&session)?;
+        let session_request =
+            serde_cbor::to_vec(&session).map_err(EngagementError::CborEncoding)?;
 
         Ok((session_manager, session_request, ble_ident))
     }
 
-    pub fn first_central_client_uuid(&self) -> Option<&Uuid> {
+    /// The session transcript both parties authenticate their messages over.
+    pub fn session_transcript(&self) -> &SessionTranscript180135 {
+        &self.session_transcript
+    }
+
+    /// The session keys derived from the ephemeral keys and the session transcript.
+    #[cfg(feature = "test-vectors")]
+    pub fn session_keys(&self) -> crate::definitions::session::SessionKeys {
+        crate::definitions::session::SessionKeys {
+            sk_device: *self.sk_device,
+            sk_reader: *self.sk_reader,
+        }
+    }
+
+    /// The servers the mdoc offers to be retrieved from, if it offers server retrieval.
+    pub fn server_retrieval_methods(&self) -> Option<&ServerRetrievalMethods> {
+        self.session_transcript
+            .0
+            .as_ref()
+            .server_retrieval_methods
+            .as_ref()
+    }
+
+    /// The device retrieval methods offered by the mdoc, in order of its preference.
+    #[cfg(feature = "transports")]
+    pub fn device_retrieval_methods(&self) -> &[DeviceRetrievalMethod] {
         self.session_transcript
             .0
             .as_ref()
             .device_retrieval_methods
             .as_ref()
-            .and_then(|ms| {
-                ms.as_ref()
-                    .iter()
-                    .filter_map(|m| match m {
-                        DeviceRetrievalMethod::BLE(opt) => {
-                            opt.central_client_mode.as_ref().map(|cc| &cc.uuid)
-                        }
-                        _ => None,
-                    })
-                    .next()
-            })
+            .map(|methods| methods.as_ref())
+            .unwrap_or_default()
+    }
+
+    /// Select the first device retrieval method offered by the mdoc that the reader supports.
+    #[cfg(feature = "transports")]
+    pub fn select_device_retrieval_method(
+        &self,
+        supported: impl Fn(&DeviceRetrievalMethod) -> bool,
+    ) -> Option<&DeviceRetrievalMethod> {
+        self.device_retrieval_methods()
+            .iter()
+            .find(|method| supported(method))
+    }
+
+    /// The service UUID the reader advertises in mdoc central client mode.
+    #[cfg(feature = "transports")]
+    pub fn first_central_client_uuid(&self) -> Option<&Uuid> {
+        super::first_central_client_uuid(self.session_transcript.0.as_ref())
+    }
+
+    /// The service UUID the mdoc advertises in mdoc peripheral server mode. The reader should
+    /// also check the Ident characteristic of the service against the BLE ident returned by
+    /// [SessionManager::establish_session].
+    #[cfg(feature = "transports")]
+    pub fn first_peripheral_server_uuid(&self) -> Option<&Uuid> {
+        super::first_peripheral_server_uuid(self.session_transcript.0.as_ref())
     }
 
-    pub fn new_request(&mut self, namespaces: device_request::Namespaces) -> Result<Vec<u8>> {
-        let request = self.build_request(namespaces)?;
+    pub fn new_request(
+        &mut self,
+        namespaces: device_request::Namespaces,
+    ) -> Result<Vec<u8>, RequestError> {
+        let request = self.build_request(mdl_request(namespaces))?;
         let session = SessionData {
             data: Some(request.into()),
             status: None,
         };
-        serde_cbor::to_vec(&session).map_err(Into::into)
+        serde_cbor::to_vec(&session).map_err(RequestError::CborEncoding)
     }
 
-    fn build_request(&mut self, namespaces: device_request::Namespaces) -> Result<Vec<u8>> {
+    fn build_request(
+        &mut self,
+        requests: NonEmptyMap<DocType, device_request::Namespaces>,
+    ) -> Result<Vec<u8>, RequestError> {
+        self.build_request_with_reader_auth(requests, |_, _| Ok(None))
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    fn build_request_with_reader_auth(
+        &mut self,
+        requests: NonEmptyMap<DocType, device_request::Namespaces>,
+        mut reader_auth: impl FnMut(
+            &SessionTranscript180135,
+            &ItemsRequestBytes,
+        ) -> Result<Option<ReaderAuth>>,
+    ) -> Result<Vec<u8>, RequestError> {
         // if !validate_request(namespaces.clone()).is_ok() {
         //     return Err(anyhow::Error::msg(
         //         "At least one of the namespaces contain an invalid combination of fields to request",
         //     ));
         // }
-        let items_request = ItemsRequest {
-            doc_type: "org.iso.18013.5.1.mDL".into(),
-            namespaces,
-            request_info: None,
-        };
-        let doc_request = DocRequest {
-            reader_auth: None,
-            items_request: Tag24::new(items_request)?,
-        };
+        self.requested = requests.clone().into_inner();
+        let doc_requests = requests
+            .into_inner()
+            .into_iter()
+            .map(|(doc_type, namespaces)| {
+                let items_request = Tag24::new(ItemsRequest {
+                    doc_type,
+                    namespaces,
+                    request_info: None,
+                })
+                .map_err(RequestError::Tag24CborEncoding)?;
+                Ok(DocRequest {
+                    reader_auth: reader_auth(&self.session_transcript, &items_request)
+                        .map_err(RequestError::ReaderAuth)?,
+                    items_request,
+                })
+            })
+            .collect::<Result<Vec<_>, RequestError>>()?;
         let device_request = DeviceRequest {
             version: DeviceRequest::VERSION.to_string(),
-            doc_requests: NonEmptyVec::new(doc_request),
+            // Safe to unwrap as there is one request for each entry of a NonEmptyMap.
+            doc_requests: doc_requests.try_into().unwrap(),
         };
-        let device_request_bytes = serde_cbor::to_vec(&device_request)?;
+        let device_request_bytes =
+            serde_cbor::to_vec(&device_request).map_err(RequestError::CborEncoding)?;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(request_len = device_request_bytes.len(), "built request");
         session::encrypt_reader_data(
-            &self.sk_reader.into(),
+            &(*self.sk_reader).into(),
             &device_request_bytes,
             &mut self.reader_message_counter,
         )
-        .map_err(|e| anyhow!("unable to encrypt request: {}", e))
+        .map_err(RequestError::Encryption)
     }
 
+    /// Trust the issuers in `registry` when handling and validating responses.
+    pub fn set_trust_anchor_registry(&mut self, registry: TrustAnchorRegistry) {
+        self.trust_anchor_registry = Some(registry);
+    }
+
+    /// Reject responses from the device that exceed `limits`, rather than the defaults.
+    pub fn set_decode_limits(&mut self, limits: DecodeLimits) {
+        self.limits = limits;
+    }
+
+    /// Reject a document whose mobile security object, the payload signed by its issuer, is
+    /// not canonically encoded, as an issuer that signs ambiguous encodings may be relied on for
+    /// a different reading of them. Off by default, for interoperability with issuers that do not
+    /// encode canonically.
+    pub fn set_strict_cbor(&mut self, strict_cbor: bool) {
+        self.strict_cbor = strict_cbor;
+    }
+
+    /// Decrypt and parse the mDL in a device response, checking that each document matches its
+    /// issuer's digests, that its mobile security object is within its validity window, and that
+    /// it was authenticated over this session by the device key its issuer bound it to.
+    ///
+    /// The issuer signature is verified against the document signer chain, which must chain to a
+    /// trust anchor in the registry set with [SessionManager::set_trust_anchor_registry]. Without
+    /// a registry, the signature is only verified against the document signer certificate that
+    /// the document carries itself, which does not authenticate the issuer: anyone can sign a
+    /// document that passes, so its claims must not be relied on.
     pub fn handle_response(
         &mut self,
         response: &[u8],
-    ) -> Result<BTreeMap<String, BTreeMap<String, Value>>, Error> {
-        let session_data: SessionData = serde_cbor::from_slice(response)?;
+    ) -> Result<BTreeMap<String, BTreeMap<String, Value>>, ResponseError> {
+        self.handle_response_with_errors(response)
+            .map(|(parsed_response, _errors)| parsed_response)
+    }
+
+    /// As [SessionManager::handle_response], additionally returning the element-level errors
+    /// the device reported for requested elements it did not return.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(response_len = response.len()))
+    )]
+    pub fn handle_response_with_errors(
+        &mut self,
+        response: &[u8],
+    ) -> Result<(ParsedResponse, ElementErrors), ResponseError> {
+        let response: DeviceResponse = serde_cbor::from_slice(&self.decrypt_response(response)?)?;
+        self.limits.check_response(&response)?;
+        for document in response
+            .documents
+            .iter()
+            .flat_map(|documents| documents.iter())
+        {
+            let issuer_auth = &document.issuer_signed.issuer_auth;
+            if self.strict_cbor {
+                check_canonical_mso(issuer_auth)?;
+            }
+            let mso = parse_mso(issuer_auth)?;
+            match &self.trust_anchor_registry {
+                Some(registry) => {
+                    verify_issuer_auth(issuer_auth, &mso, &document.doc_type, registry, None)
+                        .map_err(|e| ResponseError::UntrustedIssuer(e.to_string()))?
+                }
+                None => verify_issuer_signature(issuer_auth, &mso, &document.doc_type, None)
+                    .map_err(|e| ResponseError::InvalidIssuerSignature(e.to_string()))?,
+            }
+            verify_value_digests(document, &mso).map_err(ResponseError::DigestMismatch)?;
+            if let Some(error) = check_validity(&mso.validity_info, OffsetDateTime::now_utc()) {
+                return Err(error.into());
+            }
+            if let DeviceAuthResult::Invalid(reason) = self.device_auth(document) {
+                return Err(ResponseError::DeviceAuthenticationFailed(reason));
+            }
+        }
+        let errors = element_errors(&response);
+        Ok((parse_mdl_response(response)?, errors))
+    }
+
+    /// Decrypt the device response, and verify the issuer and device authentication of each
+    /// document it contains, trusting the issuers in `registry`.
+    pub fn validate_response(
+        &mut self,
+        response: &[u8],
+        registry: &TrustAnchorRegistry,
+    ) -> Result<ValidatedResponse, ResponseError> {
+        self.validate_response_at(response, registry, OffsetDateTime::now_utc())
+    }
+
+    /// As [SessionManager::validate_response], checking the validity window of each mobile
+    /// security object at `at` rather than now.
+    pub fn validate_response_at(
+        &mut self,
+        response: &[u8],
+        registry: &TrustAnchorRegistry,
+        at: OffsetDateTime,
+    ) -> Result<ValidatedResponse, ResponseError> {
+        let raw = self.decrypt_response(response)?;
+        validate_documents(raw, &self.limits, |document| {
+            self.validate_document(document, Some(registry), at)
+        })
+    }
+
+    /// As [SessionManager::validate_response], trusting the issuers in the registry set with
+    /// [SessionManager::set_trust_anchor_registry]. Without a registry, the issuer
+    /// authentication of each document is at best [AuthenticationStatus::Unverified].
+    pub fn validate(&mut self, response: &[u8]) -> Result<ValidatedResponse, ResponseError> {
+        let raw = self.decrypt_response(response)?;
+        validate_documents(raw, &self.limits, |document| {
+            self.validate_document(
+                document,
+                self.trust_anchor_registry.as_ref(),
+                OffsetDateTime::now_utc(),
+            )
+        })
+    }
+
+    /// Verify the device signature or MAC of `document` over this session, with the device key
+    /// from its mobile security object.
+    fn device_auth(&self, document: &Document) -> DeviceAuthResult {
+        match &document.device_signed.device_auth {
+            DeviceAuth::Signature { .. } => verify_device_auth(document, &self.session_transcript),
+            DeviceAuth::Mac { .. } => p256::SecretKey::from_bytes(&(*self.e_reader_key).into())
+                .map(|e_reader_key| {
+                    verify_device_mac(document, &self.session_transcript, &e_reader_key)
+                })
+                .unwrap_or_else(|_| {
+                    DeviceAuthResult::Invalid("the reader's ephemeral key is unavailable".into())
+                }),
+        }
+    }
+
+    fn validate_document(
+        &self,
+        document: Document,
+        registry: Option<&TrustAnchorRegistry>,
+        at: OffsetDateTime,
+    ) -> Result<ValidatedDocument, ResponseError> {
+        if self.strict_cbor {
+            check_canonical_mso(&document.issuer_signed.issuer_auth)?;
+        }
+        let device_auth = self.device_auth(&document);
+        let mut validated = validate_document(document, registry, device_auth, at)?;
+        validated.request_mismatches = request_mismatches(&self.requested, &validated);
+        Ok(validated)
+    }
+
+    /// Handle a message from the device ending the session.
+    ///
+    /// Once the session is terminated, any further responses are rejected with
+    /// [ResponseError::SessionEnded].
+    pub fn handle_termination(&mut self, message: &[u8]) -> Result<(), ResponseError> {
+        let session_data: SessionData = serde_cbor::from_slice(message)?;
+        match session_data.status {
+            Some(Status::SessionTermination) => {
+                self.terminated = true;
+                Ok(())
+            }
+            _ => Err(ResponseError::NotATermination),
+        }
+    }
+
+    /// Whether the session has been terminated.
+    pub fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+
+    fn decrypt_response(&mut self, response: &[u8]) -> Result<Vec<u8>, ResponseError> {
+        if self.terminated {
+            return Err(ResponseError::SessionEnded);
+        }
+        self.limits.check_message(response)?;
+        let session_data: SessionData =
+            serde_cbor::from_slice(response).map_err(|_| SessionError::CborDecoding)?;
         let encrypted_response = match session_data.data {
-            None => return Err(Error::HolderError),
+            None if matches!(session_data.status, Some(Status::SessionTermination)) => {
+                self.terminated = true;
+                return Err(ResponseError::SessionEnded);
+            }
+            None => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(status = ?session_data.status, "device responded without data");
+                return Err(ResponseError::HolderError);
+            }
             Some(r) => r,
         };
-        let decrypted_response = session::decrypt_device_data(
-            &self.sk_device.into(),
+        session::decrypt_device_data(
+            &(*self.sk_device).into(),
             encrypted_response.as_ref(),
             &mut self.device_message_counter,
         )
-        .map_err(|_e| Error::DecryptionError)?;
-        let response: DeviceResponse = serde_cbor::from_slice(&decrypted_response)?;
-        let mut core_namespace = BTreeMap::<String, serde_json::Value>::new();
-        let mut aamva_namespace = BTreeMap::<String, serde_json::Value>::new();
-        let mut parsed_response = BTreeMap::<String, BTreeMap<String, serde_json::Value>>::new();
+        .map_err(Into::into)
+    }
+}
 
-        let mut namespaces = response
-            .documents
-            .ok_or(Error::DeviceTransmissionError)?
-            .into_inner()
-            .into_iter()
-            .find(|doc| doc.doc_type == "org.iso.18013.5.1.mDL")
-            .ok_or(Error::DocumentTypeError)?
-            .issuer_signed
-            .namespaces
-            .ok_or(Error::NoMdlDataTransmission)?
-            .into_inner();
+/// The authenticated contents of a document in a [ValidatedResponse].
+#[derive(Debug, Clone)]
+pub struct ValidatedDocument {
+    pub doc_type: String,
+    /// The disclosed elements, by namespace, then element identifier.
+    pub namespaces: BTreeMap<String, BTreeMap<String, CborValue>>,
+    /// The elements asserted by the device itself, by namespace, then element identifier.
+    /// The issuer does not vouch for these: they are authenticated only by the device key.
+    pub device_signed_namespaces: BTreeMap<String, BTreeMap<String, CborValue>>,
+    /// The validity window of the mobile security object.
+    pub validity_info: ValidityInfo,
+    /// Whether the mobile security object is signed by a document signer that chains to a
+    /// trusted issuer.
+    pub issuer_auth: AuthenticationStatus,
+    /// Whether the document was authenticated by the device key in the mobile security object.
+    pub device_auth: DeviceAuthResult,
+    /// The disclosed elements that do not match the digests signed by the issuer.
+    pub digest_mismatches: Vec<DigestMismatch>,
+    /// Why the mobile security object was not valid when the response was validated, if it was
+    /// not.
+    pub validity_error: Option<ValidityError>,
+    /// The device signed elements that the key authorizations in the mobile security object do
+    /// not permit the device key to sign over.
+    pub unauthorized_elements: Vec<UnauthorizedElement>,
+    /// How the document differs from what the reader requested. These do not affect
+    /// [ValidatedDocument::is_verified], so that the caller decides whether they are acceptable.
+    pub request_mismatches: Vec<RequestMismatch>,
+}
 
-        namespaces
-            .remove("org.iso.18013.5.1")
-            .ok_or(Error::IncorrectNamespace)?
+/// Data returned by the device that the reader did not request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestMismatch {
+    /// A document of a type that was not requested.
+    UnexpectedDocType(String),
+    /// An element that was not requested from the document.
+    UnsolicitedElement {
+        namespace: String,
+        element_identifier: String,
+    },
+}
+
+/// The mobile security object was outside its validity window at the reference time `at`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum ValidityError {
+    #[error("the mobile security object expired at {valid_until}, before {at}")]
+    Expired {
+        valid_until: OffsetDateTime,
+        at: OffsetDateTime,
+    },
+    #[error("the mobile security object is not valid until {valid_from}, after {at}")]
+    NotYetValid {
+        valid_from: OffsetDateTime,
+        at: OffsetDateTime,
+    },
+}
+
+/// A disclosed element that has no digest in the mobile security object, or whose digest does
+/// not match the one signed by the issuer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DigestMismatch {
+    pub namespace: String,
+    pub element_identifier: String,
+    pub digest_id: DigestId,
+    /// The digest signed by the issuer, in hex, or `None` if the mobile security object has no
+    /// digest for the element.
+    pub expected_digest: Option<String>,
+    /// The digest of the disclosed element, in hex, or `None` if it could not be encoded.
+    pub actual_digest: Option<String>,
+}
+
+/// A device signed element that the device key is not authorized to sign over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnauthorizedElement {
+    pub namespace: String,
+    pub element_identifier: String,
+}
+
+/// The outcome of checking a signature over a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthenticationStatus {
+    Valid,
+    Invalid(String),
+    /// The signature is valid under the document signer certificate that the document carries,
+    /// but there were no trusted issuers to authenticate that certificate against, so the issuer
+    /// is unknown.
+    Unverified,
+}
+
+/// A device response whose documents have been authenticated.
+#[derive(Debug, Clone)]
+pub struct ValidatedResponse {
+    pub documents: Vec<ValidatedDocument>,
+    pub errors: ElementErrors,
+    /// The decrypted `DeviceResponse`, as sent by the device.
+    pub raw: Vec<u8>,
+}
+
+/// The outcome of verifying the device authentication of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceAuthResult {
+    /// The device signature is valid.
+    Signature,
+    /// The device MAC is valid.
+    Mac,
+    Invalid(String),
+}
+
+impl DeviceAuthResult {
+    pub fn is_valid(&self) -> bool {
+        !matches!(self, DeviceAuthResult::Invalid(_))
+    }
+}
+
+impl AuthenticationStatus {
+    pub fn is_valid(&self) -> bool {
+        matches!(self, AuthenticationStatus::Valid)
+    }
+}
+
+impl ValidatedDocument {
+    /// Whether the issuer and device authentication are both valid, every disclosed element
+    /// matches its digest, every device signed element is authorized, and the mobile security
+    /// object was within its validity window.
+    pub fn is_verified(&self) -> bool {
+        self.issuer_auth.is_valid()
+            && self.device_auth.is_valid()
+            && self.digest_mismatches.is_empty()
+            && self.unauthorized_elements.is_empty()
+            && self.validity_error.is_none()
+    }
+
+    /// The disclosed `portrait` of an mDL, checked to be a well-formed image of at most
+    /// `max_bytes`. `None` if the portrait was not disclosed.
+    pub fn portrait(&self, max_bytes: usize) -> Option<Result<Portrait, PortraitError>> {
+        self.namespaces
+            .get(MDL_NAMESPACE)?
+            .get("portrait")
+            .map(|value| Portrait::from_cbor(value, max_bytes))
+    }
+
+    /// Decode the disclosed element `element_identifier` in `namespace` into its typed
+    /// definition, such as
+    /// [DomesticDrivingPrivileges](crate::definitions::namespaces::org_iso_18013_5_1_aamva::DomesticDrivingPrivileges).
+    /// `None` if the element was not disclosed.
+    pub fn decode_element<T: FromCbor>(
+        &self,
+        namespace: &str,
+        element_identifier: &str,
+    ) -> Option<Result<T, FromJsonError>> {
+        self.namespaces
+            .get(namespace)?
+            .get(element_identifier)
+            .map(T::from_cbor)
+    }
+}
+
+impl ValidatedResponse {
+    /// The first document of type `doc_type`.
+    pub fn document(&self, doc_type: &str) -> Option<&ValidatedDocument> {
+        self.documents.iter().find(|doc| doc.doc_type == doc_type)
+    }
+
+    /// The disclosed elements as JSON, by document type, then namespace, then element
+    /// identifier.
+    ///
+    /// Byte strings are encoded in base64, and tagged values such as dates are replaced by their
+    /// content.
+    pub fn to_json(&self) -> Value {
+        let mut documents = serde_json::Map::new();
+        for document in self.documents.iter() {
+            let namespaces = document
+                .namespaces
+                .iter()
+                .map(|(namespace, elements)| {
+                    let elements = elements
+                        .iter()
+                        .map(|(element_identifier, value)| {
+                            (element_identifier.clone(), cbor_to_json(value))
+                        })
+                        .collect();
+                    (namespace.clone(), Value::Object(elements))
+                })
+                .collect();
+            documents.insert(document.doc_type.clone(), Value::Object(namespaces));
+        }
+        Value::Object(documents)
+    }
+}
+
+/// Verify the issuer and device authentication of each document in a device response that was
+/// not encrypted with session keys, such as one received over OpenID4VP, where
+/// `session_transcript` is built from the authorization request.
+///
+/// MAC device authentication is reported as invalid, as it requires a reader key agreed with the
+/// device.
+pub fn validate_device_response<S>(
+    response: &[u8],
+    session_transcript: &S,
+    registry: &TrustAnchorRegistry,
+) -> Result<ValidatedResponse, ResponseError>
+where
+    S: SessionTranscript + Clone,
+{
+    let limits = DecodeLimits::default();
+    limits.check_message(response)?;
+    validate_documents(response.to_vec(), &limits, |document| {
+        let device_auth = verify_device_auth(&document, session_transcript);
+        validate_document(
+            document,
+            Some(registry),
+            device_auth,
+            OffsetDateTime::now_utc(),
+        )
+    })
+}
+
+fn validate_documents(
+    raw: Vec<u8>,
+    limits: &DecodeLimits,
+    validate: impl FnMut(Document) -> Result<ValidatedDocument, ResponseError>,
+) -> Result<ValidatedResponse, ResponseError> {
+    let response: DeviceResponse = serde_cbor::from_slice(&raw)?;
+    limits.check_response(&response)?;
+    let errors = element_errors(&response);
+    let documents = response
+        .documents
+        .map(|documents| documents.into_inner())
+        .unwrap_or_default()
+        .into_iter()
+        .map(validate)
+        .collect::<Result<_, _>>()?;
+    Ok(ValidatedResponse {
+        documents,
+        errors,
+        raw,
+    })
+}
+
+fn validate_document(
+    document: Document,
+    registry: Option<&TrustAnchorRegistry>,
+    device_auth: DeviceAuthResult,
+    at: OffsetDateTime,
+) -> Result<ValidatedDocument, ResponseError> {
+    let issuer_auth = &document.issuer_signed.issuer_auth;
+    let mso = parse_mso(issuer_auth)?;
+
+    let issuer_auth = match registry {
+        Some(registry) => {
+            verify_issuer_auth(issuer_auth, &mso, &document.doc_type, registry, None).into()
+        }
+        None => match verify_issuer_signature(issuer_auth, &mso, &document.doc_type, None) {
+            Ok(()) => AuthenticationStatus::Unverified,
+            Err(e) => AuthenticationStatus::Invalid(e.to_string()),
+        },
+    };
+    let digest_mismatches = verify_value_digests(&document, &mso)
+        .err()
+        .unwrap_or_default();
+    let unauthorized_elements = verify_key_authorizations(&document, &mso)
+        .err()
+        .unwrap_or_default();
+
+    let namespaces = document
+        .issuer_signed
+        .namespaces
+        .map(|namespaces| namespaces.into_inner())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(namespace, items)| {
+            let elements = items
+                .into_inner()
+                .into_iter()
+                .map(|item| {
+                    let item = item.into_inner();
+                    (item.element_identifier, item.element_value)
+                })
+                .collect();
+            (namespace, elements)
+        })
+        .collect();
+
+    let device_signed_namespaces = document
+        .device_signed
+        .namespaces
+        .into_inner()
+        .into_iter()
+        .map(|(namespace, elements)| (namespace, elements.into_inner()))
+        .collect();
+
+    Ok(ValidatedDocument {
+        doc_type: document.doc_type,
+        namespaces,
+        device_signed_namespaces,
+        validity_error: check_validity(&mso.validity_info, at),
+        validity_info: mso.validity_info,
+        issuer_auth,
+        device_auth,
+        digest_mismatches,
+        unauthorized_elements,
+        request_mismatches: Vec::new(),
+    })
+}
+
+/// The ways in which `document` returns data that was not requested in `requested`.
+///
+/// An `age_over_NN` element answers a request for any other `age_over_NN` element of the same
+/// namespace, as the device may return the nearest age statement it holds.
+fn request_mismatches(
+    requested: &BTreeMap<DocType, device_request::Namespaces>,
+    document: &ValidatedDocument,
+) -> Vec<RequestMismatch> {
+    let requested = match requested.get(&document.doc_type) {
+        Some(requested) => requested,
+        None => {
+            return vec![RequestMismatch::UnexpectedDocType(
+                document.doc_type.clone(),
+            )]
+        }
+    };
+    document
+        .namespaces
+        .iter()
+        .chain(&document.device_signed_namespaces)
+        .flat_map(|(namespace, elements)| {
+            elements
+                .keys()
+                .map(move |element_identifier| (namespace, element_identifier))
+        })
+        .filter(|(namespace, element_identifier)| {
+            let elements = requested.get(*namespace);
+            let is_age_over = |identifier: &String| identifier.starts_with("age_over_");
+            !elements.is_some_and(|elements| {
+                elements.includes(element_identifier)
+                    || (is_age_over(element_identifier) && elements.keys().any(is_age_over))
+            })
+        })
+        .map(
+            |(namespace, element_identifier)| RequestMismatch::UnsolicitedElement {
+                namespace: namespace.clone(),
+                element_identifier: element_identifier.clone(),
+            },
+        )
+        .collect()
+}
+
+/// Check that `at` is within the validity window of a mobile security object.
+fn check_validity(validity_info: &ValidityInfo, at: OffsetDateTime) -> Option<ValidityError> {
+    if at < validity_info.valid_from {
+        Some(ValidityError::NotYetValid {
+            valid_from: validity_info.valid_from,
+            at,
+        })
+    } else if at > validity_info.valid_until {
+        Some(ValidityError::Expired {
+            valid_until: validity_info.valid_until,
+            at,
+        })
+    } else {
+        None
+    }
+}
+
+/// Check that the mobile security object in `issuer_auth` is canonically encoded, both as the
+/// payload and as the embedded CBOR within it.
+fn check_canonical_mso(issuer_auth: &CoseSign1) -> Result<(), ResponseError> {
+    let payload = issuer_auth
+        .payload()
+        .ok_or(ResponseError::InvalidIssuerAuth)?;
+    let mso: Tag24<CborValue> = serde_cbor::from_slice(payload)?;
+    if is_canonical(payload) && is_canonical(&mso.inner_bytes) {
+        Ok(())
+    } else {
+        Err(ResponseError::NonCanonicalCbor)
+    }
+}
+
+pub(crate) fn parse_mso(issuer_auth: &CoseSign1) -> Result<Mso, ResponseError> {
+    let mso: Tag24<Mso> = issuer_auth
+        .payload()
+        .ok_or(ResponseError::InvalidIssuerAuth)
+        .and_then(|payload| Ok(serde_cbor::from_slice(payload)?))?;
+    Ok(mso.into_inner())
+}
+
+/// Check that each element disclosed in `document` matches its digest in the `valueDigests` of
+/// `mso`, so that the element is as signed by the issuer.
+pub fn verify_value_digests(document: &Document, mso: &Mso) -> Result<(), Vec<DigestMismatch>> {
+    let mismatches = digest_mismatches(&document.issuer_signed, mso);
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(mismatches)
+    }
+}
+
+/// The elements of `issuer_signed` that do not match their digest in `mso`.
+pub(crate) fn digest_mismatches(issuer_signed: &IssuerSigned, mso: &Mso) -> Vec<DigestMismatch> {
+    issuer_signed
+        .namespaces
+        .iter()
+        .flat_map(|namespaces| namespaces.iter())
+        .flat_map(|(namespace, items)| items.iter().map(move |item| (namespace, item)))
+        .filter_map(|(namespace, item)| {
+            let digest_id = item.as_ref().digest_id;
+            let expected = mso
+                .value_digests
+                .get(namespace)
+                .and_then(|digests| digests.get(&digest_id));
+            let digest = serde_cbor::to_vec(item)
+                .map(|bytes| mso.digest_algorithm.digest(&bytes))
+                .ok();
+            // Digests are not secret, but are compared in constant time like any other
+            // authentication value.
+            match (&digest, expected) {
+                (Some(digest), Some(expected))
+                    if bool::from(digest.as_slice().ct_eq(expected.as_ref())) =>
+                {
+                    None
+                }
+                _ => Some(DigestMismatch {
+                    namespace: namespace.clone(),
+                    element_identifier: item.as_ref().element_identifier.clone(),
+                    digest_id,
+                    expected_digest: expected.map(hex::encode),
+                    actual_digest: digest.map(hex::encode),
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Check that the device key is authorized by `mso` to sign over each device signed element in
+/// `document`. A device key without key authorizations may not sign over any elements.
+pub fn verify_key_authorizations(
+    document: &Document,
+    mso: &Mso,
+) -> Result<(), Vec<UnauthorizedElement>> {
+    let authorizations = mso.device_key_info.key_authorizations.as_ref();
+    let unauthorized: Vec<UnauthorizedElement> = document
+        .device_signed
+        .namespaces
+        .as_ref()
+        .iter()
+        .flat_map(|(namespace, items)| items.keys().map(move |element| (namespace, element)))
+        .filter(|(namespace, element_identifier)| {
+            !authorizations.is_some_and(|authorizations| {
+                authorizations.permitted(namespace, element_identifier)
+            })
+        })
+        .map(|(namespace, element_identifier)| UnauthorizedElement {
+            namespace: namespace.clone(),
+            element_identifier: element_identifier.clone(),
+        })
+        .collect();
+    if unauthorized.is_empty() {
+        Ok(())
+    } else {
+        Err(unauthorized)
+    }
+}
+
+/// Verify an `issuer_auth` whose payload is detached, against `mso_bytes`, the tagged encoding of
+/// the mobile security object as stored alongside it, returning the object once verified.
+pub fn verify_detached_issuer_auth(
+    issuer_auth: &CoseSign1,
+    mso_bytes: &[u8],
+    doc_type: &str,
+    registry: &TrustAnchorRegistry,
+) -> Result<Mso> {
+    if issuer_auth.payload().is_some() {
+        return Err(anyhow!("issuer auth has an attached payload"));
+    }
+    let mso: Tag24<Mso> = serde_cbor::from_slice(mso_bytes)?;
+    let mso = mso.into_inner();
+    verify_issuer_auth(
+        issuer_auth,
+        &mso,
+        doc_type,
+        registry,
+        Some(mso_bytes.to_vec()),
+    )?;
+    Ok(mso)
+}
+
+pub(crate) fn verify_issuer_auth(
+    issuer_auth: &CoseSign1,
+    mso: &Mso,
+    doc_type: &str,
+    registry: &TrustAnchorRegistry,
+    detached_payload: Option<Vec<u8>>,
+) -> Result<()> {
+    let x5chain = issuer_x5chain(issuer_auth)?;
+    let errors = x5chain.validate(registry);
+    if !errors.is_empty() {
+        return Err(anyhow!("invalid document signer chain: {:?}", errors));
+    }
+    // A trust anchor from a VICAL may only vouch for the document types listed for it.
+    let anchor = registry
+        .find_anchor(x5chain.root())
+        .ok_or_else(|| anyhow!("document signer chain has no trust anchor"))?;
+    if let Some(doc_types) = registry.doc_types(anchor) {
+        if !doc_types.iter().any(|allowed| allowed == doc_type) {
+            return Err(anyhow!(
+                "trust anchor is not authorized to issue '{}'",
+                doc_type
+            ));
+        }
+    }
+    verify_issuer_signature(issuer_auth, mso, doc_type, detached_payload)
+}
+
+/// Verify the issuer signature of a document for `doc_type` against the document signer
+/// certificate in its x5chain, without checking whom that certificate chains to.
+fn verify_issuer_signature(
+    issuer_auth: &CoseSign1,
+    mso: &Mso,
+    doc_type: &str,
+    detached_payload: Option<Vec<u8>>,
+) -> Result<()> {
+    if mso.doc_type != doc_type {
+        return Err(anyhow!(
+            "mobile security object is for '{}', not '{}'",
+            mso.doc_type,
+            doc_type
+        ));
+    }
+    let x5chain = issuer_x5chain(issuer_auth)?;
+    super::verify_with_certificate(issuer_auth, x5chain.leaf(), detached_payload)
+}
+
+fn issuer_x5chain(issuer_auth: &CoseSign1) -> Result<X5Chain> {
+    issuer_auth
+        .unprotected()
+        .get_i(X5CHAIN_HEADER_LABEL)
+        .ok_or_else(|| anyhow!("issuer auth has no x5chain"))
+        .and_then(X5Chain::from_cbor)
+}
+
+/// Verify the device authentication of `document` over the DeviceAuthentication reconstructed
+/// from `session_transcript`, with the device key from its mobile security object.
+///
+/// Only signatures can be verified here, as a MAC is keyed by the reader's ephemeral key: see
+/// [verify_device_mac].
+pub fn verify_device_auth<S>(document: &Document, session_transcript: &S) -> DeviceAuthResult
+where
+    S: SessionTranscript + Clone,
+{
+    match &document.device_signed.device_auth {
+        DeviceAuth::Signature { device_signature } => {
+            verify_device_signature(document, device_signature, session_transcript)
+                .map(|()| DeviceAuthResult::Signature)
+                .unwrap_or_else(|e| DeviceAuthResult::Invalid(e.to_string()))
+        }
+        DeviceAuth::Mac { .. } => DeviceAuthResult::Invalid(
+            "MAC device authentication requires the reader's ephemeral key".into(),
+        ),
+    }
+}
+
+/// Verify the MAC device authentication of `document` over the DeviceAuthentication
+/// reconstructed from `session_transcript`, keyed by the agreement between `e_reader_key` and
+/// the device key from its mobile security object.
+pub fn verify_device_mac(
+    document: &Document,
+    session_transcript: &SessionTranscript180135,
+    e_reader_key: &p256::SecretKey,
+) -> DeviceAuthResult {
+    let device_mac = match &document.device_signed.device_auth {
+        DeviceAuth::Mac { device_mac } => device_mac,
+        DeviceAuth::Signature { .. } => {
+            return DeviceAuthResult::Invalid("document is not authenticated with a MAC".into())
+        }
+    };
+    check_device_mac(document, device_mac, session_transcript, e_reader_key)
+        .map(|()| DeviceAuthResult::Mac)
+        .unwrap_or_else(|e| DeviceAuthResult::Invalid(e.to_string()))
+}
+
+fn check_device_mac(
+    document: &Document,
+    device_mac: &CborValue,
+    session_transcript: &SessionTranscript180135,
+    e_reader_key: &p256::SecretKey,
+) -> Result<()> {
+    let mso = parse_mso(&document.issuer_signed.issuer_auth)?;
+    let shared_secret = get_shared_secret(
+        mso.device_key_info.device_key,
+        &e_reader_key.to_nonzero_scalar(),
+    )?;
+    let emac_key =
+        session::derive_emac_key(&shared_secret, &Tag24::new(session_transcript.clone())?)?;
+    let payload = serde_cbor::to_vec(&Tag24::new(DeviceAuthentication::new(
+        session_transcript.clone(),
+        document.doc_type.clone(),
+        document.device_signed.namespaces.clone(),
+    ))?)?;
+    device_signed::verify_device_mac(device_mac, &emac_key, &payload)?;
+    Ok(())
+}
+
+fn verify_device_signature<S>(
+    document: &Document,
+    device_signature: &CoseSign1,
+    session_transcript: &S,
+) -> Result<()>
+where
+    S: SessionTranscript + Clone,
+{
+    let mso = parse_mso(&document.issuer_signed.issuer_auth)?;
+    let device_key = mso.device_key_info.device_key;
+    let payload = serde_cbor::to_vec(&Tag24::new(DeviceAuthentication::new(
+        session_transcript.clone(),
+        document.doc_type.clone(),
+        document.device_signed.namespaces.clone(),
+    ))?)?;
+    let verified = match device_key {
+        CoseKey::EC2 {
+            crv: EC2Curve::P256,
+            ..
+        } => {
+            super::check_algorithm(device_signature, Algorithm::ES256)?;
+            device_signature
+                .verify::<p256::ecdsa::VerifyingKey, p256::ecdsa::Signature>(
+                    &p256::ecdsa::VerifyingKey::from_sec1_bytes(&device_key.sec1_bytes()?)?,
+                    Some(payload),
+                    None,
+                )
+                .success()
+        }
+        CoseKey::EC2 {
+            crv: EC2Curve::P384,
+            ..
+        } => {
+            super::check_algorithm(device_signature, Algorithm::ES384)?;
+            device_signature
+                .verify::<p384::ecdsa::VerifyingKey, p384::ecdsa::Signature>(
+                    &p384::ecdsa::VerifyingKey::from_sec1_bytes(&device_key.sec1_bytes()?)?,
+                    Some(payload),
+                    None,
+                )
+                .success()
+        }
+        CoseKey::EC2 {
+            crv: EC2Curve::P521,
+            ..
+        } => {
+            super::check_algorithm(device_signature, Algorithm::ES512)?;
+            device_signature
+                .verify::<super::P521VerifyingKey, p521::ecdsa::Signature>(
+                    &super::P521VerifyingKey(p521::ecdsa::VerifyingKey::from_sec1_bytes(
+                        &device_key.sec1_bytes()?,
+                    )?),
+                    Some(payload),
+                    None,
+                )
+                .success()
+        }
+        _ => return Err(anyhow!("unsupported device key")),
+    };
+    if !verified {
+        return Err(anyhow!("signature verification failed"));
+    }
+    Ok(())
+}
+
+impl From<Result<()>> for AuthenticationStatus {
+    fn from(result: Result<()>) -> Self {
+        match result {
+            Ok(()) => AuthenticationStatus::Valid,
+            Err(e) => AuthenticationStatus::Invalid(e.to_string()),
+        }
+    }
+}
+
+fn mdl_request(
+    namespaces: device_request::Namespaces,
+) -> NonEmptyMap<DocType, device_request::Namespaces> {
+    NonEmptyMap::new(MDL_DOC_TYPE.into(), namespaces)
+}
+
+/// Collect the element-level errors reported in each document of `response`.
+pub fn element_errors(response: &DeviceResponse) -> ElementErrors {
+    response
+        .documents()
+        .iter()
+        .filter_map(|document| {
+            let errors = document.errors.as_ref()?;
+            let errors = errors
+                .iter()
+                .map(|(namespace, elements)| (namespace.clone(), elements.as_ref().clone()))
+                .collect();
+            Some((document.doc_type.clone(), errors))
+        })
+        .collect()
+}
+
+fn parse_mdl_response(
+    response: DeviceResponse,
+) -> Result<BTreeMap<String, BTreeMap<String, Value>>, ResponseError> {
+    let mut core_namespace = BTreeMap::<String, serde_json::Value>::new();
+    let mut aamva_namespace = BTreeMap::<String, serde_json::Value>::new();
+    let mut parsed_response = BTreeMap::<String, BTreeMap<String, serde_json::Value>>::new();
+
+    let mut namespaces = response
+        .documents
+        .ok_or(ResponseError::DeviceTransmissionError)?
+        .into_inner()
+        .into_iter()
+        .find(|doc| doc.doc_type == MDL_DOC_TYPE)
+        .ok_or(ResponseError::DocumentTypeError)?
+        .issuer_signed
+        .namespaces
+        .ok_or(ResponseError::NoMdlDataTransmission)?
+        .into_inner();
+
+    namespaces
+        .remove("org.iso.18013.5.1")
+        .ok_or(ResponseError::IncorrectNamespace)?
+        .into_inner()
+        .into_iter()
+        .map(|item| item.into_inner())
+        .for_each(|item| {
+            let value = parse_response(item.element_value.clone());
+            if let Ok(val) = value {
+                core_namespace.insert(item.element_identifier, val);
+            }
+        });
+
+    parsed_response.insert("org.iso.18013.5.1".to_string(), core_namespace);
+
+    if let Some(aamva_response) = namespaces.remove("org.iso.18013.5.1.aamva") {
+        aamva_response
             .into_inner()
             .into_iter()
             .map(|item| item.into_inner())
             .for_each(|item| {
                 let value = parse_response(item.element_value.clone());
                 if let Ok(val) = value {
-                    core_namespace.insert(item.element_identifier, val);
+                    aamva_namespace.insert(item.element_identifier, val);
                 }
             });
 
-        parsed_response.insert("org.iso.18013.5.1".to_string(), core_namespace);
-
-        if let Some(aamva_response) = namespaces.remove("org.iso.18013.5.1.aamva") {
-            aamva_response
-                .into_inner()
-                .into_iter()
-                .map(|item| item.into_inner())
-                .for_each(|item| {
-                    let value = parse_response(item.element_value.clone());
-                    if let Ok(val) = value {
-                        aamva_namespace.insert(item.element_identifier, val);
-                    }
-                });
-
-            parsed_response.insert("org.iso.18013.5.1.aamva".to_string(), aamva_namespace);
-        }
-
-        Ok(parsed_response)
+        parsed_response.insert("org.iso.18013.5.1.aamva".to_string(), aamva_namespace);
     }
+
+    Ok(parsed_response)
 }
 
-fn parse_response(value: CborValue) -> Result<Value, Error> {
+fn parse_response(value: CborValue) -> Result<Value, ResponseError> {
     match value {
         CborValue::Text(s) => Ok(Value::String(s)),
         CborValue::Tag(_t, v) => {
             if let CborValue::Text(d) = *v {
                 Ok(Value::String(d))
             } else {
-                Err(Error::ParsingError)
+                Err(ResponseError::ParsingError)
             }
         }
         CborValue::Array(v) => {
@@ -274,33 +1474,274 @@ fn parse_response(value: CborValue) -> Result<Value, Error> {
         CborValue::Bytes(b) => Ok(json!(b)),
         CborValue::Bool(b) => Ok(json!(b)),
         CborValue::Integer(i) => Ok(json!(i)),
-        _ => Err(Error::ParsingError),
+        _ => Err(ResponseError::ParsingError),
     }
 }
 
-fn _validate_request(namespaces: device_request::Namespaces) -> Result<bool, Error> {
+fn _validate_request(namespaces: device_request::Namespaces) -> Result<bool, RequestError> {
     // Check if request follows ISO18013-5 restrictions
     // A valid mdoc request can contain a maximum of 2 age_over_NN fields
     let age_over_nn_requested: Vec<(String, bool)> = namespaces
         .get("org.iso.18013.5.1")
         .map(|k| k.clone().into_inner())
-        //To Do: get rid of unwrap
-        .unwrap()
+        .unwrap_or_default()
         .into_iter()
         .filter(|x| x.0.contains("age_over"))
         .collect();
 
     if age_over_nn_requested.len() > 2 {
         //To Do: Decide what should happen when more than two age_over_nn are requested
-        return Err(Error::InvalidRequest);
+        return Err(RequestError::InvalidRequest);
     }
 
     Ok(true)
 }
 
+/// Sign the ReaderAuthentication structure for `items_request`, embedding `x5chain` in the
+/// unprotected header.
+fn sign_reader_auth<S, Sig>(
+    session_transcript: &SessionTranscript180135,
+    items_request: &ItemsRequestBytes,
+    signer: &S,
+    x5chain: &X5Chain,
+) -> Result<ReaderAuth>
+where
+    S: Signer<Sig> + SignatureAlgorithm,
+    Sig: SignatureEncoding,
+{
+    let reader_auth = Tag24::new(ReaderAuthentication::new(
+        session_transcript.clone(),
+        items_request.clone(),
+    ))?;
+    let prepared = CoseSign1::builder()
+        .detached()
+        .payload(serde_cbor::to_vec(&reader_auth)?)
+        .signature_algorithm(signer.algorithm())
+        .prepare()
+        .map_err(|e| anyhow!("error preparing cosesign1: {}", e))?;
+    let signature = signer
+        .try_sign(prepared.signature_payload())
+        .map_err(|e| anyhow!("error signing cosesign1: {}", e))?
+        .to_vec();
+    let mut reader_auth = prepared.finalize(signature);
+    reader_auth
+        .unprotected_mut()
+        .insert_i(X5CHAIN_HEADER_LABEL, x5chain.into_cbor());
+    Ok(reader_auth)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::definitions::helpers::NonEmptyVec;
+    #[cfg(feature = "transports")]
+    use crate::definitions::{
+        device_engagement::{CentralClientMode, PeripheralServerMode},
+        BleOptions, NfcOptions,
+    };
+
+    fn namespaces() -> device_request::Namespaces {
+        device_request::Namespaces::new(
+            "org.iso.18013.5.1".into(),
+            device_request::DataElements::new("age_over_21".into(), false),
+        )
+    }
+
+    #[cfg(feature = "transports")]
+    fn engagement_qr_code(ble_options: BleOptions) -> String {
+        engagement_qr_code_with_methods(NonEmptyVec::new(DeviceRetrievalMethod::BLE(ble_options)))
+    }
+
+    #[cfg(feature = "transports")]
+    fn engagement_qr_code_with_methods(methods: NonEmptyVec<DeviceRetrievalMethod>) -> String {
+        qr_code(engagement().retrieval_methods(methods).build().unwrap())
+    }
+
+    /// A device engagement offering cipher suite 1 with a fresh ephemeral key.
+    fn engagement() -> device_engagement::Builder {
+        let (_, e_device_key_pub) = create_p256_ephemeral_keys().unwrap();
+        DeviceEngagement::builder().security(1, e_device_key_pub)
+    }
+
+    fn qr_code(device_engagement: DeviceEngagement) -> String {
+        Tag24::new(device_engagement)
+            .unwrap()
+            .to_qr_code_uri()
+            .unwrap()
+    }
+
+    #[test]
+    fn random_responses() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..200 {
+            let (mut session_manager, _, _) = SessionManager::establish_session(
+                qr_code(engagement().build().unwrap()),
+                namespaces(),
+            )
+            .unwrap();
+
+            let len = rng.gen_range(0..512);
+            let bytes: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let session_data = serde_cbor::to_vec(&SessionData {
+                data: Some(bytes.clone().into()),
+                status: None,
+            })
+            .unwrap();
+            assert!(session_manager.handle_response(&session_data).is_err());
+            assert!(session_manager.handle_response(&bytes).is_err());
+        }
+    }
+
+    #[test]
+    fn secrets_are_zeroized_on_drop() {
+        use zeroize::{Zeroize, ZeroizeOnDrop};
+
+        fn assert_zeroize_on_drop<T: ZeroizeOnDrop>(_: &T) {}
+
+        let (session_manager, _, _) =
+            SessionManager::establish_session(qr_code(engagement().build().unwrap()), namespaces())
+                .unwrap();
+
+        let SessionManager {
+            mut sk_device,
+            mut sk_reader,
+            mut e_reader_key,
+            ..
+        } = session_manager;
+        for secret in [&mut sk_device, &mut sk_reader, &mut e_reader_key] {
+            assert_zeroize_on_drop(secret);
+            assert_ne!(**secret, [0; 32]);
+            secret.zeroize();
+            assert_eq!(**secret, [0; 32]);
+        }
+    }
+
+    #[test]
+    fn response_exceeding_limits() {
+        let (mut session_manager, _, _) =
+            SessionManager::establish_session(qr_code(engagement().build().unwrap()), namespaces())
+                .unwrap();
+        session_manager.set_decode_limits(DecodeLimits {
+            max_message_bytes: 64,
+            ..Default::default()
+        });
+
+        let session_data = serde_cbor::to_vec(&SessionData {
+            data: Some(vec![0; 128].into()),
+            status: None,
+        })
+        .unwrap();
+        assert!(matches!(
+            session_manager.handle_response(&session_data),
+            Err(ResponseError::LimitExceeded(LimitExceeded::MessageBytes(_)))
+        ));
+        assert!(matches!(
+            session_manager.validate(&session_data),
+            Err(ResponseError::LimitExceeded(LimitExceeded::MessageBytes(_)))
+        ));
+    }
+
+    #[test]
+    fn engagement_errors() {
+        assert!(matches!(
+            SessionManager::establish_session("not a qr code".into(), namespaces()),
+            Err(EngagementError::InvalidQrCode(_))
+        ));
+        assert!(matches!(
+            SessionManager::establish_session_nfc(vec![0], vec![], None, namespaces()),
+            Err(EngagementError::InvalidDeviceEngagement(_))
+        ));
+    }
+
+    #[test]
+    fn cipher_suites() {
+        let offering = |cipher_suite| {
+            let mut device_engagement = engagement().build().unwrap();
+            // The builder refuses unsupported cipher suites, but an mdoc may still offer them.
+            device_engagement.security.0 = cipher_suite;
+            qr_code(device_engagement)
+        };
+        assert!(SessionManager::establish_session(offering(1), namespaces()).is_ok());
+        assert!(matches!(
+            SessionManager::establish_session(offering(2), namespaces()),
+            Err(EngagementError::UnsupportedCipherSuite(
+                device_engagement::Error::UnsupportedCipherSuite(2)
+            ))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "transports")]
+    fn select_device_retrieval_method() {
+        let ble = DeviceRetrievalMethod::BLE(BleOptions {
+            peripheral_server_mode: None,
+            central_client_mode: Some(CentralClientMode {
+                uuid: Uuid::new_v4(),
+            }),
+        });
+        let nfc = DeviceRetrievalMethod::NFC(NfcOptions::default());
+        let mut methods = NonEmptyVec::new(nfc.clone());
+        methods.extend([ble.clone()]);
+
+        let (session_manager, _, _) = SessionManager::establish_session(
+            engagement_qr_code_with_methods(methods),
+            namespaces(),
+        )
+        .unwrap();
+        assert_eq!(
+            session_manager.device_retrieval_methods(),
+            &[nfc.clone(), ble.clone()]
+        );
+        assert_eq!(
+            session_manager
+                .select_device_retrieval_method(|m| matches!(m, DeviceRetrievalMethod::BLE(_))),
+            Some(&ble)
+        );
+        assert_eq!(
+            session_manager.select_device_retrieval_method(|_| true),
+            Some(&nfc)
+        );
+        assert_eq!(
+            session_manager
+                .select_device_retrieval_method(|m| matches!(m, DeviceRetrievalMethod::WIFI(_))),
+            None
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "transports")]
+    fn ble_modes() {
+        let namespaces = namespaces();
+        let uuid = Uuid::new_v4();
+
+        let qr_code = engagement_qr_code(BleOptions {
+            peripheral_server_mode: Some(PeripheralServerMode {
+                uuid,
+                ble_device_address: None,
+            }),
+            central_client_mode: None,
+        });
+        let (session_manager, _, ble_ident) =
+            SessionManager::establish_session(qr_code.clone(), namespaces.clone()).unwrap();
+        assert_eq!(session_manager.first_peripheral_server_uuid(), Some(&uuid));
+        assert_eq!(session_manager.first_central_client_uuid(), None);
+        let device_engagement = Tag24::<DeviceEngagement>::from_qr_code_uri(&qr_code).unwrap();
+        assert_eq!(
+            ble_ident,
+            super::super::calculate_ble_ident(&device_engagement.as_ref().security.1).unwrap()
+        );
+
+        let qr_code = engagement_qr_code(BleOptions {
+            peripheral_server_mode: None,
+            central_client_mode: Some(CentralClientMode { uuid }),
+        });
+        let (session_manager, _, _) =
+            SessionManager::establish_session(qr_code, namespaces).unwrap();
+        assert_eq!(session_manager.first_peripheral_server_uuid(), None);
+        assert_eq!(session_manager.first_central_client_uuid(), Some(&uuid));
+    }
 
     #[test]
     fn nested_response_values() {
@@ -326,4 +1767,248 @@ mod test {
         );
         assert_eq!(json, expected)
     }
+
+    #[test]
+    fn unsolicited_elements() {
+        let mut elements = device_request::DataElements::new("family_name".into(), false);
+        elements.insert("age_over_18".into(), false);
+        let requested = [(
+            MDL_DOC_TYPE.to_string(),
+            device_request::Namespaces::new("org.iso.18013.5.1".into(), elements),
+        )]
+        .into_iter()
+        .collect();
+        let now = OffsetDateTime::now_utc();
+        let mut document = ValidatedDocument {
+            doc_type: MDL_DOC_TYPE.into(),
+            namespaces: [(
+                "org.iso.18013.5.1".to_string(),
+                [
+                    ("family_name".to_string(), CborValue::Text("Smith".into())),
+                    ("age_over_21".to_string(), CborValue::Bool(true)),
+                    (
+                        "resident_address".to_string(),
+                        CborValue::Text("1 Main St".into()),
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            )]
+            .into_iter()
+            .collect(),
+            device_signed_namespaces: BTreeMap::new(),
+            validity_info: ValidityInfo {
+                signed: now,
+                valid_from: now,
+                valid_until: now,
+                expected_update: None,
+            },
+            issuer_auth: AuthenticationStatus::Valid,
+            device_auth: DeviceAuthResult::Signature,
+            digest_mismatches: vec![],
+            validity_error: None,
+            unauthorized_elements: vec![],
+            request_mismatches: vec![],
+        };
+
+        // The nearest age statement answers the request for age_over_18.
+        assert_eq!(
+            request_mismatches(&requested, &document),
+            vec![RequestMismatch::UnsolicitedElement {
+                namespace: "org.iso.18013.5.1".into(),
+                element_identifier: "resident_address".into(),
+            }]
+        );
+
+        // Elements asserted by the device are checked against the request too.
+        document.namespaces.clear();
+        document.device_signed_namespaces = [(
+            "org.iso.18013.5.1".to_string(),
+            [("nationality".to_string(), CborValue::Text("US".into()))]
+                .into_iter()
+                .collect(),
+        )]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            request_mismatches(&requested, &document),
+            vec![RequestMismatch::UnsolicitedElement {
+                namespace: "org.iso.18013.5.1".into(),
+                element_identifier: "nationality".into(),
+            }]
+        );
+
+        document.doc_type = "org.iso.23220.photoid.1".into();
+        assert_eq!(
+            request_mismatches(&requested, &document),
+            vec![RequestMismatch::UnexpectedDocType(
+                "org.iso.23220.photoid.1".into()
+            )]
+        );
+    }
+
+    #[test]
+    fn issuer_signature_without_registry() {
+        static DEVICE_RESPONSE_CBOR: &str =
+            include_str!("../../test/definitions/device_response.cbor");
+        let response: DeviceResponse =
+            serde_cbor::from_slice(&hex::decode(DEVICE_RESPONSE_CBOR).unwrap()).unwrap();
+        let mut document = response.documents.unwrap().into_inner().remove(0);
+        let validate = |document: Document| {
+            validate_document(
+                document,
+                None,
+                DeviceAuthResult::Mac,
+                OffsetDateTime::now_utc(),
+            )
+            .unwrap()
+            .issuer_auth
+        };
+        assert_eq!(validate(document.clone()), AuthenticationStatus::Unverified);
+
+        // A forged signature is reported even though the issuer is not checked.
+        let mut issuer_auth =
+            serde_cbor::value::to_value(&document.issuer_signed.issuer_auth).unwrap();
+        let fields = match &mut issuer_auth {
+            CborValue::Tag(_, inner) => inner.as_mut(),
+            untagged => untagged,
+        };
+        let CborValue::Array(fields) = fields else {
+            panic!("issuer auth is not a COSE_Sign1 array");
+        };
+        let CborValue::Bytes(signature) = &mut fields[3] else {
+            panic!("issuer auth has no signature");
+        };
+        signature[0] ^= 0xff;
+        document.issuer_signed.issuer_auth = serde_cbor::value::from_value(issuer_auth).unwrap();
+        assert!(matches!(
+            validate(document),
+            AuthenticationStatus::Invalid(_)
+        ));
+    }
+
+    #[test]
+    fn tampered_value_digest() {
+        use hex::FromHex;
+
+        static DEVICE_RESPONSE_CBOR: &str =
+            include_str!("../../test/definitions/device_response.cbor");
+        let cbor_bytes =
+            <Vec<u8>>::from_hex(DEVICE_RESPONSE_CBOR).expect("unable to convert cbor hex to bytes");
+        let response: DeviceResponse =
+            serde_cbor::from_slice(&cbor_bytes).expect("unable to decode cbor as a DeviceResponse");
+        let mut document = response
+            .documents
+            .expect("response contains no documents")
+            .into_inner()
+            .remove(0);
+        let mso = parse_mso(&document.issuer_signed.issuer_auth).expect("unable to parse mso");
+        assert_eq!(verify_value_digests(&document, &mso), Ok(()));
+
+        let mut namespaces = document
+            .issuer_signed
+            .namespaces
+            .expect("document discloses no elements")
+            .into_inner();
+        let mut items = namespaces
+            .remove("org.iso.18013.5.1")
+            .expect("document discloses no org.iso.18013.5.1 elements")
+            .into_inner();
+        let mut item = items[0].clone().into_inner();
+        item.element_value = CborValue::Text("Mallory".into());
+        let signed_digest = hex::encode(&mso.value_digests["org.iso.18013.5.1"][&item.digest_id]);
+        let tampered_item = Tag24::new(item.clone()).expect("unable to encode item");
+        let tampered_digest = hex::encode(
+            mso.digest_algorithm
+                .digest(&serde_cbor::to_vec(&tampered_item).expect("unable to encode item")),
+        );
+        let tampered = DigestMismatch {
+            namespace: "org.iso.18013.5.1".into(),
+            element_identifier: item.element_identifier.clone(),
+            digest_id: item.digest_id,
+            expected_digest: Some(signed_digest),
+            actual_digest: Some(tampered_digest),
+        };
+        items[0] = tampered_item;
+        namespaces.insert(
+            "org.iso.18013.5.1".into(),
+            items.try_into().expect("no elements disclosed"),
+        );
+        document.issuer_signed.namespaces = namespaces.try_into().ok();
+
+        assert_eq!(
+            verify_value_digests(&document, &mso),
+            Err(vec![tampered.clone()])
+        );
+
+        // Without a digest signed by the issuer, only the digest of the element is reported.
+        let mut mso = mso;
+        mso.value_digests
+            .get_mut("org.iso.18013.5.1")
+            .expect("mso has no org.iso.18013.5.1 digests")
+            .remove(&tampered.digest_id);
+        assert_eq!(
+            verify_value_digests(&document, &mso),
+            Err(vec![DigestMismatch {
+                expected_digest: None,
+                ..tampered
+            }])
+        );
+    }
+
+    #[test]
+    fn unauthorized_device_signed_element() {
+        use crate::definitions::KeyAuthorizations;
+        use hex::FromHex;
+
+        static DEVICE_RESPONSE_CBOR: &str =
+            include_str!("../../test/definitions/device_response.cbor");
+        let cbor_bytes =
+            <Vec<u8>>::from_hex(DEVICE_RESPONSE_CBOR).expect("unable to convert cbor hex to bytes");
+        let response: DeviceResponse =
+            serde_cbor::from_slice(&cbor_bytes).expect("unable to decode cbor as a DeviceResponse");
+        let mut document = response
+            .documents
+            .expect("response contains no documents")
+            .into_inner()
+            .remove(0);
+        let mut mso = parse_mso(&document.issuer_signed.issuer_auth).expect("unable to parse mso");
+
+        let device_namespaces = [(
+            "org.iso.18013.5.1".to_string(),
+            NonEmptyMap::new("family_name".to_string(), CborValue::Text("Doe".into())),
+        )]
+        .into_iter()
+        .collect();
+        document.device_signed.namespaces =
+            Tag24::new(device_namespaces).expect("unable to encode device namespaces");
+        let unauthorized = UnauthorizedElement {
+            namespace: "org.iso.18013.5.1".into(),
+            element_identifier: "family_name".into(),
+        };
+
+        mso.device_key_info.key_authorizations = None;
+        assert_eq!(
+            verify_key_authorizations(&document, &mso),
+            Err(vec![unauthorized.clone()])
+        );
+
+        mso.device_key_info.key_authorizations = Some(KeyAuthorizations {
+            namespaces: None,
+            data_elements: Some(NonEmptyMap::new(
+                "org.iso.18013.5.1".into(),
+                NonEmptyVec::new("given_name".into()),
+            )),
+        });
+        assert_eq!(
+            verify_key_authorizations(&document, &mso),
+            Err(vec![unauthorized])
+        );
+
+        mso.device_key_info.key_authorizations = Some(KeyAuthorizations {
+            namespaces: Some(NonEmptyVec::new("org.iso.18013.5.1".into())),
+            data_elements: None,
+        });
+        assert_eq!(verify_key_authorizations(&document, &mso), Ok(()));
+    }
 }
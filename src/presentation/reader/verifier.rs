@@ -0,0 +1,66 @@
+//! A verifier requesting elements from a device and checking the response in one call, over the
+//! [SessionManager] state machine.
+
+use super::{EngagementError, ResponseError, SessionManager, ValidatedResponse};
+use crate::definitions::{device_request, x509::TrustAnchorRegistry};
+
+/// A reader trusting the issuers in a [TrustAnchorRegistry].
+pub struct Verifier {
+    registry: TrustAnchorRegistry,
+    session: Option<SessionManager>,
+}
+
+impl Verifier {
+    pub fn new(registry: TrustAnchorRegistry) -> Self {
+        Self {
+            registry,
+            session: None,
+        }
+    }
+
+    /// Begin a session with the device that showed `qr_code_uri`, requesting `elements` of an
+    /// mDL. Returns the session establishment message to send to the device.
+    ///
+    /// Any previous session is replaced.
+    pub fn request(
+        &mut self,
+        qr_code_uri: String,
+        elements: device_request::Namespaces,
+    ) -> Result<Vec<u8>, EngagementError> {
+        let (session_manager, request, _ble_ident) =
+            SessionManager::establish_session(qr_code_uri, elements)?;
+        self.session = Some(session_manager);
+        Ok(request)
+    }
+
+    /// Decrypt the device response, and verify the issuer authentication, device
+    /// authentication, value digests and validity window of each document it contains.
+    ///
+    /// Use [ValidatedDocument::is_verified](super::ValidatedDocument::is_verified) to check that
+    /// a document passed every check.
+    pub fn verify(&mut self, response: &[u8]) -> Result<ValidatedResponse, ResponseError> {
+        self.session
+            .as_mut()
+            .ok_or(ResponseError::NoSession)?
+            .validate_response(response, &self.registry)
+    }
+
+    /// The underlying session, once a request has been made.
+    pub fn session_manager(&self) -> Option<&SessionManager> {
+        self.session.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_without_request() {
+        let mut verifier = Verifier::new(TrustAnchorRegistry::new());
+        assert!(matches!(
+            verifier.verify(&[]),
+            Err(ResponseError::NoSession)
+        ));
+    }
+}
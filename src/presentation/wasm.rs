@@ -0,0 +1,80 @@
+//! JavaScript bindings for driving a reader session from a browser.
+use super::reader;
+use crate::definitions::{device_request, x509::TrustAnchorRegistry};
+use wasm_bindgen::prelude::*;
+
+/// A reader's session with an mdoc.
+#[wasm_bindgen]
+pub struct ReaderSession {
+    session_manager: reader::SessionManager,
+    request: Vec<u8>,
+    trusts_issuers: bool,
+    issuer_authentication: Option<String>,
+}
+
+#[wasm_bindgen]
+impl ReaderSession {
+    /// Establish a session with the mdoc that displayed the `qr` engagement URI, requesting
+    /// the mDL elements in `requested`, an object mapping each namespace to an object of
+    /// element identifiers and whether the reader intends to retain them.
+    #[wasm_bindgen(js_name = establishSession)]
+    pub fn establish_session(qr: &str, requested: JsValue) -> Result<ReaderSession, JsError> {
+        let namespaces: device_request::Namespaces = serde_wasm_bindgen::from_value(requested)?;
+        let (session_manager, request, _ble_ident) =
+            reader::SessionManager::establish_session(qr.to_string(), namespaces)?;
+        Ok(Self {
+            session_manager,
+            request,
+            trusts_issuers: false,
+            issuer_authentication: None,
+        })
+    }
+
+    /// The session establishment message to send to the mdoc.
+    #[wasm_bindgen(getter)]
+    pub fn request(&self) -> Vec<u8> {
+        self.request.clone()
+    }
+
+    /// Trust the issuers whose IACA certificates are in `trust_anchors`, PEM encoded. Without
+    /// trust anchors, the issuer of a response is not authenticated.
+    #[wasm_bindgen(js_name = setTrustAnchors)]
+    pub fn set_trust_anchors(&mut self, trust_anchors: Vec<String>) -> Result<(), JsError> {
+        let registry = trust_anchors
+            .iter()
+            .try_fold(TrustAnchorRegistry::new(), |registry, pem| {
+                registry.with_pem(pem.as_bytes())
+            })
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        self.session_manager.set_trust_anchor_registry(registry);
+        self.trusts_issuers = true;
+        Ok(())
+    }
+
+    /// Decrypt and validate the mdoc's response, returning the disclosed elements by namespace,
+    /// then element identifier.
+    ///
+    /// Check [ReaderSession::issuer_authentication] before relying on them.
+    #[wasm_bindgen(js_name = handleResponse)]
+    pub fn handle_response(&mut self, response: &[u8]) -> Result<JsValue, JsError> {
+        self.issuer_authentication = None;
+        let claims = self.session_manager.handle_response(response)?;
+        self.issuer_authentication = Some(
+            match self.trusts_issuers {
+                true => "valid",
+                false => "unverified",
+            }
+            .to_string(),
+        );
+        let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+        Ok(serde::Serialize::serialize(&claims, &serializer)?)
+    }
+
+    /// How the issuer of the last response handled was authenticated: `"valid"` if it chains to
+    /// one of the trust anchors, or `"unverified"` if no trust anchors were set, in which case
+    /// the issuer is unknown. `undefined` before a response has been handled.
+    #[wasm_bindgen(getter, js_name = issuerAuthentication)]
+    pub fn issuer_authentication(&self) -> Option<String> {
+        self.issuer_authentication.clone()
+    }
+}
@@ -1,11 +1,18 @@
+// Each test binary uses a different subset of these helpers.
+#![allow(dead_code)]
+
 use anyhow::{anyhow, Context, Result};
+use p256::pkcs8::DecodePrivateKey;
+use serde_cbor::Value as CborValue;
 use signature::Signer;
-use uuid::Uuid;
+use time::Duration;
 
-use isomdl::definitions::device_engagement::{CentralClientMode, DeviceRetrievalMethods};
-use isomdl::definitions::device_request::{DataElements, DocType, Namespaces};
-use isomdl::definitions::helpers::NonEmptyMap;
-use isomdl::definitions::{self, BleOptions, DeviceRetrievalMethod};
+use isomdl::definitions::device_request::{DataElements, Namespaces};
+use isomdl::definitions::x509::{TrustAnchorRegistry, X5Chain};
+use isomdl::definitions::{
+    self, CoseKey, DeviceKeyInfo, DigestAlgorithm, EC2Curve, KeyAuthorizations, ValidityInfo, EC2Y,
+};
+use isomdl::issuance::{self, Mdoc};
 use isomdl::presentation::device::{Document, Documents, RequestedItems, SessionManagerEngaged};
 use isomdl::presentation::{device, reader, Stringify};
 
@@ -13,33 +20,197 @@ pub const DOC_TYPE: &str = "org.iso.18013.5.1.mDL";
 pub const NAMESPACE: &str = "org.iso.18013.5.1";
 pub const AGE_OVER_21_ELEMENT: &str = "age_over_21";
 
+/// The IACA certificate that issued the test document signer.
+pub const IACA_CERT: &[u8] = include_bytes!("../test/presentation/iaca-cert.pem");
+
+/// A registry trusting only the issuer of the test document signer.
+pub fn trust_anchor_registry() -> Result<TrustAnchorRegistry> {
+    TrustAnchorRegistry::new().with_pem(IACA_CERT)
+}
+
+/// The issuer signed elements of a single namespace.
+pub fn namespaces<'a>(
+    namespace: &str,
+    elements: impl IntoIterator<Item = (&'a str, CborValue)>,
+) -> issuance::Namespaces {
+    let elements = elements
+        .into_iter()
+        .map(|(element_identifier, value)| (element_identifier.to_string(), value))
+        .collect();
+    [(namespace.to_string(), elements)].into_iter().collect()
+}
+
+/// The elements of the mDL issued by [Device::issue_mdl].
+pub fn mdl_namespaces() -> issuance::Namespaces {
+    namespaces(
+        NAMESPACE,
+        [
+            (AGE_OVER_21_ELEMENT, CborValue::Bool(true)),
+            ("family_name", CborValue::Text("Smith".into())),
+        ],
+    )
+}
+
+/// A document to issue, signed by the test document signer.
+///
+/// Unless set otherwise, it is bound to the key returned by [Device::create_signing_key],
+/// digested with SHA-256, and valid for a year from now.
+pub struct Issue {
+    doc_type: String,
+    namespaces: issuance::Namespaces,
+    device_key: Option<CoseKey>,
+    key_authorizations: Option<KeyAuthorizations>,
+    digest_algorithm: DigestAlgorithm,
+    validity_info: Option<ValidityInfo>,
+    detached_payload: bool,
+}
+
+impl Issue {
+    pub fn new(doc_type: &str, namespaces: issuance::Namespaces) -> Self {
+        Self {
+            doc_type: doc_type.to_string(),
+            namespaces,
+            device_key: None,
+            key_authorizations: None,
+            digest_algorithm: DigestAlgorithm::SHA256,
+            validity_info: None,
+            detached_payload: false,
+        }
+    }
+
+    /// Bind the document to the P-256 `device_key`.
+    pub fn device_key(self, device_key: &p256::ecdsa::VerifyingKey) -> Self {
+        self.cose_device_key(p256_cose_key(device_key))
+    }
+
+    /// Bind the document to a device key on any curve.
+    pub fn cose_device_key(mut self, device_key: CoseKey) -> Self {
+        self.device_key = Some(device_key);
+        self
+    }
+
+    /// Authorize the device key to sign the given elements itself.
+    pub fn key_authorizations(mut self, key_authorizations: KeyAuthorizations) -> Self {
+        self.key_authorizations = Some(key_authorizations);
+        self
+    }
+
+    pub fn digest_algorithm(mut self, digest_algorithm: DigestAlgorithm) -> Self {
+        self.digest_algorithm = digest_algorithm;
+        self
+    }
+
+    pub fn validity_info(mut self, validity_info: ValidityInfo) -> Self {
+        self.validity_info = Some(validity_info);
+        self
+    }
+
+    /// Leave the mobile security object out of the issuer auth, to be conveyed separately.
+    pub fn detached_payload(mut self) -> Self {
+        self.detached_payload = true;
+        self
+    }
+
+    pub fn issue(self) -> Result<Document> {
+        Ok(self.mdoc()?.into())
+    }
+
+    /// Issue the document, as the issuer holds it before provisioning.
+    pub fn mdoc(self) -> Result<Mdoc> {
+        let device_key = match self.device_key {
+            Some(device_key) => device_key,
+            None => p256_cose_key(Device::create_signing_key()?.verifying_key()),
+        };
+        let validity_info = match self.validity_info {
+            Some(validity_info) => validity_info,
+            None => ValidityInfo::builder()
+                .valid_for(Duration::days(365))
+                .build()?,
+        };
+        let device_key_info = DeviceKeyInfo {
+            device_key,
+            key_authorizations: self.key_authorizations,
+            key_info: None,
+        };
+        let (x5chain, signer) = document_signer()?;
+        Mdoc::builder()
+            .doc_type(self.doc_type)
+            .namespaces(self.namespaces)
+            .validity_info(validity_info)
+            .digest_algorithm(self.digest_algorithm)
+            .device_key_info(device_key_info)
+            .detached_payload(self.detached_payload)
+            .issue::<_, p256::ecdsa::Signature>(x5chain, signer)
+    }
+}
+
+/// The x5chain and signing key of the test document signer.
+pub fn document_signer() -> Result<(X5Chain, p256::ecdsa::SigningKey)> {
+    let x5chain = X5Chain::builder()
+        .with_pem(include_bytes!("../test/presentation/ds-cert.pem"))?
+        .build()?;
+    let signer =
+        p256::SecretKey::from_pkcs8_pem(include_str!("../test/presentation/ds-key.pem"))?.into();
+    Ok((x5chain, signer))
+}
+
+fn p256_cose_key(key: &p256::ecdsa::VerifyingKey) -> CoseKey {
+    let point = key.to_encoded_point(false);
+    CoseKey::EC2 {
+        crv: EC2Curve::P256,
+        // An uncompressed point of a verifying key always has both coordinates.
+        x: point.x().expect("missing x coordinate").to_vec(),
+        y: EC2Y::Value(point.y().expect("missing y coordinate").to_vec()),
+    }
+}
+
 pub struct Device {}
 
 impl Device {
     /// Parse the mDL encoded string into a [Documents] object.
-    pub fn parse_mdl() -> Result<NonEmptyMap<DocType, Document>> {
+    pub fn parse_mdl() -> Result<Documents> {
         let mdl_encoded = include_str!("data/stringified-mdl.txt");
         let mdl = Document::parse(mdl_encoded.to_string()).context("could not parse mDL")?;
         let docs = Documents::new(DOC_TYPE.to_string(), mdl);
         Ok(docs)
     }
 
+    /// Issue an mDL of [mdl_namespaces] signed by the test document signer, bound to the key
+    /// returned by [Device::create_signing_key].
+    pub fn issue_mdl() -> Result<Documents> {
+        let mdl = Issue::new(DOC_TYPE, mdl_namespaces()).issue()?;
+        Ok(Documents::new(DOC_TYPE.to_string(), mdl))
+    }
+
     /// Creates a QR code containing `DeviceEngagement` data, which includes its public key.
     pub fn initialise_session() -> Result<(SessionManagerEngaged, String)> {
-        // Parse the mDL
-        let docs = Device::parse_mdl()?;
+        let docs = Device::issue_mdl()?;
+        Device::initialise_session_with(docs)
+    }
 
-        let drms = DeviceRetrievalMethods::new(DeviceRetrievalMethod::BLE(BleOptions {
-            peripheral_server_mode: None,
-            central_client_mode: Some(CentralClientMode {
-                uuid: Uuid::new_v4(),
-            }),
-        }));
+    /// As [Device::initialise_session], presenting `docs`.
+    pub fn initialise_session_with(docs: Documents) -> Result<(SessionManagerEngaged, String)> {
+        #[cfg(feature = "transports")]
+        let session = {
+            use isomdl::definitions::device_engagement::{
+                CentralClientMode, DeviceRetrievalMethods,
+            };
+            use isomdl::definitions::{BleOptions, DeviceRetrievalMethod};
+            use uuid::Uuid;
 
-        let session = device::SessionManagerInit::initialise(docs, Some(drms), None)
-            .context("failed to initialize device")?;
+            let drms = DeviceRetrievalMethods::new(DeviceRetrievalMethod::BLE(BleOptions {
+                peripheral_server_mode: None,
+                central_client_mode: Some(CentralClientMode {
+                    uuid: Uuid::new_v4(),
+                }),
+            }));
+            device::SessionManagerInit::initialise(docs, Some(drms), None)
+        };
+        #[cfg(not(feature = "transports"))]
+        let session = device::SessionManagerInit::initialise(docs, None);
 
         session
+            .context("failed to initialize device")?
             .qr_engagement()
             .context("could not generate qr engagement")
     }
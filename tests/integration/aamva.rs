@@ -0,0 +1,71 @@
+use anyhow::Result;
+use isomdl::definitions::device_request::{DataElements, DocType, Namespaces};
+use isomdl::definitions::helpers::NonEmptyMap;
+use isomdl::definitions::namespaces::org_iso_18013_5_1_aamva::{
+    elements, DHSCompliance, DomesticDrivingPrivileges, NAMESPACE as AAMVA_NAMESPACE,
+};
+use isomdl::issuance;
+use isomdl::presentation::device::{Documents, Wallet};
+use isomdl::presentation::reader;
+
+use crate::common::{trust_anchor_registry, Device, Issue, DOC_TYPE};
+
+const MDL_JSON: &str = include_str!("../../test/issuance/mdl.json");
+
+#[test]
+fn request_real_id_and_domestic_driving_privileges() -> Result<()> {
+    let key = Device::create_signing_key()?;
+    let namespaces = issuance::namespaces_from_json(&serde_json::from_str(MDL_JSON)?)?;
+    let mdl = Issue::new(DOC_TYPE, namespaces).issue()?;
+    let wallet = Wallet::new(Documents::new(DOC_TYPE.to_string(), mdl), key);
+
+    let mut requested = DataElements::new(elements::DHS_COMPLIANCE.to_string(), false);
+    requested.insert(elements::DOMESTIC_DRIVING_PRIVILEGES.to_string(), false);
+    let requested: NonEmptyMap<DocType, Namespaces> = NonEmptyMap::new(
+        DOC_TYPE.to_string(),
+        Namespaces::new(AAMVA_NAMESPACE.to_string(), requested),
+    );
+
+    let (mut engaged, qr_code_uri) = wallet.engage()?;
+    let (mut reader_session_manager, request, _ble_ident) =
+        reader::SessionManager::establish_session_for_documents(qr_code_uri, requested)?;
+    engaged.receive_request(&request)?;
+    let permitted = [(
+        DOC_TYPE.to_string(),
+        [(
+            AAMVA_NAMESPACE.to_string(),
+            vec![
+                elements::DHS_COMPLIANCE.to_string(),
+                elements::DOMESTIC_DRIVING_PRIVILEGES.to_string(),
+            ],
+        )]
+        .into_iter()
+        .collect(),
+    )]
+    .into_iter()
+    .collect();
+    let response = engaged.respond(permitted)?;
+
+    let registry = trust_anchor_registry()?;
+    let validated = reader_session_manager.validate_response(&response, &registry)?;
+    let document = validated
+        .document(DOC_TYPE)
+        .expect("response contains no mDL");
+    assert!(document.is_verified());
+
+    let dhs_compliance: DHSCompliance = document
+        .decode_element(AAMVA_NAMESPACE, elements::DHS_COMPLIANCE)
+        .expect("DHS_compliance was not disclosed")?;
+    assert!(dhs_compliance.is_real_id());
+
+    let privileges: DomesticDrivingPrivileges = document
+        .decode_element(AAMVA_NAMESPACE, elements::DOMESTIC_DRIVING_PRIVILEGES)
+        .expect("domestic_driving_privileges was not disclosed")?;
+    let class_codes: Vec<&str> = privileges
+        .iter()
+        .filter_map(|privilege| privilege.domestic_vehicle_class.as_ref())
+        .map(|class| class.domestic_vehicle_class_code.as_str())
+        .collect();
+    assert_eq!(class_codes, ["A", "B"]);
+    Ok(())
+}
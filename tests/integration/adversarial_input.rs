@@ -0,0 +1,68 @@
+use anyhow::Result;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::common::Device;
+use isomdl::definitions::device_key::cose_key::{CoseKey, EC2Curve, OKPCurve, EC2Y};
+use isomdl::definitions::helpers::Tag24;
+use isomdl::definitions::{SessionData, SessionEstablishment};
+
+fn random_bytes(rng: &mut StdRng) -> Vec<u8> {
+    let len = rng.gen_range(0..512);
+    (0..len).map(|_| rng.gen()).collect()
+}
+
+#[test]
+pub fn random_session_establishment() -> Result<()> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let (_, e_reader_key) = isomdl::definitions::session::create_p256_ephemeral_keys()?;
+    let malformed_keys = [
+        CoseKey::EC2 {
+            crv: EC2Curve::P256,
+            x: vec![1; 31],
+            y: EC2Y::Value(vec![1; 32]),
+        },
+        CoseKey::EC2 {
+            crv: EC2Curve::P256,
+            x: vec![0; 32],
+            y: EC2Y::SignBit(false),
+        },
+        CoseKey::OKP {
+            crv: OKPCurve::X25519,
+            x: vec![1; 32],
+        },
+    ];
+
+    for e_reader_key in malformed_keys
+        .into_iter()
+        .chain(std::iter::repeat_n(e_reader_key, 50))
+    {
+        let session_establishment = SessionEstablishment {
+            e_reader_key: Tag24::new(e_reader_key)?,
+            data: random_bytes(&mut rng).into(),
+        };
+        let (engaged_state, _) = Device::initialise_session()?;
+        assert!(engaged_state
+            .process_session_establishment(session_establishment)
+            .is_err());
+    }
+    Ok(())
+}
+
+#[test]
+pub fn random_responses() -> Result<()> {
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..50 {
+        let (_, qr_code_uri) = Device::initialise_session()?;
+        let (mut reader_session_manager, _) = Device::establish_reader_session(qr_code_uri)?;
+        let bytes = random_bytes(&mut rng);
+        let session_data = serde_cbor::to_vec(&SessionData {
+            data: Some(bytes.clone().into()),
+            status: None,
+        })?;
+        assert!(reader_session_manager
+            .handle_response(&session_data)
+            .is_err());
+        assert!(reader_session_manager.handle_response(&bytes).is_err());
+    }
+    Ok(())
+}
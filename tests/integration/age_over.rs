@@ -0,0 +1,81 @@
+use anyhow::{anyhow, Result};
+use serde_cbor::Value as CborValue;
+use signature::Signer;
+
+use crate::common::{namespaces, trust_anchor_registry, Device, Issue, DOC_TYPE, NAMESPACE};
+use isomdl::definitions::device_request::{age_over_element, DataElements, Namespaces};
+use isomdl::presentation::device::Documents;
+use isomdl::presentation::reader;
+
+/// Request `age_over_NN` from an mDL issued `age_over_21: true` and `age_over_60: false`, and
+/// return the age statements the reader receives.
+fn request_age_over(nn: u8) -> Result<Vec<(String, CborValue)>> {
+    let namespaces = namespaces(
+        NAMESPACE,
+        [
+            ("age_over_21", CborValue::Bool(true)),
+            ("age_over_60", CborValue::Bool(false)),
+        ],
+    );
+    let mdl = Issue::new(DOC_TYPE, namespaces).issue()?;
+    let key = Device::create_signing_key()?;
+    let (engaged_state, qr_code_uri) =
+        Device::initialise_session_with(Documents::new(DOC_TYPE.to_string(), mdl))?;
+
+    let requested_elements = Namespaces::new(NAMESPACE.into(), DataElements::age_over(nn, false)?);
+    let (mut reader_session_manager, request, _) =
+        reader::SessionManager::establish_session(qr_code_uri, requested_elements)?;
+    let (mut device_session_manager, requested_items) =
+        Device::handle_request(engaged_state, request)?;
+
+    let permitted_items = [(
+        DOC_TYPE.to_string(),
+        [(NAMESPACE.to_string(), vec![age_over_element(nn)?])]
+            .into_iter()
+            .collect(),
+    )]
+    .into_iter()
+    .collect();
+    device_session_manager.prepare_response(&requested_items, permitted_items);
+    let (_, payload) = device_session_manager
+        .get_next_signature_payload()
+        .ok_or(anyhow!("no documents to sign"))?;
+    let signature: p256::ecdsa::Signature = key.sign(payload);
+    device_session_manager.submit_next_signature(signature.to_vec())?;
+    let response = device_session_manager
+        .retrieve_response()
+        .ok_or(anyhow!("cannot prepare response"))?;
+
+    let registry = trust_anchor_registry()?;
+    let validated = reader_session_manager.validate_response(&response, &registry)?;
+    let document = validated
+        .document(DOC_TYPE)
+        .ok_or(anyhow!("response contains no mDL"))?;
+    assert!(document.is_verified());
+    assert!(validated.errors.is_empty());
+    Ok(document
+        .namespaces
+        .get(NAMESPACE)
+        .map(|elements| elements.clone().into_iter().collect())
+        .unwrap_or_default())
+}
+
+#[test]
+pub fn age_over_18() -> Result<()> {
+    // Being over 21 answers whether the holder is over 18.
+    assert_eq!(
+        request_age_over(18)?,
+        vec![("age_over_21".to_string(), CborValue::Bool(true))]
+    );
+    Ok(())
+}
+
+#[test]
+pub fn age_over_65() -> Result<()> {
+    // Not being over 60 answers whether the holder is over 65.
+    assert_eq!(
+        request_age_over(65)?,
+        vec![("age_over_60".to_string(), CborValue::Bool(false))]
+    );
+    Ok(())
+}
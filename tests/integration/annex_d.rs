@@ -0,0 +1,171 @@
+//! The examples of ISO/IEC 18013-5 Annex D, checked byte for byte against what the crate parses
+//! and encodes.
+//!
+//! Each structure is decoded and encoded again. A structure that does not reproduce the example
+//! fails with the path of the first field that differs.
+
+use anyhow::{anyhow, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_cbor::Value as CborValue;
+
+use isomdl::definitions::device_request::DeviceRequest;
+use isomdl::definitions::helpers::Tag24;
+use isomdl::definitions::session::{
+    decrypt_reader_data, derive_session_key, get_shared_secret, SessionTranscriptBytes,
+};
+use isomdl::definitions::{DeviceResponse, Mso, SessionEstablishment};
+use isomdl::presentation::reader::verify_value_digests;
+
+static SESSION_TRANSCRIPT: &str =
+    include_str!("../../test/definitions/session/session_transcript.cbor");
+static SESSION_ESTABLISHMENT: &str =
+    include_str!("../../test/definitions/session/session_establishment.cbor");
+static E_DEVICE_KEY: &str = include_str!("../../test/definitions/session/e_device_key.cbor");
+static SHARED_SECRET: &str = include_str!("../../test/definitions/session/shared_secret.cbor");
+static READER_SESSION_KEY: &str =
+    include_str!("../../test/definitions/session/reader_session_key.cbor");
+static DEVICE_RESPONSE: &str = include_str!("../../test/definitions/device_response.cbor");
+
+/// Decode `bytes` as a `T` and check that encoding it again gives back `bytes`.
+fn assert_reencodes<T: Serialize + DeserializeOwned>(name: &str, bytes: &[u8]) -> Result<T> {
+    let decoded: T = serde_cbor::from_slice(bytes)
+        .map_err(|e| anyhow!("unable to decode the example {}: {}", name, e))?;
+    let encoded = serde_cbor::to_vec(&decoded)?;
+    if encoded != bytes {
+        let expected: CborValue = serde_cbor::from_slice(bytes)?;
+        let actual: CborValue = serde_cbor::from_slice(&encoded)?;
+        let field = first_difference(&expected, &actual, name.to_string())
+            .unwrap_or_else(|| format!("{} (map order or integer width)", name));
+        panic!(
+            "the encoding of {} differs from the example at {}",
+            name, field
+        );
+    }
+    Ok(decoded)
+}
+
+/// The path of the first value that differs between `expected` and `actual`, looking inside
+/// embedded CBOR.
+fn first_difference(expected: &CborValue, actual: &CborValue, path: String) -> Option<String> {
+    match (expected, actual) {
+        (CborValue::Tag(24, expected), CborValue::Tag(24, actual)) => {
+            match (expected.as_ref(), actual.as_ref()) {
+                (CborValue::Bytes(expected_bytes), CborValue::Bytes(actual_bytes)) => {
+                    let expected: CborValue = serde_cbor::from_slice(expected_bytes).ok()?;
+                    let actual: CborValue = serde_cbor::from_slice(actual_bytes).ok()?;
+                    first_difference(&expected, &actual, format!("{}/24", path)).or_else(|| {
+                        (expected_bytes != actual_bytes).then(|| format!("{}/24 (encoding)", path))
+                    })
+                }
+                _ => first_difference(expected, actual, path),
+            }
+        }
+        (CborValue::Array(expected), CborValue::Array(actual)) => expected
+            .iter()
+            .zip(actual)
+            .enumerate()
+            .find_map(|(index, (expected, actual))| {
+                first_difference(expected, actual, format!("{}[{}]", path, index))
+            })
+            .or_else(|| (expected.len() != actual.len()).then(|| format!("{} (length)", path))),
+        (CborValue::Map(expected), CborValue::Map(actual)) => expected
+            .iter()
+            .find_map(|(key, expected)| match actual.get(key) {
+                Some(actual) => first_difference(expected, actual, format!("{}.{:?}", path, key)),
+                None => Some(format!("{}.{:?} (missing)", path, key)),
+            })
+            .or_else(|| {
+                actual
+                    .keys()
+                    .find(|key| !expected.contains_key(key))
+                    .map(|key| format!("{}.{:?} (unexpected)", path, key))
+            }),
+        (expected, actual) => (expected != actual).then_some(path),
+    }
+}
+
+#[test]
+pub fn session_transcript() -> Result<()> {
+    let transcript_bytes = hex::decode(SESSION_TRANSCRIPT)?;
+    let transcript: SessionTranscriptBytes =
+        assert_reencodes("SessionTranscriptBytes", &transcript_bytes)?;
+
+    // The transcript binds the ephemeral reader key that the reader sent.
+    let session_establishment: SessionEstablishment =
+        assert_reencodes("SessionEstablishment", &hex::decode(SESSION_ESTABLISHMENT)?)?;
+    assert_eq!(
+        transcript.as_ref().1.inner_bytes,
+        session_establishment.e_reader_key.inner_bytes,
+        "the transcript holds a different EReaderKeyBytes from the session establishment"
+    );
+
+    // Building the transcript from its parts reproduces the example.
+    let rebuilt = Tag24::new(transcript.as_ref().clone())?;
+    assert_eq!(serde_cbor::to_vec(&rebuilt)?, transcript_bytes);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "transports")]
+pub fn device_engagement() -> Result<()> {
+    let transcript: SessionTranscriptBytes =
+        serde_cbor::from_slice(&hex::decode(SESSION_TRANSCRIPT)?)?;
+    let device_engagement_bytes = serde_cbor::to_vec(&transcript.as_ref().0)?;
+    let device_engagement: Tag24<isomdl::definitions::DeviceEngagement> =
+        assert_reencodes("DeviceEngagementBytes", &device_engagement_bytes)?;
+    assert_eq!(device_engagement.as_ref().version, "1.0");
+    Ok(())
+}
+
+#[test]
+pub fn session_establishment() -> Result<()> {
+    let transcript: SessionTranscriptBytes =
+        serde_cbor::from_slice(&hex::decode(SESSION_TRANSCRIPT)?)?;
+    let session_establishment: SessionEstablishment =
+        serde_cbor::from_slice(&hex::decode(SESSION_ESTABLISHMENT)?)?;
+
+    let e_device_key = p256::SecretKey::from_slice(&hex::decode(E_DEVICE_KEY)?)?;
+    let shared_secret = get_shared_secret(
+        session_establishment.e_reader_key.as_ref().clone(),
+        &e_device_key.to_nonzero_scalar(),
+    )?;
+    assert_eq!(
+        hex::encode(shared_secret.raw_secret_bytes()),
+        SHARED_SECRET,
+        "the shared secret differs from the example"
+    );
+
+    let sk_reader = derive_session_key(&shared_secret, &transcript, true)?;
+    assert_eq!(
+        hex::encode(sk_reader),
+        READER_SESSION_KEY,
+        "SKReader differs from the example"
+    );
+
+    // The request decrypts, and is encoded as the reader encoded it.
+    let request = decrypt_reader_data(&sk_reader, session_establishment.data.as_ref(), &mut 0)
+        .map_err(|e| anyhow!("unable to decrypt the example request: {:?}", e))?;
+    let request: DeviceRequest = assert_reencodes("DeviceRequest", &request)?;
+    assert_eq!(request.version, "1.0");
+    Ok(())
+}
+
+#[test]
+pub fn device_response() -> Result<()> {
+    let response: DeviceResponse =
+        assert_reencodes("DeviceResponse", &hex::decode(DEVICE_RESPONSE)?)?;
+    assert!(!response.documents().is_empty());
+    for document in response.documents() {
+        let payload = document
+            .issuer_signed
+            .issuer_auth
+            .payload()
+            .ok_or(anyhow!("the example issuer auth has a detached payload"))?;
+        let mso: Tag24<Mso> = assert_reencodes("MobileSecurityObjectBytes", payload)?;
+        assert_eq!(mso.as_ref().doc_type, document.doc_type);
+        // Each disclosed element hashes to its digest in the MSO.
+        verify_value_digests(document, mso.as_ref())
+            .map_err(|mismatches| anyhow!("digest mismatches: {:?}", mismatches))?;
+    }
+    Ok(())
+}
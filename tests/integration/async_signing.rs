@@ -0,0 +1,60 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{anyhow, Result};
+
+use crate::common::{trust_anchor_registry, Device, AGE_OVER_21_ELEMENT, DOC_TYPE, NAMESPACE};
+use isomdl::presentation::reader::DeviceAuthResult;
+
+/// Stands in for a remote key, counting the signatures it is asked for.
+struct MockHsm {
+    key: p256::ecdsa::SigningKey,
+    signatures: AtomicUsize,
+}
+
+impl signature::Signer<p256::ecdsa::Signature> for MockHsm {
+    fn try_sign(&self, msg: &[u8]) -> Result<p256::ecdsa::Signature, signature::Error> {
+        self.signatures.fetch_add(1, Ordering::SeqCst);
+        self.key.try_sign(msg)
+    }
+}
+
+#[tokio::test]
+pub async fn prepare_response_async() -> Result<()> {
+    let hsm = MockHsm {
+        key: Device::create_signing_key()?,
+        signatures: AtomicUsize::new(0),
+    };
+    let registry = trust_anchor_registry()?;
+
+    let (engaged_state, qr_code_uri) = Device::initialise_session_with(Device::issue_mdl()?)?;
+    let (mut reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+    let (mut device_session_manager, requested_items) =
+        Device::handle_request(engaged_state, request)?;
+
+    let permitted_items = [(
+        DOC_TYPE.to_string(),
+        [(NAMESPACE.to_string(), vec![AGE_OVER_21_ELEMENT.to_string()])]
+            .into_iter()
+            .collect(),
+    )]
+    .into_iter()
+    .collect();
+    device_session_manager
+        .prepare_response_async::<_, p256::ecdsa::Signature>(
+            &requested_items,
+            permitted_items,
+            &hsm,
+        )
+        .await?;
+    assert_eq!(hsm.signatures.load(Ordering::SeqCst), 1);
+    let response = device_session_manager
+        .retrieve_response()
+        .ok_or(anyhow!("cannot prepare response"))?;
+
+    let validated = reader_session_manager.validate_response(&response, &registry)?;
+    let document = validated
+        .document(DOC_TYPE)
+        .ok_or(anyhow!("response contains no mDL"))?;
+    assert_eq!(document.device_auth, DeviceAuthResult::Signature);
+    Ok(())
+}
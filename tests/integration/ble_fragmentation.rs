@@ -0,0 +1,36 @@
+use anyhow::{anyhow, Result};
+
+use crate::common::{Device, AGE_OVER_21_ELEMENT, NAMESPACE};
+use isomdl::presentation::ble::{self, MessageAssembler};
+
+#[test]
+pub fn ble_fragmentation() -> Result<()> {
+    let (engaged_state, qr_code_uri) = Device::initialise_session()?;
+    let (mut reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+    let (device_session_manager, requested_items) = Device::handle_request(engaged_state, request)?;
+    let response = Device::create_response(
+        device_session_manager,
+        requested_items,
+        &Device::create_signing_key()?,
+    )?;
+
+    // The response is written in 20 byte fragments, as with the default BLE MTU.
+    let fragments = ble::fragment(&response, 20);
+    assert!(fragments.len() > 1);
+    let mut assembler = MessageAssembler::default();
+    let mut session_data = None;
+    for fragment in &fragments {
+        assert!(session_data.is_none(), "message completed early");
+        session_data = assembler.push(fragment)?;
+    }
+    let session_data = session_data.ok_or(anyhow!("message was not completed"))?;
+
+    let reassembled = serde_cbor::to_vec(&session_data)?;
+    assert_eq!(reassembled, response);
+    let parsed = reader_session_manager.handle_response(&reassembled)?;
+    assert_eq!(
+        parsed[NAMESPACE][AGE_OVER_21_ELEMENT],
+        serde_json::Value::Bool(true)
+    );
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{anyhow, Result};
+use cose_rs::algorithm::Algorithm;
+use signature::Signer;
+
+use crate::common::{trust_anchor_registry, Device, AGE_OVER_21_ELEMENT, DOC_TYPE, NAMESPACE};
+use isomdl::presentation::device::{self, MdocSigner, PermittedItems};
+
+/// A signer standing in for a remote key, which counts the payloads it signs.
+struct InMemorySigner {
+    key: p256::ecdsa::SigningKey,
+    algorithm: Algorithm,
+    signed: AtomicUsize,
+}
+
+impl MdocSigner for InMemorySigner {
+    fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    fn sign(&self, payload: &[u8]) -> Result<Vec<u8>> {
+        self.signed.fetch_add(1, Ordering::SeqCst);
+        let signature: p256::ecdsa::Signature = self.key.try_sign(payload)?;
+        Ok(signature.to_vec())
+    }
+}
+
+fn permitted() -> PermittedItems {
+    [(
+        DOC_TYPE.to_string(),
+        [(NAMESPACE.to_string(), vec![AGE_OVER_21_ELEMENT.to_string()])]
+            .into_iter()
+            .collect(),
+    )]
+    .into_iter()
+    .collect()
+}
+
+fn prepared_session() -> Result<(
+    isomdl::presentation::reader::SessionManager,
+    device::SessionManager,
+)> {
+    let (engaged_state, qr_code_uri) = Device::initialise_session()?;
+    let (reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+    let (mut device_session_manager, requested_items) =
+        Device::handle_request(engaged_state, request)?;
+    device_session_manager.prepare_response(&requested_items, permitted());
+    Ok((reader_session_manager, device_session_manager))
+}
+
+#[test]
+pub fn custom_signer() -> Result<()> {
+    let (mut reader_session_manager, mut device_session_manager) = prepared_session()?;
+    let signer = InMemorySigner {
+        key: Device::create_signing_key()?,
+        algorithm: Algorithm::ES256,
+        signed: AtomicUsize::new(0),
+    };
+    device_session_manager.sign_response(&signer)?;
+    assert_eq!(signer.signed.load(Ordering::SeqCst), 1);
+    let response = device_session_manager
+        .retrieve_response()
+        .ok_or(anyhow!("cannot prepare response"))?;
+
+    let registry = trust_anchor_registry()?;
+    let validated = reader_session_manager.validate_response(&response, &registry)?;
+    let document = validated
+        .document(DOC_TYPE)
+        .ok_or(anyhow!("response contains no mDL"))?;
+    assert!(document.is_verified());
+    Ok(())
+}
+
+#[test]
+pub fn signer_algorithm_mismatch() -> Result<()> {
+    let (_, mut device_session_manager) = prepared_session()?;
+    let signer = InMemorySigner {
+        key: Device::create_signing_key()?,
+        algorithm: Algorithm::ES384,
+        signed: AtomicUsize::new(0),
+    };
+    let error = device_session_manager
+        .sign_response(&signer)
+        .expect_err("a P-256 device key cannot be signed for with ES384");
+    assert!(matches!(
+        error.downcast_ref::<device::Error>(),
+        Some(device::Error::SignerAlgorithm {
+            signer: Algorithm::ES384,
+            expected: Algorithm::ES256,
+            ..
+        })
+    ));
+    assert_eq!(signer.signed.load(Ordering::SeqCst), 0);
+    assert!(device_session_manager.retrieve_response().is_none());
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use anyhow::Result;
+use isomdl::definitions::device_request::{DataElements, Namespaces};
+use isomdl::definitions::helpers::FullDate;
+use isomdl::issuance;
+use isomdl::presentation::device::{Documents, Wallet};
+use isomdl::presentation::reader::Verifier;
+
+use crate::common::{trust_anchor_registry, Device, Issue, DOC_TYPE, NAMESPACE};
+
+const MDL_JSON: &str = include_str!("../../test/issuance/mdl.json");
+
+#[test]
+fn birth_date_roundtrip() -> Result<()> {
+    let key = Device::create_signing_key()?;
+    let namespaces = issuance::namespaces_from_json(&serde_json::from_str(MDL_JSON)?)?;
+    let mdl = Issue::new(DOC_TYPE, namespaces).issue()?;
+    let wallet = Wallet::new(Documents::new(DOC_TYPE.to_string(), mdl), key);
+
+    let mut verifier = Verifier::new(trust_anchor_registry()?);
+    let (mut engaged, qr_code_uri) = wallet.engage()?;
+    let request = verifier.request(
+        qr_code_uri,
+        Namespaces::new(
+            NAMESPACE.to_string(),
+            DataElements::new("birth_date".to_string(), false),
+        ),
+    )?;
+    engaged.receive_request(&request)?;
+    let permitted = [(
+        DOC_TYPE.to_string(),
+        [(NAMESPACE.to_string(), vec!["birth_date".to_string()])]
+            .into_iter()
+            .collect(),
+    )]
+    .into_iter()
+    .collect();
+    let validated = verifier.verify(&engaged.respond(permitted)?)?;
+
+    let document = validated
+        .document(DOC_TYPE)
+        .expect("response contains no mDL");
+    assert!(document.is_verified());
+    let birth_date = FullDate::try_from(document.namespaces[NAMESPACE]["birth_date"].clone())?;
+    assert_eq!(birth_date.to_string(), "1980-01-01");
+    Ok(())
+}
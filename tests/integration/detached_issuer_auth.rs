@@ -0,0 +1,32 @@
+use anyhow::Result;
+
+use crate::common::{mdl_namespaces, trust_anchor_registry, Issue, DOC_TYPE};
+use isomdl::definitions::helpers::Tag24;
+use isomdl::presentation::reader::verify_detached_issuer_auth;
+
+#[test]
+pub fn detached_payload() -> Result<()> {
+    let mdoc = Issue::new(DOC_TYPE, mdl_namespaces())
+        .detached_payload()
+        .mdoc()?;
+    assert!(mdoc.issuer_auth.payload().is_none());
+
+    let registry = trust_anchor_registry()?;
+    let mso_bytes = mdoc.mso_bytes()?;
+    let mso = verify_detached_issuer_auth(&mdoc.issuer_auth, &mso_bytes, DOC_TYPE, &registry)?;
+    assert_eq!(mso.doc_type, DOC_TYPE);
+    assert_eq!(mso.value_digests, mdoc.mso.value_digests);
+
+    // The signature does not cover any other object.
+    let mut other = mdoc.mso.clone();
+    other.doc_type = "org.iso.7367.1.mVRC".to_string();
+    let other_bytes = serde_cbor::to_vec(&Tag24::new(other)?)?;
+    assert!(verify_detached_issuer_auth(
+        &mdoc.issuer_auth,
+        &other_bytes,
+        "org.iso.7367.1.mVRC",
+        &registry
+    )
+    .is_err());
+    Ok(())
+}
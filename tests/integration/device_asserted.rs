@@ -0,0 +1,87 @@
+use anyhow::{anyhow, Result};
+use serde_cbor::Value as CborValue;
+
+use crate::common::{
+    namespaces, trust_anchor_registry, Device, Issue, AGE_OVER_21_ELEMENT, DOC_TYPE, NAMESPACE,
+};
+use isomdl::definitions::device_request::{DataElements, Namespaces};
+use isomdl::definitions::helpers::{NonEmptyMap, NonEmptyVec};
+use isomdl::definitions::KeyAuthorizations;
+use isomdl::presentation::device::{Decision, DeviceAssertedItems, Document, Documents};
+use isomdl::presentation::reader;
+
+const NATIONALITY_ELEMENT: &str = "nationality";
+
+/// Issue an mDL whose device key is authorized to assert its holder's nationality.
+fn issue() -> Result<Document> {
+    Issue::new(
+        DOC_TYPE,
+        namespaces(NAMESPACE, [(AGE_OVER_21_ELEMENT, CborValue::Bool(true))]),
+    )
+    .key_authorizations(KeyAuthorizations {
+        namespaces: None,
+        data_elements: Some(NonEmptyMap::new(
+            NAMESPACE.to_string(),
+            NonEmptyVec::new(NATIONALITY_ELEMENT.to_string()),
+        )),
+    })
+    .issue()
+}
+
+#[test]
+pub fn device_asserted_element() -> Result<()> {
+    let (engaged_state, qr_code_uri) =
+        Device::initialise_session_with(Documents::new(DOC_TYPE.to_string(), issue()?))?;
+    let mut elements = DataElements::new(AGE_OVER_21_ELEMENT.to_string(), false);
+    elements.insert(NATIONALITY_ELEMENT.to_string(), false);
+    let (mut reader_session_manager, request, _) = reader::SessionManager::establish_session(
+        qr_code_uri,
+        Namespaces::new(NAMESPACE.to_string(), elements),
+    )?;
+    let (mut device_session_manager, requested_items) =
+        Device::handle_request(engaged_state, request)?;
+
+    let device_asserted: DeviceAssertedItems = [(
+        DOC_TYPE.to_string(),
+        [(
+            NAMESPACE.to_string(),
+            [(
+                NATIONALITY_ELEMENT.to_string(),
+                CborValue::Text("US".into()),
+            )]
+            .into_iter()
+            .collect(),
+        )]
+        .into_iter()
+        .collect(),
+    )]
+    .into_iter()
+    .collect();
+    device_session_manager.set_device_asserted_items(device_asserted);
+    device_session_manager.prepare_response_with(&requested_items, |_| Decision::Allow);
+    device_session_manager.sign_response(&Device::create_signing_key()?)?;
+    let response = device_session_manager
+        .retrieve_response()
+        .ok_or(anyhow!("cannot prepare response"))?;
+
+    let registry = trust_anchor_registry()?;
+    let validated = reader_session_manager.validate_response(&response, &registry)?;
+    let document = validated
+        .document(DOC_TYPE)
+        .ok_or(anyhow!("response contains no mDL"))?;
+    assert!(document.is_verified(), "{:?}", document);
+    assert!(document.request_mismatches.is_empty());
+
+    // The issuer vouches for the age statement, and the device alone for the nationality.
+    let issuer_signed = &document.namespaces[NAMESPACE];
+    assert_eq!(
+        issuer_signed.get(AGE_OVER_21_ELEMENT),
+        Some(&CborValue::Bool(true))
+    );
+    assert!(!issuer_signed.contains_key(NATIONALITY_ELEMENT));
+    assert_eq!(
+        document.device_signed_namespaces[NAMESPACE].get(NATIONALITY_ELEMENT),
+        Some(&CborValue::Text("US".into()))
+    );
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use anyhow::Result;
+
+use crate::common::{trust_anchor_registry, Device, AGE_OVER_21_ELEMENT, DOC_TYPE, NAMESPACE};
+use isomdl::definitions::helpers::Tag24;
+use isomdl::definitions::session::create_p256_ephemeral_keys;
+use isomdl::definitions::x509::TrustAnchorRegistry;
+use isomdl::definitions::{DeviceResponse, SessionTranscript180135};
+use isomdl::presentation::device::DeviceAuthMode;
+use isomdl::presentation::reader::{self, DeviceAuthResult};
+
+/// Run a session, returning the response the reader received and its session transcript.
+fn device_response() -> Result<(DeviceResponse, SessionTranscript180135)> {
+    let key = Device::create_signing_key()?;
+    let (engaged_state, qr_code_uri) = Device::initialise_session_with(Device::issue_mdl()?)?;
+    let (mut reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+    let (device_session_manager, requested_items) = Device::handle_request(engaged_state, request)?;
+    let response = Device::create_response(device_session_manager, requested_items, &key)?;
+
+    let validated =
+        reader_session_manager.validate_response(&response, &TrustAnchorRegistry::new())?;
+    Ok((
+        serde_cbor::from_slice(&validated.raw)?,
+        reader_session_manager.session_transcript().clone(),
+    ))
+}
+
+#[test]
+pub fn device_signature() -> Result<()> {
+    let (response, session_transcript) = device_response()?;
+    for document in response
+        .documents
+        .expect("response contains no documents")
+        .iter()
+    {
+        assert_eq!(
+            reader::verify_device_auth(document, &session_transcript),
+            DeviceAuthResult::Signature
+        );
+    }
+    Ok(())
+}
+
+#[test]
+pub fn tampered_session_transcript() -> Result<()> {
+    let (response, session_transcript) = device_response()?;
+    let (_, other_reader_key) = create_p256_ephemeral_keys()?;
+    let tampered = SessionTranscript180135(
+        session_transcript.0,
+        Tag24::new(other_reader_key)?,
+        session_transcript.2,
+    );
+    for document in response
+        .documents
+        .expect("response contains no documents")
+        .iter()
+    {
+        assert!(!reader::verify_device_auth(document, &tampered).is_valid());
+    }
+    Ok(())
+}
+
+#[test]
+pub fn device_mac() -> Result<()> {
+    let device_key = p256::SecretKey::from_sec1_pem(include_str!("../data/sec1.pem"))?;
+    let registry = trust_anchor_registry()?;
+
+    let (engaged_state, qr_code_uri) = Device::initialise_session_with(Device::issue_mdl()?)?;
+    let (mut reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+    let (mut device_session_manager, requested_items) =
+        Device::handle_request(engaged_state, request)?;
+
+    let permitted_items = [(
+        DOC_TYPE.to_string(),
+        [(NAMESPACE.to_string(), vec![AGE_OVER_21_ELEMENT.to_string()])]
+            .into_iter()
+            .collect(),
+    )]
+    .into_iter()
+    .collect();
+    device_session_manager.prepare_response_with_mode(
+        &requested_items,
+        permitted_items,
+        DeviceAuthMode::Mac(device_key),
+    )?;
+    assert!(device_session_manager
+        .get_next_signature_payload()
+        .is_none());
+    let response = device_session_manager
+        .retrieve_response()
+        .expect("response is not ready");
+
+    let validated = reader_session_manager.validate_response(&response, &registry)?;
+    let document = validated
+        .document(DOC_TYPE)
+        .expect("response contains no mDL");
+    assert_eq!(document.device_auth, DeviceAuthResult::Mac);
+    assert!(document.is_verified());
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use anyhow::Result;
+use serde_cbor::Value as CborValue;
+
+use crate::common::{namespaces, Device, Issue, AGE_OVER_21_ELEMENT, DOC_TYPE, NAMESPACE};
+use isomdl::presentation::device::Documents;
+use isomdl::presentation::reader;
+
+#[test]
+pub fn device_key_mismatch() -> Result<()> {
+    // The mDL is bound to a key other than the one the device signs the response with.
+    let namespaces = namespaces(NAMESPACE, [(AGE_OVER_21_ELEMENT, CborValue::Bool(true))]);
+    let other_key = p256::ecdsa::SigningKey::random(&mut rand::thread_rng());
+    let mdl = Issue::new(DOC_TYPE, namespaces)
+        .device_key(other_key.verifying_key())
+        .issue()?;
+    let key = Device::create_signing_key()?;
+
+    let (engaged_state, qr_code_uri) =
+        Device::initialise_session_with(Documents::new(DOC_TYPE.to_string(), mdl))?;
+    let (mut reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+    let (device_session_manager, requested_items) = Device::handle_request(engaged_state, request)?;
+    let response = Device::create_response(device_session_manager, requested_items, &key)?;
+
+    assert!(matches!(
+        reader_session_manager.handle_response(&response),
+        Err(reader::ResponseError::DeviceAuthenticationFailed(_))
+    ));
+    Ok(())
+}
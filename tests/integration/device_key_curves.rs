@@ -0,0 +1,91 @@
+use anyhow::{anyhow, Result};
+use cose_rs::algorithm::Algorithm;
+use serde_cbor::Value as CborValue;
+use signature::Signer;
+
+use crate::common::{
+    namespaces, trust_anchor_registry, Device, Issue, AGE_OVER_21_ELEMENT, DOC_TYPE, NAMESPACE,
+};
+use isomdl::definitions::{CoseKey, EC2Curve, EC2Y};
+use isomdl::presentation::device::Documents;
+use isomdl::presentation::reader::DeviceAuthResult;
+
+/// Present an mDL bound to `device_key`, signing the device authentication with `sign`, and
+/// check that the reader verifies it.
+fn present(
+    device_key: CoseKey,
+    algorithm: Algorithm,
+    sign: impl Fn(&[u8]) -> Vec<u8>,
+) -> Result<()> {
+    let namespaces = namespaces(NAMESPACE, [(AGE_OVER_21_ELEMENT, CborValue::Bool(true))]);
+    let mdl = Issue::new(DOC_TYPE, namespaces)
+        .cose_device_key(device_key)
+        .issue()?;
+
+    let (engaged_state, qr_code_uri) =
+        Device::initialise_session_with(Documents::new(DOC_TYPE.to_string(), mdl))?;
+    let (mut reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+    let (mut device_session_manager, requested_items) =
+        Device::handle_request(engaged_state, request)?;
+    let permitted_items = [(
+        DOC_TYPE.to_string(),
+        [(NAMESPACE.to_string(), vec![AGE_OVER_21_ELEMENT.to_string()])]
+            .into_iter()
+            .collect(),
+    )]
+    .into_iter()
+    .collect();
+    device_session_manager.prepare_response(&requested_items, permitted_items);
+
+    let (id, payload) = device_session_manager
+        .get_next_signature_payload()
+        .ok_or(anyhow!("no signature payload"))?;
+    assert_eq!(
+        device_session_manager.signature_algorithm(id),
+        Some(algorithm)
+    );
+    let signature = sign(payload);
+    device_session_manager.submit_next_signature(signature)?;
+    let response = device_session_manager
+        .retrieve_response()
+        .ok_or(anyhow!("cannot prepare response"))?;
+
+    let registry = trust_anchor_registry()?;
+    let validated = reader_session_manager.validate_response(&response, &registry)?;
+    let document = validated
+        .document(DOC_TYPE)
+        .ok_or(anyhow!("response contains no mDL"))?;
+    assert_eq!(document.device_auth, DeviceAuthResult::Signature);
+    assert!(document.is_verified());
+    Ok(())
+}
+
+#[test]
+pub fn p384_device_key() -> Result<()> {
+    let key = p384::ecdsa::SigningKey::random(&mut rand::thread_rng());
+    let point = key.verifying_key().to_encoded_point(false);
+    let device_key = CoseKey::EC2 {
+        crv: EC2Curve::P384,
+        x: point.x().ok_or(anyhow!("missing x coordinate"))?.to_vec(),
+        y: EC2Y::Value(point.y().ok_or(anyhow!("missing y coordinate"))?.to_vec()),
+    };
+    present(device_key, Algorithm::ES384, |payload| {
+        let signature: p384::ecdsa::Signature = key.sign(payload);
+        signature.to_vec()
+    })
+}
+
+#[test]
+pub fn p521_device_key() -> Result<()> {
+    let key = p521::ecdsa::SigningKey::random(&mut rand::thread_rng());
+    let point = p521::ecdsa::VerifyingKey::from(&key).to_encoded_point(false);
+    let device_key = CoseKey::EC2 {
+        crv: EC2Curve::P521,
+        x: point.x().ok_or(anyhow!("missing x coordinate"))?.to_vec(),
+        y: EC2Y::Value(point.y().ok_or(anyhow!("missing y coordinate"))?.to_vec()),
+    };
+    present(device_key, Algorithm::ES512, |payload| {
+        let signature: p521::ecdsa::Signature = key.sign(payload);
+        signature.to_vec()
+    })
+}
@@ -0,0 +1,64 @@
+use anyhow::{anyhow, Result};
+use serde_cbor::Value as CborValue;
+
+use crate::common::{
+    namespaces, trust_anchor_registry, Device, Issue, AGE_OVER_21_ELEMENT, DOC_TYPE, NAMESPACE,
+};
+use isomdl::definitions::DigestAlgorithm;
+use isomdl::presentation::device::Documents;
+
+/// Issue an mDL with `digest_algorithm`, present it, and check the reader verifies its digests.
+fn round_trip(digest_algorithm: DigestAlgorithm, name: &str, digest_len: usize) -> Result<()> {
+    let namespaces = namespaces(NAMESPACE, [(AGE_OVER_21_ELEMENT, CborValue::Bool(true))]);
+    let mdl = Issue::new(DOC_TYPE, namespaces)
+        .digest_algorithm(digest_algorithm)
+        .issue()?;
+    let key = Device::create_signing_key()?;
+
+    // The chosen algorithm is recorded in the MSO.
+    assert_eq!(mdl.mso.digest_algorithm, digest_algorithm);
+    let mso = match serde_cbor::value::to_value(&mdl.mso)? {
+        CborValue::Map(mso) => mso,
+        _ => return Err(anyhow!("the MSO is not encoded as a map")),
+    };
+    assert_eq!(
+        mso.get(&CborValue::Text("digestAlgorithm".into())),
+        Some(&CborValue::Text(name.into()))
+    );
+    assert!(mdl
+        .mso
+        .value_digests
+        .values()
+        .flat_map(|digests| digests.values())
+        .all(|digest| digest.as_ref().len() == digest_len));
+
+    let (engaged_state, qr_code_uri) =
+        Device::initialise_session_with(Documents::new(DOC_TYPE.to_string(), mdl))?;
+    let (mut reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+    let (device_session_manager, requested_items) = Device::handle_request(engaged_state, request)?;
+    let response = Device::create_response(device_session_manager, requested_items, &key)?;
+
+    let registry = trust_anchor_registry()?;
+    let validated = reader_session_manager.validate_response(&response, &registry)?;
+    let document = validated
+        .document(DOC_TYPE)
+        .ok_or(anyhow!("response contains no mDL"))?;
+    assert!(document.digest_mismatches.is_empty());
+    assert!(document.is_verified());
+    Ok(())
+}
+
+#[test]
+pub fn sha256() -> Result<()> {
+    round_trip(DigestAlgorithm::SHA256, "SHA-256", 32)
+}
+
+#[test]
+pub fn sha384() -> Result<()> {
+    round_trip(DigestAlgorithm::SHA384, "SHA-384", 48)
+}
+
+#[test]
+pub fn sha512() -> Result<()> {
+    round_trip(DigestAlgorithm::SHA512, "SHA-512", 64)
+}
@@ -0,0 +1,81 @@
+use anyhow::Result;
+
+use crate::common::{Device, AGE_OVER_21_ELEMENT, DOC_TYPE, NAMESPACE};
+use isomdl::definitions::device_request::{DataElements, ItemsRequest, Namespaces};
+use isomdl::presentation::device::{DisclosurePolicy, PermittedItems, RequestedItems};
+
+/// A request for both elements of the test mDL, and a portrait that it does not hold.
+fn requested() -> RequestedItems {
+    let mut elements = DataElements::new(AGE_OVER_21_ELEMENT.to_string(), false);
+    elements.insert("family_name".to_string(), false);
+    elements.insert("portrait".to_string(), false);
+    vec![ItemsRequest {
+        doc_type: DOC_TYPE.to_string(),
+        namespaces: Namespaces::new(NAMESPACE.to_string(), elements),
+        request_info: None,
+    }]
+}
+
+fn permitted(elements: &[&str]) -> PermittedItems {
+    [(
+        DOC_TYPE.to_string(),
+        [(
+            NAMESPACE.to_string(),
+            elements.iter().map(|element| element.to_string()).collect(),
+        )]
+        .into_iter()
+        .collect(),
+    )]
+    .into_iter()
+    .collect()
+}
+
+#[test]
+pub fn allow_list() -> Result<()> {
+    let documents = Device::issue_mdl()?;
+
+    let policy = DisclosurePolicy::allow_list()
+        .element(NAMESPACE.to_string(), AGE_OVER_21_ELEMENT.to_string())
+        .element(NAMESPACE.to_string(), "portrait".to_string());
+    assert_eq!(
+        documents.filter_disclosure(&requested(), &policy),
+        permitted(&[AGE_OVER_21_ELEMENT])
+    );
+
+    let policy = DisclosurePolicy::allow_list().namespace(NAMESPACE.to_string());
+    assert_eq!(
+        documents.filter_disclosure(&requested(), &policy),
+        permitted(&[AGE_OVER_21_ELEMENT, "family_name"])
+    );
+
+    let policy = DisclosurePolicy::allow_list();
+    assert!(documents
+        .filter_disclosure(&requested(), &policy)
+        .is_empty());
+    Ok(())
+}
+
+#[test]
+pub fn deny_list() -> Result<()> {
+    let documents = Device::issue_mdl()?;
+
+    let policy =
+        DisclosurePolicy::deny_list().element(NAMESPACE.to_string(), "family_name".to_string());
+    assert_eq!(
+        documents.filter_disclosure(&requested(), &policy),
+        permitted(&[AGE_OVER_21_ELEMENT])
+    );
+
+    let policy = DisclosurePolicy::deny_list().namespace(NAMESPACE.to_string());
+    assert!(documents
+        .filter_disclosure(&requested(), &policy)
+        .is_empty());
+
+    // Nothing is disclosed from a document that was not requested.
+    let mut other_doc_type = requested();
+    other_doc_type[0].doc_type = "org.iso.7367.1.mVRC".to_string();
+    assert!(documents
+        .filter_disclosure(&other_doc_type, &DisclosurePolicy::deny_list())
+        .is_empty());
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use anyhow::{anyhow, Result};
+use signature::Signer;
+
+use crate::common::{Device, AGE_OVER_21_ELEMENT, DOC_TYPE, NAMESPACE};
+use isomdl::definitions::device_request::{DataElements, Namespaces};
+use isomdl::definitions::device_response::DocumentErrorCode;
+use isomdl::definitions::SessionEstablishment;
+use isomdl::presentation::reader;
+
+/// An element the test mDL does not contain.
+const MISSING_ELEMENT: &str = "signature_usual_mark";
+
+#[test]
+pub fn missing_element() -> Result<()> {
+    let key = Device::create_signing_key()?;
+    let (engaged_state, qr_code_uri) = Device::initialise_session()?;
+
+    let mut data_elements = DataElements::new(AGE_OVER_21_ELEMENT.to_string(), false);
+    data_elements.insert(MISSING_ELEMENT.to_string(), false);
+    let (mut reader_session_manager, request, _ble_ident) =
+        reader::SessionManager::establish_session(
+            qr_code_uri,
+            Namespaces::new(NAMESPACE.into(), data_elements),
+        )?;
+
+    let session_establishment: SessionEstablishment = serde_cbor::from_slice(&request)?;
+    let (mut session_manager, requested_items) =
+        engaged_state.process_session_establishment(session_establishment)?;
+
+    // The holder permits everything that was requested.
+    let permitted_items = [(
+        DOC_TYPE.to_string(),
+        [(
+            NAMESPACE.to_string(),
+            vec![AGE_OVER_21_ELEMENT.to_string(), MISSING_ELEMENT.to_string()],
+        )]
+        .into_iter()
+        .collect(),
+    )]
+    .into_iter()
+    .collect();
+    session_manager.prepare_response(&requested_items, permitted_items);
+    let (_, sign_payload) = session_manager
+        .get_next_signature_payload()
+        .ok_or(anyhow!("no signature payload"))?;
+    let signature: p256::ecdsa::Signature = key.sign(sign_payload);
+    session_manager.submit_next_signature(signature.to_vec())?;
+    let response = session_manager
+        .retrieve_response()
+        .ok_or(anyhow!("cannot prepare response"))?;
+
+    let (parsed_response, errors) =
+        reader_session_manager.handle_response_with_errors(&response)?;
+    assert!(parsed_response[NAMESPACE].contains_key(AGE_OVER_21_ELEMENT));
+    assert_eq!(
+        errors[DOC_TYPE][NAMESPACE],
+        [(
+            MISSING_ELEMENT.to_string(),
+            DocumentErrorCode::DataNotReturned
+        )]
+        .into_iter()
+        .collect()
+    );
+    Ok(())
+}
@@ -0,0 +1,22 @@
+use anyhow::{anyhow, Result};
+
+use crate::common::{trust_anchor_registry, Device, DOC_TYPE};
+use isomdl::presentation::{device::Document, Stringify};
+
+#[test]
+pub fn parse_validated() -> Result<()> {
+    let documents = Device::issue_mdl()?;
+    let encoded = documents
+        .get(DOC_TYPE)
+        .ok_or(anyhow!("no mDL was issued"))?
+        .stringify()?;
+    let registry = trust_anchor_registry()?;
+
+    let (document, report) = Document::parse_validated(encoded, &registry)?;
+    assert!(report.is_valid(), "{:?}", report.errors());
+    assert_eq!(
+        document.issuer_subject().as_deref(),
+        Some("CN=Test Document Signer,O=Test Issuing Authority,C=US")
+    );
+    Ok(())
+}
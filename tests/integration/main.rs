@@ -0,0 +1,44 @@
+//! The integration tests, built as one binary.
+
+#[path = "../common.rs"]
+mod common;
+
+mod aamva;
+mod adversarial_input;
+mod age_over;
+mod annex_d;
+#[cfg(feature = "async")]
+mod async_signing;
+#[cfg(feature = "transports")]
+mod ble_fragmentation;
+mod custom_signer;
+mod dates;
+mod detached_issuer_auth;
+mod device_asserted;
+mod device_authentication;
+mod device_key_binding;
+mod device_key_curves;
+mod digest_algorithms;
+mod disclosure_policy;
+mod element_errors;
+mod issuer_certificate;
+mod multiple_documents;
+#[cfg(feature = "transports")]
+mod nfc_engagement;
+mod oid4vp;
+mod pid;
+mod portrait;
+mod reader_authentication;
+mod session_errors;
+mod session_termination;
+#[cfg(feature = "tracing")]
+mod spans;
+mod stored_credential;
+mod strict_cbor;
+mod trust_anchors;
+mod validated_response;
+mod validity_window;
+mod verifier;
+mod verify_issuer_signed;
+mod wallet;
+mod wildcard_request;
@@ -0,0 +1,297 @@
+use anyhow::{anyhow, Result};
+use serde_cbor::Value as CborValue;
+use signature::Signer;
+
+use crate::common::{
+    namespaces, trust_anchor_registry, Device, Issue, AGE_OVER_21_ELEMENT, DOC_TYPE, NAMESPACE,
+};
+use isomdl::definitions::device_request::{DataElements, Namespaces};
+use isomdl::definitions::device_response::Status;
+use isomdl::definitions::helpers::NonEmptyMap;
+use isomdl::definitions::DeviceResponse;
+use isomdl::issuance;
+use isomdl::presentation::device::{self, Documents, PayloadId};
+use isomdl::presentation::reader::{self, DeviceAuthResult};
+
+const VEHICLE_DOC_TYPE: &str = "org.iso.7367.1.mVRC";
+const VEHICLE_NAMESPACE: &str = "org.iso.7367.1";
+const VIN_ELEMENT: &str = "vehicle_identification_number";
+
+fn vehicle_namespaces() -> issuance::Namespaces {
+    namespaces(
+        VEHICLE_NAMESPACE,
+        [(VIN_ELEMENT, CborValue::Text("1M8GDM9AXKP042788".into()))],
+    )
+}
+
+/// The device and reader after the device has prepared a response disclosing one element from
+/// each of an mDL and a vehicle registration.
+struct PreparedSession {
+    device: device::SessionManager,
+    reader: reader::SessionManager,
+    mdl_key: p256::ecdsa::SigningKey,
+    vehicle_key: p256::ecdsa::SigningKey,
+}
+
+impl PreparedSession {
+    fn new() -> Result<Self> {
+        // Each document is bound to its own device key.
+        let mdl_key = Device::create_signing_key()?;
+        let vehicle_key = p256::ecdsa::SigningKey::random(&mut rand::thread_rng());
+        let mut docs: Documents = Device::issue_mdl()?;
+        docs.add(
+            VEHICLE_DOC_TYPE.to_string(),
+            Issue::new(VEHICLE_DOC_TYPE, vehicle_namespaces())
+                .device_key(vehicle_key.verifying_key())
+                .issue()?,
+        );
+
+        let (engaged_state, qr_code_uri) = Device::initialise_session_with(docs)?;
+        let mut requests = NonEmptyMap::new(
+            DOC_TYPE.to_string(),
+            Namespaces::new(
+                NAMESPACE.into(),
+                DataElements::new(AGE_OVER_21_ELEMENT.to_string(), false),
+            ),
+        );
+        requests.insert(
+            VEHICLE_DOC_TYPE.to_string(),
+            Namespaces::new(
+                VEHICLE_NAMESPACE.into(),
+                DataElements::new(VIN_ELEMENT.to_string(), false),
+            ),
+        );
+        let (reader, request, _ble_ident) =
+            reader::SessionManager::establish_session_for_documents(qr_code_uri, requests)?;
+        let (mut device, requested_items) = Device::handle_request(engaged_state, request)?;
+        assert_eq!(requested_items.len(), 2);
+
+        let permitted_items = [
+            (
+                DOC_TYPE.to_string(),
+                [(NAMESPACE.to_string(), vec![AGE_OVER_21_ELEMENT.to_string()])]
+                    .into_iter()
+                    .collect(),
+            ),
+            (
+                VEHICLE_DOC_TYPE.to_string(),
+                [(VEHICLE_NAMESPACE.to_string(), vec![VIN_ELEMENT.to_string()])]
+                    .into_iter()
+                    .collect(),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        device.prepare_response(&requested_items, permitted_items);
+
+        Ok(Self {
+            device,
+            reader,
+            mdl_key,
+            vehicle_key,
+        })
+    }
+
+    /// Sign the payload with the key of the document it authenticates.
+    fn sign(&self, id: PayloadId, payload: &[u8]) -> Result<Vec<u8>> {
+        // The trait is not imported, as its prepare_response would shadow that of the session
+        // manager.
+        let (doc_type, _) = device::DeviceSession::documents(&self.device)
+            .iter()
+            .find(|(_, doc)| doc.id == id)
+            .ok_or(anyhow!("signature payload for an unknown document"))?;
+        let key = if doc_type == DOC_TYPE {
+            &self.mdl_key
+        } else {
+            &self.vehicle_key
+        };
+        let signature: p256::ecdsa::Signature = key.sign(payload);
+        Ok(signature.to_vec())
+    }
+
+    /// Check that the reader receives both documents, authenticated.
+    fn verify_response(mut self) -> Result<()> {
+        let registry = trust_anchor_registry()?;
+        let response = self
+            .device
+            .retrieve_response()
+            .ok_or(anyhow!("cannot prepare response"))?;
+
+        let validated = self.reader.validate_response(&response, &registry)?;
+        assert_eq!(validated.documents.len(), 2);
+        for (doc_type, namespace, element) in [
+            (DOC_TYPE, NAMESPACE, AGE_OVER_21_ELEMENT),
+            (VEHICLE_DOC_TYPE, VEHICLE_NAMESPACE, VIN_ELEMENT),
+        ] {
+            let document = validated
+                .document(doc_type)
+                .ok_or(anyhow!("response contains no {}", doc_type))?;
+            assert!(document.namespaces[namespace].contains_key(element));
+            assert_eq!(document.device_auth, DeviceAuthResult::Signature);
+            assert!(document.is_verified());
+        }
+
+        let raw: DeviceResponse = serde_cbor::from_slice(&validated.raw)?;
+        assert_eq!(raw.status(), Status::OK);
+        assert!(raw.document_errors().is_empty());
+        let doc_types: Vec<&str> = raw
+            .documents()
+            .iter()
+            .map(|document| document.doc_type.as_str())
+            .collect();
+        assert_eq!(doc_types, [DOC_TYPE, VEHICLE_DOC_TYPE]);
+        Ok(())
+    }
+}
+
+#[test]
+pub fn multiple_documents() -> Result<()> {
+    let mut session = PreparedSession::new()?;
+
+    // One payload per document, each signed with the key of that document.
+    let mut signed = 0;
+    while let Some((id, payload)) = session.device.get_next_signature_payload() {
+        let signature = session.sign(id, payload)?;
+        session.device.submit_next_signature(signature)?;
+        signed += 1;
+    }
+    assert_eq!(signed, 2);
+
+    session.verify_response()
+}
+
+#[test]
+pub fn batch_signing() -> Result<()> {
+    let mut session = PreparedSession::new()?;
+
+    let payloads = session.device.pending_signature_payloads();
+    assert_eq!(payloads.len(), 2);
+    let signatures = payloads
+        .iter()
+        .map(|(id, payload)| Ok((*id, session.sign(*id, payload)?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    // A missing or unknown payload is rejected, leaving the response pending.
+    let error = session
+        .device
+        .submit_signatures(signatures[..1].to_vec())
+        .expect_err("missing signature accepted");
+    assert!(matches!(
+        error.downcast_ref::<device::Error>(),
+        Some(device::Error::MissingSignature(id)) if *id == signatures[1].0
+    ));
+    let unknown = (uuid::Uuid::new_v4(), signatures[0].1.clone());
+    let error = session
+        .device
+        .submit_signatures(vec![signatures[0].clone(), signatures[1].clone(), unknown])
+        .expect_err("unknown payload id accepted");
+    assert!(matches!(
+        error.downcast_ref::<device::Error>(),
+        Some(device::Error::UnknownPayloadId(_))
+    ));
+    assert!(!session.device.response_ready());
+
+    session.device.submit_signatures(signatures)?;
+    assert!(session.device.pending_signature_payloads().is_empty());
+    session.verify_response()
+}
+
+#[test]
+pub fn select_held_document() -> Result<()> {
+    let mdl = |element: &str, value: CborValue| {
+        Issue::new(DOC_TYPE, namespaces(NAMESPACE, [(element, value)])).issue()
+    };
+
+    // Two mDLs, of which only the second holds the requested element, and a vehicle
+    // registration that is not requested.
+    let mut docs = Documents::new(
+        DOC_TYPE.to_string(),
+        mdl("family_name", CborValue::Text("Smith".into()))?,
+    );
+    let second = mdl(AGE_OVER_21_ELEMENT, CborValue::Bool(true))?;
+    let second_id = second.id;
+    docs.add(DOC_TYPE.to_string(), second);
+    docs.add(
+        VEHICLE_DOC_TYPE.to_string(),
+        Issue::new(VEHICLE_DOC_TYPE, vehicle_namespaces()).issue()?,
+    );
+    assert_eq!(docs.get_all(DOC_TYPE).count(), 2);
+    assert_eq!(docs.iter().count(), 3);
+
+    let (engaged_state, qr_code_uri) = Device::initialise_session_with(docs)?;
+    let (mut reader, request) = Device::establish_reader_session(qr_code_uri)?;
+    let (mut device, requested_items) = Device::handle_request(engaged_state, request)?;
+    let permitted_items = [(
+        DOC_TYPE.to_string(),
+        [(NAMESPACE.to_string(), vec![AGE_OVER_21_ELEMENT.to_string()])]
+            .into_iter()
+            .collect(),
+    )]
+    .into_iter()
+    .collect();
+    device.prepare_response(&requested_items, permitted_items);
+
+    // Only the mDL that holds the element is presented.
+    let (id, payload) = device
+        .get_next_signature_payload()
+        .ok_or(anyhow!("no document to sign"))?;
+    assert_eq!(id, second_id);
+    let signature: p256::ecdsa::Signature = Device::create_signing_key()?.sign(payload);
+    device.submit_next_signature(signature.to_vec())?;
+    assert!(device.get_next_signature_payload().is_none());
+    let response = device
+        .retrieve_response()
+        .ok_or(anyhow!("cannot prepare response"))?;
+
+    let registry = trust_anchor_registry()?;
+    let validated = reader.validate_response(&response, &registry)?;
+    assert_eq!(validated.documents.len(), 1);
+    let document = validated
+        .document(DOC_TYPE)
+        .ok_or(anyhow!("response contains no mDL"))?;
+    assert!(document.namespaces[NAMESPACE].contains_key(AGE_OVER_21_ELEMENT));
+    assert!(document.is_verified());
+    Ok(())
+}
+
+#[test]
+pub fn selection_carried_from_request_to_response() -> Result<()> {
+    let mdl = |elements: &[(&str, CborValue)]| {
+        Issue::new(DOC_TYPE, namespaces(NAMESPACE, elements.iter().cloned())).issue()
+    };
+    let family_name = ("family_name", CborValue::Text("Smith".into()));
+
+    // The second mDL holds more of the namespace requested by the wildcard.
+    let mut docs = Documents::new(
+        DOC_TYPE.to_string(),
+        mdl(std::slice::from_ref(&family_name))?,
+    );
+    let second = mdl(&[family_name, (AGE_OVER_21_ELEMENT, CborValue::Bool(true))])?;
+    let second_id = second.id;
+    docs.add(DOC_TYPE.to_string(), second);
+
+    let (engaged_state, qr_code_uri) = Device::initialise_session_with(docs)?;
+    let (_reader, request, _ble_ident) = reader::SessionManager::establish_session(
+        qr_code_uri,
+        Namespaces::new(NAMESPACE.into(), DataElements::all(false)),
+    )?;
+    let (mut device, requested_items) = Device::handle_request(engaged_state, request)?;
+    assert_eq!(requested_items[0].namespaces[NAMESPACE].len(), 2);
+
+    // The holder permits an element that both mDLs hold, but the response is prepared from the
+    // mDL whose elements they were asked about.
+    let permitted_items = [(
+        DOC_TYPE.to_string(),
+        [(NAMESPACE.to_string(), vec!["family_name".to_string()])]
+            .into_iter()
+            .collect(),
+    )]
+    .into_iter()
+    .collect();
+    device.prepare_response(&requested_items, permitted_items);
+    let (id, _) = device
+        .get_next_signature_payload()
+        .ok_or(anyhow!("no document to sign"))?;
+    assert_eq!(id, second_id);
+    Ok(())
+}
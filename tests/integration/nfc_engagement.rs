@@ -0,0 +1,85 @@
+use anyhow::{Context, Result};
+
+use crate::common::{Device, AGE_OVER_21_ELEMENT, NAMESPACE};
+use isomdl::definitions::device_engagement::{CentralClientMode, DeviceRetrievalMethods};
+use isomdl::definitions::device_request::{DataElements, Namespaces};
+use isomdl::definitions::{self, BleOptions, DeviceRetrievalMethod};
+use isomdl::presentation::device::{self, SessionManagerEngaged};
+use isomdl::presentation::reader;
+
+// Stand-ins for the NDEF handover messages, which the session transcript carries opaquely.
+const HANDOVER_SELECT: &[u8] = b"handover select";
+const HANDOVER_REQUEST: &[u8] = b"handover request";
+
+/// Engage over NFC, offering to transfer over BLE, returning the encoded device engagement.
+fn nfc_engagement(handover_request: Option<Vec<u8>>) -> Result<(SessionManagerEngaged, Vec<u8>)> {
+    let drms = DeviceRetrievalMethods::new(DeviceRetrievalMethod::BLE(BleOptions {
+        peripheral_server_mode: None,
+        central_client_mode: Some(CentralClientMode {
+            uuid: uuid::Uuid::new_v4(),
+        }),
+    }));
+    let session = device::SessionManagerInit::initialise(Device::issue_mdl()?, Some(drms), None)
+        .context("failed to initialize device")?;
+    let device_engagement_bytes = session.device_engagement_bytes().to_vec();
+    Ok((
+        session.nfc_engagement(HANDOVER_SELECT.to_vec(), handover_request),
+        device_engagement_bytes,
+    ))
+}
+
+fn reader_session(
+    device_engagement_bytes: Vec<u8>,
+    handover_request: Option<Vec<u8>>,
+) -> Result<(reader::SessionManager, definitions::SessionEstablishment)> {
+    let (reader_session_manager, request, _ble_ident) =
+        reader::SessionManager::establish_session_nfc(
+            device_engagement_bytes,
+            HANDOVER_SELECT.to_vec(),
+            handover_request,
+            Namespaces::new(
+                NAMESPACE.into(),
+                DataElements::new(AGE_OVER_21_ELEMENT.to_string(), false),
+            ),
+        )?;
+    Ok((reader_session_manager, serde_cbor::from_slice(&request)?))
+}
+
+#[test]
+pub fn nfc_handover_round_trip() -> Result<()> {
+    for handover_request in [None, Some(HANDOVER_REQUEST.to_vec())] {
+        let (engaged_state, device_engagement_bytes) = nfc_engagement(handover_request.clone())?;
+        let (mut reader_session_manager, session_establishment) =
+            reader_session(device_engagement_bytes, handover_request)?;
+
+        // The device can only decrypt the request if it derived the same session keys.
+        let (device_session_manager, requested_items) =
+            engaged_state.process_session_establishment(session_establishment)?;
+        assert_eq!(
+            serde_cbor::to_vec(&device::DeviceSession::session_transcript(
+                &device_session_manager
+            ))?,
+            serde_cbor::to_vec(reader_session_manager.session_transcript())?
+        );
+
+        let response = Device::create_response(
+            device_session_manager,
+            requested_items,
+            &Device::create_signing_key()?,
+        )?;
+        reader_session_manager.handle_response(&response)?;
+    }
+    Ok(())
+}
+
+#[test]
+pub fn mismatched_handover() -> Result<()> {
+    let (engaged_state, device_engagement_bytes) = nfc_engagement(None)?;
+    let (_, session_establishment) =
+        reader_session(device_engagement_bytes, Some(HANDOVER_REQUEST.to_vec()))?;
+
+    assert!(engaged_state
+        .process_session_establishment(session_establishment)
+        .is_err());
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use anyhow::Result;
+use signature::Signer;
+
+use crate::common::{trust_anchor_registry, Device, AGE_OVER_21_ELEMENT, DOC_TYPE, NAMESPACE};
+use isomdl::definitions::device_request::{DataElements, ItemsRequest, Namespaces};
+use isomdl::definitions::OID4VPSessionTranscript;
+use isomdl::presentation::device::{DeviceSession, TranscriptSession};
+use isomdl::presentation::reader::{self, DeviceAuthResult};
+
+const CLIENT_ID: &str = "example.com";
+const RESPONSE_URI: &str = "https://example.com/12345/response";
+const NONCE: &str = "abcdefgh1234567890";
+const MDOC_GENERATED_NONCE: &str = "1234567890abcdefgh";
+
+/// Present the mDL over a session transcript built from an OpenID4VP authorization request.
+fn present(session_transcript: OID4VPSessionTranscript) -> Result<Vec<u8>> {
+    let key = Device::create_signing_key()?;
+    let session = TranscriptSession::new(Device::issue_mdl()?, session_transcript);
+    let requests = vec![ItemsRequest {
+        doc_type: DOC_TYPE.to_string(),
+        namespaces: Namespaces::new(
+            NAMESPACE.into(),
+            DataElements::new(AGE_OVER_21_ELEMENT.to_string(), false),
+        ),
+        request_info: None,
+    }];
+    let permitted_items = [(
+        DOC_TYPE.to_string(),
+        [(NAMESPACE.to_string(), vec![AGE_OVER_21_ELEMENT.to_string()])]
+            .into_iter()
+            .collect(),
+    )]
+    .into_iter()
+    .collect();
+
+    let mut prepared_response = session.prepare_response(&requests, permitted_items);
+    while let Some((_, payload)) = prepared_response.get_next_signature_payload() {
+        let signature: p256::ecdsa::Signature = key.sign(payload);
+        prepared_response.submit_next_signature(signature.to_vec());
+    }
+    Ok(serde_cbor::to_vec(&prepared_response.finalize_response())?)
+}
+
+fn session_transcript(nonce: &str) -> Result<OID4VPSessionTranscript> {
+    Ok(OID4VPSessionTranscript::new_oid4vp(
+        CLIENT_ID,
+        RESPONSE_URI,
+        nonce,
+        MDOC_GENERATED_NONCE,
+    )?)
+}
+
+#[test]
+pub fn oid4vp_presentation() -> Result<()> {
+    let registry = trust_anchor_registry()?;
+    let response = present(session_transcript(NONCE)?)?;
+
+    let validated =
+        reader::validate_device_response(&response, &session_transcript(NONCE)?, &registry)?;
+    let document = validated
+        .document(DOC_TYPE)
+        .expect("response contains no mDL");
+    assert_eq!(document.device_auth, DeviceAuthResult::Signature);
+    assert!(document.is_verified());
+
+    // A response to a different authorization request is not authenticated.
+    let validated = reader::validate_device_response(
+        &response,
+        &session_transcript("another nonce")?,
+        &registry,
+    )?;
+    let document = validated
+        .document(DOC_TYPE)
+        .expect("response contains no mDL");
+    assert!(!document.device_auth.is_valid());
+    Ok(())
+}
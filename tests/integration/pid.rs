@@ -0,0 +1,89 @@
+use anyhow::{anyhow, Result};
+use serde_cbor::Value as CborValue;
+use serde_json::json;
+
+use crate::common::{trust_anchor_registry, Device, Issue};
+use isomdl::definitions::device_request::{DataElements, Namespaces};
+use isomdl::definitions::helpers::NonEmptyMap;
+use isomdl::definitions::traits::FromJson;
+use isomdl::issuance;
+use isomdl::presentation::device::Documents;
+use isomdl::presentation::reader;
+
+const PID_DOC_TYPE: &str = "eu.europa.ec.eudi.pid.1";
+const PID_NAMESPACE: &str = "eu.europa.ec.eudi.pid.1";
+
+#[test]
+pub fn pid() -> Result<()> {
+    let namespaces = issuance::Namespaces::from_json(&json!({
+        PID_NAMESPACE: {
+            "family_name": "Mustermann",
+            "given_name": "Erika",
+            "age_over_18": true,
+            "age_in_years": 40,
+            "nationality": ["DE"],
+        }
+    }))?;
+    let key = Device::create_signing_key()?;
+    let pid = Issue::new(PID_DOC_TYPE, namespaces).issue()?;
+
+    let (engaged_state, qr_code_uri) =
+        Device::initialise_session_with(Documents::new(PID_DOC_TYPE.to_string(), pid))?;
+    let mut elements = DataElements::new("family_name".to_string(), false);
+    elements.insert("age_over_18".to_string(), false);
+    elements.insert("nationality".to_string(), false);
+    let (mut reader_session_manager, request, _ble_ident) =
+        reader::SessionManager::establish_session_for_documents(
+            qr_code_uri,
+            NonEmptyMap::new(
+                PID_DOC_TYPE.to_string(),
+                Namespaces::new(PID_NAMESPACE.to_string(), elements),
+            ),
+        )?;
+    let (mut device_session_manager, requested_items) =
+        Device::handle_request(engaged_state, request)?;
+
+    let permitted_items = [(
+        PID_DOC_TYPE.to_string(),
+        [(
+            PID_NAMESPACE.to_string(),
+            vec![
+                "family_name".to_string(),
+                "age_over_18".to_string(),
+                "nationality".to_string(),
+            ],
+        )]
+        .into_iter()
+        .collect(),
+    )]
+    .into_iter()
+    .collect();
+    device_session_manager.prepare_response(&requested_items, permitted_items);
+    while let Some((_, payload)) = device_session_manager.get_next_signature_payload() {
+        let signature: p256::ecdsa::Signature = signature::Signer::sign(&key, payload);
+        device_session_manager.submit_next_signature(signature.to_vec())?;
+    }
+    let response = device_session_manager
+        .retrieve_response()
+        .ok_or(anyhow!("cannot prepare response"))?;
+
+    let registry = trust_anchor_registry()?;
+    let validated = reader_session_manager.validate_response(&response, &registry)?;
+    let document = validated
+        .document(PID_DOC_TYPE)
+        .ok_or(anyhow!("response contains no PID"))?;
+    assert!(document.is_verified());
+
+    let elements = &document.namespaces[PID_NAMESPACE];
+    assert_eq!(elements.len(), 3);
+    assert_eq!(
+        elements["family_name"],
+        CborValue::Text("Mustermann".into())
+    );
+    assert_eq!(elements["age_over_18"], CborValue::Bool(true));
+    assert_eq!(
+        elements["nationality"],
+        CborValue::Array(vec![CborValue::Text("DE".into())])
+    );
+    Ok(())
+}
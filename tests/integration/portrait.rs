@@ -0,0 +1,57 @@
+use anyhow::Result;
+use isomdl::definitions::device_request::{DataElements, Namespaces};
+use isomdl::definitions::namespaces::org_iso_18013_5_1::{Portrait, PortraitError};
+use isomdl::presentation::device::{Documents, Wallet};
+use isomdl::presentation::reader::Verifier;
+
+use crate::common::{namespaces, trust_anchor_registry, Device, Issue, DOC_TYPE, NAMESPACE};
+
+const PORTRAIT: &[u8] = include_bytes!("../../test/issuance/portrait.jpg");
+
+#[test]
+fn disclosed_portrait() -> Result<()> {
+    let key = Device::create_signing_key()?;
+    let portrait = Portrait::new(PORTRAIT.to_vec())?;
+    let mdl = Issue::new(
+        DOC_TYPE,
+        namespaces(NAMESPACE, [("portrait", portrait.into())]),
+    )
+    .issue()?;
+    let wallet = Wallet::new(Documents::new(DOC_TYPE.to_string(), mdl), key);
+
+    let mut verifier = Verifier::new(trust_anchor_registry()?);
+    let (mut engaged, qr_code_uri) = wallet.engage()?;
+    let request = verifier.request(
+        qr_code_uri,
+        Namespaces::new(
+            NAMESPACE.to_string(),
+            DataElements::new("portrait".to_string(), false),
+        ),
+    )?;
+    engaged.receive_request(&request)?;
+    let permitted = [(
+        DOC_TYPE.to_string(),
+        [(NAMESPACE.to_string(), vec!["portrait".to_string()])]
+            .into_iter()
+            .collect(),
+    )]
+    .into_iter()
+    .collect();
+    let validated = verifier.verify(&engaged.respond(permitted)?)?;
+    let document = validated
+        .document(DOC_TYPE)
+        .expect("response contains no mDL");
+
+    let portrait = document
+        .portrait(PORTRAIT.len())
+        .expect("portrait was not disclosed")?;
+    assert_eq!(portrait.as_bytes(), PORTRAIT);
+    assert_eq!(
+        document.portrait(1024),
+        Some(Err(PortraitError::TooLarge {
+            size: PORTRAIT.len(),
+            max: 1024
+        }))
+    );
+    Ok(())
+}
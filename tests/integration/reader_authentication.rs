@@ -0,0 +1,156 @@
+use anyhow::Result;
+use p256::pkcs8::DecodePrivateKey;
+
+use crate::common::{Device, AGE_OVER_21_ELEMENT, NAMESPACE};
+use isomdl::definitions::device_request::{DataElements, Namespaces};
+use isomdl::definitions::x509::{TrustAnchorRegistry, X5Chain, X509};
+use isomdl::definitions::SessionEstablishment;
+use isomdl::presentation::device::ReaderAuthOutcome;
+use isomdl::presentation::reader;
+
+const READER_KEY: &str = include_str!("../../test/presentation/reader-key.pem");
+const READER_CERT: &[u8] = include_bytes!("../../test/presentation/reader-cert.pem");
+const READER_ROOT: &[u8] = include_bytes!("../../test/presentation/reader-root-cert.pem");
+const OTHER_KEY: &str = include_str!("../../test/issuance/256-key.pem");
+const OTHER_CERT: &[u8] = include_bytes!("../../test/issuance/256-cert.pem");
+
+/// Run a session in which the reader signs its request with `key`, presenting `cert`, and
+/// return the outcome of reader authentication on the device.
+fn reader_auth_outcome(key: &str, cert: &[u8]) -> Result<ReaderAuthOutcome> {
+    let key: p256::ecdsa::SigningKey = p256::SecretKey::from_pkcs8_pem(key)?.into();
+    let x5chain = X5Chain::builder().with_pem(cert)?.build()?;
+    let registry = TrustAnchorRegistry::new().with_pem(READER_ROOT)?;
+
+    let (engaged_state, qr_code_uri) = Device::initialise_session()?;
+    let requested_elements = Namespaces::new(
+        NAMESPACE.into(),
+        DataElements::new(AGE_OVER_21_ELEMENT.to_string(), false),
+    );
+    let (_reader_session_manager, request, _ble_ident) =
+        reader::SessionManager::establish_session_signed::<_, p256::ecdsa::Signature>(
+            qr_code_uri,
+            requested_elements,
+            key,
+            x5chain,
+        )?;
+
+    let session_establishment: SessionEstablishment = serde_cbor::from_slice(&request)?;
+    let (_device_session_manager, _requested_items, outcome) = engaged_state
+        .process_session_establishment_with_reader_auth(session_establishment, &registry)?;
+    Ok(outcome)
+}
+
+#[test]
+pub fn trusted_reader() -> Result<()> {
+    assert_eq!(
+        reader_auth_outcome(READER_KEY, READER_CERT)?,
+        ReaderAuthOutcome {
+            authenticated: true,
+            certificate: Some(X509::from_pem(READER_CERT)?),
+        }
+    );
+    Ok(())
+}
+
+#[test]
+pub fn untrusted_reader() -> Result<()> {
+    assert_eq!(
+        reader_auth_outcome(OTHER_KEY, OTHER_CERT)?,
+        ReaderAuthOutcome {
+            authenticated: false,
+            certificate: Some(X509::from_pem(OTHER_CERT)?),
+        }
+    );
+    Ok(())
+}
+
+#[test]
+pub fn invalid_reader_signature() -> Result<()> {
+    assert_eq!(
+        reader_auth_outcome(OTHER_KEY, READER_CERT)?,
+        ReaderAuthOutcome {
+            authenticated: false,
+            certificate: None,
+        }
+    );
+    Ok(())
+}
+
+#[test]
+pub fn unauthenticated_request() -> Result<()> {
+    let registry = TrustAnchorRegistry::new().with_pem(READER_ROOT)?;
+    let (engaged_state, qr_code_uri) = Device::initialise_session()?;
+    let (_reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+
+    let session_establishment: SessionEstablishment = serde_cbor::from_slice(&request)?;
+    let (device_session_manager, _requested_items, outcome) = engaged_state
+        .process_session_establishment_with_reader_auth(session_establishment, &registry)?;
+    assert!(device_session_manager.reader_x5chain().is_none());
+    assert_eq!(
+        outcome,
+        ReaderAuthOutcome {
+            authenticated: false,
+            certificate: None,
+        }
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "test-vectors")]
+pub fn partially_authenticated_request() -> Result<()> {
+    use isomdl::definitions::device_request::{DeviceRequest, DocRequest};
+    use isomdl::definitions::session;
+
+    let key: p256::ecdsa::SigningKey = p256::SecretKey::from_pkcs8_pem(READER_KEY)?.into();
+    let x5chain = X5Chain::builder().with_pem(READER_CERT)?.build()?;
+    let registry = TrustAnchorRegistry::new().with_pem(READER_ROOT)?;
+
+    let (engaged_state, qr_code_uri) = Device::initialise_session()?;
+    let requested_elements = Namespaces::new(
+        NAMESPACE.into(),
+        DataElements::new(AGE_OVER_21_ELEMENT.to_string(), false),
+    );
+    let (reader_session_manager, request, _ble_ident) =
+        reader::SessionManager::establish_session_signed::<_, p256::ecdsa::Signature>(
+            qr_code_uri,
+            requested_elements,
+            key,
+            x5chain,
+        )?;
+
+    // Add an unsigned document request alongside the signed one.
+    let sk_reader = reader_session_manager.session_keys().sk_reader;
+    let mut session_establishment: SessionEstablishment = serde_cbor::from_slice(&request)?;
+    let mut device_request: DeviceRequest = serde_cbor::from_slice(&session::decrypt_reader_data(
+        (&sk_reader).into(),
+        session_establishment.data.as_ref(),
+        &mut 0,
+    )?)?;
+    let items_request = device_request.doc_requests.first().items_request.clone();
+    device_request.doc_requests.push(DocRequest {
+        items_request,
+        reader_auth: None,
+    });
+    session_establishment.data = session::encrypt_reader_data(
+        (&sk_reader).into(),
+        &serde_cbor::to_vec(&device_request)?,
+        &mut 0,
+    )?
+    .into();
+
+    let (device_session_manager, _requested_items, outcome) = engaged_state
+        .process_session_establishment_with_reader_auth(session_establishment, &registry)?;
+    assert!(matches!(
+        device_session_manager.reader_x5chain(),
+        Some(Err(_))
+    ));
+    assert_eq!(
+        outcome,
+        ReaderAuthOutcome {
+            authenticated: false,
+            certificate: None,
+        }
+    );
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use anyhow::Result;
+
+use crate::common::{Device, AGE_OVER_21_ELEMENT, NAMESPACE};
+use isomdl::definitions::device_request::{DataElements, Namespaces};
+use isomdl::definitions::session::{SessionError, Status};
+use isomdl::definitions::SessionData;
+use isomdl::presentation::{device, reader};
+
+/// Flip a bit of the ciphertext in the encoded session data.
+fn tamper(message: &[u8]) -> Result<Vec<u8>> {
+    let session_data: SessionData = serde_cbor::from_slice(message)?;
+    let mut ciphertext = session_data
+        .data
+        .expect("session data carries no ciphertext")
+        .as_ref()
+        .to_vec();
+    ciphertext[0] ^= 1;
+    Ok(serde_cbor::to_vec(&SessionData {
+        data: Some(ciphertext.into()),
+        status: None,
+    })?)
+}
+
+/// Check that the error is reported to the peer with the expected status code.
+fn assert_reported(error: SessionError, status: Status) -> Result<()> {
+    assert_eq!(error.status(), status);
+    let session_data: SessionData = serde_cbor::from_slice(&error.to_session_data())?;
+    assert!(session_data.data.is_none());
+    assert_eq!(session_data.status, Some(status));
+    Ok(())
+}
+
+fn device_session() -> Result<(device::SessionManager, reader::SessionManager)> {
+    let (engaged_state, qr_code_uri) = Device::initialise_session()?;
+    let (reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+    let (device_session_manager, _) = Device::handle_request(engaged_state, request)?;
+    Ok((device_session_manager, reader_session_manager))
+}
+
+fn session_error(error: &anyhow::Error) -> Option<SessionError> {
+    match error.downcast_ref::<device::Error>() {
+        Some(device::Error::Session(e)) => Some(*e),
+        _ => None,
+    }
+}
+
+#[test]
+pub fn device_decoding_error() -> Result<()> {
+    let (mut device_session_manager, _) = device_session()?;
+    let error = device_session_manager
+        .handle_request(&[0xff, 0x00])
+        .expect_err("malformed request accepted");
+    assert_eq!(session_error(&error), Some(SessionError::CborDecoding));
+    assert_reported(SessionError::CborDecoding, Status::CborDecodingError)
+}
+
+#[test]
+pub fn device_decryption_error() -> Result<()> {
+    let (mut device_session_manager, mut reader_session_manager) = device_session()?;
+    let request = reader_session_manager.new_request(Namespaces::new(
+        NAMESPACE.into(),
+        DataElements::new(AGE_OVER_21_ELEMENT.to_string(), false),
+    ))?;
+    let error = device_session_manager
+        .handle_request(&tamper(&request)?)
+        .expect_err("tampered request accepted");
+    assert_eq!(session_error(&error), Some(SessionError::Encryption));
+    assert_reported(SessionError::Encryption, Status::SessionEncryptionError)
+}
+
+#[test]
+pub fn reader_errors() -> Result<()> {
+    let key = Device::create_signing_key()?;
+    let (engaged_state, qr_code_uri) = Device::initialise_session()?;
+    let (mut reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+    let (device_session_manager, requested_items) = Device::handle_request(engaged_state, request)?;
+    let response = Device::create_response(device_session_manager, requested_items, &key)?;
+
+    assert!(matches!(
+        reader_session_manager.handle_response(&[0xff, 0x00]),
+        Err(reader::ResponseError::Session(SessionError::CborDecoding))
+    ));
+    assert!(matches!(
+        reader_session_manager.handle_response(&tamper(&response)?),
+        Err(reader::ResponseError::Session(SessionError::Encryption))
+    ));
+    Ok(())
+}
+
+#[test]
+pub fn replayed_request() -> Result<()> {
+    let key = Device::create_signing_key()?;
+    let (mut device_session_manager, mut reader_session_manager) = device_session()?;
+    let request = reader_session_manager.new_request(Namespaces::new(
+        NAMESPACE.into(),
+        DataElements::new(AGE_OVER_21_ELEMENT.to_string(), false),
+    ))?;
+    let requested_items = device_session_manager.handle_request(&request)?;
+
+    let error = device_session_manager
+        .handle_request(&request)
+        .expect_err("replayed request accepted");
+    assert_eq!(session_error(&error), Some(SessionError::Encryption));
+
+    // The replay does not disrupt the session.
+    let response = Device::create_response(device_session_manager, requested_items, &key)?;
+    reader_session_manager.handle_response(&response)?;
+    Ok(())
+}
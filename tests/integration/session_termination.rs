@@ -0,0 +1,65 @@
+use anyhow::Result;
+
+use crate::common::{Device, AGE_OVER_21_ELEMENT, NAMESPACE};
+use isomdl::definitions::device_request::{DataElements, Namespaces};
+use isomdl::definitions::session::Status;
+use isomdl::definitions::SessionData;
+use isomdl::presentation::{device, reader};
+
+#[test]
+pub fn session_termination() -> Result<()> {
+    let key = Device::create_signing_key()?;
+    let (engaged_state, qr_code_uri) = Device::initialise_session()?;
+    let (mut reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+    let (mut device_session_manager, requested_items) =
+        Device::handle_request(engaged_state, request)?;
+    let response = Device::create_response(device_session_manager.clone(), requested_items, &key)?;
+    reader_session_manager.handle_response(&response)?;
+
+    let next_request = reader_session_manager.new_request(Namespaces::new(
+        NAMESPACE.into(),
+        DataElements::new(AGE_OVER_21_ELEMENT.to_string(), false),
+    ))?;
+    let termination = device_session_manager.terminate();
+    let session_data: SessionData = serde_cbor::from_slice(&termination)?;
+    assert!(session_data.data.is_none());
+    assert!(matches!(
+        session_data.status,
+        Some(Status::SessionTermination)
+    ));
+
+    // The device rejects any requests after the termination.
+    let error = device_session_manager
+        .handle_request(&next_request)
+        .expect_err("request accepted after termination");
+    assert!(matches!(
+        error.downcast_ref::<device::Error>(),
+        Some(device::Error::SessionEnded)
+    ));
+
+    // As does the reader for responses.
+    reader_session_manager.handle_termination(&termination)?;
+    assert!(reader_session_manager.is_terminated());
+    assert!(matches!(
+        reader_session_manager.handle_response(&response),
+        Err(reader::ResponseError::SessionEnded)
+    ));
+    Ok(())
+}
+
+#[test]
+pub fn not_a_termination() -> Result<()> {
+    let key = Device::create_signing_key()?;
+    let (engaged_state, qr_code_uri) = Device::initialise_session()?;
+    let (mut reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+    let (device_session_manager, requested_items) = Device::handle_request(engaged_state, request)?;
+    let response = Device::create_response(device_session_manager, requested_items, &key)?;
+
+    assert!(matches!(
+        reader_session_manager.handle_termination(&response),
+        Err(reader::ResponseError::NotATermination)
+    ));
+    assert!(!reader_session_manager.is_terminated());
+    reader_session_manager.handle_response(&response)?;
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+use crate::common::Device;
+
+/// Records the name of every span created.
+#[derive(Clone, Default)]
+struct SpanNames(Arc<Mutex<Vec<&'static str>>>);
+
+impl Subscriber for SpanNames {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut names = self.0.lock().unwrap();
+        names.push(span.metadata().name());
+        Id::from_u64(names.len() as u64)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, _: &Event<'_>) {}
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+#[test]
+pub fn session_spans() -> Result<()> {
+    let span_names = SpanNames::default();
+    tracing::subscriber::with_default(span_names.clone(), || -> Result<()> {
+        let (engaged_state, qr_code_uri) = Device::initialise_session()?;
+        let (mut reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+        let (device_session_manager, requested_items) =
+            Device::handle_request(engaged_state, request)?;
+        let response = Device::create_response(
+            device_session_manager,
+            requested_items,
+            &Device::create_signing_key()?,
+        )?;
+        reader_session_manager.handle_response(&response)?;
+        Ok(())
+    })?;
+
+    let names = span_names.0.lock().unwrap();
+    for expected in [
+        "initialise",
+        "qr_engagement",
+        "establish_session_with_handover",
+        "build_request_with_reader_auth",
+        "process_session_establishment",
+        "prepare_response",
+        "submit_next_signature",
+        "respond",
+        "handle_response_with_errors",
+    ] {
+        assert!(names.contains(&expected), "no {expected} span in {names:?}");
+    }
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Result};
+
+use crate::common::{Device, DOC_TYPE};
+use isomdl::definitions::helpers::Tag24;
+use isomdl::presentation::device::{
+    Document, StorageError, StoredCredential, STORED_CREDENTIAL_VERSION,
+};
+
+#[test]
+pub fn round_trip() -> Result<()> {
+    let documents = Device::issue_mdl()?;
+    let document = documents
+        .get(DOC_TYPE)
+        .ok_or(anyhow!("no mDL was issued"))?;
+
+    let bytes = StoredCredential::from(document).to_bytes()?;
+    let stored = StoredCredential::from_bytes(&bytes)?;
+    assert_eq!(stored.version(), STORED_CREDENTIAL_VERSION);
+    assert_eq!(stored.doc_type, DOC_TYPE);
+
+    let restored = Document::try_from(stored)?;
+    assert_eq!(restored.mso.doc_type, DOC_TYPE);
+    assert_eq!(restored.available_elements(), document.available_elements());
+    // Nothing is lost: the restored document is stored as the same bytes.
+    assert_eq!(StoredCredential::from(&restored).to_bytes()?, bytes);
+    Ok(())
+}
+
+#[test]
+pub fn older_version() -> Result<()> {
+    // A document stringified before versioned storage existed.
+    let legacy = base64::decode(include_str!("../data/stringified-mdl.txt").trim())?;
+    let error = StoredCredential::from_bytes(&legacy).expect_err("a legacy blob was parsed");
+    assert!(matches!(error, StorageError::Unversioned));
+    assert_eq!(
+        error.to_string(),
+        "the stored credential has no version, so it predates versioned storage"
+    );
+
+    // A credential from a release that used a different layout.
+    let documents = Device::issue_mdl()?;
+    let document = documents
+        .get(DOC_TYPE)
+        .ok_or(anyhow!("no mDL was issued"))?;
+    let mut value: serde_cbor::Value =
+        serde_cbor::from_slice(&StoredCredential::from(document).to_bytes()?)?;
+    if let serde_cbor::Value::Map(map) = &mut value {
+        map.insert(
+            serde_cbor::Value::Text("version".into()),
+            serde_cbor::Value::Integer(0),
+        );
+    }
+    let error = StoredCredential::from_bytes(&serde_cbor::to_vec(&value)?)
+        .expect_err("a version 0 blob was parsed");
+    assert!(matches!(
+        error,
+        StorageError::UnsupportedVersion { found: 0 }
+    ));
+    Ok(())
+}
+
+#[test]
+pub fn inconsistent_credential() -> Result<()> {
+    let documents = Device::issue_mdl()?;
+    let document = documents
+        .get(DOC_TYPE)
+        .ok_or(anyhow!("no mDL was issued"))?;
+
+    // The envelope claims a doc type other than the one the issuer signed.
+    let mut stored = StoredCredential::from(document);
+    stored.doc_type = "org.iso.7367.1.mVRC".into();
+    assert!(matches!(
+        Document::try_from(stored),
+        Err(StorageError::DocTypeMismatch { .. })
+    ));
+
+    // An element was altered after it was stored.
+    let mut stored = StoredCredential::from(document);
+    let mut namespaces = stored
+        .issuer_signed
+        .namespaces
+        .take()
+        .ok_or(anyhow!("no elements were stored"))?
+        .into_inner();
+    for items in namespaces.values_mut() {
+        *items = items.clone().map(|item| {
+            let mut item = item.into_inner();
+            item.random = vec![0; 16].into();
+            Tag24::new(item).unwrap()
+        });
+    }
+    stored.issuer_signed.namespaces = Some(namespaces.try_into()?);
+    assert!(matches!(
+        Document::try_from(stored),
+        Err(StorageError::DigestMismatches(mismatches)) if !mismatches.is_empty()
+    ));
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use anyhow::{anyhow, Result};
+use cose_rs::algorithm::Algorithm;
+use cose_rs::CoseSign1;
+use signature::Signer;
+
+use crate::common::{document_signer, mdl_namespaces, Device, Issue, DOC_TYPE};
+use isomdl::definitions::helpers::Tag24;
+use isomdl::definitions::x509::x5chain::X5CHAIN_HEADER_LABEL;
+use isomdl::definitions::Mso;
+use isomdl::presentation::device::{Document, Documents};
+use isomdl::presentation::reader::ResponseError;
+
+/// Issue an mDL whose mobile security object is encoded with its keys in the order of the fields
+/// of [Mso], rather than in canonical order, and signed as such.
+fn issue_non_canonical() -> Result<Document> {
+    let mut mdoc = Issue::new(DOC_TYPE, mdl_namespaces()).mdoc()?;
+    let (x5chain, signer) = document_signer()?;
+
+    // "version" is the first field of Mso, but sorts after "docType".
+    let mso: Tag24<Mso> = Tag24::from_bytes(serde_cbor::to_vec(&mdoc.mso)?)?;
+    assert!(mso.inner_bytes[1..].starts_with(b"\x67version"));
+    let prepared = CoseSign1::builder()
+        .payload(serde_cbor::to_vec(&mso)?)
+        .signature_algorithm(Algorithm::ES256)
+        .prepare()
+        .map_err(|e| anyhow!("error preparing cosesign1: {}", e))?;
+    let signature: p256::ecdsa::Signature = signer.sign(prepared.signature_payload());
+    let mut issuer_auth = prepared.finalize(signature.to_vec());
+    issuer_auth
+        .unprotected_mut()
+        .insert_i(X5CHAIN_HEADER_LABEL, x5chain.into_cbor());
+    mdoc.issuer_auth = issuer_auth;
+    Ok(mdoc.into())
+}
+
+fn present(document: Document, strict_cbor: bool) -> Result<Result<(), ResponseError>> {
+    let (engaged_state, qr_code_uri) =
+        Device::initialise_session_with(Documents::new(DOC_TYPE.to_string(), document))?;
+    let (mut reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+    reader_session_manager.set_strict_cbor(strict_cbor);
+    let (device_session_manager, requested_items) = Device::handle_request(engaged_state, request)?;
+    let response = Device::create_response(
+        device_session_manager,
+        requested_items,
+        &Device::create_signing_key()?,
+    )?;
+    Ok(reader_session_manager
+        .handle_response(&response)
+        .map(|_| ()))
+}
+
+#[test]
+pub fn strict_cbor() -> Result<()> {
+    let document = issue_non_canonical()?;
+    assert!(present(document.clone(), false)?.is_ok());
+    assert!(matches!(
+        present(document, true)?,
+        Err(ResponseError::NonCanonicalCbor)
+    ));
+
+    // A canonically encoded mobile security object is accepted either way.
+    let documents = Device::issue_mdl()?;
+    let document = documents
+        .get(DOC_TYPE)
+        .ok_or(anyhow!("no mDL was issued"))?
+        .clone();
+    assert!(present(document, true)?.is_ok());
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use anyhow::{anyhow, Result};
+use p256::pkcs8::DecodePrivateKey;
+
+use crate::common::{trust_anchor_registry, Device, DOC_TYPE, IACA_CERT};
+use isomdl::definitions::x509::{TrustAnchorRegistry, X509};
+use isomdl::presentation::reader::{self, AuthenticationStatus};
+
+const OTHER_CERT: &[u8] = include_bytes!("../../test/issuance/256-cert.pem");
+/// A VICAL listing the issuer of the test mDL for vehicle registrations only.
+const MVRC_VICAL: &[u8] = include_bytes!("../../test/definitions/x509/vical/mvrc-vical.cbor");
+const VICAL_SIGNER_KEY: &str = include_str!("../../test/issuance/256-key.pem");
+
+/// Present an mDL to a reader trusting the issuers in `trusted`, returning the reader's session
+/// and the encrypted response.
+fn present(trusted: Option<TrustAnchorRegistry>) -> Result<(reader::SessionManager, Vec<u8>)> {
+    let key = Device::create_signing_key()?;
+    let (engaged_state, qr_code_uri) = Device::initialise_session_with(Device::issue_mdl()?)?;
+    let (mut reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+    if let Some(trusted) = trusted {
+        reader_session_manager.set_trust_anchor_registry(trusted);
+    }
+    let (device_session_manager, requested_items) = Device::handle_request(engaged_state, request)?;
+    let response = Device::create_response(device_session_manager, requested_items, &key)?;
+    Ok((reader_session_manager, response))
+}
+
+fn issuer_auth(trusted: Option<TrustAnchorRegistry>) -> Result<AuthenticationStatus> {
+    let (mut reader_session_manager, response) = present(trusted)?;
+    let validated = reader_session_manager.validate(&response)?;
+    let document = validated
+        .document(DOC_TYPE)
+        .ok_or(anyhow!("response contains no mDL"))?;
+    Ok(document.issuer_auth.clone())
+}
+
+#[test]
+pub fn trusted_issuer() -> Result<()> {
+    assert_eq!(
+        issuer_auth(Some(trust_anchor_registry()?))?,
+        AuthenticationStatus::Valid
+    );
+
+    let (mut reader_session_manager, response) = present(Some(trust_anchor_registry()?))?;
+    assert!(reader_session_manager.handle_response(&response).is_ok());
+    Ok(())
+}
+
+#[test]
+pub fn untrusted_issuer() -> Result<()> {
+    let other = || TrustAnchorRegistry::new().with_pem(OTHER_CERT);
+    assert!(matches!(
+        issuer_auth(Some(other()?))?,
+        AuthenticationStatus::Invalid(_)
+    ));
+
+    let (mut reader_session_manager, response) = present(Some(other()?))?;
+    assert!(matches!(
+        reader_session_manager.handle_response(&response),
+        Err(reader::ResponseError::UntrustedIssuer(_))
+    ));
+    Ok(())
+}
+
+#[test]
+pub fn no_trusted_issuers() -> Result<()> {
+    assert_eq!(issuer_auth(None)?, AuthenticationStatus::Unverified);
+    Ok(())
+}
+
+#[test]
+pub fn issuer_scoped_to_other_doc_type() -> Result<()> {
+    let verifier = *p256::ecdsa::SigningKey::from_pkcs8_pem(VICAL_SIGNER_KEY)?.verifying_key();
+    let vical =
+        || TrustAnchorRegistry::from_vical::<_, p256::ecdsa::Signature>(MVRC_VICAL, &verifier);
+    assert_eq!(
+        vical()?.doc_types(&X509::from_pem(IACA_CERT)?),
+        Some(&["org.iso.7367.1.mVRC".to_string()][..])
+    );
+
+    // The issuer is trusted, but not to issue mDLs.
+    assert!(matches!(
+        issuer_auth(Some(vical()?))?,
+        AuthenticationStatus::Invalid(_)
+    ));
+    let (mut reader_session_manager, response) = present(Some(vical()?))?;
+    assert!(matches!(
+        reader_session_manager.handle_response(&response),
+        Err(reader::ResponseError::UntrustedIssuer(_))
+    ));
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use anyhow::Result;
+use serde_cbor::Value as CborValue;
+
+use crate::common::{trust_anchor_registry, Device, AGE_OVER_21_ELEMENT, DOC_TYPE, NAMESPACE};
+use isomdl::definitions::x509::TrustAnchorRegistry;
+use isomdl::definitions::DeviceResponse;
+use isomdl::presentation::reader::{AuthenticationStatus, DeviceAuthResult};
+
+#[test]
+pub fn validated_response() -> Result<()> {
+    let key = Device::create_signing_key()?;
+    let registry = trust_anchor_registry()?;
+
+    let (engaged_state, qr_code_uri) = Device::initialise_session_with(Device::issue_mdl()?)?;
+    let (mut reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+    let (device_session_manager, requested_items) = Device::handle_request(engaged_state, request)?;
+    let response = Device::create_response(device_session_manager, requested_items, &key)?;
+
+    let validated = reader_session_manager.validate_response(&response, &registry)?;
+    let document = validated
+        .document(DOC_TYPE)
+        .expect("response contains no mDL");
+    assert_eq!(
+        document.namespaces[NAMESPACE].get(AGE_OVER_21_ELEMENT),
+        Some(&CborValue::Bool(true))
+    );
+    assert_eq!(document.issuer_auth, AuthenticationStatus::Valid);
+    assert_eq!(document.device_auth, DeviceAuthResult::Signature);
+    assert!(document.is_verified());
+    assert!(document.request_mismatches.is_empty());
+    assert_eq!(
+        validated.to_json(),
+        serde_json::json!({ DOC_TYPE: { NAMESPACE: { AGE_OVER_21_ELEMENT: true } } })
+    );
+
+    // The raw response remains available.
+    let raw: DeviceResponse = serde_cbor::from_slice(&validated.raw)?;
+    assert!(raw.documents.is_some());
+    Ok(())
+}
+
+#[test]
+pub fn untrusted_issuer() -> Result<()> {
+    let key = Device::create_signing_key()?;
+
+    let (engaged_state, qr_code_uri) = Device::initialise_session_with(Device::issue_mdl()?)?;
+    let (mut reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+    let (device_session_manager, requested_items) = Device::handle_request(engaged_state, request)?;
+    let response = Device::create_response(device_session_manager, requested_items, &key)?;
+
+    let validated =
+        reader_session_manager.validate_response(&response, &TrustAnchorRegistry::new())?;
+    let document = validated
+        .document(DOC_TYPE)
+        .expect("response contains no mDL");
+    assert!(!document.issuer_auth.is_valid());
+    assert_eq!(document.device_auth, DeviceAuthResult::Signature);
+    assert!(!document.is_verified());
+    Ok(())
+}
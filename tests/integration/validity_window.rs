@@ -0,0 +1,180 @@
+use anyhow::{anyhow, Result};
+use serde_cbor::Value as CborValue;
+use time::{Duration, OffsetDateTime};
+
+use crate::common::{
+    mdl_namespaces, namespaces, trust_anchor_registry, Device, Issue, AGE_OVER_21_ELEMENT,
+    DOC_TYPE, NAMESPACE,
+};
+use isomdl::definitions::{RefreshStatus, ValidityInfo};
+use isomdl::presentation::device::Documents;
+use isomdl::presentation::reader::{self, ResponseError, ValidatedDocument, ValidityError};
+
+/// Present an mDL valid during `validity_info`, returning the reader's session and the
+/// device's response.
+fn respond(validity_info: ValidityInfo) -> Result<(reader::SessionManager, Vec<u8>)> {
+    let namespaces = namespaces(NAMESPACE, [(AGE_OVER_21_ELEMENT, CborValue::Bool(true))]);
+    let key = Device::create_signing_key()?;
+    let mdl = Issue::new(DOC_TYPE, namespaces)
+        .validity_info(validity_info)
+        .issue()?;
+
+    let (engaged_state, qr_code_uri) =
+        Device::initialise_session_with(Documents::new(DOC_TYPE.to_string(), mdl))?;
+    let (reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+    let (device_session_manager, requested_items) = Device::handle_request(engaged_state, request)?;
+    let response = Device::create_response(device_session_manager, requested_items, &key)?;
+    Ok((reader_session_manager, response))
+}
+
+/// Present an mDL valid during `validity_info`, returning the document the reader validated at
+/// `at`.
+fn present(validity_info: ValidityInfo, at: OffsetDateTime) -> Result<ValidatedDocument> {
+    let (mut reader_session_manager, response) = respond(validity_info)?;
+    let registry = trust_anchor_registry()?;
+    reader_session_manager
+        .validate_response_at(&response, &registry, at)?
+        .document(DOC_TYPE)
+        .cloned()
+        .ok_or(anyhow!("response contains no mDL"))
+}
+
+#[test]
+pub fn one_year_validity() -> Result<()> {
+    // Whole seconds, as the encoded dates are truncated.
+    let signed = OffsetDateTime::now_utc().replace_nanosecond(0)?;
+    let document = present(
+        ValidityInfo::builder()
+            .signed(signed)
+            .valid_for(Duration::days(365))
+            .build()?,
+        OffsetDateTime::now_utc(),
+    )?;
+
+    assert_eq!(document.validity_info.signed, signed);
+    assert_eq!(document.validity_info.valid_from, signed);
+    assert_eq!(
+        document.validity_info.valid_until,
+        signed + Duration::days(365)
+    );
+    assert!(document.is_verified());
+    Ok(())
+}
+
+#[test]
+pub fn not_yet_valid() -> Result<()> {
+    let valid_from = OffsetDateTime::now_utc() + Duration::days(30);
+    let document = present(
+        ValidityInfo::builder()
+            .valid_from(valid_from)
+            .valid_for(Duration::days(365))
+            .build()?,
+        OffsetDateTime::now_utc(),
+    )?;
+
+    assert!(document.issuer_auth.is_valid());
+    assert!(document.device_auth.is_valid());
+    assert!(matches!(
+        document.validity_error,
+        Some(ValidityError::NotYetValid { .. })
+    ));
+    assert!(!document.is_verified());
+    Ok(())
+}
+
+#[test]
+pub fn past_window() -> Result<()> {
+    let signed = OffsetDateTime::from_unix_timestamp(1_577_836_800)?; // 2020-01-01
+    let validity_info = ValidityInfo::builder()
+        .signed(signed)
+        .valid_for(Duration::days(365))
+        .build()?;
+
+    let at = signed + Duration::days(730);
+    let document = present(validity_info.clone(), at)?;
+    assert_eq!(
+        document.validity_error,
+        Some(ValidityError::Expired {
+            valid_until: validity_info.valid_until,
+            at
+        })
+    );
+    assert!(!document.is_verified());
+
+    let document = present(validity_info, signed + Duration::days(180))?;
+    assert_eq!(document.validity_error, None);
+    Ok(())
+}
+
+#[test]
+pub fn handle_expired_response() -> Result<()> {
+    let signed = OffsetDateTime::from_unix_timestamp(1_577_836_800)?; // 2020-01-01
+    let validity_info = ValidityInfo::builder()
+        .signed(signed)
+        .valid_for(Duration::days(365))
+        .build()?;
+
+    let (mut reader_session_manager, response) = respond(validity_info.clone())?;
+    match reader_session_manager.handle_response(&response) {
+        Err(ResponseError::Validity(ValidityError::Expired { valid_until, .. })) => {
+            assert_eq!(valid_until, validity_info.valid_until)
+        }
+        other => panic!(
+            "expected an expired mobile security object, got {:?}",
+            other
+        ),
+    }
+    Ok(())
+}
+
+#[test]
+pub fn future_window() -> Result<()> {
+    let signed = OffsetDateTime::from_unix_timestamp(1_577_836_800)?; // 2020-01-01
+    let validity_info = ValidityInfo::builder()
+        .signed(signed)
+        .valid_from(signed + Duration::days(365))
+        .valid_for(Duration::days(365))
+        .build()?;
+
+    let at = signed + Duration::days(180);
+    let document = present(validity_info.clone(), at)?;
+    assert_eq!(
+        document.validity_error,
+        Some(ValidityError::NotYetValid {
+            valid_from: validity_info.valid_from,
+            at
+        })
+    );
+    assert!(!document.is_verified());
+
+    let document = present(validity_info, signed + Duration::days(540))?;
+    assert_eq!(document.validity_error, None);
+    Ok(())
+}
+
+#[test]
+pub fn refresh_status() -> Result<()> {
+    let signed = OffsetDateTime::from_unix_timestamp(1_577_836_800)?; // 2020-01-01
+    let validity_info = ValidityInfo::builder()
+        .signed(signed)
+        .valid_for(Duration::days(365))
+        .expected_update(signed + Duration::days(300))
+        .build()?;
+    let mdl = Issue::new(DOC_TYPE, mdl_namespaces())
+        .validity_info(validity_info)
+        .issue()?;
+
+    assert_eq!(
+        mdl.refresh_status(signed + Duration::days(180)),
+        RefreshStatus::Current
+    );
+    assert_eq!(
+        mdl.refresh_status(signed + Duration::days(330)),
+        RefreshStatus::UpdateDue
+    );
+    assert_eq!(
+        mdl.refresh_status(signed + Duration::days(400)),
+        RefreshStatus::Expired
+    );
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use anyhow::Result;
+use serde_cbor::Value as CborValue;
+
+use crate::common::{trust_anchor_registry, Device, AGE_OVER_21_ELEMENT, DOC_TYPE, NAMESPACE};
+use isomdl::definitions::device_request::{DataElements, Namespaces};
+use isomdl::presentation::device::Wallet;
+use isomdl::presentation::reader::{AuthenticationStatus, DeviceAuthResult, Verifier};
+
+#[test]
+fn verifier_authenticates_age_over_21() -> Result<()> {
+    let mut verifier = Verifier::new(trust_anchor_registry()?);
+    let wallet = Wallet::new(Device::issue_mdl()?, Device::create_signing_key()?);
+
+    let (mut engaged, qr_code_uri) = wallet.engage()?;
+    let request = verifier.request(
+        qr_code_uri,
+        Namespaces::new(
+            NAMESPACE.into(),
+            DataElements::new(AGE_OVER_21_ELEMENT.to_string(), false),
+        ),
+    )?;
+    engaged.receive_request(&request)?;
+    let permitted = [(
+        DOC_TYPE.to_string(),
+        [(NAMESPACE.to_string(), vec![AGE_OVER_21_ELEMENT.to_string()])]
+            .into_iter()
+            .collect(),
+    )]
+    .into_iter()
+    .collect();
+    let response = engaged.respond(permitted)?;
+
+    let validated = verifier.verify(&response)?;
+    let document = validated
+        .document(DOC_TYPE)
+        .expect("response contains no mDL");
+    assert_eq!(
+        document.namespaces[NAMESPACE].get(AGE_OVER_21_ELEMENT),
+        Some(&CborValue::Bool(true))
+    );
+    assert_eq!(document.issuer_auth, AuthenticationStatus::Valid);
+    assert_eq!(document.device_auth, DeviceAuthResult::Signature);
+    assert!(document.is_verified());
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use serde_cbor::Value as CborValue;
+
+use crate::common::{mdl_namespaces, trust_anchor_registry, Issue, AGE_OVER_21_ELEMENT, DOC_TYPE};
+use isomdl::definitions::helpers::Tag24;
+use isomdl::definitions::x509::TrustAnchorRegistry;
+use isomdl::issuance::{verify_issuer_signed, Mdoc};
+use isomdl::presentation::reader::AuthenticationStatus;
+
+fn issue() -> Result<Mdoc> {
+    Issue::new(DOC_TYPE, mdl_namespaces()).mdoc()
+}
+
+#[test]
+pub fn issued() -> Result<()> {
+    let report = verify_issuer_signed(&issue()?.issuer_signed(), &trust_anchor_registry()?);
+    assert!(report.is_valid(), "{:?}", report);
+    assert_eq!(report.doc_type.as_deref(), Some(DOC_TYPE));
+    Ok(())
+}
+
+#[test]
+pub fn tampered() -> Result<()> {
+    let mut issuer_signed = issue()?.issuer_signed();
+    let namespaces = issuer_signed
+        .namespaces
+        .take()
+        .ok_or(anyhow!("no elements were issued"))?
+        .into_inner()
+        .into_iter()
+        .map(|(namespace, items)| {
+            let items = items.map(|item| {
+                let mut item = item.into_inner();
+                if item.element_identifier == AGE_OVER_21_ELEMENT {
+                    item.element_value = CborValue::Bool(false);
+                }
+                Tag24::new(item).expect("unable to encode the tampered element")
+            });
+            (namespace, items)
+        })
+        .collect::<BTreeMap<_, _>>();
+    issuer_signed.namespaces = Some(namespaces.try_into()?);
+
+    let report = verify_issuer_signed(&issuer_signed, &trust_anchor_registry()?);
+    assert!(!report.is_valid());
+    // The issuer auth is intact, but the element no longer matches its digest.
+    assert_eq!(report.issuer_auth, AuthenticationStatus::Valid);
+    let mismatches: Vec<_> = report
+        .digest_mismatches
+        .iter()
+        .map(|mismatch| mismatch.element_identifier.as_str())
+        .collect();
+    assert_eq!(mismatches, [AGE_OVER_21_ELEMENT]);
+    Ok(())
+}
+
+#[test]
+pub fn untrusted_issuer() -> Result<()> {
+    let report = verify_issuer_signed(&issue()?.issuer_signed(), &TrustAnchorRegistry::new());
+    assert!(matches!(
+        report.issuer_auth,
+        AuthenticationStatus::Invalid(_)
+    ));
+    assert!(report.digest_mismatches.is_empty());
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use anyhow::Result;
+use isomdl::definitions::SessionEstablishment;
+use isomdl::presentation::device::Wallet;
+
+use crate::common::{Device, AGE_OVER_21_ELEMENT, DOC_TYPE, NAMESPACE};
+
+#[test]
+fn wallet_presents_requested_elements() -> Result<()> {
+    let wallet = Wallet::new(Device::issue_mdl()?, Device::create_signing_key()?);
+
+    let (mut engaged, qr_code_uri) = wallet.engage()?;
+    let (mut reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+
+    let requested = engaged.receive_request(&request)?;
+    assert_eq!(requested.len(), 1);
+    assert_eq!(requested[0].doc_type, DOC_TYPE);
+
+    let permitted = [(
+        DOC_TYPE.to_string(),
+        [(NAMESPACE.to_string(), vec![AGE_OVER_21_ELEMENT.to_string()])]
+            .into_iter()
+            .collect(),
+    )]
+    .into_iter()
+    .collect();
+    let response = engaged.respond(permitted)?;
+
+    let parsed = reader_session_manager.handle_response(&response)?;
+    assert_eq!(
+        parsed[NAMESPACE][AGE_OVER_21_ELEMENT],
+        serde_json::Value::Bool(true)
+    );
+
+    let termination = engaged.terminate()?;
+    reader_session_manager.handle_termination(&termination)?;
+    assert!(reader_session_manager.is_terminated());
+    Ok(())
+}
+
+#[test]
+fn wallet_rejects_response_before_request() -> Result<()> {
+    let wallet = Wallet::new(Device::issue_mdl()?, Device::create_signing_key()?);
+    let (mut engaged, _) = wallet.engage()?;
+    assert!(engaged.respond(Default::default()).is_err());
+    Ok(())
+}
+
+#[test]
+fn wallet_remains_engaged_after_garbled_session_establishment() -> Result<()> {
+    let wallet = Wallet::new(Device::issue_mdl()?, Device::create_signing_key()?);
+    let (mut engaged, qr_code_uri) = wallet.engage()?;
+    let (_reader_session_manager, request) = Device::establish_reader_session(qr_code_uri)?;
+
+    let mut session_establishment: SessionEstablishment = serde_cbor::from_slice(&request)?;
+    let mut data: Vec<u8> = session_establishment.data.into();
+    data[0] ^= 1;
+    session_establishment.data = data.into();
+    let garbled = serde_cbor::to_vec(&session_establishment)?;
+    assert!(engaged.receive_request(&garbled).is_err());
+    assert!(engaged.session_manager().is_none());
+
+    let requested = engaged.receive_request(&request)?;
+    assert_eq!(requested[0].doc_type, DOC_TYPE);
+    assert!(engaged.session_manager().is_some());
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use anyhow::{anyhow, Result};
+use signature::Signer;
+
+use crate::common::{trust_anchor_registry, Device, AGE_OVER_21_ELEMENT, DOC_TYPE, NAMESPACE};
+use isomdl::definitions::device_request::{DataElements, Namespaces};
+use isomdl::presentation::device::Decision;
+use isomdl::presentation::reader;
+
+#[test]
+pub fn wildcard_request() -> Result<()> {
+    let (engaged_state, qr_code_uri) = Device::initialise_session_with(Device::issue_mdl()?)?;
+    let (mut reader_session_manager, request, _) = reader::SessionManager::establish_session(
+        qr_code_uri,
+        Namespaces::new(NAMESPACE.to_string(), DataElements::all(false)),
+    )?;
+    let (mut device_session_manager, requested_items) =
+        Device::handle_request(engaged_state, request)?;
+
+    // The holder is asked about each element the mDL holds in the namespace.
+    let mut asked = Vec::new();
+    device_session_manager.prepare_response_with(&requested_items, |item| {
+        asked.push(item.element_identifier.to_string());
+        assert!(!item.intent_to_retain);
+        if item.element_identifier == AGE_OVER_21_ELEMENT {
+            Decision::Allow
+        } else {
+            Decision::Deny
+        }
+    });
+    assert_eq!(asked, [AGE_OVER_21_ELEMENT, "family_name"]);
+
+    let (_, payload) = device_session_manager
+        .get_next_signature_payload()
+        .ok_or(anyhow!("no signature payload"))?;
+    let signature: p256::ecdsa::Signature = Device::create_signing_key()?.sign(payload);
+    device_session_manager.submit_next_signature(signature.to_vec())?;
+    let response = device_session_manager
+        .retrieve_response()
+        .ok_or(anyhow!("cannot prepare response"))?;
+
+    let registry = trust_anchor_registry()?;
+    let validated = reader_session_manager.validate_response(&response, &registry)?;
+    let document = validated
+        .document(DOC_TYPE)
+        .ok_or(anyhow!("response contains no mDL"))?;
+    assert!(document.is_verified());
+    assert!(document.request_mismatches.is_empty());
+    assert_eq!(
+        document.namespaces[NAMESPACE].keys().collect::<Vec<_>>(),
+        [AGE_OVER_21_ELEMENT]
+    );
+    Ok(())
+}
+
+#[test]
+pub fn wildcard_intent_to_retain() -> Result<()> {
+    let (engaged_state, qr_code_uri) = Device::initialise_session_with(Device::issue_mdl()?)?;
+    let (_reader_session_manager, request, _) = reader::SessionManager::establish_session(
+        qr_code_uri,
+        Namespaces::new(NAMESPACE.to_string(), DataElements::all(true)),
+    )?;
+    let (_device_session_manager, requested_items) =
+        Device::handle_request(engaged_state, request)?;
+
+    // Each element the wildcard expands to carries its intent to retain.
+    let elements = &requested_items[0].namespaces[NAMESPACE];
+    assert_eq!(
+        elements.keys().collect::<Vec<_>>(),
+        [AGE_OVER_21_ELEMENT, "family_name"]
+    );
+    assert!(elements.values().all(|intent_to_retain| *intent_to_retain));
+    Ok(())
+}
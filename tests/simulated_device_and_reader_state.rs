@@ -1,13 +1,14 @@
+#![cfg(feature = "transports")]
+
 use std::sync::{Arc, Mutex};
 
 use anyhow::{Context, Result};
-use signature::Signer;
 use uuid::Uuid;
 
 use isomdl::definitions::device_engagement::{CentralClientMode, DeviceRetrievalMethods};
 use isomdl::definitions::device_request::{DataElements, Namespaces};
 use isomdl::definitions::{self, BleOptions, DeviceRetrievalMethod};
-use isomdl::presentation::device::{Documents, RequestedItems};
+use isomdl::presentation::device::{Documents, MdocSigner, RequestedItems};
 use isomdl::presentation::{device, reader};
 
 use crate::common::{Device, AGE_OVER_21_ELEMENT, DOC_TYPE, NAMESPACE};
@@ -26,18 +27,19 @@ struct RequestData {
 struct SessionManager {
     inner: Mutex<device::SessionManager>,
     items_requests: RequestedItems,
-    key: Arc<p256::ecdsa::SigningKey>,
+    key: Arc<dyn MdocSigner + Send + Sync>,
 }
 
 struct SessionManagerEngaged(device::SessionManagerEngaged);
 
 #[test]
 pub fn simulated_device_and_reader_interaction() -> Result<()> {
-    let key: Arc<p256::ecdsa::SigningKey> =
-        Arc::new(p256::SecretKey::from_sec1_pem(include_str!("data/sec1.pem"))?.into());
+    let key: Arc<dyn MdocSigner + Send + Sync> = Arc::new(p256::ecdsa::SigningKey::from(
+        p256::SecretKey::from_sec1_pem(include_str!("data/sec1.pem"))?,
+    ));
 
-    // Parse the mDL
-    let docs = Device::parse_mdl()?;
+    // Issue the mDL
+    let docs = Device::issue_mdl()?;
 
     // Device initialization and engagement
     let session_data = initialise_session(docs, Uuid::new_v4())?;
@@ -107,7 +109,7 @@ fn handle_request(
     state: Arc<SessionManagerEngaged>,
     reader_session_manager: &mut reader::SessionManager,
     request: Vec<u8>,
-    key: Arc<p256::ecdsa::SigningKey>,
+    key: Arc<dyn MdocSigner + Send + Sync>,
 ) -> Result<Option<RequestData>> {
     let (session_manager, items_requests) = {
         let session_establishment: definitions::SessionEstablishment =
@@ -168,9 +170,9 @@ fn sign_pending_and_retrieve_response(
                     );
                 }
             }
-            let signature: p256::ecdsa::Signature = session_manager.key.sign(payload);
+            let signature = session_manager.key.sign(payload)?;
             guard
-                .submit_next_signature(signature.to_vec())
+                .submit_next_signature(signature)
                 .context("failed to submit signature")?;
             signed += 1;
         } else {
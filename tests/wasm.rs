@@ -0,0 +1,45 @@
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use isomdl::definitions::device_request::{DataElements, Namespaces};
+use isomdl::presentation::wasm::ReaderSession;
+use serde::Serialize;
+use serde_json::Value;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use crate::common::{Device, AGE_OVER_21_ELEMENT, NAMESPACE};
+
+#[wasm_bindgen_test]
+fn establish_session_and_handle_response() {
+    let (engaged_state, qr_code_uri) = Device::initialise_session().unwrap();
+    let requested = Namespaces::new(
+        NAMESPACE.into(),
+        DataElements::new(AGE_OVER_21_ELEMENT.to_string(), false),
+    )
+    .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+    .unwrap();
+
+    let mut session = ReaderSession::establish_session(&qr_code_uri, requested).unwrap();
+    let (device_session_manager, requested_items) =
+        Device::handle_request(engaged_state, session.request()).unwrap();
+    let response = Device::create_response(
+        device_session_manager,
+        requested_items,
+        &Device::create_signing_key().unwrap(),
+    )
+    .unwrap();
+
+    session
+        .set_trust_anchors(vec![
+            include_str!("../test/presentation/iaca-cert.pem").to_string()
+        ])
+        .unwrap();
+    assert_eq!(session.issuer_authentication(), None);
+    let claims: BTreeMap<String, BTreeMap<String, Value>> =
+        serde_wasm_bindgen::from_value(session.handle_response(&response).unwrap()).unwrap();
+    assert_eq!(claims[NAMESPACE][AGE_OVER_21_ELEMENT], Value::Bool(true));
+    assert_eq!(session.issuer_authentication().as_deref(), Some("valid"));
+}